@@ -0,0 +1,13 @@
+-- Pin, archive and soft-delete (trash) support for conversations
+ALTER TABLE conversations
+ADD COLUMN IF NOT EXISTS pinned_at TIMESTAMPTZ,
+ADD COLUMN IF NOT EXISTS archived_at TIMESTAMPTZ,
+ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ;
+
+-- The list endpoint filters on status and sorts pinned conversations first
+CREATE INDEX IF NOT EXISTS idx_conversations_user_status
+    ON conversations(user_id, deleted_at, archived_at, pinned_at DESC, updated_at DESC);
+
+-- Used by the trash purge job
+CREATE INDEX IF NOT EXISTS idx_conversations_deleted_at
+    ON conversations(deleted_at) WHERE deleted_at IS NOT NULL;
@@ -0,0 +1,7 @@
+-- Keyset pagination for the conversation list: (pinned, updated_at, id) descending
+CREATE INDEX IF NOT EXISTS idx_conversations_user_keyset
+    ON conversations(user_id, (pinned_at IS NOT NULL) DESC, updated_at DESC, id DESC);
+
+-- Windowed loading of long threads orders messages by (created_at, id)
+CREATE INDEX IF NOT EXISTS idx_messages_conversation_created
+    ON messages(conversation_id, created_at, id);
@@ -0,0 +1,17 @@
+-- Read-only public share links. The snapshot is taken when the link is created,
+-- so later edits to the conversation are not visible through the link.
+CREATE TABLE IF NOT EXISTS share_links (
+    id UUID PRIMARY KEY,
+    token TEXT NOT NULL UNIQUE,
+    conversation_id UUID NOT NULL REFERENCES conversations(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    include_branches BOOLEAN NOT NULL DEFAULT FALSE,
+    snapshot JSONB NOT NULL,
+    expires_at TIMESTAMPTZ,
+    revoked_at TIMESTAMPTZ,
+    view_count BIGINT NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_share_links_conversation_id ON share_links(conversation_id);
+CREATE INDEX IF NOT EXISTS idx_share_links_user_id ON share_links(user_id, created_at DESC);
@@ -0,0 +1,23 @@
+-- Collaborators on a conversation. The owner is always `conversations.user_id`;
+-- this table holds everyone else the conversation is shared with.
+CREATE TABLE IF NOT EXISTS conversation_members (
+    conversation_id UUID NOT NULL REFERENCES conversations(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    role VARCHAR(20) NOT NULL CHECK (role IN ('editor', 'viewer')),
+    added_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (conversation_id, user_id)
+);
+
+-- Used by the conversation list to find conversations shared with a user
+CREATE INDEX IF NOT EXISTS idx_conversation_members_user_id ON conversation_members(user_id);
+
+-- The user who wrote a message; NULL for assistant and system messages
+ALTER TABLE messages
+ADD COLUMN IF NOT EXISTS author_id UUID REFERENCES users(id) ON DELETE SET NULL;
+
+-- Existing user messages were written by the conversation owner
+UPDATE messages m
+SET author_id = c.user_id
+FROM conversations c
+WHERE m.conversation_id = c.id AND m.role = 'user' AND m.author_id IS NULL;
@@ -0,0 +1,25 @@
+-- Highlights, notes and labels on messages. Annotations point at message ids, which
+-- never change (an edit creates a new message), so they survive branch switches.
+CREATE TABLE IF NOT EXISTS message_annotations (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    message_id UUID NOT NULL REFERENCES messages(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    -- Highlighted character range [start_offset, end_offset); NULL for the whole message
+    start_offset INTEGER,
+    end_offset INTEGER,
+    -- The highlighted text, copied when the annotation is created
+    quote TEXT,
+    note TEXT,
+    label VARCHAR(50),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK ((start_offset IS NULL) = (end_offset IS NULL)),
+    CHECK (start_offset IS NULL OR (start_offset >= 0 AND end_offset > start_offset))
+);
+
+CREATE INDEX IF NOT EXISTS idx_message_annotations_message_id ON message_annotations(message_id);
+CREATE INDEX IF NOT EXISTS idx_message_annotations_user_id ON message_annotations(user_id, created_at DESC);
+
+CREATE TRIGGER update_message_annotations_updated_at
+    BEFORE UPDATE ON message_annotations
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
@@ -0,0 +1,10 @@
+-- Separate edit history and deletion from branch selection. `is_active` now only
+-- means "on the currently selected branch".
+
+-- The message this one is an edited version of
+ALTER TABLE messages ADD COLUMN IF NOT EXISTS edited_from UUID REFERENCES messages(id) ON DELETE SET NULL;
+
+-- Soft delete; deleting a message also deletes the replies below it
+ALTER TABLE messages ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS idx_messages_edited_from ON messages(edited_from) WHERE edited_from IS NOT NULL;
@@ -0,0 +1,89 @@
+-- The selected branch is stored as a single leaf pointer on the conversation instead of
+-- per-message is_active flags. Every message keeps its root-to-message path so threads,
+-- ancestors and subtrees can be read without recursive queries.
+
+-- Chat used to store every message as a root. Link such flat histories into one thread.
+WITH flat AS (
+    SELECT id,
+           LAG(id) OVER (PARTITION BY conversation_id ORDER BY created_at, id) AS previous_id
+    FROM messages
+    WHERE parent_id IS NULL AND is_active = true
+)
+UPDATE messages m
+SET parent_id = flat.previous_id
+FROM flat
+WHERE m.id = flat.id AND flat.previous_id IS NOT NULL;
+
+-- Message ids from the root down to the message itself
+ALTER TABLE messages ADD COLUMN IF NOT EXISTS path UUID[];
+
+WITH RECURSIVE tree AS (
+    SELECT id, ARRAY[id] AS path
+    FROM messages
+    WHERE parent_id IS NULL
+
+    UNION ALL
+
+    SELECT m.id, t.path || m.id
+    FROM messages m
+    INNER JOIN tree t ON m.parent_id = t.id
+)
+UPDATE messages m
+SET path = tree.path
+FROM tree
+WHERE m.id = tree.id;
+
+ALTER TABLE messages ALTER COLUMN path SET NOT NULL;
+
+-- Finds the subtree below a message: path @> ARRAY[id]
+CREATE INDEX IF NOT EXISTS idx_messages_path ON messages USING GIN (path);
+
+-- Parents must be inserted before their replies, also within a single multi-row insert
+CREATE OR REPLACE FUNCTION set_message_path()
+RETURNS TRIGGER AS $$
+DECLARE
+    parent_path UUID[];
+BEGIN
+    IF NEW.parent_id IS NULL THEN
+        NEW.path := ARRAY[NEW.id];
+    ELSE
+        SELECT path INTO parent_path FROM messages WHERE id = NEW.parent_id;
+        IF parent_path IS NULL THEN
+            RAISE EXCEPTION 'Parent message % must exist before its replies', NEW.parent_id;
+        END IF;
+        NEW.path := parent_path || NEW.id;
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE 'plpgsql';
+
+CREATE TRIGGER set_messages_path
+    BEFORE INSERT ON messages
+    FOR EACH ROW EXECUTE FUNCTION set_message_path();
+
+-- The last message of the selected branch; the active thread is its path
+ALTER TABLE conversations
+ADD COLUMN IF NOT EXISTS active_leaf_id UUID REFERENCES messages(id) ON DELETE SET NULL;
+
+UPDATE conversations c
+SET active_leaf_id = leaf.id
+FROM (
+    SELECT DISTINCT ON (conversation_id) conversation_id, id
+    FROM messages
+    WHERE is_active = true AND deleted_at IS NULL
+    ORDER BY conversation_id, cardinality(path) DESC, created_at DESC
+) leaf
+WHERE c.id = leaf.conversation_id;
+
+DROP INDEX IF EXISTS idx_messages_active_created;
+ALTER TABLE messages DROP COLUMN IF EXISTS is_active;
+
+-- Messages with is_active derived from the conversation's leaf. Read through this view;
+-- write to messages.
+CREATE OR REPLACE VIEW message_nodes AS
+SELECT m.id, m.conversation_id, m.parent_id, m.role, m.content, m.tokens_used, m.created_at,
+       COALESCE(m.id = ANY(leaf.path), false) AS is_active,
+       m.metadata, m.author_id, m.edited_from, m.deleted_at, m.path
+FROM messages m
+LEFT JOIN conversations c ON c.id = m.conversation_id
+LEFT JOIN messages leaf ON leaf.id = c.active_leaf_id;
@@ -0,0 +1,9 @@
+-- Usage records point at the answer they paid for so conversation stats can sum cost
+-- exactly. Records outlive their message for billing.
+ALTER TABLE api_usage
+ADD COLUMN IF NOT EXISTS message_id UUID REFERENCES messages(id) ON DELETE SET NULL;
+
+CREATE INDEX IF NOT EXISTS idx_api_usage_message_id ON api_usage(message_id);
+
+-- Attachments and embeddings are summed per conversation through their message
+CREATE INDEX IF NOT EXISTS idx_attachments_message_id ON attachments(message_id);
@@ -0,0 +1,15 @@
+-- Lexical search over message content. Exact terms such as identifiers and error codes
+-- are kept as lexemes, so they match even when embeddings do not.
+ALTER TABLE messages
+ADD COLUMN IF NOT EXISTS content_tsv tsvector
+    GENERATED ALWAYS AS (to_tsvector('english', content)) STORED;
+
+CREATE INDEX IF NOT EXISTS idx_messages_content_tsv ON messages USING GIN (content_tsv);
+
+CREATE OR REPLACE VIEW message_nodes AS
+SELECT m.id, m.conversation_id, m.parent_id, m.role, m.content, m.tokens_used, m.created_at,
+       COALESCE(m.id = ANY(leaf.path), false) AS is_active,
+       m.metadata, m.author_id, m.edited_from, m.deleted_at, m.path, m.content_tsv
+FROM messages m
+LEFT JOIN conversations c ON c.id = m.conversation_id
+LEFT JOIN messages leaf ON leaf.id = c.active_leaf_id;
@@ -0,0 +1,45 @@
+-- Queue of messages waiting to be embedded. Workers claim batches with SKIP LOCKED,
+-- failed batches are retried with backoff until the attempts run out.
+CREATE TABLE IF NOT EXISTS embedding_jobs (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    message_id UUID NOT NULL UNIQUE REFERENCES messages(id) ON DELETE CASCADE,
+    status VARCHAR(20) NOT NULL DEFAULT 'pending'
+        CHECK (status IN ('pending', 'processing', 'completed', 'failed')),
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    run_after TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_embedding_jobs_claimable
+ON embedding_jobs (run_after)
+WHERE status IN ('pending', 'processing');
+
+CREATE INDEX IF NOT EXISTS idx_embedding_jobs_status ON embedding_jobs (status, updated_at DESC);
+
+CREATE TRIGGER update_embedding_jobs_updated_at
+    BEFORE UPDATE ON embedding_jobs
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+-- Every new message is queued, whichever code path inserts it
+CREATE OR REPLACE FUNCTION enqueue_message_embedding()
+RETURNS TRIGGER AS $$
+BEGIN
+    INSERT INTO embedding_jobs (message_id) VALUES (NEW.id)
+    ON CONFLICT (message_id) DO NOTHING;
+    RETURN NEW;
+END;
+$$ LANGUAGE 'plpgsql';
+
+CREATE TRIGGER enqueue_messages_embedding
+    AFTER INSERT ON messages
+    FOR EACH ROW EXECUTE FUNCTION enqueue_message_embedding();
+
+-- Backfill the existing history
+INSERT INTO embedding_jobs (message_id)
+SELECT m.id
+FROM messages m
+LEFT JOIN message_embeddings me ON me.message_id = m.id
+WHERE me.id IS NULL AND m.deleted_at IS NULL
+ON CONFLICT (message_id) DO NOTHING;
@@ -0,0 +1,24 @@
+-- Embeddings record the model that produced them, so a new model can be rolled out by
+-- re-indexing while search keeps comparing vectors of one model only.
+ALTER TABLE message_embeddings
+ADD COLUMN IF NOT EXISTS model VARCHAR(100) NOT NULL DEFAULT 'text-embedding-3-small';
+
+ALTER TABLE message_embeddings
+ADD COLUMN IF NOT EXISTS dimensions INTEGER NOT NULL DEFAULT 1536;
+
+ALTER TABLE message_embeddings ALTER COLUMN model DROP DEFAULT;
+ALTER TABLE message_embeddings ALTER COLUMN dimensions DROP DEFAULT;
+
+CREATE INDEX IF NOT EXISTS idx_message_embeddings_model ON message_embeddings (model);
+
+-- Any dimension can be stored. HNSW needs a fixed one, so each dimension in use gets a
+-- partial index over a cast; re-indexing to a new dimension creates its index.
+DROP INDEX IF EXISTS idx_message_embeddings_embedding_hnsw;
+
+ALTER TABLE message_embeddings ALTER COLUMN embedding TYPE vector;
+
+CREATE INDEX IF NOT EXISTS idx_message_embeddings_hnsw_1536
+ON message_embeddings
+USING hnsw ((embedding::vector(1536)) vector_cosine_ops)
+WITH (m = 16, ef_construction = 64)
+WHERE dimensions = 1536;
@@ -0,0 +1,43 @@
+-- Long messages are embedded as overlapping chunks so one vector does not have to
+-- stand for a whole document. Offsets count characters into the message content.
+-- message_embeddings keeps one vector per message, the mean of its chunks.
+CREATE TABLE message_chunks (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    message_id UUID NOT NULL REFERENCES messages(id) ON DELETE CASCADE,
+    chunk_index INTEGER NOT NULL,
+    start_offset INTEGER NOT NULL,
+    end_offset INTEGER NOT NULL,
+    content TEXT NOT NULL,
+    embedding vector NOT NULL,
+    model VARCHAR(100) NOT NULL,
+    dimensions INTEGER NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT message_chunks_message_chunk_unique UNIQUE (message_id, chunk_index),
+    CONSTRAINT message_chunks_offsets_check CHECK (start_offset >= 0 AND end_offset > start_offset)
+);
+
+CREATE INDEX idx_message_chunks_message_id ON message_chunks (message_id);
+CREATE INDEX idx_message_chunks_model ON message_chunks (model);
+
+CREATE INDEX idx_message_chunks_hnsw_1536
+ON message_chunks
+USING hnsw ((embedding::vector(1536)) vector_cosine_ops)
+WITH (m = 16, ef_construction = 64)
+WHERE dimensions = 1536;
+
+-- Messages short enough for a single chunk keep their embedding as that chunk
+INSERT INTO message_chunks (
+    message_id, chunk_index, start_offset, end_offset, content, embedding, model, dimensions
+)
+SELECT me.message_id, 0, 0, char_length(m.content), m.content, me.embedding, me.model, me.dimensions
+FROM message_embeddings me
+JOIN messages m ON m.id = me.message_id
+WHERE char_length(m.content) BETWEEN 1 AND 2000;
+
+-- Longer ones are embedded again in chunks
+INSERT INTO embedding_jobs (message_id)
+SELECT m.id
+FROM messages m
+WHERE char_length(m.content) > 2000 AND m.deleted_at IS NULL
+ON CONFLICT (message_id) DO UPDATE
+SET status = 'pending', attempts = 0, last_error = NULL, run_after = NOW();
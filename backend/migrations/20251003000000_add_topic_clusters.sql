@@ -0,0 +1,22 @@
+-- Topics found by clustering a user's message embeddings. Each run replaces the
+-- user's previous clusters; x and y place a message on a 2-D map of the corpus.
+CREATE TABLE topic_clusters (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    cluster_index INTEGER NOT NULL,
+    label VARCHAR(255) NOT NULL,
+    keywords TEXT[] NOT NULL DEFAULT '{}',
+    size INTEGER NOT NULL,
+    model VARCHAR(100) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT topic_clusters_user_index_unique UNIQUE (user_id, cluster_index)
+);
+
+CREATE TABLE message_topics (
+    message_id UUID PRIMARY KEY REFERENCES messages(id) ON DELETE CASCADE,
+    cluster_id UUID NOT NULL REFERENCES topic_clusters(id) ON DELETE CASCADE,
+    x REAL NOT NULL,
+    y REAL NOT NULL
+);
+
+CREATE INDEX idx_message_topics_cluster_id ON message_topics (cluster_id);
@@ -0,0 +1,21 @@
+-- Searches a user keeps to run again. A background task re-runs them when new messages
+-- or embeddings arrive and records how many matches are newer than last_viewed_at.
+CREATE TABLE saved_searches (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name VARCHAR(255) NOT NULL,
+    query TEXT NOT NULL,
+    mode VARCHAR(20) NOT NULL DEFAULT 'hybrid',
+    filters JSONB NOT NULL DEFAULT '{}',
+    similarity_threshold REAL,
+    new_match_count INTEGER NOT NULL DEFAULT 0,
+    last_viewed_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_checked_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT saved_searches_mode_check CHECK (mode IN ('semantic', 'lexical', 'hybrid'))
+);
+
+CREATE INDEX idx_saved_searches_user_id ON saved_searches (user_id);
+CREATE INDEX idx_saved_searches_last_checked_at ON saved_searches (last_checked_at);
+CREATE INDEX idx_message_embeddings_created_at ON message_embeddings (created_at);
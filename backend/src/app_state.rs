@@ -1,13 +1,15 @@
 use crate::{
     config::AppConfig,
     services::{
-        auth::AuthService, chat::ChatService, conversation::ConversationService, DataAccessLayer,
+        auth::AuthService, authorization::AuthorizationService, chat::ChatService,
+        conversation::ConversationService, DataAccessLayer,
     },
 };
 
 #[derive(Clone)]
 pub struct AppState {
     pub auth_service: AuthService,
+    pub authorization_service: AuthorizationService,
     pub conversation_service: ConversationService,
     pub chat_service: ChatService,
     pub dal: DataAccessLayer,
@@ -17,6 +19,7 @@ pub struct AppState {
 impl AppState {
     pub fn new(
         auth_service: AuthService,
+        authorization_service: AuthorizationService,
         conversation_service: ConversationService,
         chat_service: ChatService,
         dal: DataAccessLayer,
@@ -24,6 +27,7 @@ impl AppState {
     ) -> Self {
         Self {
             auth_service,
+            authorization_service,
             conversation_service,
             chat_service,
             dal,
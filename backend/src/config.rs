@@ -88,9 +88,32 @@ pub struct AppConfig {
     pub redis_url: String,
     pub session_timeout_hours: u64,
     pub storage_path: String,
+    /// Attachment storage allowed per user, summed over their conversations
+    pub storage_quota_mb: u64,
+    pub import_max_size_mb: u64,
+    pub trash_retention_days: u32,
+    /// Let the chat LLM name topics from message excerpts; otherwise topics are named
+    /// by their keywords and no text leaves the server for it
+    pub topic_llm_labels: bool,
     pub rate_limit: RateLimitConfig,
     pub cors_origins: Vec<String>,
     pub cookie_security: CookieSecurityConfig,
+    pub embedding: EmbeddingConfig,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct EmbeddingConfig {
+    /// `openai`, `openai_compatible` or `local`
+    pub provider: String,
+    /// Sentence-transformers model directory the `local` provider runs in-process;
+    /// without it `local` uses hashed n-grams
+    pub model_path: Option<String>,
+    pub model: String,
+    pub dimensions: usize,
+    /// Base URL of an OpenAI-compatible server, e.g. `http://localhost:11434/v1`
+    pub api_base: Option<String>,
+    /// Key for the OpenAI-compatible server; OpenAI itself uses `OPENAI_API_KEY`
+    pub api_key: String,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -133,6 +156,10 @@ impl Default for AppConfig {
             redis_url: "redis://127.0.0.1:6379".to_string(),
             session_timeout_hours: 24,
             storage_path: "/tmp/workbench_storage".to_string(),
+            storage_quota_mb: 100,
+            import_max_size_mb: 256,
+            trash_retention_days: 30,
+            topic_llm_labels: true,
             rate_limit: RateLimitConfig {
                 global_requests_per_hour: 1000,
                 api_requests_per_hour: 100,
@@ -150,6 +177,14 @@ impl Default for AppConfig {
                 same_site: "Strict".to_string(),
                 environment: "development".to_string(),
             },
+            embedding: EmbeddingConfig {
+                provider: "openai".to_string(),
+                model: "text-embedding-3-small".to_string(),
+                model_path: None,
+                dimensions: 1536,
+                api_base: None,
+                api_key: String::new(),
+            },
         }
     }
 }
@@ -231,6 +266,23 @@ impl AppConfig {
         let storage_path =
             std::env::var("STORAGE_PATH").unwrap_or_else(|_| "/tmp/workbench_storage".to_string());
 
+        let storage_quota_mb = std::env::var("STORAGE_QUOTA_MB")
+            .unwrap_or_else(|_| "100".to_string())
+            .parse()
+            .unwrap_or(100);
+
+        // Upper bound for uploaded ChatGPT / Claude export archives
+        let import_max_size_mb = std::env::var("IMPORT_MAX_SIZE_MB")
+            .unwrap_or_else(|_| "256".to_string())
+            .parse()
+            .unwrap_or(256);
+
+        // Days a trashed conversation is kept before it is permanently deleted
+        let trash_retention_days = std::env::var("TRASH_RETENTION_DAYS")
+            .unwrap_or_else(|_| "30".to_string())
+            .parse()
+            .unwrap_or(30);
+
         // Rate limiting configuration
         let rate_limit = RateLimitConfig {
             global_requests_per_hour: std::env::var("RATE_LIMIT_GLOBAL_REQUESTS_PER_HOUR")
@@ -289,6 +341,35 @@ impl AppConfig {
             environment,
         };
 
+        // Embedding provider for search; `local` keeps message text in the process
+        let embedding_provider =
+            std::env::var("EMBEDDING_PROVIDER").unwrap_or_else(|_| "openai".to_string());
+        let model_path = std::env::var("EMBEDDING_MODEL_PATH").ok();
+        let (default_model, default_dimensions) = match embedding_provider.as_str() {
+            "local" if model_path.is_some() => ("all-MiniLM-L6-v2", 384),
+            "local" => (crate::embeddings::local::HASHED_NGRAMS_MODEL, 384),
+            "openai_compatible" => ("nomic-embed-text", 768),
+            _ => ("text-embedding-3-small", 1536),
+        };
+        let embedding = EmbeddingConfig {
+            model: std::env::var("EMBEDDING_MODEL").unwrap_or_else(|_| default_model.to_string()),
+            model_path,
+            dimensions: std::env::var("EMBEDDING_DIMENSIONS")
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(default_dimensions),
+            api_base: std::env::var("EMBEDDING_API_BASE").ok(),
+            api_key: std::env::var("EMBEDDING_API_KEY").unwrap_or_else(|_| String::new()),
+            provider: embedding_provider,
+        };
+
+        // Deployments that keep embeddings local don't send excerpts out for topic names
+        // unless asked to
+        let topic_llm_labels = std::env::var("TOPIC_LLM_LABELS")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(embedding.provider != "local");
+
         Ok(Self {
             bind_address,
             openai_api_key,
@@ -306,9 +387,14 @@ impl AppConfig {
             redis_url,
             session_timeout_hours,
             storage_path,
+            storage_quota_mb,
+            import_max_size_mb,
+            trash_retention_days,
+            topic_llm_labels,
             rate_limit,
             cors_origins,
             cookie_security,
+            embedding,
         })
     }
 }
@@ -1,14 +1,61 @@
 use anyhow::Result;
 use sqlx::{
+    pool::PoolConnection,
     postgres::{PgConnectOptions, PgPoolOptions},
-    Pool, Postgres,
+    PgConnection, Pool, Postgres, Transaction,
 };
-use std::time::Duration;
+use std::{
+    future::Future,
+    ops::{Deref, DerefMut},
+    sync::Arc,
+    time::Duration,
+};
+use tokio::sync::{MappedMutexGuard, Mutex, MutexGuard};
 use tracing::info;
 
-#[derive(Debug, Clone)]
+/// A transaction shared by every repository created inside a unit of work.
+/// Taken out of the mutex when it is committed or rolled back.
+type SharedTransaction = Arc<Mutex<Option<Transaction<'static, Postgres>>>>;
+
+#[derive(Clone)]
 pub struct Database {
     pub pool: Pool<Postgres>,
+    transaction: Option<SharedTransaction>,
+}
+
+impl std::fmt::Debug for Database {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Database")
+            .field("pool", &self.pool)
+            .field("in_transaction", &self.transaction.is_some())
+            .finish()
+    }
+}
+
+/// The connection a repository runs its next query on
+pub enum DbConnection<'a> {
+    Pool(PoolConnection<Postgres>),
+    Transaction(MappedMutexGuard<'a, Transaction<'static, Postgres>>),
+}
+
+impl Deref for DbConnection<'_> {
+    type Target = PgConnection;
+
+    fn deref(&self) -> &PgConnection {
+        match self {
+            DbConnection::Pool(connection) => connection,
+            DbConnection::Transaction(transaction) => transaction,
+        }
+    }
+}
+
+impl DerefMut for DbConnection<'_> {
+    fn deref_mut(&mut self) -> &mut PgConnection {
+        match self {
+            DbConnection::Pool(connection) => connection,
+            DbConnection::Transaction(transaction) => transaction,
+        }
+    }
 }
 
 impl Database {
@@ -42,7 +89,10 @@ impl Database {
         // sqlx::migrate!("./migrations").run(&pool).await?;
         // info!("Database migrations completed successfully");
 
-        Ok(Self { pool })
+        Ok(Self {
+            pool,
+            transaction: None,
+        })
     }
 
     pub async fn new(database_url: &str) -> Result<Self> {
@@ -62,7 +112,10 @@ impl Database {
         // sqlx::migrate!("./migrations").run(&pool).await?;
         // info!("Database migrations completed successfully");
 
-        Ok(Self { pool })
+        Ok(Self {
+            pool,
+            transaction: None,
+        })
     }
 
     pub async fn health_check(&self) -> Result<()> {
@@ -73,6 +126,64 @@ impl Database {
     pub fn pool(&self) -> Pool<Postgres> {
         self.pool.clone()
     }
+
+    /// The shared transaction inside a unit of work, otherwise a connection from the pool.
+    /// Drop it before running the next query; inside a transaction queries take turns.
+    pub async fn connection(&self) -> Result<DbConnection<'_>> {
+        match &self.transaction {
+            None => Ok(DbConnection::Pool(self.pool.acquire().await?)),
+            Some(transaction) => {
+                let guard = transaction.lock().await;
+                let guard = MutexGuard::try_map(guard, |t| t.as_mut())
+                    .map_err(|_| anyhow::anyhow!("Transaction has already finished"))?;
+                Ok(DbConnection::Transaction(guard))
+            }
+        }
+    }
+
+    pub fn in_transaction(&self) -> bool {
+        self.transaction.is_some()
+    }
+
+    /// Run `work` with a `Database` whose queries all go through one transaction. It is
+    /// committed when `work` returns `Ok` and rolled back when it fails. Inside an existing
+    /// transaction `work` simply joins it.
+    pub async fn transaction<T, F, Fut>(&self, work: F) -> Result<T>
+    where
+        F: FnOnce(Database) -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        if self.in_transaction() {
+            return work(self.clone()).await;
+        }
+
+        let shared: SharedTransaction = Arc::new(Mutex::new(Some(self.pool.begin().await?)));
+        let database = Database {
+            pool: self.pool.clone(),
+            transaction: Some(shared.clone()),
+        };
+
+        let result = work(database).await;
+
+        // Anything still holding the transaction can no longer use it
+        let transaction = shared
+            .lock()
+            .await
+            .take()
+            .ok_or_else(|| anyhow::anyhow!("Transaction has already finished"))?;
+        match result {
+            Ok(value) => {
+                transaction.commit().await?;
+                Ok(value)
+            }
+            Err(e) => {
+                if let Err(rollback) = transaction.rollback().await {
+                    tracing::warn!("Failed to roll back transaction: {}", rollback);
+                }
+                Err(e)
+            }
+        }
+    }
 }
 
 // Database configuration struct
@@ -0,0 +1,91 @@
+use async_trait::async_trait;
+use std::collections::HashMap;
+
+use super::EmbeddingProvider;
+use crate::error::AppError;
+
+/// Weight of a whole word relative to one of its character trigrams
+const WORD_WEIGHT: f32 = 1.0;
+const TRIGRAM_WEIGHT: f32 = 0.5;
+
+/// The only model name the local provider answers to; stored with its vectors
+pub const HASHED_NGRAMS_MODEL: &str = "hashed-ngrams-v1";
+
+/// In-process embeddings that never leave the server. This is not a language model:
+/// words and their character trigrams are hashed into a fixed number of buckets with
+/// a random sign, weighted by log term frequency and normalized, so cosine similarity
+/// measures shared vocabulary, including partial matches of identifiers and inflected
+/// words, but not meaning. It needs no model files or native runtime. Set
+/// `EMBEDDING_MODEL_PATH` to run a real encoder in-process instead.
+#[derive(Debug, Clone)]
+pub struct LocalEmbeddingProvider {
+    dimensions: usize,
+}
+
+impl LocalEmbeddingProvider {
+    pub fn new(dimensions: usize) -> Self {
+        Self { dimensions }
+    }
+
+    pub fn embed_text(&self, text: &str) -> Vec<f32> {
+        let mut features: HashMap<String, (f32, u32)> = HashMap::new();
+
+        for word in text
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+        {
+            let word = word.to_lowercase();
+            let padded: Vec<char> = format!("#{}#", word).chars().collect();
+            for trigram in padded.windows(3) {
+                let entry = features
+                    .entry(format!("t:{}", trigram.iter().collect::<String>()))
+                    .or_insert((TRIGRAM_WEIGHT, 0));
+                entry.1 += 1;
+            }
+            features
+                .entry(format!("w:{}", word))
+                .or_insert((WORD_WEIGHT, 0))
+                .1 += 1;
+        }
+
+        let mut vector = vec![0.0f32; self.dimensions];
+        for (feature, (weight, count)) in features {
+            let hash = fnv1a(feature.as_bytes());
+            let bucket = (hash % self.dimensions as u64) as usize;
+            let sign = if hash >> 63 == 0 { 1.0 } else { -1.0 };
+            vector[bucket] += sign * weight * (1.0 + (count as f32).ln());
+        }
+
+        let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+        if norm > 0.0 {
+            vector.iter_mut().for_each(|v| *v /= norm);
+        }
+        vector
+    }
+}
+
+/// 64-bit FNV-1a; stable across platforms and releases, unlike std's hasher
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[async_trait]
+impl EmbeddingProvider for LocalEmbeddingProvider {
+    fn model(&self) -> &str {
+        HASHED_NGRAMS_MODEL
+    }
+
+    fn dimensions(&self) -> usize {
+        self.dimensions
+    }
+
+    fn is_configured(&self) -> bool {
+        true
+    }
+
+    async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>, AppError> {
+        Ok(texts.iter().map(|text| self.embed_text(text)).collect())
+    }
+}
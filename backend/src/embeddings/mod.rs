@@ -0,0 +1,116 @@
+pub mod local;
+pub mod openai;
+pub mod sentence_transformer;
+
+use async_trait::async_trait;
+use std::{fmt::Debug, sync::Arc};
+
+use crate::{config::EmbeddingConfig, error::AppError};
+
+/// Turns text into vectors for semantic search. The model name and dimension are
+/// stored with every embedding, so vectors of different models are never compared.
+#[async_trait]
+pub trait EmbeddingProvider: Debug + Send + Sync {
+    /// Model name stored with each embedding
+    fn model(&self) -> &str;
+
+    /// Length of every returned vector
+    fn dimensions(&self) -> usize;
+
+    /// Whether the provider can embed at all, e.g. has an API key
+    fn is_configured(&self) -> bool;
+
+    /// Embed several texts, returning one vector per text in input order
+    async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>, AppError>;
+}
+
+/// Create the provider selected by `EMBEDDING_PROVIDER`
+pub fn create_embedding_provider(
+    config: &EmbeddingConfig,
+    openai_api_key: &str,
+) -> Result<Arc<dyn EmbeddingProvider>, AppError> {
+    if config.dimensions == 0 {
+        return Err(AppError::BadRequest(
+            "Embedding dimensions must be positive".to_string(),
+        ));
+    }
+
+    match config.provider.as_str() {
+        "openai" => Ok(Arc::new(openai::OpenAIEmbeddingProvider::openai(
+            openai_api_key,
+            &config.model,
+            config.dimensions,
+        ))),
+        "openai_compatible" => {
+            let api_base = config.api_base.as_deref().ok_or_else(|| {
+                AppError::BadRequest(
+                    "EMBEDDING_API_BASE is required for the openai_compatible provider".to_string(),
+                )
+            })?;
+            Ok(Arc::new(openai::OpenAIEmbeddingProvider::compatible(
+                api_base,
+                &config.api_key,
+                &config.model,
+                config.dimensions,
+            )))
+        }
+        "local" => {
+            if let Some(model_path) = &config.model_path {
+                let provider = sentence_transformer::SentenceTransformerProvider::load(
+                    model_path,
+                    &config.model,
+                )?;
+                if provider.dimensions() != config.dimensions {
+                    return Err(AppError::BadRequest(format!(
+                        "The model in {} has {} dimensions; set EMBEDDING_DIMENSIONS={}",
+                        model_path,
+                        provider.dimensions(),
+                        provider.dimensions()
+                    )));
+                }
+                return Ok(Arc::new(provider));
+            }
+
+            // Vectors are stored under the model name, so hashed vectors must not
+            // pose as those of a real encoder
+            if config.model != local::HASHED_NGRAMS_MODEL {
+                return Err(AppError::BadRequest(format!(
+                    "Without EMBEDDING_MODEL_PATH the local embedding provider only supports \
+                     EMBEDDING_MODEL={}, not '{}'",
+                    local::HASHED_NGRAMS_MODEL,
+                    config.model
+                )));
+            }
+            Ok(Arc::new(local::LocalEmbeddingProvider::new(
+                config.dimensions,
+            )))
+        }
+        other => Err(AppError::BadRequest(format!(
+            "Unknown embedding provider '{}'",
+            other
+        ))),
+    }
+}
+
+/// Check that a provider returned one vector of the expected length per input
+pub(crate) fn check_embeddings(
+    embeddings: &[Vec<f32>],
+    expected_count: usize,
+    dimensions: usize,
+) -> Result<(), AppError> {
+    if embeddings.len() != expected_count {
+        return Err(AppError::Embedding(format!(
+            "Expected {} embeddings, got {}",
+            expected_count,
+            embeddings.len()
+        )));
+    }
+    if let Some(embedding) = embeddings.iter().find(|e| e.len() != dimensions) {
+        return Err(AppError::Embedding(format!(
+            "Expected {} dimensions, got {}; set EMBEDDING_DIMENSIONS to the model's size",
+            dimensions,
+            embedding.len()
+        )));
+    }
+    Ok(())
+}
@@ -0,0 +1,99 @@
+use async_openai::{
+    config::OpenAIConfig,
+    types::{CreateEmbeddingRequest, EmbeddingInput},
+    Client as OpenAIClient,
+};
+use async_trait::async_trait;
+
+use super::{check_embeddings, EmbeddingProvider};
+use crate::error::AppError;
+
+/// Embeddings from OpenAI, or from a server speaking the same API such as Ollama,
+/// vLLM or text-embeddings-inference running next to the backend
+#[derive(Debug, Clone)]
+pub struct OpenAIEmbeddingProvider {
+    client: OpenAIClient<OpenAIConfig>,
+    model: String,
+    dimensions: usize,
+    configured: bool,
+    /// OpenAI proper; it accepts the dimensions parameter and reports OpenAI errors
+    hosted: bool,
+}
+
+impl OpenAIEmbeddingProvider {
+    pub fn openai(api_key: &str, model: &str, dimensions: usize) -> Self {
+        Self {
+            client: OpenAIClient::with_config(OpenAIConfig::new().with_api_key(api_key)),
+            model: model.to_string(),
+            dimensions,
+            configured: !api_key.is_empty(),
+            hosted: true,
+        }
+    }
+
+    /// A self-hosted OpenAI-compatible endpoint. Most do not need a key.
+    pub fn compatible(api_base: &str, api_key: &str, model: &str, dimensions: usize) -> Self {
+        let config = OpenAIConfig::new()
+            .with_api_base(api_base.trim_end_matches('/'))
+            .with_api_key(api_key);
+        Self {
+            client: OpenAIClient::with_config(config),
+            model: model.to_string(),
+            dimensions,
+            configured: !api_base.is_empty(),
+            hosted: false,
+        }
+    }
+
+    fn error(&self, message: String) -> AppError {
+        if self.hosted {
+            AppError::OpenAI(message)
+        } else {
+            AppError::Embedding(message)
+        }
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for OpenAIEmbeddingProvider {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn dimensions(&self) -> usize {
+        self.dimensions
+    }
+
+    fn is_configured(&self) -> bool {
+        self.configured
+    }
+
+    async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>, AppError> {
+        let expected = texts.len();
+        let request = CreateEmbeddingRequest {
+            model: self.model.clone(),
+            input: EmbeddingInput::StringArray(texts),
+            encoding_format: None,
+            // Only OpenAI's text-embedding-3 models can shorten their vectors
+            dimensions: self.hosted.then_some(self.dimensions as u32),
+            user: None,
+        };
+
+        let response = self
+            .client
+            .embeddings()
+            .create(request)
+            .await
+            .map_err(|e| self.error(format!("Failed to generate embeddings: {}", e)))?;
+
+        let mut data = response.data;
+        data.sort_by_key(|embedding| embedding.index);
+        let embeddings: Vec<Vec<f32>> = data
+            .into_iter()
+            .map(|embedding| embedding.embedding)
+            .collect();
+
+        check_embeddings(&embeddings, expected, self.dimensions)?;
+        Ok(embeddings)
+    }
+}
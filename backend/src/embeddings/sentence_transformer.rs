@@ -0,0 +1,839 @@
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::{collections::HashMap, path::Path, sync::Arc};
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+use super::EmbeddingProvider;
+use crate::error::AppError;
+
+/// Longest input in word pieces, counting `[CLS]` and `[SEP]`, when the model
+/// directory doesn't say; the limit sentence-transformers uses for MiniLM
+const DEFAULT_MAX_SEQ_LENGTH: usize = 256;
+
+/// Words longer than this many characters become `[UNK]`, as in BERT's tokenizer
+const MAX_WORD_CHARS: usize = 100;
+
+/// A BERT sentence encoder such as `all-MiniLM-L6-v2`, run in the server process.
+/// Loads a sentence-transformers model directory as published on Hugging Face:
+/// `config.json`, `vocab.txt` and `model.safetensors`, plus `sentence_bert_config.json`
+/// and `tokenizer_config.json` when present. Texts are embedded as the normalized
+/// mean of the last layer's token states, which is how these models are trained.
+#[derive(Debug, Clone)]
+pub struct SentenceTransformerProvider {
+    model: String,
+    encoder: Arc<BertEncoder>,
+}
+
+impl SentenceTransformerProvider {
+    /// Load the model in `path`; `model` is the name stored with its vectors
+    pub fn load(path: impl AsRef<Path>, model: &str) -> Result<Self, AppError> {
+        let path = path.as_ref();
+        let encoder = BertEncoder::load(path).map_err(|e| {
+            AppError::Embedding(format!(
+                "Failed to load embedding model from {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        tracing::info!(
+            "Loaded embedding model {} from {} ({} layers, {} dimensions)",
+            model,
+            path.display(),
+            encoder.layers.len(),
+            encoder.hidden_size
+        );
+
+        Ok(Self {
+            model: model.to_string(),
+            encoder: Arc::new(encoder),
+        })
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for SentenceTransformerProvider {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn dimensions(&self) -> usize {
+        self.encoder.hidden_size
+    }
+
+    fn is_configured(&self) -> bool {
+        true
+    }
+
+    async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>, AppError> {
+        // Inference is CPU-bound; keep it off the async workers
+        let encoder = self.encoder.clone();
+        tokio::task::spawn_blocking(move || texts.iter().map(|text| encoder.embed(text)).collect())
+            .await
+            .map_err(|e| AppError::Embedding(format!("Embedding task failed: {}", e)))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct BertConfig {
+    hidden_size: usize,
+    num_attention_heads: usize,
+    num_hidden_layers: usize,
+    intermediate_size: usize,
+    max_position_embeddings: usize,
+    #[serde(default = "default_layer_norm_eps")]
+    layer_norm_eps: f32,
+    #[serde(default = "default_hidden_act")]
+    hidden_act: String,
+}
+
+fn default_layer_norm_eps() -> f32 {
+    1e-12
+}
+
+fn default_hidden_act() -> String {
+    "gelu".to_string()
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct SentenceBertConfig {
+    max_seq_length: Option<usize>,
+    do_lower_case: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct TokenizerConfig {
+    do_lower_case: Option<bool>,
+}
+
+#[derive(Debug)]
+struct BertEncoder {
+    tokenizer: WordPieceTokenizer,
+    hidden_size: usize,
+    num_heads: usize,
+    max_tokens: usize,
+    word_embeddings: Vec<f32>,
+    position_embeddings: Vec<f32>,
+    /// The embedding of token type 0; a single text has no second segment
+    token_type_embedding: Vec<f32>,
+    embedding_norm: LayerNorm,
+    layers: Vec<EncoderLayer>,
+}
+
+#[derive(Debug)]
+struct EncoderLayer {
+    query: Linear,
+    key: Linear,
+    value: Linear,
+    attention_output: Linear,
+    attention_norm: LayerNorm,
+    intermediate: Linear,
+    output: Linear,
+    output_norm: LayerNorm,
+}
+
+impl BertEncoder {
+    fn load(path: &Path) -> Result<Self, String> {
+        let config: BertConfig = read_json(&path.join("config.json"))?;
+        if config.hidden_act != "gelu" {
+            return Err(format!("unsupported activation '{}'", config.hidden_act));
+        }
+        if config.num_attention_heads == 0
+            || !config
+                .hidden_size
+                .is_multiple_of(config.num_attention_heads)
+        {
+            return Err("hidden size is not a multiple of the attention heads".to_string());
+        }
+        let sentence_config: SentenceBertConfig =
+            read_optional_json(&path.join("sentence_bert_config.json"))?;
+        let tokenizer_config: TokenizerConfig =
+            read_optional_json(&path.join("tokenizer_config.json"))?;
+
+        let vocab = std::fs::read_to_string(path.join("vocab.txt"))
+            .map_err(|e| format!("vocab.txt: {}", e))?;
+        let lowercase = sentence_config
+            .do_lower_case
+            .or(tokenizer_config.do_lower_case)
+            .unwrap_or(true);
+        let tokenizer = WordPieceTokenizer::new(vocab.lines(), lowercase)?;
+
+        let weights_path = path.join("model.safetensors");
+        let bytes =
+            std::fs::read(&weights_path).map_err(|e| format!("model.safetensors: {}", e))?;
+        let mut weights = Weights {
+            tensors: read_safetensors(&bytes)?,
+        };
+
+        let hidden = config.hidden_size;
+        let eps = config.layer_norm_eps;
+        let word_embeddings = weights.take(
+            "embeddings.word_embeddings.weight",
+            &[tokenizer.vocab_size(), hidden],
+        )?;
+        let position_embeddings = weights.take(
+            "embeddings.position_embeddings.weight",
+            &[config.max_position_embeddings, hidden],
+        )?;
+        let token_type_embeddings =
+            weights.take_any_rows("embeddings.token_type_embeddings.weight", hidden)?;
+        let embedding_norm = weights.layer_norm("embeddings.LayerNorm", hidden, eps)?;
+
+        let layers = (0..config.num_hidden_layers)
+            .map(|index| {
+                let prefix = format!("encoder.layer.{}", index);
+                let intermediate = config.intermediate_size;
+                Ok(EncoderLayer {
+                    query: weights.linear(
+                        &format!("{prefix}.attention.self.query"),
+                        hidden,
+                        hidden,
+                    )?,
+                    key: weights.linear(&format!("{prefix}.attention.self.key"), hidden, hidden)?,
+                    value: weights.linear(
+                        &format!("{prefix}.attention.self.value"),
+                        hidden,
+                        hidden,
+                    )?,
+                    attention_output: weights.linear(
+                        &format!("{prefix}.attention.output.dense"),
+                        hidden,
+                        hidden,
+                    )?,
+                    attention_norm: weights.layer_norm(
+                        &format!("{prefix}.attention.output.LayerNorm"),
+                        hidden,
+                        eps,
+                    )?,
+                    intermediate: weights.linear(
+                        &format!("{prefix}.intermediate.dense"),
+                        hidden,
+                        intermediate,
+                    )?,
+                    output: weights.linear(
+                        &format!("{prefix}.output.dense"),
+                        intermediate,
+                        hidden,
+                    )?,
+                    output_norm: weights.layer_norm(
+                        &format!("{prefix}.output.LayerNorm"),
+                        hidden,
+                        eps,
+                    )?,
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        let max_tokens = sentence_config
+            .max_seq_length
+            .unwrap_or(DEFAULT_MAX_SEQ_LENGTH)
+            .min(config.max_position_embeddings);
+
+        Ok(Self {
+            tokenizer,
+            hidden_size: hidden,
+            num_heads: config.num_attention_heads,
+            max_tokens,
+            word_embeddings,
+            position_embeddings,
+            token_type_embedding: token_type_embeddings[..hidden].to_vec(),
+            embedding_norm,
+            layers,
+        })
+    }
+
+    /// Mean of the token states of the last layer, normalized to unit length
+    fn embed(&self, text: &str) -> Vec<f32> {
+        let tokens = self.tokenizer.encode(text, self.max_tokens);
+        let hidden = self.hidden_size;
+        let count = tokens.len();
+
+        let mut states = Vec::with_capacity(count * hidden);
+        for (position, token) in tokens.iter().enumerate() {
+            let word = &self.word_embeddings[token * hidden..(token + 1) * hidden];
+            let place = &self.position_embeddings[position * hidden..(position + 1) * hidden];
+            states.extend(
+                word.iter()
+                    .zip(place)
+                    .zip(&self.token_type_embedding)
+                    .map(|((w, p), t)| w + p + t),
+            );
+        }
+        self.embedding_norm.apply(&mut states);
+
+        for layer in &self.layers {
+            states = self.encoder_layer(layer, states, count);
+        }
+
+        let mut pooled = vec![0.0f32; hidden];
+        for row in states.chunks_exact(hidden) {
+            pooled.iter_mut().zip(row).for_each(|(sum, v)| *sum += v);
+        }
+        let norm = pooled.iter().map(|v| v * v).sum::<f32>().sqrt();
+        if norm > 0.0 {
+            pooled.iter_mut().for_each(|v| *v /= norm);
+        }
+        pooled
+    }
+
+    fn encoder_layer(&self, layer: &EncoderLayer, states: Vec<f32>, count: usize) -> Vec<f32> {
+        let hidden = self.hidden_size;
+        let head_size = hidden / self.num_heads;
+        let scale = 1.0 / (head_size as f32).sqrt();
+
+        let query = layer.query.forward(&states);
+        let key = layer.key.forward(&states);
+        let value = layer.value.forward(&states);
+
+        let mut context = vec![0.0f32; count * hidden];
+        let mut scores = vec![0.0f32; count];
+        for head in 0..self.num_heads {
+            let columns = head * head_size..(head + 1) * head_size;
+            for i in 0..count {
+                let q = &query[i * hidden..][columns.clone()];
+                for (j, score) in scores.iter_mut().enumerate() {
+                    *score = dot(q, &key[j * hidden..][columns.clone()]) * scale;
+                }
+                softmax(&mut scores);
+
+                let out = &mut context[i * hidden..][columns.clone()];
+                for (j, weight) in scores.iter().enumerate() {
+                    let v = &value[j * hidden..][columns.clone()];
+                    out.iter_mut().zip(v).for_each(|(o, v)| *o += weight * v);
+                }
+            }
+        }
+
+        let mut attended = layer.attention_output.forward(&context);
+        attended.iter_mut().zip(&states).for_each(|(a, s)| *a += s);
+        layer.attention_norm.apply(&mut attended);
+
+        let mut intermediate = layer.intermediate.forward(&attended);
+        intermediate.iter_mut().for_each(|v| *v = gelu(*v));
+        let mut output = layer.output.forward(&intermediate);
+        output.iter_mut().zip(&attended).for_each(|(o, a)| *o += a);
+        layer.output_norm.apply(&mut output);
+        output
+    }
+}
+
+/// A dense layer; `weight` is stored row-major as `[out, in]` like PyTorch's
+#[derive(Debug)]
+struct Linear {
+    weight: Vec<f32>,
+    bias: Vec<f32>,
+    in_features: usize,
+}
+
+impl Linear {
+    /// Apply the layer to every row of `input`
+    fn forward(&self, input: &[f32]) -> Vec<f32> {
+        let out_features = self.bias.len();
+        let mut output = Vec::with_capacity(input.len() / self.in_features * out_features);
+        for row in input.chunks_exact(self.in_features) {
+            output.extend(
+                self.weight
+                    .chunks_exact(self.in_features)
+                    .zip(&self.bias)
+                    .map(|(weights, bias)| dot(row, weights) + bias),
+            );
+        }
+        output
+    }
+}
+
+#[derive(Debug)]
+struct LayerNorm {
+    weight: Vec<f32>,
+    bias: Vec<f32>,
+    eps: f32,
+}
+
+impl LayerNorm {
+    /// Normalize every row of `states` in place
+    fn apply(&self, states: &mut [f32]) {
+        for row in states.chunks_exact_mut(self.weight.len()) {
+            let mean = row.iter().sum::<f32>() / row.len() as f32;
+            let variance =
+                row.iter().map(|v| (v - mean) * (v - mean)).sum::<f32>() / row.len() as f32;
+            let scale = 1.0 / (variance + self.eps).sqrt();
+            for ((v, w), b) in row.iter_mut().zip(&self.weight).zip(&self.bias) {
+                *v = (*v - mean) * scale * w + b;
+            }
+        }
+    }
+}
+
+/// Dot product with independent lanes, so the compiler can vectorize it
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    let mut lanes = [0.0f32; 8];
+    let mut a_chunks = a.chunks_exact(8);
+    let mut b_chunks = b.chunks_exact(8);
+    for (x, y) in (&mut a_chunks).zip(&mut b_chunks) {
+        for lane in 0..8 {
+            lanes[lane] += x[lane] * y[lane];
+        }
+    }
+    let rest: f32 = a_chunks
+        .remainder()
+        .iter()
+        .zip(b_chunks.remainder())
+        .map(|(x, y)| x * y)
+        .sum();
+    lanes.iter().sum::<f32>() + rest
+}
+
+fn softmax(values: &mut [f32]) {
+    let max = values.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let mut sum = 0.0;
+    for v in values.iter_mut() {
+        *v = (*v - max).exp();
+        sum += *v;
+    }
+    values.iter_mut().for_each(|v| *v /= sum);
+}
+
+/// BERT's exact GELU, not the tanh approximation
+fn gelu(x: f32) -> f32 {
+    0.5 * x * (1.0 + libm::erff(x / std::f32::consts::SQRT_2))
+}
+
+/// Lowercasing, accent stripping, punctuation splitting and greedy longest-match
+/// word pieces, as done by BERT's uncased tokenizer
+#[derive(Debug)]
+struct WordPieceTokenizer {
+    vocab: HashMap<String, usize>,
+    lowercase: bool,
+    unknown: usize,
+    start: usize,
+    end: usize,
+}
+
+impl WordPieceTokenizer {
+    fn new<'a>(tokens: impl Iterator<Item = &'a str>, lowercase: bool) -> Result<Self, String> {
+        let vocab: HashMap<String, usize> = tokens
+            .enumerate()
+            .map(|(id, token)| (token.trim_end().to_string(), id))
+            .collect();
+        let special = |token: &str| {
+            vocab
+                .get(token)
+                .copied()
+                .ok_or_else(|| format!("vocab.txt has no {} token", token))
+        };
+
+        Ok(Self {
+            unknown: special("[UNK]")?,
+            start: special("[CLS]")?,
+            end: special("[SEP]")?,
+            vocab,
+            lowercase,
+        })
+    }
+
+    fn vocab_size(&self) -> usize {
+        self.vocab.values().max().map_or(0, |id| id + 1)
+    }
+
+    /// Token ids of `text` between `[CLS]` and `[SEP]`, at most `max_tokens` in all
+    fn encode(&self, text: &str, max_tokens: usize) -> Vec<usize> {
+        let mut ids = vec![self.start];
+        for word in self.split_words(text) {
+            ids.extend(self.word_pieces(&word));
+            if ids.len() >= max_tokens - 1 {
+                ids.truncate(max_tokens - 1);
+                break;
+            }
+        }
+        ids.push(self.end);
+        ids
+    }
+
+    fn split_words(&self, text: &str) -> Vec<String> {
+        let mut words = Vec::new();
+        let mut word = String::new();
+        let text: String = if self.lowercase {
+            text.to_lowercase()
+                .nfd()
+                .filter(|c| !is_combining_mark(*c))
+                .collect()
+        } else {
+            text.to_string()
+        };
+
+        for c in text.chars() {
+            if c.is_whitespace() || c.is_control() || c == '\u{fffd}' {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+            } else if is_punctuation(c) || is_cjk(c) {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+                words.push(c.to_string());
+            } else {
+                word.push(c);
+            }
+        }
+        if !word.is_empty() {
+            words.push(word);
+        }
+        words
+    }
+
+    fn word_pieces(&self, word: &str) -> Vec<usize> {
+        let chars: Vec<char> = word.chars().collect();
+        if chars.len() > MAX_WORD_CHARS {
+            return vec![self.unknown];
+        }
+
+        let mut pieces = Vec::new();
+        let mut start = 0;
+        while start < chars.len() {
+            let piece = (start + 1..=chars.len()).rev().find_map(|end| {
+                let mut candidate: String = chars[start..end].iter().collect();
+                if start > 0 {
+                    candidate.insert_str(0, "##");
+                }
+                self.vocab.get(&candidate).map(|id| (*id, end))
+            });
+            match piece {
+                Some((id, end)) => {
+                    pieces.push(id);
+                    start = end;
+                }
+                None => return vec![self.unknown],
+            }
+        }
+        pieces
+    }
+}
+
+/// ASCII punctuation and the general and CJK punctuation blocks
+fn is_punctuation(c: char) -> bool {
+    c.is_ascii_punctuation()
+        || ('\u{2000}'..='\u{206f}').contains(&c)
+        || ('\u{3000}'..='\u{303f}').contains(&c)
+}
+
+/// CJK ideographs are tokenized one character at a time
+fn is_cjk(c: char) -> bool {
+    matches!(c as u32,
+        0x4e00..=0x9fff
+        | 0x3400..=0x4dbf
+        | 0x20000..=0x2a6df
+        | 0x2a700..=0x2b73f
+        | 0x2b740..=0x2b81f
+        | 0x2b820..=0x2ceaf
+        | 0xf900..=0xfaff
+        | 0x2f800..=0x2fa1f)
+}
+
+fn read_json<T: for<'de> Deserialize<'de>>(path: &Path) -> Result<T, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    serde_json::from_str(&text).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+fn read_optional_json<T: for<'de> Deserialize<'de> + Default>(path: &Path) -> Result<T, String> {
+    if path.exists() {
+        read_json(path)
+    } else {
+        Ok(T::default())
+    }
+}
+
+#[derive(Debug)]
+struct Tensor {
+    shape: Vec<usize>,
+    data: Vec<f32>,
+}
+
+/// Tensors of a checkpoint, looked up without the `bert.` prefix some exports add
+struct Weights {
+    tensors: HashMap<String, Tensor>,
+}
+
+impl Weights {
+    fn take_tensor(&mut self, name: &str) -> Result<Tensor, String> {
+        self.tensors
+            .remove(name)
+            .or_else(|| self.tensors.remove(&format!("bert.{}", name)))
+            .ok_or_else(|| format!("model.safetensors has no tensor {}", name))
+    }
+
+    fn take(&mut self, name: &str, shape: &[usize]) -> Result<Vec<f32>, String> {
+        let tensor = self.take_tensor(name)?;
+        if tensor.shape != shape {
+            return Err(format!(
+                "tensor {} has shape {:?}, expected {:?}",
+                name, tensor.shape, shape
+            ));
+        }
+        Ok(tensor.data)
+    }
+
+    /// A tensor of any number of rows of `width` values
+    fn take_any_rows(&mut self, name: &str, width: usize) -> Result<Vec<f32>, String> {
+        let tensor = self.take_tensor(name)?;
+        match tensor.shape.as_slice() {
+            [rows, columns] if *rows > 0 && *columns == width => Ok(tensor.data),
+            shape => Err(format!("tensor {} has shape {:?}", name, shape)),
+        }
+    }
+
+    fn linear(
+        &mut self,
+        name: &str,
+        in_features: usize,
+        out_features: usize,
+    ) -> Result<Linear, String> {
+        Ok(Linear {
+            weight: self.take(&format!("{}.weight", name), &[out_features, in_features])?,
+            bias: self.take(&format!("{}.bias", name), &[out_features])?,
+            in_features,
+        })
+    }
+
+    fn layer_norm(&mut self, name: &str, size: usize, eps: f32) -> Result<LayerNorm, String> {
+        Ok(LayerNorm {
+            weight: self.take(&format!("{}.weight", name), &[size])?,
+            bias: self.take(&format!("{}.bias", name), &[size])?,
+            eps,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct TensorInfo {
+    dtype: String,
+    shape: Vec<usize>,
+    data_offsets: (usize, usize),
+}
+
+/// Parse a safetensors file: a little-endian header length, a JSON header of tensor
+/// names, types, shapes and byte ranges, then the data. Half-precision weights are
+/// widened to f32.
+fn read_safetensors(bytes: &[u8]) -> Result<HashMap<String, Tensor>, String> {
+    let header_len = bytes
+        .get(..8)
+        .map(|len| u64::from_le_bytes(len.try_into().unwrap()) as usize)
+        .ok_or("model.safetensors is truncated")?;
+    let header = bytes
+        .get(8..8 + header_len)
+        .ok_or("model.safetensors is truncated")?;
+    let data = &bytes[8 + header_len..];
+
+    let mut entries: HashMap<String, serde_json::Value> =
+        serde_json::from_slice(header).map_err(|e| format!("model.safetensors header: {}", e))?;
+    entries.remove("__metadata__");
+
+    let mut tensors = HashMap::new();
+    for (name, entry) in entries {
+        let info: TensorInfo =
+            serde_json::from_value(entry).map_err(|e| format!("tensor {}: {}", name, e))?;
+        let (start, end) = info.data_offsets;
+        let raw = data
+            .get(start..end)
+            .ok_or_else(|| format!("tensor {} lies outside the file", name))?;
+        let values: Vec<f32> = match info.dtype.as_str() {
+            "F32" => raw
+                .chunks_exact(4)
+                .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                .collect(),
+            "F16" => raw
+                .chunks_exact(2)
+                .map(|b| f16_to_f32(u16::from_le_bytes([b[0], b[1]])))
+                .collect(),
+            "BF16" => raw
+                .chunks_exact(2)
+                .map(|b| f32::from_bits((u16::from_le_bytes([b[0], b[1]]) as u32) << 16))
+                .collect(),
+            other => return Err(format!("tensor {} has unsupported type {}", name, other)),
+        };
+        if values.len() != info.shape.iter().product::<usize>() {
+            return Err(format!("tensor {} doesn't match its shape", name));
+        }
+        tensors.insert(
+            name,
+            Tensor {
+                shape: info.shape,
+                data: values,
+            },
+        );
+    }
+    Ok(tensors)
+}
+
+fn f16_to_f32(bits: u16) -> f32 {
+    let sign = if bits >> 15 == 1 { -1.0 } else { 1.0 };
+    let exponent = ((bits >> 10) & 0x1f) as i32;
+    let fraction = (bits & 0x3ff) as f32;
+    match exponent {
+        0 => sign * fraction * 2f32.powi(-24),
+        0x1f if fraction == 0.0 => sign * f32::INFINITY,
+        0x1f => f32::NAN,
+        _ => sign * (1.0 + fraction / 1024.0) * 2f32.powi(exponent - 15),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VOCAB: &[&str] = &[
+        "[PAD]",
+        "[UNK]",
+        "[CLS]",
+        "[SEP]",
+        "the",
+        "pool",
+        "database",
+        "connection",
+        "un",
+        "##aff",
+        "##able",
+        "cafe",
+        ",",
+        "!",
+        "bread",
+        "banana",
+        "##s",
+    ];
+
+    fn tokenizer() -> WordPieceTokenizer {
+        WordPieceTokenizer::new(VOCAB.iter().copied(), true).unwrap()
+    }
+
+    /// Write a tiny model with deterministic pseudo-random weights
+    fn write_model(dir: &Path, hidden: usize, heads: usize, layers: usize) {
+        let intermediate = hidden * 2;
+        std::fs::write(dir.join("vocab.txt"), VOCAB.join("\n")).unwrap();
+        std::fs::write(
+            dir.join("config.json"),
+            serde_json::json!({
+                "hidden_size": hidden,
+                "num_attention_heads": heads,
+                "num_hidden_layers": layers,
+                "intermediate_size": intermediate,
+                "max_position_embeddings": 32,
+                "hidden_act": "gelu",
+            })
+            .to_string(),
+        )
+        .unwrap();
+
+        let mut shapes: Vec<(String, Vec<usize>)> = vec![
+            (
+                "embeddings.word_embeddings.weight".into(),
+                vec![VOCAB.len(), hidden],
+            ),
+            (
+                "embeddings.position_embeddings.weight".into(),
+                vec![32, hidden],
+            ),
+            (
+                "embeddings.token_type_embeddings.weight".into(),
+                vec![2, hidden],
+            ),
+            ("embeddings.LayerNorm.weight".into(), vec![hidden]),
+            ("embeddings.LayerNorm.bias".into(), vec![hidden]),
+        ];
+        for layer in 0..layers {
+            let p = format!("bert.encoder.layer.{}", layer);
+            for (name, out, inp) in [
+                ("attention.self.query", hidden, hidden),
+                ("attention.self.key", hidden, hidden),
+                ("attention.self.value", hidden, hidden),
+                ("attention.output.dense", hidden, hidden),
+                ("intermediate.dense", intermediate, hidden),
+                ("output.dense", hidden, intermediate),
+            ] {
+                shapes.push((format!("{p}.{name}.weight"), vec![out, inp]));
+                shapes.push((format!("{p}.{name}.bias"), vec![out]));
+            }
+            for name in ["attention.output.LayerNorm", "output.LayerNorm"] {
+                shapes.push((format!("{p}.{name}.weight"), vec![hidden]));
+                shapes.push((format!("{p}.{name}.bias"), vec![hidden]));
+            }
+        }
+
+        let mut seed = 0x2545f4914f6cdd1du64;
+        let mut header = serde_json::Map::new();
+        let mut data = Vec::new();
+        for (name, shape) in shapes {
+            let start = data.len();
+            for _ in 0..shape.iter().product::<usize>() {
+                seed ^= seed << 13;
+                seed ^= seed >> 7;
+                seed ^= seed << 17;
+                let value = (seed % 1000) as f32 / 1000.0 - 0.5;
+                data.extend_from_slice(&value.to_le_bytes());
+            }
+            header.insert(
+                name,
+                serde_json::json!({"dtype": "F32", "shape": shape, "data_offsets": [start, data.len()]}),
+            );
+        }
+        let header = serde_json::Value::Object(header).to_string();
+        let mut file = (header.len() as u64).to_le_bytes().to_vec();
+        file.extend_from_slice(header.as_bytes());
+        file.extend_from_slice(&data);
+        std::fs::write(dir.join("model.safetensors"), file).unwrap();
+    }
+
+    #[test]
+    fn test_word_pieces_follow_bert_tokenization() {
+        let tokenizer = tokenizer();
+        assert_eq!(
+            tokenizer.encode("Unaffable  CAFÉ, the bananas!", 32),
+            vec![2, 8, 9, 10, 11, 12, 4, 15, 16, 13, 3]
+        );
+        // A word without a full split is unknown as a whole
+        assert_eq!(tokenizer.encode("unaffablex", 32), vec![2, 1, 3]);
+        // Long inputs are cut to fit, keeping the end marker
+        assert_eq!(tokenizer.encode("the the the the the", 4), vec![2, 4, 4, 3]);
+    }
+
+    #[test]
+    fn test_half_precision_values_are_widened() {
+        assert_eq!(f16_to_f32(0x3c00), 1.0);
+        assert_eq!(f16_to_f32(0xc000), -2.0);
+        assert_eq!(f16_to_f32(0x3555), 0.333_251_95);
+        assert_eq!(f16_to_f32(0x0001), 2f32.powi(-24));
+        assert_eq!(f16_to_f32(0x7c00), f32::INFINITY);
+    }
+
+    #[tokio::test]
+    async fn test_encoder_loads_a_model_directory_and_embeds() {
+        let dir = tempfile::tempdir().unwrap();
+        write_model(dir.path(), 16, 4, 2);
+
+        let provider = SentenceTransformerProvider::load(dir.path(), "tiny-bert").unwrap();
+        assert_eq!(provider.model(), "tiny-bert");
+        assert_eq!(provider.dimensions(), 16);
+
+        let embeddings = provider
+            .embed(vec![
+                "The database connection pool".to_string(),
+                "Banana bread".to_string(),
+            ])
+            .await
+            .unwrap();
+        assert_eq!(embeddings.len(), 2);
+        for embedding in &embeddings {
+            assert_eq!(embedding.len(), 16);
+            let norm = embedding.iter().map(|v| v * v).sum::<f32>().sqrt();
+            assert!((norm - 1.0).abs() < 1e-5);
+        }
+        assert_ne!(embeddings[0], embeddings[1]);
+        // Embedding doesn't depend on the rest of the batch
+        let alone = provider
+            .embed(vec!["The database connection pool".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(alone[0], embeddings[0]);
+
+        // Missing weights are reported rather than guessed
+        std::fs::remove_file(dir.path().join("model.safetensors")).unwrap();
+        assert!(SentenceTransformerProvider::load(dir.path(), "tiny-bert").is_err());
+    }
+}
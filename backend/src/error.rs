@@ -52,7 +52,7 @@ impl ErrorResponse {
 #[derive(Error, Debug)]
 pub enum AppError {
     #[error("Internal server error")]
-    Internal(#[from] anyhow::Error),
+    Internal(anyhow::Error),
 
     #[error("OpenAI API error: {0}")]
     OpenAI(String),
@@ -60,6 +60,9 @@ pub enum AppError {
     #[error("Anthropic API error: {0}")]
     Anthropic(String),
 
+    #[error("Embedding provider error: {0}")]
+    Embedding(String),
+
     #[error("Invalid request: {0}")]
     BadRequest(String),
 
@@ -118,6 +121,13 @@ impl IntoResponse for AppError {
                     ErrorResponse::new("EXTERNAL_API_ERROR", msg),
                 )
             }
+            AppError::Embedding(ref msg) => {
+                tracing::error!("Embedding provider error: {}", msg);
+                (
+                    StatusCode::BAD_GATEWAY,
+                    ErrorResponse::new("EMBEDDING_PROVIDER_ERROR", msg),
+                )
+            }
             AppError::BadRequest(ref msg) => (
                 StatusCode::BAD_REQUEST,
                 ErrorResponse::new("BAD_REQUEST", msg),
@@ -187,6 +197,18 @@ impl IntoResponse for AppError {
 }
 
 // Implement From traits for common error types
+
+// Services return anyhow errors; an AppError raised inside one (e.g. by the
+// authorization service) keeps its status instead of becoming a 500
+impl From<anyhow::Error> for AppError {
+    fn from(err: anyhow::Error) -> Self {
+        match err.downcast::<AppError>() {
+            Ok(app_error) => app_error,
+            Err(err) => AppError::Internal(err),
+        }
+    }
+}
+
 impl From<reqwest::Error> for AppError {
     fn from(err: reqwest::Error) -> Self {
         AppError::OpenAI(err.to_string())
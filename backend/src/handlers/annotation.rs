@@ -0,0 +1,171 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json,
+};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    app_state::AppState,
+    error::AppError,
+    models::{
+        Bookmark, BookmarkParams, ConversationRole, CreateAnnotationRequest, MessageAnnotation,
+        UpdateAnnotationRequest, UserResponse,
+    },
+};
+
+/// Highlight part of a message, or the whole message, with an optional note and label
+pub async fn create_annotation(
+    State(app_state): State<AppState>,
+    Path(message_id): Path<Uuid>,
+    user: UserResponse,
+    Json(request): Json<CreateAnnotationRequest>,
+) -> Result<(StatusCode, Json<MessageAnnotation>), AppError> {
+    request.validate().map_err(|e| AppError::ValidationError {
+        field: "payload".to_string(),
+        message: format!("Validation failed: {}", e),
+    })?;
+
+    // Anyone who can read the conversation can annotate it
+    let (message, _) = app_state
+        .authorization_service
+        .require_for_message(message_id, user.id, ConversationRole::Viewer)
+        .await?;
+
+    let (range, quote) = match (request.start_offset, request.end_offset) {
+        (None, None) => (None, None),
+        (Some(start), Some(end)) => {
+            let length = message.content.chars().count();
+            if start >= end || end as usize > length {
+                return Err(AppError::ValidationError {
+                    field: "end_offset".to_string(),
+                    message: format!(
+                        "Highlight must be a non-empty range within the message's {} characters",
+                        length
+                    ),
+                });
+            }
+            let quote: String = message
+                .content
+                .chars()
+                .skip(start as usize)
+                .take((end - start) as usize)
+                .collect();
+            (Some((start as i32, end as i32)), Some(quote))
+        }
+        _ => {
+            return Err(AppError::ValidationError {
+                field: "start_offset".to_string(),
+                message: "start_offset and end_offset must be given together".to_string(),
+            })
+        }
+    };
+
+    let annotation = app_state
+        .dal
+        .annotations()
+        .create(
+            message_id,
+            user.id,
+            range,
+            quote.as_deref(),
+            not_blank(&request.note),
+            not_blank(&request.label),
+        )
+        .await?;
+    Ok((StatusCode::CREATED, Json(annotation)))
+}
+
+/// All annotations on a conversation, across every branch
+pub async fn list_conversation_annotations(
+    State(app_state): State<AppState>,
+    Path(conversation_id): Path<Uuid>,
+    user: UserResponse,
+) -> Result<Json<Vec<MessageAnnotation>>, AppError> {
+    app_state
+        .authorization_service
+        .require(conversation_id, user.id, ConversationRole::Viewer)
+        .await?;
+
+    let annotations = app_state
+        .dal
+        .annotations()
+        .find_by_conversation_id(conversation_id)
+        .await?;
+    Ok(Json(annotations))
+}
+
+/// The user's own annotations across all conversations, filtered by label or note text
+pub async fn list_bookmarks(
+    State(app_state): State<AppState>,
+    user: UserResponse,
+    Query(params): Query<BookmarkParams>,
+) -> Result<Json<Vec<Bookmark>>, AppError> {
+    let limit = params.limit.unwrap_or(50).clamp(1, 200) as i64;
+    let offset = params.offset.unwrap_or(0) as i64;
+
+    let bookmarks = app_state
+        .dal
+        .annotations()
+        .find_bookmarks(user.id, &params, limit, offset)
+        .await?;
+    Ok(Json(bookmarks))
+}
+
+/// Change the note or label of an annotation. The highlighted range is fixed.
+pub async fn update_annotation(
+    State(app_state): State<AppState>,
+    Path(annotation_id): Path<Uuid>,
+    user: UserResponse,
+    Json(request): Json<UpdateAnnotationRequest>,
+) -> Result<Json<MessageAnnotation>, AppError> {
+    request.validate().map_err(|e| AppError::ValidationError {
+        field: "payload".to_string(),
+        message: format!("Validation failed: {}", e),
+    })?;
+
+    find_own_annotation(&app_state, annotation_id, user.id).await?;
+
+    let annotation = app_state
+        .dal
+        .annotations()
+        .update(annotation_id, &request)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Annotation not found".to_string()))?;
+    Ok(Json(annotation))
+}
+
+pub async fn delete_annotation(
+    State(app_state): State<AppState>,
+    Path(annotation_id): Path<Uuid>,
+    user: UserResponse,
+) -> Result<StatusCode, AppError> {
+    find_own_annotation(&app_state, annotation_id, user.id).await?;
+
+    app_state.dal.annotations().delete(annotation_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Annotations can only be changed by the user who made them
+async fn find_own_annotation(
+    app_state: &AppState,
+    annotation_id: Uuid,
+    user_id: Uuid,
+) -> Result<MessageAnnotation, AppError> {
+    let annotation = app_state
+        .dal
+        .annotations()
+        .find_by_id(annotation_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Annotation not found".to_string()))?;
+
+    if annotation.user_id != user_id {
+        return Err(AppError::Forbidden("Access denied".to_string()));
+    }
+    Ok(annotation)
+}
+
+fn not_blank(value: &Option<String>) -> Option<&str> {
+    value.as_deref().map(str::trim).filter(|v| !v.is_empty())
+}
@@ -1,5 +1,5 @@
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     response::Json,
 };
 use serde_json::Value;
@@ -9,10 +9,12 @@ use crate::{
     app_state::AppState,
     error::AppError,
     llm::{ChatMessage, ChatRequest, LLMServiceFactory},
-    models::{MessageRole, UserResponse},
+    models::{ConversationRole, MessageRole, MessageWindowParams, UserResponse},
     repositories::Repository,
     services::{
         chat::{ChatService, SendMessageRequest},
+        embedding::EmbeddingService,
+        retrieval::RetrievalService,
         DataAccessLayer,
     },
 };
@@ -24,17 +26,11 @@ pub async fn send_message(
     user: UserResponse, // This comes from our auth middleware
     Json(request): Json<SendMessageRequest>,
 ) -> Result<Json<Value>, AppError> {
-    // Get the conversation to find out which model to use
-    let conversation_repo = app_state.dal.conversations();
-    let conversation = conversation_repo
-        .find_by_id(conversation_id)
-        .await?
-        .ok_or_else(|| AppError::NotFound("Conversation not found".to_string()))?;
-
-    // Verify user owns the conversation
-    if conversation.user_id != user.id {
-        return Err(AppError::Forbidden("Access denied".to_string()));
-    }
+    // Get the conversation to find out which model to use; sending needs editor access
+    let conversation = app_state
+        .authorization_service
+        .require(conversation_id, user.id, ConversationRole::Editor)
+        .await?;
 
     // Save user message to database
     let user_message = app_state
@@ -49,7 +45,7 @@ pub async fn send_message(
         .await?;
 
     // Convert messages to LLM format
-    let chat_messages: Vec<ChatMessage> = messages
+    let mut chat_messages: Vec<ChatMessage> = messages
         .iter()
         .map(|msg| ChatMessage {
             role: match msg.role {
@@ -61,6 +57,15 @@ pub async fn send_message(
         })
         .collect();
 
+    // Add passages from the owner's other conversations when retrieval is on
+    let retrieval = RetrievalService::new(EmbeddingService::new(
+        app_state.config.clone(),
+        app_state.dal.clone(),
+    )?);
+    let citations = retrieval
+        .augment(&conversation, &mut chat_messages, None)
+        .await;
+
     // Create LLM request
     let llm_request = ChatRequest {
         model: conversation.model.clone(),
@@ -86,38 +91,75 @@ pub async fn send_message(
     let llm_service = LLMServiceFactory::create_service(&provider, config)?;
 
     // Call the LLM service
-    let llm_response = llm_service.chat_completion(llm_request).await?;
+    let start_time = std::time::Instant::now();
+    let mut llm_response = llm_service.chat_completion(llm_request).await?;
+    llm_response.latency_ms = Some(start_time.elapsed().as_millis() as u64);
 
     // Save assistant response to database
     let assistant_message = app_state
         .chat_service
-        .send_assistant_message(conversation_id, llm_response.message.content.clone())
+        .save_llm_response(user.id, conversation_id, &llm_response, None, &citations)
         .await?;
 
-    Ok(Json(serde_json::json!({
+    let mut response = serde_json::json!({
         "user_message": user_message,
         "assistant_message": assistant_message,
         "conversation_id": conversation_id,
+        "usage": llm_response.usage,
+        "citations": citations,
         "status": "completed"
-    })))
+    });
+    if request.include_reasoning {
+        response["reasoning"] = serde_json::json!(llm_response.reasoning);
+    }
+
+    Ok(Json(response))
 }
 
-// Get all messages in a conversation
+// Get the messages of a conversation's active thread, optionally a window before or after a message
 pub async fn get_messages(
     State(app_state): State<AppState>,
     Path(conversation_id): Path<Uuid>,
     user: UserResponse, // This comes from our auth middleware
+    Query(params): Query<MessageWindowParams>,
 ) -> Result<Json<Value>, AppError> {
-    let messages = app_state
+    if params.before.is_some() && params.after.is_some() {
+        return Err(AppError::BadRequest(
+            "Use either 'before' or 'after', not both".to_string(),
+        ));
+    }
+
+    app_state
+        .authorization_service
+        .require(conversation_id, user.id, ConversationRole::Viewer)
+        .await?;
+
+    // The anchor must be a message of this conversation
+    if let Some(anchor_id) = params.before.or(params.after) {
+        let anchor = app_state
+            .dal
+            .messages()
+            .find_by_id(anchor_id)
+            .await?
+            .filter(|m| m.conversation_id == conversation_id)
+            .ok_or_else(|| AppError::NotFound("Message not found".to_string()))?;
+        tracing::debug!("Loading message window around {}", anchor.id);
+    }
+
+    let mut window = app_state
         .chat_service
-        .get_conversation_messages(user.id, conversation_id)
+        .get_message_window(user.id, conversation_id, &params)
         .await?;
 
-    Ok(Json(serde_json::json!({
-        "conversation_id": conversation_id,
-        "messages": messages,
-        "total_count": messages.len()
-    })))
+    if !params.include_reasoning {
+        window.messages = window
+            .messages
+            .into_iter()
+            .map(|m| m.without_reasoning())
+            .collect();
+    }
+
+    Ok(Json(serde_json::to_value(window)?))
 }
 
 // Create a message branch (for conversation threading)
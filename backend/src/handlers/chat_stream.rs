@@ -12,8 +12,8 @@ use crate::{
     app_state::AppState,
     error::AppError,
     llm::{ChatMessage, ChatRequest, LLMServiceFactory},
-    models::{MessageRole, UserResponse},
-    repositories::Repository,
+    models::{ConversationRole, MessageRole, UserResponse},
+    services::{embedding::EmbeddingService, retrieval::RetrievalService},
 };
 
 #[derive(serde::Deserialize, Debug)]
@@ -22,6 +22,9 @@ pub struct StreamChatRequest {
     pub model: Option<String>,
     pub temperature: Option<f32>,
     pub max_tokens: Option<u32>,
+    /// Stream the model's reasoning as `reasoning` events (hidden by default)
+    #[serde(default)]
+    pub include_reasoning: bool,
 }
 
 // Streaming endpoint that uses the actual LLM service
@@ -41,11 +44,9 @@ pub async fn stream_message(
     // Get the conversation to find out which model to use
     tracing::debug!("Looking up conversation with ID: {}", conversation_id);
     let conversation = app_state
-        .dal
-        .conversations()
-        .find_by_id(conversation_id)
-        .await?
-        .ok_or_else(|| AppError::NotFound("Conversation not found".to_string()))?;
+        .authorization_service
+        .require(conversation_id, user.id, ConversationRole::Editor)
+        .await?;
 
     tracing::info!(
         "Found conversation: provider={}, model={}, title='{}'",
@@ -54,11 +55,6 @@ pub async fn stream_message(
         conversation.title.as_deref().unwrap_or("No title")
     );
 
-    // Verify user owns the conversation
-    if conversation.user_id != user.id {
-        return Err(AppError::Forbidden("Access denied".to_string()));
-    }
-
     // Save user message to database
     tracing::debug!("Saving user message to database");
     let _user_message = app_state
@@ -67,6 +63,31 @@ pub async fn stream_message(
         .await?;
     tracing::debug!("User message saved successfully");
 
+    // Embed the prompt once, for the similar-question search and for retrieval
+    let embedding_service = EmbeddingService::new(app_state.config.clone(), app_state.dal.clone())?;
+    let prompt_embedding = if embedding_service.provider().is_configured() {
+        match embedding_service.generate_embedding(&request.content).await {
+            Ok(embedding) => Some(embedding),
+            Err(e) => {
+                tracing::warn!("Failed to embed prompt: {}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    // Look for earlier questions like this one while the model answers
+    let suggestions = prompt_embedding.clone().map(|prompt_embedding| {
+        let embedding_service = embedding_service.clone();
+        let user_id = user.id;
+        tokio::spawn(async move {
+            embedding_service
+                .find_similar_questions(prompt_embedding, user_id, conversation_id)
+                .await
+        })
+    });
+
     // Get conversation history for context
     tracing::debug!("Loading conversation history");
     let messages = app_state
@@ -79,7 +100,7 @@ pub async fn stream_message(
     );
 
     // Convert messages to LLM format
-    let chat_messages: Vec<ChatMessage> = messages
+    let mut chat_messages: Vec<ChatMessage> = messages
         .iter()
         .map(|msg| ChatMessage {
             role: match msg.role {
@@ -91,6 +112,12 @@ pub async fn stream_message(
         })
         .collect();
 
+    // Add passages from the owner's other conversations when retrieval is on
+    let retrieval = RetrievalService::new(embedding_service);
+    let citations = retrieval
+        .augment(&conversation, &mut chat_messages, prompt_embedding)
+        .await;
+
     // Get the appropriate LLM service
     let config = &app_state.config;
     let provider = match conversation.provider.as_str() {
@@ -125,99 +152,141 @@ pub async fn stream_message(
         provider
     );
 
-    // Call the LLM service
-    let start_time = std::time::Instant::now();
-    let llm_response = llm_service.chat_completion(llm_request_non_stream).await?;
-    let duration = start_time.elapsed();
+    // The answer is produced inside the stream, so the suggestions can be sent while
+    // the model is still working
+    let chat_service = app_state.chat_service.clone();
+    let user_id = user.id;
+    let include_reasoning = request.include_reasoning;
+    let answer = async move {
+        let start_time = std::time::Instant::now();
+        let mut llm_response = llm_service.chat_completion(llm_request_non_stream).await?;
+        let duration = start_time.elapsed();
+        llm_response.latency_ms = Some(duration.as_millis() as u64);
 
-    tracing::info!(
-        "LLM service call completed in {:?}, response length: {} chars",
-        duration,
-        llm_response.message.content.len()
-    );
-
-    // Save assistant response to database
-    tracing::debug!("Saving assistant response to database");
-    let assistant_message = app_state
-        .chat_service
-        .send_assistant_message(conversation_id, llm_response.message.content.clone())
-        .await?;
-    tracing::debug!("Assistant response saved with ID: {}", assistant_message.id);
-
-    // Create a stream that sends the response as tokens
-    let content = llm_response.message.content;
-    let message_id = assistant_message.id;
+        tracing::info!(
+            "LLM service call completed in {:?}, response length: {} chars",
+            duration,
+            llm_response.message.content.len()
+        );
 
-    // Split the content into words for simulated streaming
-    let words: Vec<String> = content
-        .split_whitespace()
-        .map(|w| format!("{w} "))
-        .collect();
+        // Save assistant response to database
+        tracing::debug!("Saving assistant response to database");
+        let assistant_message = chat_service
+            .save_llm_response(user_id, conversation_id, &llm_response, None, &citations)
+            .await?;
+        tracing::debug!("Assistant response saved with ID: {}", assistant_message.id);
 
-    tracing::info!(
-        "Starting stream simulation with {} words for message ID: {}",
-        words.len(),
-        message_id
-    );
+        Ok::<_, AppError>((assistant_message.id, llm_response, citations))
+    };
 
-    // First send a start event with metadata
-    let start_stream = futures::stream::once(async move {
-        tracing::debug!("Sending stream start event for message ID: {}", message_id);
-        Ok::<Event, Infallible>(
-            Event::default().data(
-                serde_json::json!({
-                    "type": "start",
+    let answer_stream = async_stream::stream! {
+        let (message_id, llm_response, citations) = match answer.await {
+            Ok(answer) => answer,
+            Err(e) => {
+                tracing::error!(
+                    "Failed to answer in conversation {}: {}",
+                    conversation_id,
+                    e
+                );
+                yield stream_event(serde_json::json!({
+                    "type": "error",
                     "data": {
-                        "conversationId": conversation_id,
-                        "messageId": message_id
+                        "message": client_error_message(&e)
                     }
-                })
-                .to_string(),
-            ),
-        )
-    });
+                }));
+                return;
+            }
+        };
 
-    // Then send all words as token events
-    let word_stream = futures::stream::iter(words.into_iter().enumerate()).map(move |(i, word)| {
-        tracing::debug!(
-            "Sending token {} for message ID: {}: '{}'",
-            i,
-            message_id,
-            word.trim()
-        );
-        Ok::<Event, Infallible>(
-            Event::default().data(
-                serde_json::json!({
-                    "type": "token",
-                    "data": {
-                        "content": word
-                    }
-                })
-                .to_string(),
-            ),
-        )
-    });
+        // First send a start event with metadata
+        tracing::debug!("Sending stream start event for message ID: {}", message_id);
+        yield stream_event(serde_json::json!({
+            "type": "start",
+            "data": {
+                "conversationId": conversation_id,
+                "messageId": message_id,
+                "citations": citations
+            }
+        }));
+
+        // Reasoning is sent as a single event ahead of the answer tokens
+        if let Some(reasoning) = llm_response
+            .reasoning
+            .filter(|r| include_reasoning && !r.is_empty())
+        {
+            yield stream_event(serde_json::json!({
+                "type": "reasoning",
+                "data": {
+                    "content": reasoning
+                }
+            }));
+        }
 
-    let stream = start_stream
-        .chain(word_stream)
-        .chain(futures::stream::once(async move {
-            // Send completion event
+        // Split the content into words for simulated streaming
+        let words: Vec<String> = llm_response
+            .message
+            .content
+            .split_whitespace()
+            .map(|w| format!("{w} "))
+            .collect();
+        tracing::info!(
+            "Starting stream simulation with {} words for message ID: {}",
+            words.len(),
+            message_id
+        );
+        for (i, word) in words.into_iter().enumerate() {
             tracing::debug!(
-                "Sending stream completion event for message ID: {}",
-                message_id
+                "Sending token {} for message ID: {}: '{}'",
+                i,
+                message_id,
+                word.trim()
             );
-            Ok::<Event, Infallible>(
-                Event::default().data(
-                    serde_json::json!({
-                        "type": "done",
-                        "data": {
-                            "messageId": message_id.to_string()
-                        }
-                    })
-                    .to_string(),
-                ),
-            )
+            yield stream_event(serde_json::json!({
+                "type": "token",
+                "data": {
+                    "content": word
+                }
+            }));
+        }
+
+        // Send completion event
+        tracing::debug!(
+            "Sending stream completion event for message ID: {}",
+            message_id
+        );
+        yield stream_event(serde_json::json!({
+            "type": "done",
+            "data": {
+                "messageId": message_id.to_string(),
+                "usage": llm_response.usage
+            }
         }));
+    };
+
+    // Similar earlier questions go out as soon as the search is done, between
+    // whichever answer events are ready at the time
+    let suggestion_stream = futures::stream::once(async move {
+        match suggestions?.await {
+            Ok(Ok(questions)) if !questions.is_empty() => Some(stream_event(serde_json::json!({
+                "type": "suggestion",
+                "data": {
+                    "suggestions": questions
+                }
+            }))),
+            Ok(Ok(_)) => None,
+            Ok(Err(e)) => {
+                tracing::warn!("Similar question search failed: {}", e);
+                None
+            }
+            Err(e) => {
+                tracing::warn!("Similar question search panicked: {}", e);
+                None
+            }
+        }
+    })
+    .filter_map(futures::future::ready);
+
+    let stream = futures::stream::select(answer_stream, suggestion_stream);
 
     Ok(Sse::new(stream).keep_alive(
         axum::response::sse::KeepAlive::new()
@@ -225,3 +294,20 @@ pub async fn stream_message(
             .text("keep-alive"),
     ))
 }
+
+fn stream_event(data: serde_json::Value) -> Result<Event, Infallible> {
+    Ok(Event::default().data(data.to_string()))
+}
+
+/// What a client is told about a failed answer; internal errors stay in the log, as
+/// they do in error responses
+fn client_error_message(error: &AppError) -> String {
+    match error {
+        AppError::OpenAI(msg)
+        | AppError::Anthropic(msg)
+        | AppError::BadRequest(msg)
+        | AppError::TooManyRequests(msg) => msg.clone(),
+        AppError::RateLimit => "Rate limit exceeded".to_string(),
+        _ => "Failed to generate a response".to_string(),
+    }
+}
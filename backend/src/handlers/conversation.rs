@@ -1,7 +1,8 @@
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
-    response::Json,
+    body::Bytes,
+    extract::{Path, Query, State},
+    http::{header, HeaderValue, StatusCode},
+    response::{IntoResponse, Json, Response},
 };
 use serde_json::Value;
 use uuid::Uuid;
@@ -9,8 +10,17 @@ use uuid::Uuid;
 use crate::{
     app_state::AppState,
     error::AppError,
-    models::{CreateConversationRequest, UserResponse},
-    services::{conversation::ConversationService, DataAccessLayer},
+    models::{
+        ConversationCursor, ConversationListParams, ConversationRole, CreateConversationRequest,
+        ReasoningParams, RetrievalSettings, UserResponse,
+    },
+    services::{
+        conversation::ConversationService,
+        embedding::EmbeddingService,
+        export::{ExportFormat, ExportScope, ExportService},
+        import::{ImportService, ImportSource},
+        DataAccessLayer,
+    },
 };
 
 // Create a new conversation
@@ -26,11 +36,26 @@ pub async fn create_conversation(
     Ok(Json(serde_json::to_value(conversation)?))
 }
 
-// Get user's conversations with pagination (temporary - no auth for now)
-pub async fn get_user_conversations() -> Result<Json<Value>, AppError> {
-    // For now, return empty array until authentication is properly configured
-    let empty_conversations: Vec<serde_json::Value> = vec![];
-    Ok(Json(serde_json::to_value(empty_conversations)?))
+// Get user's conversations with cursor pagination and filters
+pub async fn get_user_conversations(
+    State(app_state): State<AppState>,
+    user: UserResponse, // This comes from our auth middleware
+    Query(params): Query<ConversationListParams>,
+) -> Result<Json<Value>, AppError> {
+    let cursor = params
+        .cursor
+        .as_deref()
+        .map(|cursor| {
+            ConversationCursor::decode(cursor)
+                .ok_or_else(|| AppError::BadRequest("Invalid cursor".to_string()))
+        })
+        .transpose()?;
+
+    let page = app_state
+        .conversation_service
+        .get_user_conversations(user.id, &params, cursor.as_ref())
+        .await?;
+    Ok(Json(serde_json::to_value(page)?))
 }
 
 // Get a specific conversation with messages
@@ -38,13 +63,23 @@ pub async fn get_conversation(
     State(app_state): State<AppState>,
     Path(conversation_id): Path<Uuid>,
     user: UserResponse, // This comes from our auth middleware
+    Query(params): Query<ReasoningParams>,
 ) -> Result<Json<Value>, AppError> {
     match app_state
         .conversation_service
         .get_conversation_with_messages(conversation_id, user.id)
         .await?
     {
-        Some(conversation) => Ok(Json(serde_json::to_value(conversation)?)),
+        Some(mut conversation) => {
+            if !params.include_reasoning {
+                conversation.messages = conversation
+                    .messages
+                    .into_iter()
+                    .map(|m| m.without_reasoning())
+                    .collect();
+            }
+            Ok(Json(serde_json::to_value(conversation)?))
+        }
         None => Err(AppError::NotFound("Conversation not found".to_string())),
     }
 }
@@ -68,16 +103,24 @@ pub async fn update_conversation_title(
     }
 }
 
-// Delete a conversation
+// Delete a conversation: moves it to the trash, or removes it for good with `?permanent=true`
 pub async fn delete_conversation(
     State(app_state): State<AppState>,
     Path(conversation_id): Path<Uuid>,
     user: UserResponse, // This comes from our auth middleware
+    Query(params): Query<DeleteParams>,
 ) -> Result<StatusCode, AppError> {
-    let deleted = app_state
-        .conversation_service
-        .delete_conversation(conversation_id, user.id)
-        .await?;
+    let deleted = if params.permanent {
+        app_state
+            .conversation_service
+            .delete_conversation(conversation_id, user.id)
+            .await?
+    } else {
+        app_state
+            .conversation_service
+            .trash_conversation(conversation_id, user.id)
+            .await?
+    };
 
     if deleted {
         Ok(StatusCode::NO_CONTENT)
@@ -86,6 +129,100 @@ pub async fn delete_conversation(
     }
 }
 
+// Restore a conversation from the trash
+pub async fn restore_conversation(
+    State(app_state): State<AppState>,
+    Path(conversation_id): Path<Uuid>,
+    user: UserResponse, // This comes from our auth middleware
+) -> Result<Json<Value>, AppError> {
+    let restored = app_state
+        .conversation_service
+        .restore_conversation(conversation_id, user.id)
+        .await?;
+
+    if restored {
+        Ok(Json(serde_json::json!({"success": true})))
+    } else {
+        Err(AppError::NotFound(
+            "Conversation not found in trash".to_string(),
+        ))
+    }
+}
+
+// Pin a conversation to the top of the list
+pub async fn pin_conversation(
+    State(app_state): State<AppState>,
+    Path(conversation_id): Path<Uuid>,
+    user: UserResponse, // This comes from our auth middleware
+) -> Result<Json<Value>, AppError> {
+    set_pinned(app_state, conversation_id, user.id, true).await
+}
+
+// Unpin a conversation
+pub async fn unpin_conversation(
+    State(app_state): State<AppState>,
+    Path(conversation_id): Path<Uuid>,
+    user: UserResponse, // This comes from our auth middleware
+) -> Result<Json<Value>, AppError> {
+    set_pinned(app_state, conversation_id, user.id, false).await
+}
+
+// Archive a conversation
+pub async fn archive_conversation(
+    State(app_state): State<AppState>,
+    Path(conversation_id): Path<Uuid>,
+    user: UserResponse, // This comes from our auth middleware
+) -> Result<Json<Value>, AppError> {
+    set_archived(app_state, conversation_id, user.id, true).await
+}
+
+// Move a conversation out of the archive
+pub async fn unarchive_conversation(
+    State(app_state): State<AppState>,
+    Path(conversation_id): Path<Uuid>,
+    user: UserResponse, // This comes from our auth middleware
+) -> Result<Json<Value>, AppError> {
+    set_archived(app_state, conversation_id, user.id, false).await
+}
+
+async fn set_pinned(
+    app_state: AppState,
+    conversation_id: Uuid,
+    user_id: Uuid,
+    pinned: bool,
+) -> Result<Json<Value>, AppError> {
+    let updated = app_state
+        .conversation_service
+        .set_pinned(conversation_id, user_id, pinned)
+        .await?;
+
+    if updated {
+        Ok(Json(serde_json::json!({"success": true, "pinned": pinned})))
+    } else {
+        Err(AppError::NotFound("Conversation not found".to_string()))
+    }
+}
+
+async fn set_archived(
+    app_state: AppState,
+    conversation_id: Uuid,
+    user_id: Uuid,
+    archived: bool,
+) -> Result<Json<Value>, AppError> {
+    let updated = app_state
+        .conversation_service
+        .set_archived(conversation_id, user_id, archived)
+        .await?;
+
+    if updated {
+        Ok(Json(
+            serde_json::json!({"success": true, "archived": archived}),
+        ))
+    } else {
+        Err(AppError::NotFound("Conversation not found".to_string()))
+    }
+}
+
 // Get conversation statistics
 pub async fn get_conversation_stats(
     State(app_state): State<AppState>,
@@ -99,6 +236,131 @@ pub async fn get_conversation_stats(
     Ok(Json(serde_json::to_value(stats)?))
 }
 
+// Get the retrieval settings of a conversation
+pub async fn get_retrieval_settings(
+    State(app_state): State<AppState>,
+    Path(conversation_id): Path<Uuid>,
+    user: UserResponse, // This comes from our auth middleware
+) -> Result<Json<RetrievalSettings>, AppError> {
+    let settings = app_state
+        .conversation_service
+        .get_retrieval_settings(conversation_id, user.id)
+        .await?;
+    Ok(Json(settings))
+}
+
+// Turn retrieval over past conversations on or off and tune it
+pub async fn update_retrieval_settings(
+    State(app_state): State<AppState>,
+    Path(conversation_id): Path<Uuid>,
+    user: UserResponse, // This comes from our auth middleware
+    Json(settings): Json<RetrievalSettings>,
+) -> Result<Json<RetrievalSettings>, AppError> {
+    let settings = app_state
+        .conversation_service
+        .update_retrieval_settings(conversation_id, user.id, settings)
+        .await?;
+    Ok(Json(settings))
+}
+
+// Export a conversation as Markdown, JSON or HTML
+pub async fn export_conversation(
+    State(app_state): State<AppState>,
+    Path(conversation_id): Path<Uuid>,
+    user: UserResponse, // This comes from our auth middleware
+    Query(params): Query<ExportParams>,
+) -> Result<Response, AppError> {
+    let conversation = app_state
+        .authorization_service
+        .require(conversation_id, user.id, ConversationRole::Viewer)
+        .await?;
+
+    let export = ExportService::new(app_state.dal.clone())
+        .export_conversation(conversation, params.scope, params.include_reasoning)
+        .await?;
+    let body = export.render(params.format)?;
+    let disposition = HeaderValue::from_str(&format!(
+        "attachment; filename=\"{}\"",
+        export.filename(params.format)
+    ))?;
+
+    Ok((
+        [
+            (
+                header::CONTENT_TYPE,
+                HeaderValue::from_static(params.format.content_type()),
+            ),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+#[derive(serde::Deserialize)]
+pub struct ExportParams {
+    #[serde(default)]
+    pub format: ExportFormat,
+    #[serde(default)]
+    pub scope: ExportScope,
+    #[serde(default)]
+    pub include_reasoning: bool,
+}
+
+// Import conversations from a ChatGPT or Claude data export (zip archive or conversations.json)
+pub async fn import_conversations(
+    State(app_state): State<AppState>,
+    user: UserResponse, // This comes from our auth middleware
+    Query(params): Query<ImportParams>,
+    body: Bytes,
+) -> Result<Json<Value>, AppError> {
+    if body.is_empty() {
+        return Err(AppError::BadRequest("Empty import body".to_string()));
+    }
+
+    let max_bytes = app_state.config.import_max_size_mb * 1024 * 1024;
+    let report = ImportService::new(app_state.dal.clone())
+        .import(user.id, &body, params.source, max_bytes)
+        .await?;
+
+    tracing::info!(
+        "User {} imported {} conversations ({} messages, {} skipped items) from {}",
+        user.id,
+        report.conversations_imported,
+        report.messages_imported,
+        report.skipped.len(),
+        report.source.as_str()
+    );
+
+    // Embed the imported history in the background so it becomes searchable
+    if report.embeddings_queued > 0 {
+        match EmbeddingService::new(app_state.config.clone(), app_state.dal.clone()) {
+            Ok(embedding_service) => {
+                tokio::spawn(async move {
+                    if let Err(e) = embedding_service.background_embedding_job().await {
+                        tracing::warn!("Embedding job after import failed: {}", e);
+                    }
+                });
+            }
+            Err(e) => tracing::warn!("Skipping embeddings for imported messages: {}", e),
+        }
+    }
+
+    Ok(Json(serde_json::to_value(report)?))
+}
+
+#[derive(serde::Deserialize)]
+pub struct ImportParams {
+    /// Export format; detected from the payload when omitted
+    pub source: Option<ImportSource>,
+}
+
+#[derive(serde::Deserialize)]
+pub struct DeleteParams {
+    #[serde(default)]
+    pub permanent: bool,
+}
+
 #[derive(serde::Deserialize)]
 pub struct UpdateTitleRequest {
     pub title: String,
@@ -0,0 +1,175 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    app_state::AppState,
+    error::AppError,
+    models::{
+        AddMemberRequest, ConversationMember, ConversationRole, UpdateMemberRequest, UserResponse,
+    },
+    repositories::Repository,
+};
+
+/// List everyone with access to a conversation, owner first
+pub async fn list_members(
+    State(app_state): State<AppState>,
+    Path(conversation_id): Path<Uuid>,
+    user: UserResponse,
+) -> Result<Json<Vec<ConversationMember>>, AppError> {
+    let conversation = app_state
+        .authorization_service
+        .require(conversation_id, user.id, ConversationRole::Viewer)
+        .await?;
+
+    let owner = app_state
+        .dal
+        .users()
+        .find_by_id(conversation.user_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Conversation owner not found".to_string()))?;
+
+    let mut members = vec![ConversationMember {
+        conversation_id,
+        user_id: owner.id,
+        username: owner.username,
+        email: owner.email,
+        role: ConversationRole::Owner,
+        added_by: None,
+        created_at: conversation.created_at,
+    }];
+    members.extend(
+        app_state
+            .dal
+            .conversation_members()
+            .find_by_conversation_id(conversation_id)
+            .await?,
+    );
+    Ok(Json(members))
+}
+
+/// Share a conversation with another user as editor or viewer. The response is the
+/// same whether or not the email belongs to an account, so owners cannot use it to
+/// find out who is registered.
+pub async fn add_member(
+    State(app_state): State<AppState>,
+    Path(conversation_id): Path<Uuid>,
+    user: UserResponse,
+    Json(request): Json<AddMemberRequest>,
+) -> Result<(StatusCode, Json<serde_json::Value>), AppError> {
+    request.validate().map_err(|e| AppError::ValidationError {
+        field: "payload".to_string(),
+        message: format!("Validation failed: {}", e),
+    })?;
+    reject_owner_role(request.role)?;
+
+    let conversation = app_state
+        .authorization_service
+        .require(conversation_id, user.id, ConversationRole::Owner)
+        .await?;
+
+    let accepted = (
+        StatusCode::ACCEPTED,
+        Json(serde_json::json!({"success": true, "role": request.role})),
+    );
+
+    let Some(member) = app_state
+        .dal
+        .users()
+        .find_by_email(request.email.trim())
+        .await?
+    else {
+        tracing::info!(
+            "User {} tried to share conversation {} with an email that has no account",
+            user.id,
+            conversation_id
+        );
+        return Ok(accepted);
+    };
+
+    // Only the owner's own email can reach this, so it reveals nothing
+    if member.id == conversation.user_id {
+        return Err(AppError::BadRequest(
+            "The owner already has full access".to_string(),
+        ));
+    }
+
+    app_state
+        .dal
+        .conversation_members()
+        .upsert(conversation_id, member.id, request.role, user.id)
+        .await?;
+
+    Ok(accepted)
+}
+
+/// Change a member's role
+pub async fn update_member(
+    State(app_state): State<AppState>,
+    Path((conversation_id, member_id)): Path<(Uuid, Uuid)>,
+    user: UserResponse,
+    Json(request): Json<UpdateMemberRequest>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    reject_owner_role(request.role)?;
+
+    app_state
+        .authorization_service
+        .require(conversation_id, user.id, ConversationRole::Owner)
+        .await?;
+
+    let updated = app_state
+        .dal
+        .conversation_members()
+        .update_role(conversation_id, member_id, request.role)
+        .await?;
+
+    if updated {
+        Ok(Json(
+            serde_json::json!({"success": true, "role": request.role}),
+        ))
+    } else {
+        Err(AppError::NotFound("Member not found".to_string()))
+    }
+}
+
+/// Remove a member. The owner can remove anyone; members can remove themselves.
+pub async fn remove_member(
+    State(app_state): State<AppState>,
+    Path((conversation_id, member_id)): Path<(Uuid, Uuid)>,
+    user: UserResponse,
+) -> Result<StatusCode, AppError> {
+    let required = if member_id == user.id {
+        ConversationRole::Viewer
+    } else {
+        ConversationRole::Owner
+    };
+    app_state
+        .authorization_service
+        .require(conversation_id, user.id, required)
+        .await?;
+
+    let removed = app_state
+        .dal
+        .conversation_members()
+        .remove(conversation_id, member_id)
+        .await?;
+
+    if removed {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(AppError::NotFound("Member not found".to_string()))
+    }
+}
+
+fn reject_owner_role(role: ConversationRole) -> Result<(), AppError> {
+    if role == ConversationRole::Owner {
+        return Err(AppError::BadRequest(
+            "Members can only be editors or viewers".to_string(),
+        ));
+    }
+    Ok(())
+}
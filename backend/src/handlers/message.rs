@@ -1,19 +1,22 @@
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
     response::Json,
 };
-use serde_json::Value;
 use uuid::Uuid;
+use validator::Validate;
 
 use crate::{
     app_state::AppState,
     error::AppError,
     models::{
-        ConversationTreeResponse, EditMessageRequest, EditMessageResponse, SwitchBranchRequest,
-        SwitchBranchResponse, UserResponse,
+        BranchComparison, BranchKind, CompareBranchesParams, Conversation, ConversationRole,
+        ConversationTreeResponse, EditMessageRequest, EditMessageResponse, ForkConversationRequest,
+        Message, MessageBranch, MessageRole, SwitchBranchRequest, SwitchBranchResponse,
+        UserResponse,
     },
     repositories::{message::MessageRepository, Repository},
+    services::compare::CompareService,
 };
 
 /// Edit a message and create a new branch
@@ -23,33 +26,27 @@ pub async fn edit_message(
     user: UserResponse,
     Json(request): Json<EditMessageRequest>,
 ) -> Result<Json<EditMessageResponse>, AppError> {
-    // Validate user owns the message through conversation ownership
     let message_repo = app_state.dal.messages();
-    let conversation_repo = app_state.dal.conversations();
-
-    // Get the original message
-    let original_message = message_repo
-        .find_by_id(message_id)
-        .await?
-        .ok_or_else(|| AppError::NotFound("Message not found".to_string()))?;
-
-    // Verify user owns the conversation
-    let conversation = conversation_repo
-        .find_by_id(original_message.conversation_id)
-        .await?
-        .ok_or_else(|| AppError::NotFound("Conversation not found".to_string()))?;
-
-    if conversation.user_id != user.id {
-        return Err(AppError::Forbidden("Access denied".to_string()));
-    }
+
+    // Editing needs editor access to the message's conversation
+    let (original_message, _) = app_state
+        .authorization_service
+        .require_for_message(message_id, user.id, ConversationRole::Editor)
+        .await?;
 
     // Get messages that will be deactivated (downstream from edit point)
     let downstream_messages = get_downstream_messages(&message_repo, message_id).await?;
     let affected_message_ids: Vec<Uuid> = downstream_messages.iter().map(|m| m.id).collect();
 
+    // An edited user message is authored by whoever edited it
+    let author_id = match original_message.role {
+        MessageRole::User => Some(user.id),
+        _ => original_message.author_id,
+    };
+
     // Perform the edit and branch creation
     let edited_message = message_repo
-        .edit_message_and_branch(message_id, request.content)
+        .edit_message_and_branch(message_id, request.content, author_id)
         .await?;
 
     Ok(Json(EditMessageResponse {
@@ -66,17 +63,11 @@ pub async fn switch_branch(
     Json(request): Json<SwitchBranchRequest>,
 ) -> Result<Json<SwitchBranchResponse>, AppError> {
     let message_repo = app_state.dal.messages();
-    let conversation_repo = app_state.dal.conversations();
-
-    // Verify user owns the conversation
-    let conversation = conversation_repo
-        .find_by_id(conversation_id)
-        .await?
-        .ok_or_else(|| AppError::NotFound("Conversation not found".to_string()))?;
 
-    if conversation.user_id != user.id {
-        return Err(AppError::Forbidden("Access denied".to_string()));
-    }
+    app_state
+        .authorization_service
+        .require(conversation_id, user.id, ConversationRole::Editor)
+        .await?;
 
     // Switch to the target branch
     let active_messages = message_repo
@@ -89,6 +80,25 @@ pub async fn switch_branch(
     }))
 }
 
+/// Spin the thread ending at a message out into a new conversation
+pub async fn fork_message(
+    State(app_state): State<AppState>,
+    Path(message_id): Path<Uuid>,
+    user: UserResponse,
+    Json(request): Json<ForkConversationRequest>,
+) -> Result<(StatusCode, Json<Conversation>), AppError> {
+    request.validate().map_err(|e| AppError::ValidationError {
+        field: "payload".to_string(),
+        message: format!("Validation failed: {}", e),
+    })?;
+
+    let conversation = app_state
+        .conversation_service
+        .fork_from_message(user.id, message_id, request)
+        .await?;
+    Ok((StatusCode::CREATED, Json(conversation)))
+}
+
 /// Get the full conversation tree with branch information
 pub async fn get_conversation_tree(
     State(app_state): State<AppState>,
@@ -96,17 +106,11 @@ pub async fn get_conversation_tree(
     user: UserResponse,
 ) -> Result<Json<ConversationTreeResponse>, AppError> {
     let message_repo = app_state.dal.messages();
-    let conversation_repo = app_state.dal.conversations();
-
-    // Verify user owns the conversation
-    let conversation = conversation_repo
-        .find_by_id(conversation_id)
-        .await?
-        .ok_or_else(|| AppError::NotFound("Conversation not found".to_string()))?;
 
-    if conversation.user_id != user.id {
-        return Err(AppError::Forbidden("Access denied".to_string()));
-    }
+    app_state
+        .authorization_service
+        .require(conversation_id, user.id, ConversationRole::Viewer)
+        .await?;
 
     // Get all messages in the conversation tree
     let all_messages = message_repo.find_conversation_tree(conversation_id).await?;
@@ -129,61 +133,76 @@ pub async fn get_conversation_tree(
     }))
 }
 
-/// Get branches for a specific message
+/// Compare two messages of a conversation from the point where their paths diverge
+pub async fn compare_branches(
+    State(app_state): State<AppState>,
+    Path(conversation_id): Path<Uuid>,
+    user: UserResponse,
+    Query(params): Query<CompareBranchesParams>,
+) -> Result<Json<BranchComparison>, AppError> {
+    let comparison = CompareService::new(app_state.dal.clone())
+        .compare_branches(user.id, conversation_id, params.left, params.right)
+        .await?;
+    Ok(Json(comparison))
+}
+
+/// Get branches for a specific message, marking each as original, edited or alternative
 pub async fn get_message_branches(
     State(app_state): State<AppState>,
     Path(message_id): Path<Uuid>,
     user: UserResponse,
-) -> Result<Json<Value>, AppError> {
+) -> Result<Json<Vec<MessageBranch>>, AppError> {
     let message_repo = app_state.dal.messages();
-    let conversation_repo = app_state.dal.conversations();
-
-    // Get the message to verify conversation ownership
-    let message = message_repo
-        .find_by_id(message_id)
-        .await?
-        .ok_or_else(|| AppError::NotFound("Message not found".to_string()))?;
-
-    // Verify user owns the conversation
-    let conversation = conversation_repo
-        .find_by_id(message.conversation_id)
-        .await?
-        .ok_or_else(|| AppError::NotFound("Conversation not found".to_string()))?;
-
-    if conversation.user_id != user.id {
-        return Err(AppError::Forbidden("Access denied".to_string()));
-    }
+
+    app_state
+        .authorization_service
+        .require_for_message(message_id, user.id, ConversationRole::Viewer)
+        .await?;
 
     // Get branches for this message
     let branches = message_repo.find_message_branches(message_id).await?;
+    let kinds = BranchKind::classify(&branches);
+
+    Ok(Json(
+        branches
+            .into_iter()
+            .zip(kinds)
+            .map(|(message, kind)| MessageBranch { message, kind })
+            .collect(),
+    ))
+}
 
-    Ok(Json(serde_json::to_value(branches)?))
+/// Every edited version of a message, oldest first
+pub async fn get_message_history(
+    State(app_state): State<AppState>,
+    Path(message_id): Path<Uuid>,
+    user: UserResponse,
+) -> Result<Json<Vec<Message>>, AppError> {
+    app_state
+        .authorization_service
+        .require_for_message(message_id, user.id, ConversationRole::Viewer)
+        .await?;
+
+    let versions = app_state
+        .dal
+        .messages()
+        .find_edit_history(message_id)
+        .await?;
+    Ok(Json(versions))
 }
 
-/// Delete a message (soft delete by setting is_active = false)
+/// Delete a message and the replies below it (soft delete through `deleted_at`)
 pub async fn delete_message(
     State(app_state): State<AppState>,
     Path(message_id): Path<Uuid>,
     user: UserResponse,
 ) -> Result<StatusCode, AppError> {
     let message_repo = app_state.dal.messages();
-    let conversation_repo = app_state.dal.conversations();
-
-    // Get the message to verify conversation ownership
-    let message = message_repo
-        .find_by_id(message_id)
-        .await?
-        .ok_or_else(|| AppError::NotFound("Message not found".to_string()))?;
-
-    // Verify user owns the conversation
-    let conversation = conversation_repo
-        .find_by_id(message.conversation_id)
-        .await?
-        .ok_or_else(|| AppError::NotFound("Conversation not found".to_string()))?;
-
-    if conversation.user_id != user.id {
-        return Err(AppError::Forbidden("Access denied".to_string()));
-    }
+
+    app_state
+        .authorization_service
+        .require_for_message(message_id, user.id, ConversationRole::Editor)
+        .await?;
 
     // Perform soft delete
     let deleted = message_repo.delete(message_id).await?;
@@ -199,7 +218,7 @@ pub async fn delete_message(
 async fn get_downstream_messages(
     message_repo: &MessageRepository,
     message_id: Uuid,
-) -> Result<Vec<crate::models::Message>, AppError> {
+) -> Result<Vec<Message>, AppError> {
     // This is a simplified version - in a real implementation, you'd want to
     // recursively find all child messages that would be deactivated
     let branches = message_repo.find_message_branches(message_id).await?;
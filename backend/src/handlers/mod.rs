@@ -1,10 +1,17 @@
+pub mod annotation;
 pub mod auth;
 pub mod chat;
 pub mod chat_persistent;
 pub mod chat_stream;
 pub mod conversation;
-// pub mod file;    // Temporarily disabled - needs additional imports
+pub mod file;
 pub mod health;
+pub mod member;
 pub mod message;
 pub mod models;
+pub mod project;
+pub mod saved_search;
 pub mod search;
+pub mod share;
+pub mod tag;
+pub mod topic;
@@ -0,0 +1,202 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    app_state::AppState,
+    error::AppError,
+    models::{
+        ConversationRole, CreateProjectRequest, Project, ProjectSummary, UpdateProjectRequest,
+        UserResponse,
+    },
+    repositories::Repository,
+};
+
+/// List the user's projects with their conversation counts
+pub async fn list_projects(
+    State(app_state): State<AppState>,
+    user: UserResponse,
+) -> Result<Json<Vec<ProjectSummary>>, AppError> {
+    let projects = app_state.dal.projects().find_by_user_id(user.id).await?;
+    Ok(Json(projects))
+}
+
+/// Create a project
+pub async fn create_project(
+    State(app_state): State<AppState>,
+    user: UserResponse,
+    Json(request): Json<CreateProjectRequest>,
+) -> Result<(StatusCode, Json<Project>), AppError> {
+    request.validate().map_err(|e| AppError::ValidationError {
+        field: "payload".to_string(),
+        message: format!("Validation failed: {}", e),
+    })?;
+    validate_default_model(&app_state, request.default_model.as_deref())?;
+
+    let project_repo = app_state.dal.projects();
+    if project_repo
+        .name_exists(user.id, request.name.trim(), None)
+        .await?
+    {
+        return Err(AppError::ValidationError {
+            field: "name".to_string(),
+            message: "A project with this name already exists".to_string(),
+        });
+    }
+
+    let project = project_repo.create_from_request(user.id, request).await?;
+    Ok((StatusCode::CREATED, Json(project)))
+}
+
+/// Get a single project
+pub async fn get_project(
+    State(app_state): State<AppState>,
+    Path(project_id): Path<Uuid>,
+    user: UserResponse,
+) -> Result<Json<Project>, AppError> {
+    let project = find_owned_project(&app_state, project_id, user.id).await?;
+    Ok(Json(project))
+}
+
+/// Update a project's name, description or conversation defaults
+pub async fn update_project(
+    State(app_state): State<AppState>,
+    Path(project_id): Path<Uuid>,
+    user: UserResponse,
+    Json(request): Json<UpdateProjectRequest>,
+) -> Result<Json<Project>, AppError> {
+    request.validate().map_err(|e| AppError::ValidationError {
+        field: "payload".to_string(),
+        message: format!("Validation failed: {}", e),
+    })?;
+    validate_default_model(
+        &app_state,
+        request.default_model.as_deref().filter(|m| !m.is_empty()),
+    )?;
+
+    find_owned_project(&app_state, project_id, user.id).await?;
+
+    let project_repo = app_state.dal.projects();
+    if let Some(name) = &request.name {
+        if project_repo
+            .name_exists(user.id, name.trim(), Some(project_id))
+            .await?
+        {
+            return Err(AppError::ValidationError {
+                field: "name".to_string(),
+                message: "A project with this name already exists".to_string(),
+            });
+        }
+    }
+
+    let project = project_repo
+        .update_from_request(project_id, request)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Project not found".to_string()))?;
+    Ok(Json(project))
+}
+
+/// Delete a project. Its conversations are kept and simply unlinked.
+pub async fn delete_project(
+    State(app_state): State<AppState>,
+    Path(project_id): Path<Uuid>,
+    user: UserResponse,
+) -> Result<StatusCode, AppError> {
+    find_owned_project(&app_state, project_id, user.id).await?;
+    app_state.dal.projects().delete(project_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Add a conversation to a project
+pub async fn add_conversation_to_project(
+    State(app_state): State<AppState>,
+    Path((project_id, conversation_id)): Path<(Uuid, Uuid)>,
+    user: UserResponse,
+) -> Result<StatusCode, AppError> {
+    find_owned_project(&app_state, project_id, user.id).await?;
+    app_state
+        .authorization_service
+        .require(conversation_id, user.id, ConversationRole::Owner)
+        .await?;
+
+    app_state
+        .dal
+        .projects()
+        .add_conversation(project_id, conversation_id)
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Remove a conversation from a project
+pub async fn remove_conversation_from_project(
+    State(app_state): State<AppState>,
+    Path((project_id, conversation_id)): Path<(Uuid, Uuid)>,
+    user: UserResponse,
+) -> Result<StatusCode, AppError> {
+    find_owned_project(&app_state, project_id, user.id).await?;
+
+    let removed = app_state
+        .dal
+        .projects()
+        .remove_conversation(project_id, conversation_id)
+        .await?;
+
+    if removed {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(AppError::NotFound(
+            "Conversation is not in this project".to_string(),
+        ))
+    }
+}
+
+/// List the projects a conversation belongs to
+pub async fn get_conversation_projects(
+    State(app_state): State<AppState>,
+    Path(conversation_id): Path<Uuid>,
+    user: UserResponse,
+) -> Result<Json<Vec<Project>>, AppError> {
+    app_state
+        .authorization_service
+        .require(conversation_id, user.id, ConversationRole::Owner)
+        .await?;
+
+    let projects = app_state
+        .dal
+        .projects()
+        .find_by_conversation_id(conversation_id)
+        .await?;
+    Ok(Json(projects))
+}
+
+async fn find_owned_project(
+    app_state: &AppState,
+    project_id: Uuid,
+    user_id: Uuid,
+) -> Result<Project, AppError> {
+    let project = app_state
+        .dal
+        .projects()
+        .find_by_id(project_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Project not found".to_string()))?;
+
+    if project.user_id != user_id {
+        return Err(AppError::Forbidden("Access denied".to_string()));
+    }
+
+    Ok(project)
+}
+
+fn validate_default_model(app_state: &AppState, model: Option<&str>) -> Result<(), AppError> {
+    match model {
+        Some(model) if !app_state.conversation_service.is_model_supported(model) => Err(
+            AppError::BadRequest(format!("Unsupported model: {}", model)),
+        ),
+        _ => Ok(()),
+    }
+}
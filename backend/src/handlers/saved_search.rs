@@ -0,0 +1,151 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{sse::Event, Json, Sse},
+};
+use futures::Stream;
+use std::{convert::Infallible, time::Duration};
+use uuid::Uuid;
+
+use crate::{
+    app_state::AppState,
+    error::AppError,
+    handlers::search::search_response,
+    models::{
+        RunSavedSearchParams, SaveSearchRequest, SavedSearch, SavedSearchMatches,
+        SavedSearchRunResponse, UserResponse,
+    },
+    services::saved_search::SavedSearchService,
+};
+
+/// How often the notification stream looks for changed new-match counts
+const MATCH_POLL_INTERVAL: Duration = Duration::from_secs(15);
+
+fn saved_search_service(app_state: &AppState) -> Result<SavedSearchService, AppError> {
+    SavedSearchService::new(app_state.config.clone(), app_state.dal.clone())
+}
+
+/// The user's saved searches, those with new matches first
+pub async fn list_saved_searches(
+    State(app_state): State<AppState>,
+    user: UserResponse,
+) -> Result<Json<Vec<SavedSearch>>, AppError> {
+    let saved_searches = saved_search_service(&app_state)?.list(user.id).await?;
+    Ok(Json(saved_searches))
+}
+
+pub async fn create_saved_search(
+    State(app_state): State<AppState>,
+    user: UserResponse,
+    Json(request): Json<SaveSearchRequest>,
+) -> Result<(StatusCode, Json<SavedSearch>), AppError> {
+    let saved_search = saved_search_service(&app_state)?
+        .create(user.id, request)
+        .await?;
+    Ok((StatusCode::CREATED, Json(saved_search)))
+}
+
+pub async fn get_saved_search(
+    State(app_state): State<AppState>,
+    Path(id): Path<Uuid>,
+    user: UserResponse,
+) -> Result<Json<SavedSearch>, AppError> {
+    let saved_search = saved_search_service(&app_state)?.get(id, user.id).await?;
+    Ok(Json(saved_search))
+}
+
+/// Replace a saved search; its new matches are counted from now on
+pub async fn update_saved_search(
+    State(app_state): State<AppState>,
+    Path(id): Path<Uuid>,
+    user: UserResponse,
+    Json(request): Json<SaveSearchRequest>,
+) -> Result<Json<SavedSearch>, AppError> {
+    let saved_search = saved_search_service(&app_state)?
+        .update(id, user.id, request)
+        .await?;
+    Ok(Json(saved_search))
+}
+
+pub async fn delete_saved_search(
+    State(app_state): State<AppState>,
+    Path(id): Path<Uuid>,
+    user: UserResponse,
+) -> Result<StatusCode, AppError> {
+    saved_search_service(&app_state)?
+        .delete(id, user.id)
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Run a saved search and mark its new matches seen
+pub async fn run_saved_search(
+    State(app_state): State<AppState>,
+    Path(id): Path<Uuid>,
+    user: UserResponse,
+    Query(params): Query<RunSavedSearchParams>,
+) -> Result<Json<SavedSearchRunResponse>, AppError> {
+    let run = saved_search_service(&app_state)?
+        .run(id, user.id, params.limit)
+        .await?;
+
+    Ok(Json(SavedSearchRunResponse {
+        search: search_response(run.saved_search.query.clone(), run.search),
+        saved_search: run.saved_search,
+        new_since: run.new_since,
+        new_message_ids: run.new_message_ids,
+    }))
+}
+
+/// Stream the new-match counts of the user's saved searches: once on connect, then
+/// whenever the background check changes them
+pub async fn saved_search_events(
+    State(app_state): State<AppState>,
+    user: UserResponse,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, AppError> {
+    let dal = app_state.dal.clone();
+    let mut interval = tokio::time::interval(MATCH_POLL_INTERVAL);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    let stream = futures::stream::unfold(
+        (interval, None::<Vec<SavedSearchMatches>>),
+        move |(mut interval, mut last_sent)| {
+            let dal = dal.clone();
+            async move {
+                loop {
+                    interval.tick().await;
+                    let counts = match dal.saved_searches().find_match_counts(user.id).await {
+                        Ok(counts) => counts,
+                        Err(e) => {
+                            tracing::warn!(
+                                "Failed to load saved search counts for user {}: {}",
+                                user.id,
+                                e
+                            );
+                            continue;
+                        }
+                    };
+                    if last_sent.as_ref() == Some(&counts) {
+                        continue;
+                    }
+
+                    let event = Event::default().data(
+                        serde_json::json!({
+                            "type": "matches",
+                            "data": { "saved_searches": &counts }
+                        })
+                        .to_string(),
+                    );
+                    last_sent = Some(counts);
+                    return Some((Ok(event), (interval, last_sent)));
+                }
+            }
+        },
+    );
+
+    Ok(Sse::new(stream).keep_alive(
+        axum::response::sse::KeepAlive::new()
+            .interval(Duration::from_secs(30))
+            .text("keep-alive"),
+    ))
+}
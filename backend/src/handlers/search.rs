@@ -1,16 +1,22 @@
 use axum::{
-    extract::{Query, State},
+    extract::{Path, Query, State},
     response::Json,
 };
+use chrono::{DateTime, Utc};
 use serde::Deserialize;
+use uuid::Uuid;
 use validator::Validate;
 
 use crate::{
     app_state::AppState,
     error::AppError,
     models::UserResponse,
-    models::{EmbeddingJobResponse, SearchRequest, SearchResponse, SearchResultResponse},
-    services::embedding::EmbeddingService,
+    models::{
+        ConversationRole, EmbeddingJobResponse, MessageRole, RelatedConversationsResponse,
+        SearchFilters, SearchMode, SearchRequest, SearchResponse, SearchResultResponse,
+    },
+    repositories::embedding_job::EmbeddingQueueStatus,
+    services::embedding::{EmbeddingService, MessageSearch},
 };
 
 #[derive(Debug, Deserialize)]
@@ -18,9 +24,68 @@ pub struct SearchQueryParams {
     pub q: String,
     pub limit: Option<i64>,
     pub similarity_threshold: Option<f32>,
+    #[serde(default)]
+    pub mode: SearchMode,
+    /// Comma-separated conversation IDs
+    pub conversation_ids: Option<String>,
+    pub role: Option<MessageRole>,
+    pub model: Option<String>,
+    pub provider: Option<String>,
+    pub created_after: Option<DateTime<Utc>>,
+    pub created_before: Option<DateTime<Utc>>,
+    pub active_branch_only: Option<bool>,
 }
 
-/// Search messages using semantic similarity
+impl SearchQueryParams {
+    fn filters(&self) -> Result<SearchFilters, AppError> {
+        let conversation_ids = match &self.conversation_ids {
+            Some(ids) => Some(
+                ids.split(',')
+                    .filter(|id| !id.trim().is_empty())
+                    .map(|id| {
+                        id.trim().parse::<Uuid>().map_err(|_| {
+                            AppError::BadRequest(format!("Invalid conversation ID '{}'", id))
+                        })
+                    })
+                    .collect::<Result<Vec<_>, _>>()?,
+            ),
+            None => None,
+        };
+
+        let filters = SearchFilters {
+            conversation_ids,
+            role: self.role.clone(),
+            model: self.model.clone(),
+            provider: self.provider.clone(),
+            created_after: self.created_after,
+            created_before: self.created_before,
+            active_branch_only: self.active_branch_only.unwrap_or(true),
+            indexed_after: None,
+        };
+        filters
+            .validate()
+            .map_err(|e| AppError::BadRequest(format!("Validation error: {e}")))?;
+        Ok(filters)
+    }
+}
+
+pub(crate) fn search_response(query: String, search: MessageSearch) -> SearchResponse {
+    let results: Vec<SearchResultResponse> = search
+        .results
+        .into_iter()
+        .map(SearchResultResponse::from_search_result)
+        .collect();
+
+    SearchResponse {
+        query,
+        mode: search.mode,
+        total_found: results.len(),
+        results,
+        facets: search.facets,
+    }
+}
+
+/// Search messages by meaning, by exact terms, or both
 pub async fn search_messages(
     user: UserResponse,
     State(state): State<AppState>,
@@ -40,31 +105,22 @@ pub async fn search_messages(
     }
 
     // Create embedding service
-    let embedding_repository = state.dal.embeddings().clone();
-    let embedding_service = EmbeddingService::new(state.config.clone(), embedding_repository)?;
+    let embedding_service = EmbeddingService::new(state.config.clone(), state.dal.clone())?;
 
     // Perform search
-    let results = embedding_service
-        .search_similar_messages(
+    let filters = params.filters()?;
+    let search = embedding_service
+        .search_messages(
             &params.q,
             Some(user.id),
+            params.mode,
+            &filters,
             params.limit,
             params.similarity_threshold,
         )
         .await?;
 
-    let search_results: Vec<SearchResultResponse> = results
-        .into_iter()
-        .map(SearchResultResponse::from_search_result)
-        .collect();
-
-    let response = SearchResponse {
-        query: params.q,
-        total_found: search_results.len(),
-        results: search_results,
-    };
-
-    Ok(Json(response))
+    Ok(Json(search_response(params.q, search)))
 }
 
 /// Search messages using POST request with JSON body
@@ -85,63 +141,100 @@ pub async fn search_messages_post(
     );
 
     // Create embedding service
-    let embedding_repository = state.dal.embeddings().clone();
-    let embedding_service = EmbeddingService::new(state.config.clone(), embedding_repository)?;
+    let embedding_service = EmbeddingService::new(state.config.clone(), state.dal.clone())?;
 
     // Perform search
-    let results = embedding_service
-        .search_similar_messages(
+    let search = embedding_service
+        .search_messages(
             &request.query,
             Some(user.id),
+            request.mode,
+            &request.filters,
             request.limit,
             request.similarity_threshold,
         )
         .await?;
 
-    let search_results: Vec<SearchResultResponse> = results
-        .into_iter()
-        .map(SearchResultResponse::from_search_result)
-        .collect();
+    Ok(Json(search_response(request.query, search)))
+}
 
-    let response = SearchResponse {
-        query: request.query,
-        total_found: search_results.len(),
-        results: search_results,
-    };
+#[derive(Debug, Deserialize)]
+pub struct RelatedConversationsParams {
+    pub limit: Option<i64>,
+}
+
+/// Conversations the user can open that cover the same ground, by centroid similarity
+pub async fn related_conversations(
+    user: UserResponse,
+    State(state): State<AppState>,
+    Path(conversation_id): Path<Uuid>,
+    Query(params): Query<RelatedConversationsParams>,
+) -> Result<Json<RelatedConversationsResponse>, AppError> {
+    state
+        .authorization_service
+        .require(conversation_id, user.id, ConversationRole::Viewer)
+        .await?;
 
-    Ok(Json(response))
+    let embedding_service = EmbeddingService::new(state.config.clone(), state.dal.clone())?;
+    let related = embedding_service
+        .find_related_conversations(conversation_id, user.id, params.limit)
+        .await?;
+
+    Ok(Json(RelatedConversationsResponse {
+        conversation_id,
+        related,
+    }))
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct EmbeddingJobParams {
+    /// Give jobs that ran out of attempts another round of retries
+    #[serde(default)]
+    pub retry_failed: bool,
 }
 
 /// Admin endpoint to trigger background embedding job
 pub async fn trigger_embedding_job(
     user: UserResponse,
     State(state): State<AppState>,
+    Query(params): Query<EmbeddingJobParams>,
 ) -> Result<Json<EmbeddingJobResponse>, AppError> {
     // Admin role check - for now just log the user
     tracing::info!("Triggering background embedding job for user: {}", user.id);
 
     // Create embedding service
-    let embedding_repository = state.dal.embeddings().clone();
-    let embedding_service = EmbeddingService::new(state.config.clone(), embedding_repository)?;
+    let embedding_service = EmbeddingService::new(state.config.clone(), state.dal.clone())?;
+
+    if params.retry_failed {
+        let requeued = state.dal.embedding_jobs().retry_failed().await?;
+        tracing::info!("Requeued {} failed embedding jobs", requeued);
+    }
 
     // Run background job
-    match embedding_service.background_embedding_job().await {
-        Ok(processed_count) => {
-            let response = EmbeddingJobResponse {
-                processed_count,
-                success: true,
-            };
-            Ok(Json(response))
-        }
+    let (processed_count, error) = match embedding_service.background_embedding_job().await {
+        Ok(processed_count) => (processed_count, None),
         Err(e) => {
             tracing::error!("Background embedding job failed: {}", e);
-            let response = EmbeddingJobResponse {
-                processed_count: 0,
-                success: false,
-            };
-            Ok(Json(response))
+            (0, Some(e.to_string()))
         }
-    }
+    };
+
+    Ok(Json(EmbeddingJobResponse {
+        processed_count,
+        success: error.is_none(),
+        error,
+        status: embedding_service.queue_status(user.id).await?,
+    }))
+}
+
+/// Progress of the embedding queue for the user's messages with their most recent
+/// failures
+pub async fn embedding_job_status(
+    user: UserResponse,
+    State(state): State<AppState>,
+) -> Result<Json<EmbeddingQueueStatus>, AppError> {
+    let embedding_service = EmbeddingService::new(state.config.clone(), state.dal.clone())?;
+    Ok(Json(embedding_service.queue_status(user.id).await?))
 }
 
 /// Health check endpoint for search service
@@ -167,14 +260,9 @@ pub async fn search_health(
 pub async fn search_stats(
     State(state): State<AppState>,
 ) -> Result<Json<serde_json::Value>, AppError> {
-    // Count messages without embeddings
-    let pending_embeddings = state
-        .dal
-        .repositories
-        .embeddings
-        .find_messages_without_embeddings(1000)
-        .await?
-        .len();
+    // Count messages waiting in the embedding queue
+    let queue = state.dal.embedding_jobs().status(None, 0).await?;
+    let pending_embeddings = queue.pending + queue.processing;
 
     Ok(Json(serde_json::json!({
         "pending_embeddings": pending_embeddings,
@@ -0,0 +1,105 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    app_state::AppState,
+    error::AppError,
+    models::{
+        ConversationRole, CreateShareLinkRequest, ShareLink, SharedConversation, UserResponse,
+    },
+    services::share::ShareService,
+};
+
+/// Create a read-only share link for a conversation
+pub async fn create_share_link(
+    State(app_state): State<AppState>,
+    Path(conversation_id): Path<Uuid>,
+    user: UserResponse,
+    Json(request): Json<CreateShareLinkRequest>,
+) -> Result<(StatusCode, Json<ShareLink>), AppError> {
+    request.validate().map_err(|e| AppError::ValidationError {
+        field: "payload".to_string(),
+        message: format!("Validation failed: {}", e),
+    })?;
+
+    let conversation = app_state
+        .authorization_service
+        .require(conversation_id, user.id, ConversationRole::Owner)
+        .await?;
+    if conversation.deleted_at.is_some() {
+        return Err(AppError::NotFound("Conversation not found".to_string()));
+    }
+
+    let link = ShareService::new(app_state.dal.clone())
+        .create_share_link(&conversation, &request)
+        .await?;
+    Ok((StatusCode::CREATED, Json(link)))
+}
+
+/// List the share links of a conversation, including revoked and expired ones
+pub async fn list_conversation_share_links(
+    State(app_state): State<AppState>,
+    Path(conversation_id): Path<Uuid>,
+    user: UserResponse,
+) -> Result<Json<Vec<ShareLink>>, AppError> {
+    app_state
+        .authorization_service
+        .require(conversation_id, user.id, ConversationRole::Owner)
+        .await?;
+
+    let links = app_state
+        .dal
+        .share_links()
+        .find_by_conversation_id(conversation_id)
+        .await?;
+    Ok(Json(links))
+}
+
+/// List all share links the user has created
+pub async fn list_share_links(
+    State(app_state): State<AppState>,
+    user: UserResponse,
+) -> Result<Json<Vec<ShareLink>>, AppError> {
+    let links = app_state.dal.share_links().find_by_user_id(user.id).await?;
+    Ok(Json(links))
+}
+
+/// Revoke a share link; the token stops working immediately
+pub async fn revoke_share_link(
+    State(app_state): State<AppState>,
+    Path(link_id): Path<Uuid>,
+    user: UserResponse,
+) -> Result<StatusCode, AppError> {
+    let share_links = app_state.dal.share_links();
+    let link = share_links
+        .find_by_id(link_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Share link not found".to_string()))?;
+
+    if link.user_id != user.id {
+        return Err(AppError::Forbidden("Access denied".to_string()));
+    }
+
+    share_links.revoke(link_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Public, unauthenticated view of a shared conversation snapshot
+pub async fn get_shared_conversation(
+    State(app_state): State<AppState>,
+    Path(token): Path<String>,
+) -> Result<Json<SharedConversation>, AppError> {
+    // Revoked, expired and unknown tokens all look the same to the viewer
+    let snapshot = app_state
+        .dal
+        .share_links()
+        .view_snapshot(&token)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Share link not found".to_string()))?;
+    Ok(Json(snapshot))
+}
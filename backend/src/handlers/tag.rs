@@ -0,0 +1,194 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+};
+use serde::Deserialize;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    app_state::AppState,
+    error::AppError,
+    models::{ConversationRole, CreateTagRequest, Tag, TagSummary, UpdateTagRequest, UserResponse},
+    repositories::Repository,
+};
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct TagConversationRequest {
+    /// Tag name; created on first use
+    #[validate(length(min = 1, max = 100))]
+    pub name: String,
+}
+
+/// List the user's tags with their conversation counts
+pub async fn list_tags(
+    State(app_state): State<AppState>,
+    user: UserResponse,
+) -> Result<Json<Vec<TagSummary>>, AppError> {
+    let tags = app_state.dal.tags().find_by_user_id(user.id).await?;
+    Ok(Json(tags))
+}
+
+/// Create a tag
+pub async fn create_tag(
+    State(app_state): State<AppState>,
+    user: UserResponse,
+    Json(request): Json<CreateTagRequest>,
+) -> Result<(StatusCode, Json<Tag>), AppError> {
+    request.validate().map_err(|e| AppError::ValidationError {
+        field: "payload".to_string(),
+        message: format!("Validation failed: {}", e),
+    })?;
+
+    let tag_repo = app_state.dal.tags();
+    if tag_repo
+        .name_exists(user.id, request.name.trim(), None)
+        .await?
+    {
+        return Err(AppError::ValidationError {
+            field: "name".to_string(),
+            message: "A tag with this name already exists".to_string(),
+        });
+    }
+
+    let tag = tag_repo.create_from_request(user.id, request).await?;
+    Ok((StatusCode::CREATED, Json(tag)))
+}
+
+/// Rename or recolor a tag
+pub async fn update_tag(
+    State(app_state): State<AppState>,
+    Path(tag_id): Path<Uuid>,
+    user: UserResponse,
+    Json(request): Json<UpdateTagRequest>,
+) -> Result<Json<Tag>, AppError> {
+    request.validate().map_err(|e| AppError::ValidationError {
+        field: "payload".to_string(),
+        message: format!("Validation failed: {}", e),
+    })?;
+
+    find_owned_tag(&app_state, tag_id, user.id).await?;
+
+    let tag_repo = app_state.dal.tags();
+    if let Some(name) = &request.name {
+        if tag_repo
+            .name_exists(user.id, name.trim(), Some(tag_id))
+            .await?
+        {
+            return Err(AppError::ValidationError {
+                field: "name".to_string(),
+                message: "A tag with this name already exists".to_string(),
+            });
+        }
+    }
+
+    let tag = tag_repo
+        .update_from_request(tag_id, request)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Tag not found".to_string()))?;
+    Ok(Json(tag))
+}
+
+/// Delete a tag and remove it from all conversations
+pub async fn delete_tag(
+    State(app_state): State<AppState>,
+    Path(tag_id): Path<Uuid>,
+    user: UserResponse,
+) -> Result<StatusCode, AppError> {
+    find_owned_tag(&app_state, tag_id, user.id).await?;
+    app_state.dal.tags().delete(tag_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// List the tags on a conversation
+pub async fn get_conversation_tags(
+    State(app_state): State<AppState>,
+    Path(conversation_id): Path<Uuid>,
+    user: UserResponse,
+) -> Result<Json<Vec<Tag>>, AppError> {
+    app_state
+        .authorization_service
+        .require(conversation_id, user.id, ConversationRole::Owner)
+        .await?;
+
+    let tags = app_state
+        .dal
+        .tags()
+        .find_by_conversation_id(conversation_id)
+        .await?;
+    Ok(Json(tags))
+}
+
+/// Tag a conversation by name, creating the tag if it doesn't exist yet
+pub async fn tag_conversation(
+    State(app_state): State<AppState>,
+    Path(conversation_id): Path<Uuid>,
+    user: UserResponse,
+    Json(request): Json<TagConversationRequest>,
+) -> Result<Json<Tag>, AppError> {
+    request.validate().map_err(|e| AppError::ValidationError {
+        field: "name".to_string(),
+        message: format!("Validation failed: {}", e),
+    })?;
+
+    app_state
+        .authorization_service
+        .require(conversation_id, user.id, ConversationRole::Owner)
+        .await?;
+
+    let tag_repo = app_state.dal.tags();
+    let tag = tag_repo
+        .find_or_create_by_name(user.id, &request.name)
+        .await?;
+    tag_repo
+        .add_to_conversation(tag.id, conversation_id)
+        .await?;
+    Ok(Json(tag))
+}
+
+/// Remove a tag from a conversation
+pub async fn untag_conversation(
+    State(app_state): State<AppState>,
+    Path((conversation_id, tag_id)): Path<(Uuid, Uuid)>,
+    user: UserResponse,
+) -> Result<StatusCode, AppError> {
+    app_state
+        .authorization_service
+        .require(conversation_id, user.id, ConversationRole::Owner)
+        .await?;
+    find_owned_tag(&app_state, tag_id, user.id).await?;
+
+    let removed = app_state
+        .dal
+        .tags()
+        .remove_from_conversation(tag_id, conversation_id)
+        .await?;
+
+    if removed {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(AppError::NotFound(
+            "Tag is not on this conversation".to_string(),
+        ))
+    }
+}
+
+async fn find_owned_tag(
+    app_state: &AppState,
+    tag_id: Uuid,
+    user_id: Uuid,
+) -> Result<Tag, AppError> {
+    let tag = app_state
+        .dal
+        .tags()
+        .find_by_id(tag_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Tag not found".to_string()))?;
+
+    if tag.user_id != user_id {
+        return Err(AppError::Forbidden("Access denied".to_string()));
+    }
+
+    Ok(tag)
+}
@@ -0,0 +1,48 @@
+use axum::{extract::State, http::StatusCode, response::Json};
+use serde_json::{json, Value};
+
+use crate::{
+    app_state::AppState,
+    error::AppError,
+    models::{TopicMapResponse, TopicsResponse, UserResponse},
+    services::topic::TopicService,
+};
+
+/// Start clustering the user's messages again in the background; the new topics
+/// replace the old ones in `list_topics` once the run is done
+pub async fn refresh_topics(
+    State(app_state): State<AppState>,
+    user: UserResponse,
+) -> Result<(StatusCode, Json<Value>), AppError> {
+    let topic_service = TopicService::new(app_state.config.clone(), app_state.dal.clone())?;
+    let user_id = user.id;
+    tokio::spawn(async move {
+        if let Err(e) = topic_service.refresh_users(Some(user_id)).await {
+            tracing::error!("Failed to refresh topics for user {}: {}", user_id, e);
+        }
+    });
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(json!({ "status": "refreshing" })),
+    ))
+}
+
+/// The user's topics from the last run, each with its messages and conversations
+pub async fn list_topics(
+    State(app_state): State<AppState>,
+    user: UserResponse,
+) -> Result<Json<TopicsResponse>, AppError> {
+    let topics = app_state.dal.topics().find_members(user.id).await?;
+    Ok(Json(TopicsResponse { topics }))
+}
+
+/// Map coordinates of every clustered message, with the topics they belong to
+pub async fn topic_map(
+    State(app_state): State<AppState>,
+    user: UserResponse,
+) -> Result<Json<TopicMapResponse>, AppError> {
+    let topics = app_state.dal.topics().find_clusters(user.id).await?;
+    let points = app_state.dal.topics().find_points(user.id).await?;
+    Ok(Json(TopicMapResponse { topics, points }))
+}
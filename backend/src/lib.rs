@@ -2,6 +2,7 @@
 pub mod app_state;
 pub mod config;
 pub mod database;
+pub mod embeddings;
 pub mod error;
 pub mod handlers;
 pub mod llm;
@@ -61,13 +61,17 @@ impl LLMService for AnthropicService {
                 role: "assistant".to_string(),
                 content: "This is a placeholder response from Anthropic service. The actual integration is pending.".to_string(),
             },
+            reasoning: None,
             usage: Some(Usage {
                 prompt_tokens: 10,
                 completion_tokens: 20,
                 total_tokens: 30,
+                reasoning_tokens: 0,
+                cached_tokens: 0,
             }),
             model: request.model.clone(),
             provider: "anthropic".to_string(),
+            latency_ms: None,
         })
     }
 
@@ -111,6 +115,8 @@ impl LLMService for AnthropicService {
                     prompt_tokens: 10,
                     completion_tokens: 20,
                     total_tokens: 30,
+                    reasoning_tokens: 0,
+                    cached_tokens: 0,
                 }),
                 model: Some(model.clone()),
                 provider: Some("anthropic".to_string()),
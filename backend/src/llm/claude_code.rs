@@ -4,6 +4,7 @@ use serde::Deserialize;
 use std::pin::Pin;
 use std::process::Stdio;
 use std::time::Duration;
+use thiserror::Error;
 use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::process::Command;
 use tokio::time::timeout;
@@ -47,6 +48,7 @@ struct ClaudeCodeUsage {
     input_tokens: Option<u32>,
     output_tokens: Option<u32>,
     total_tokens: Option<u32>,
+    cache_read_input_tokens: Option<u32>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -65,6 +67,194 @@ struct ClaudeCodeStreamEvent {
     content: Option<String>,
     usage: Option<ClaudeCodeUsage>,
     model: Option<String>,
+    /// The model's turn, on `assistant` events of the CLI's stream-json output
+    message: Option<ClaudeCodeMessage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ClaudeCodeMessage {
+    model: Option<String>,
+    #[serde(default)]
+    content: Vec<ClaudeCodeContentBlock>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClaudeCodeContentBlock {
+    Text {
+        text: String,
+    },
+    Thinking {
+        thinking: String,
+    },
+    #[serde(other)]
+    Other,
+}
+
+/// Turn one line of the CLI's stream-json output into stream events. Thinking blocks
+/// become reasoning events and the final `result` line ends the stream with its usage.
+pub fn stream_events_from_line(line: &str) -> Result<Vec<StreamEvent>, AppError> {
+    let event: ClaudeCodeStreamEvent = serde_json::from_str(line)
+        .map_err(|e| AppError::InternalServerError(format!("Stream parsing error: {}", e)))?;
+    let provider = Some("claude-code".to_string());
+
+    let events = match event.event_type.as_str() {
+        "assistant" => {
+            let Some(message) = event.message else {
+                return Ok(Vec::new());
+            };
+            message
+                .content
+                .into_iter()
+                .filter_map(|block| {
+                    let (event_type, data) = match block {
+                        ClaudeCodeContentBlock::Text { text } => (StreamEventType::Token, text),
+                        ClaudeCodeContentBlock::Thinking { thinking } => {
+                            (StreamEventType::Reasoning, thinking)
+                        }
+                        ClaudeCodeContentBlock::Other => return None,
+                    };
+                    Some(StreamEvent {
+                        event_type,
+                        data: Some(data),
+                        usage: None,
+                        model: message.model.clone(),
+                        provider: provider.clone(),
+                    })
+                })
+                .collect()
+        }
+        "content_block_delta" | "content" => event
+            .content
+            .map(|content| StreamEvent {
+                event_type: StreamEventType::Token,
+                data: Some(content),
+                usage: ClaudeCodeService::convert_usage(event.usage, None),
+                model: event.model,
+                provider,
+            })
+            .into_iter()
+            .collect(),
+        "thinking" | "thinking_delta" => event
+            .content
+            .map(|content| StreamEvent {
+                event_type: StreamEventType::Reasoning,
+                data: Some(content),
+                usage: None,
+                model: event.model,
+                provider,
+            })
+            .into_iter()
+            .collect(),
+        "result" => {
+            let result: ClaudeCodeResponse = serde_json::from_str(line).map_err(|e| {
+                AppError::InternalServerError(format!("Stream parsing error: {}", e))
+            })?;
+            if result.is_error.unwrap_or(false) {
+                return Err(AppError::InternalServerError(format!(
+                    "Claude Code API error: {}",
+                    result.result
+                )));
+            }
+            vec![StreamEvent {
+                event_type: StreamEventType::Done,
+                data: None,
+                usage: ClaudeCodeService::convert_usage(result.usage, result.model_usage.as_ref()),
+                model: event.model,
+                provider,
+            }]
+        }
+        "message_stop" | "done" => vec![StreamEvent {
+            event_type: StreamEventType::Done,
+            data: None,
+            usage: ClaudeCodeService::convert_usage(event.usage, None),
+            model: event.model,
+            provider,
+        }],
+        "error" => {
+            return Err(AppError::InternalServerError(
+                event
+                    .content
+                    .unwrap_or_else(|| "Unknown Claude Code error".to_string()),
+            ))
+        }
+        _ => {
+            // Ignore system and tool events
+            tracing::debug!(
+                "Unknown Claude Code stream event type: {}",
+                event.event_type
+            );
+            Vec::new()
+        }
+    };
+
+    Ok(events)
+}
+
+/// Why the output of a non-streaming run holds no answer
+#[derive(Debug, Error)]
+pub enum ClaudeCodeOutputError {
+    /// The CLI ran and reported an error from the API in its result line
+    #[error("Claude Code API error: {0}")]
+    Api(String),
+    /// The output could not be read as stream-json
+    #[error("Failed to parse Claude Code response: {0}")]
+    Invalid(String),
+}
+
+impl From<ClaudeCodeOutputError> for AppError {
+    fn from(err: ClaudeCodeOutputError) -> Self {
+        AppError::InternalServerError(err.to_string())
+    }
+}
+
+/// Build a response from the complete stream-json output of a non-streaming run: the
+/// answer and usage come from the final `result` line and the reasoning from the
+/// thinking blocks before it
+pub fn response_from_stream_output(
+    output: &str,
+    model: &str,
+) -> Result<ChatResponse, ClaudeCodeOutputError> {
+    let mut reasoning = Vec::new();
+    let mut result = None;
+    for line in output.lines().filter(|line| !line.trim().is_empty()) {
+        let event: ClaudeCodeStreamEvent = serde_json::from_str(line)
+            .map_err(|e| ClaudeCodeOutputError::Invalid(e.to_string()))?;
+        match event.event_type.as_str() {
+            "assistant" => {
+                let blocks = event.message.map(|m| m.content).unwrap_or_default();
+                reasoning.extend(blocks.into_iter().filter_map(|block| match block {
+                    ClaudeCodeContentBlock::Thinking { thinking } => Some(thinking),
+                    _ => None,
+                }));
+            }
+            "result" => {
+                result = Some(
+                    serde_json::from_str::<ClaudeCodeResponse>(line)
+                        .map_err(|e| ClaudeCodeOutputError::Invalid(e.to_string()))?,
+                );
+            }
+            _ => {}
+        }
+    }
+
+    let result =
+        result.ok_or_else(|| ClaudeCodeOutputError::Invalid("no result line".to_string()))?;
+    if result.is_error.unwrap_or(false) {
+        return Err(ClaudeCodeOutputError::Api(result.result));
+    }
+
+    Ok(ChatResponse {
+        message: ChatMessage {
+            role: "assistant".to_string(),
+            content: result.result,
+        },
+        reasoning: (!reasoning.is_empty()).then(|| reasoning.join("\n\n")),
+        usage: ClaudeCodeService::convert_usage(result.usage, result.model_usage.as_ref()),
+        model: model.to_string(),
+        provider: "claude-code".to_string(),
+        latency_ms: None,
+    })
 }
 
 impl ClaudeCodeService {
@@ -83,18 +273,17 @@ impl ClaudeCodeService {
     async fn execute_claude_command(
         &self,
         prompt: &str,
-        stream: bool,
     ) -> Result<(tokio::process::Child, String), AppError> {
         let mut cmd = Command::new("/home/ladvien/.npm-global/bin/claude");
 
         // Add basic flags
         cmd.arg("--print"); // Non-interactive mode
 
-        if stream {
-            cmd.arg("--output-format").arg("stream-json");
-        } else {
-            cmd.arg("--output-format").arg("json");
-        }
+        // stream-json is the only output format that includes thinking blocks; the
+        // CLI requires --verbose for it in print mode
+        cmd.arg("--output-format")
+            .arg("stream-json")
+            .arg("--verbose");
 
         // Add model specification if configured
         if !self.config.claude_code_model.is_empty() {
@@ -199,6 +388,8 @@ impl ClaudeCodeService {
                     prompt_tokens: input_tokens,
                     completion_tokens: output_tokens,
                     total_tokens: input_tokens + output_tokens,
+                    reasoning_tokens: 0,
+                    cached_tokens: model_usage.cache_read_input_tokens.unwrap_or(0),
                 });
             }
         }
@@ -210,6 +401,8 @@ impl ClaudeCodeService {
             total_tokens: u
                 .total_tokens
                 .unwrap_or(u.input_tokens.unwrap_or(0) + u.output_tokens.unwrap_or(0)),
+            reasoning_tokens: 0,
+            cached_tokens: u.cache_read_input_tokens.unwrap_or(0),
         })
     }
 
@@ -280,7 +473,7 @@ impl LLMService for ClaudeCodeService {
 
         let prompt = Self::build_prompt_from_messages(&request.messages);
         tracing::info!("Claude Code chat_completion prompt: '{}'", prompt);
-        let (child, command_debug) = self.execute_claude_command(&prompt, false).await?;
+        let (child, command_debug) = self.execute_claude_command(&prompt).await?;
 
         // Add timeout to prevent hanging - Claude Code should respond within 120 seconds
         let timeout_duration = Duration::from_secs(120);
@@ -319,15 +512,12 @@ impl LLMService for ClaudeCodeService {
             );
             tracing::error!("Claude Code CLI command: {}", command_debug);
 
-            // Try to parse the JSON response to get a more specific error
-            if let Ok(claude_response) = serde_json::from_str::<ClaudeCodeResponse>(&stdout) {
-                if claude_response.is_error.unwrap_or(false) {
-                    tracing::error!("Claude Code API error: {}", claude_response.result);
-                    return Err(AppError::InternalServerError(format!(
-                        "Claude Code API error: {}",
-                        claude_response.result
-                    )));
-                }
+            // The result line carries a more specific error
+            if let Err(e @ ClaudeCodeOutputError::Api(_)) =
+                response_from_stream_output(&stdout, &request.model)
+            {
+                tracing::error!("{}", e);
+                return Err(e.into());
             }
 
             return Err(AppError::InternalServerError(format!(
@@ -338,23 +528,9 @@ impl LLMService for ClaudeCodeService {
 
         tracing::debug!("Claude Code CLI response: {}", stdout);
 
-        let claude_response: ClaudeCodeResponse = serde_json::from_str(&stdout).map_err(|e| {
-            tracing::error!(
-                "Failed to parse Claude Code CLI JSON response: {} - Response: {}",
-                e,
-                stdout
-            );
-            AppError::InternalServerError(format!("Failed to parse Claude Code response: {}", e))
-        })?;
-
-        Ok(ChatResponse {
-            message: ChatMessage {
-                role: "assistant".to_string(),
-                content: claude_response.result,
-            },
-            usage: Self::convert_usage(claude_response.usage, claude_response.model_usage.as_ref()),
-            model: request.model.clone(),
-            provider: "claude-code".to_string(),
+        response_from_stream_output(&stdout, &request.model).map_err(|e| {
+            tracing::error!("{} - Response: {}", e, stdout);
+            e.into()
         })
     }
 
@@ -369,7 +545,7 @@ impl LLMService for ClaudeCodeService {
         }
 
         let prompt = Self::build_prompt_from_messages(&request.messages);
-        let (mut child, _command_debug) = self.execute_claude_command(&prompt, true).await?;
+        let (mut child, _command_debug) = self.execute_claude_command(&prompt).await?;
 
         let stdout = child.stdout.take().ok_or_else(|| {
             AppError::InternalServerError("Failed to capture Claude Code CLI stdout".to_string())
@@ -384,45 +560,21 @@ impl LLMService for ClaudeCodeService {
                     continue;
                 }
 
-                match serde_json::from_str::<ClaudeCodeStreamEvent>(&line) {
-                    Ok(event) => {
-                        match event.event_type.as_str() {
-                            "content_block_delta" | "content" => {
-                                if let Some(content) = event.content {
-                                    yield Ok(StreamEvent {
-                                        event_type: StreamEventType::Token,
-                                        data: Some(content),
-                                        usage: Self::convert_usage(event.usage, None),
-                                        model: event.model,
-                                        provider: Some("claude-code".to_string()),
-                                    });
-                                }
-                            }
-                            "message_stop" | "done" => {
-                                yield Ok(StreamEvent {
-                                    event_type: StreamEventType::Done,
-                                    data: None,
-                                    usage: Self::convert_usage(event.usage, None),
-                                    model: event.model,
-                                    provider: Some("claude-code".to_string()),
-                                });
-                                break;
-                            }
-                            "error" => {
-                                yield Err(AppError::InternalServerError(
-                                    event.content.unwrap_or_else(|| "Unknown Claude Code error".to_string())
-                                ));
-                                break;
-                            }
-                            _ => {
-                                // Ignore unknown event types
-                                tracing::debug!("Unknown Claude Code stream event type: {}", event.event_type);
-                            }
+                match stream_events_from_line(&line) {
+                    Ok(events) => {
+                        let done = events
+                            .iter()
+                            .any(|event| matches!(event.event_type, StreamEventType::Done));
+                        for event in events {
+                            yield Ok(event);
+                        }
+                        if done {
+                            break;
                         }
                     }
                     Err(e) => {
-                        tracing::error!("Failed to parse Claude Code stream event: {} - Line: {}", e, line);
-                        yield Err(AppError::InternalServerError(format!("Stream parsing error: {}", e)));
+                        tracing::error!("Failed to handle Claude Code stream event: {} - Line: {}", e, line);
+                        yield Err(e);
                         break;
                     }
                 }
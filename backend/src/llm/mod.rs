@@ -30,17 +30,39 @@ pub struct ChatRequest {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ChatResponse {
     pub message: ChatMessage,
+    /// Reasoning / thinking content returned separately from the answer
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reasoning: Option<String>,
     pub usage: Option<Usage>,
     pub model: String,
     pub provider: String,
+    /// Wall-clock time of the provider call, filled in by the caller that timed it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
 }
 
 /// Token usage information
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Usage {
     pub prompt_tokens: u32,
+    /// Tokens of the visible answer. Providers that count reasoning inside their
+    /// completion figure (OpenAI) subtract it when building the usage; those that
+    /// do not report it separately (Anthropic) leave it in here.
     pub completion_tokens: u32,
     pub total_tokens: u32,
+    /// Tokens spent on reasoning / thinking before the answer
+    #[serde(default)]
+    pub reasoning_tokens: u32,
+    /// Prompt tokens served from the provider's prompt cache
+    #[serde(default)]
+    pub cached_tokens: u32,
+}
+
+impl Usage {
+    /// Tokens billed at the output rate (reasoning is billed as output)
+    pub fn output_tokens(&self) -> u32 {
+        self.completion_tokens + self.reasoning_tokens
+    }
 }
 
 /// Streaming event types
@@ -57,6 +79,7 @@ pub struct StreamEvent {
 #[serde(rename_all = "snake_case")]
 pub enum StreamEventType {
     Token,
+    Reasoning,
     Usage,
     Error,
     Done,
@@ -196,6 +219,20 @@ impl LLMServiceFactory {
         models
     }
 
+    /// Calculate the cost of a completion in cents.
+    ///
+    /// `cost_per_token` is priced per 1K tokens. Reasoning tokens are counted as
+    /// output tokens. Returns `None` for models without direct cost.
+    pub fn calculate_cost_cents(model_id: &str, usage: &Usage) -> Option<i32> {
+        let cost_per_1k = Self::available_models()
+            .into_iter()
+            .find(|m| m.id == model_id)?
+            .cost_per_token?;
+
+        let billable_tokens = (usage.prompt_tokens + usage.output_tokens()) as f64;
+        Some((billable_tokens / 1000.0 * cost_per_1k as f64 * 100.0).ceil() as i32)
+    }
+
     /// Parse model ID to determine provider
     pub fn provider_from_model(model_id: &str) -> Result<Provider, AppError> {
         if model_id.starts_with("gpt-") {
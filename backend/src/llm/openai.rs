@@ -1,4 +1,5 @@
 use async_openai::{
+    config::Config,
     types::{
         ChatCompletionRequestMessage, ChatCompletionRequestSystemMessage,
         ChatCompletionRequestUserMessage, CreateChatCompletionRequest,
@@ -19,6 +20,8 @@ use crate::{config::AppConfig, error::AppError};
 #[derive(Debug, Clone)]
 pub struct OpenAIService {
     client: OpenAIClient<async_openai::config::OpenAIConfig>,
+    /// Shared with `client`, for the requests whose response is read as raw JSON
+    http_client: reqwest::Client,
     config: AppConfig,
 }
 
@@ -27,9 +30,14 @@ impl OpenAIService {
         let openai_config =
             async_openai::config::OpenAIConfig::new().with_api_key(&config.openai_api_key);
 
-        let client = OpenAIClient::with_config(openai_config);
+        let http_client = reqwest::Client::new();
+        let client = OpenAIClient::with_config(openai_config).with_http_client(http_client.clone());
 
-        Ok(Self { client, config })
+        Ok(Self {
+            client,
+            http_client,
+            config,
+        })
     }
 
     #[allow(deprecated)] // function_call field required by async-openai v0.20 struct
@@ -68,11 +76,49 @@ impl OpenAIService {
             .collect()
     }
 
+    /// Send a chat completion and return the raw response body. async-openai v0.20
+    /// drops `completion_tokens_details` and `prompt_tokens_details`, which carry the
+    /// reasoning and cached token counts, so the body is read as JSON here.
+    async fn create_raw(
+        &self,
+        request: &CreateChatCompletionRequest,
+    ) -> Result<serde_json::Value, AppError> {
+        let config = self.client.config();
+        let response = self
+            .http_client
+            .post(config.url("/chat/completions"))
+            .headers(config.headers())
+            .json(request)
+            .send()
+            .await
+            .map_err(|e| AppError::OpenAI(e.to_string()))?;
+
+        let status = response.status();
+        let body: serde_json::Value = response
+            .json()
+            .await
+            .map_err(|e| AppError::OpenAI(format!("Invalid OpenAI response: {}", e)))?;
+        if !status.is_success() {
+            let message = body["error"]["message"]
+                .as_str()
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("HTTP {}", status));
+            return Err(AppError::OpenAI(message));
+        }
+
+        Ok(body)
+    }
+
     fn extract_response(
         &self,
-        response: CreateChatCompletionResponse,
+        body: serde_json::Value,
         model: &str,
     ) -> Result<ChatResponse, AppError> {
+        let usage = usage_from_json(&body["usage"]);
+        let reasoning = reasoning_from_json(&body);
+        let response: CreateChatCompletionResponse = serde_json::from_value(body)
+            .map_err(|e| AppError::OpenAI(format!("Invalid OpenAI response: {}", e)))?;
+
         let choice = response
             .choices
             .first()
@@ -81,24 +127,50 @@ impl OpenAIService {
         let message = &choice.message;
         let content = message.content.as_ref().unwrap_or(&String::new()).clone();
 
-        let usage = response.usage.map(|u| Usage {
-            prompt_tokens: u.prompt_tokens,
-            completion_tokens: u.completion_tokens,
-            total_tokens: u.total_tokens,
-        });
-
         Ok(ChatResponse {
             message: super::ChatMessage {
                 role: "assistant".to_string(),
                 content,
             },
+            reasoning,
             usage,
             model: model.to_string(),
             provider: "openai".to_string(),
+            latency_ms: None,
         })
     }
 }
 
+/// Usage from an OpenAI chat completion body. OpenAI counts reasoning tokens inside
+/// `completion_tokens`; they are moved to `reasoning_tokens` so the answer and the
+/// reasoning are not billed twice.
+pub fn usage_from_json(usage: &serde_json::Value) -> Option<Usage> {
+    let count = |value: &serde_json::Value| value.as_u64().unwrap_or(0) as u32;
+    if !usage.is_object() {
+        return None;
+    }
+
+    let completion_tokens = count(&usage["completion_tokens"]);
+    let reasoning_tokens = count(&usage["completion_tokens_details"]["reasoning_tokens"]);
+    Some(Usage {
+        prompt_tokens: count(&usage["prompt_tokens"]),
+        completion_tokens: completion_tokens.saturating_sub(reasoning_tokens),
+        total_tokens: count(&usage["total_tokens"]),
+        reasoning_tokens,
+        cached_tokens: count(&usage["prompt_tokens_details"]["cached_tokens"]),
+    })
+}
+
+/// Reasoning text of the first choice. OpenAI keeps its reasoning hidden, but
+/// compatible servers for reasoning models (DeepSeek, vLLM) return it as
+/// `reasoning_content`.
+pub fn reasoning_from_json(body: &serde_json::Value) -> Option<String> {
+    body["choices"][0]["message"]["reasoning_content"]
+        .as_str()
+        .filter(|reasoning| !reasoning.trim().is_empty())
+        .map(str::to_string)
+}
+
 #[async_trait]
 impl LLMService for OpenAIService {
     fn provider(&self) -> Provider {
@@ -146,14 +218,8 @@ impl LLMService for OpenAIService {
             ..Default::default()
         };
 
-        let response = self
-            .client
-            .chat()
-            .create(openai_request)
-            .await
-            .map_err(|e| AppError::OpenAI(e.to_string()))?;
-
-        self.extract_response(response, &request.model)
+        let body = self.create_raw(&openai_request).await?;
+        self.extract_response(body, &request.model)
     }
 
     async fn chat_completion_stream(
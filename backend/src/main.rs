@@ -4,6 +4,7 @@ use tracing_subscriber;
 mod app_state;
 mod config;
 mod database;
+mod embeddings;
 mod error;
 mod handlers;
 mod llm;
@@ -16,10 +17,11 @@ mod services;
 use app_state::AppState;
 use config::AppConfig;
 use database::Database;
-use middleware::rate_limit::api_rate_limit_middleware;
+use middleware::rate_limit::{api_rate_limit_middleware, upload_rate_limit_middleware};
 use services::{
-    auth::AuthService, redis_session_store::PersistentSessionStore, session::SessionManager,
-    DataAccessLayer,
+    auth::AuthService, authorization::AuthorizationService, embedding::EmbeddingService,
+    redis_session_store::PersistentSessionStore, saved_search::SavedSearchService,
+    session::SessionManager, topic::TopicService, DataAccessLayer,
 };
 use time::Duration;
 use tower_sessions::{Expiry, SessionManagerLayer};
@@ -69,6 +71,31 @@ async fn main() -> anyhow::Result<()> {
     // Initialize data access layer
     let dal = DataAccessLayer::new(database.clone());
 
+    // `reindex-embeddings` re-embeds every message with the configured model and exits
+    if std::env::args().nth(1).as_deref() == Some("reindex-embeddings") {
+        let embedding_service = EmbeddingService::new(config.clone(), dal.clone())?;
+        let queued = embedding_service.reindex().await?;
+        let embedded = embedding_service.background_embedding_job().await?;
+        tracing::info!(
+            "Re-indexed embeddings: {} messages queued, {} jobs processed",
+            queued,
+            embedded
+        );
+        for (model, count) in dal.embeddings().count_by_model().await? {
+            tracing::info!("{} embeddings from {}", count, model);
+        }
+        return Ok(());
+    }
+
+    // `cluster-topics` clusters every user's messages into topics and exits
+    if std::env::args().nth(1).as_deref() == Some("cluster-topics") {
+        let clustered = TopicService::new(config.clone(), dal.clone())?
+            .refresh_users(None)
+            .await?;
+        tracing::info!("Clustered topics for {} users", clustered);
+        return Ok(());
+    }
+
     // Initialize admin user if configured
     initialize_admin_user(&dal).await?;
 
@@ -120,12 +147,14 @@ async fn main() -> anyhow::Result<()> {
         .with_session_manager(session_manager.clone());
 
     // Create services
+    let authorization_service = AuthorizationService::new(dal.clone());
     let conversation_service = handlers::conversation::create_conversation_service(dal.clone());
     let chat_service = handlers::chat_persistent::create_chat_service(dal.clone());
 
     // Create shared app state
     let app_state = AppState::new(
         auth_service,
+        authorization_service,
         conversation_service,
         chat_service,
         dal,
@@ -153,6 +182,69 @@ async fn main() -> anyhow::Result<()> {
         }
     });
 
+    // Start background trash purge task
+    let purge_conversation_service = app_state.conversation_service.clone();
+    let trash_retention_days = config.trash_retention_days;
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(3600)); // Every hour
+        loop {
+            interval.tick().await;
+            match purge_conversation_service
+                .purge_trash(trash_retention_days)
+                .await
+            {
+                Ok(count) => {
+                    if count > 0 {
+                        tracing::info!("Purged {} conversations from trash", count);
+                    }
+                }
+                Err(e) => {
+                    tracing::error!("Failed to purge conversation trash: {}", e);
+                }
+            }
+        }
+    });
+
+    // Start background embedding worker for queued messages
+    let embedding_service = EmbeddingService::new(config.clone(), app_state.dal.clone())?;
+    if embedding_service.provider().is_configured() {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(60)); // Every minute
+            loop {
+                interval.tick().await;
+                match embedding_service.background_embedding_job().await {
+                    Ok(count) => {
+                        if count > 0 {
+                            tracing::info!("Embedded {} queued messages", count);
+                        }
+                    }
+                    Err(e) => {
+                        tracing::error!("Failed to process embedding queue: {}", e);
+                    }
+                }
+            }
+        });
+    }
+
+    // Start background check of saved searches for new matches
+    let saved_search_service = SavedSearchService::new(config.clone(), app_state.dal.clone())?;
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(60)); // Every minute
+        loop {
+            interval.tick().await;
+            match saved_search_service.check_due().await {
+                Ok(count) => {
+                    if count > 0 {
+                        tracing::info!("Checked {} saved searches for new matches", count);
+                    }
+                }
+                Err(e) => {
+                    tracing::error!("Failed to check saved searches: {}", e);
+                }
+            }
+        }
+    });
+
     // Build the application with all routes
     let app = create_app(app_state, session_layer, &config).await?;
 
@@ -219,6 +311,14 @@ async fn create_app(
             "/api/v1/conversations",
             axum::routing::post(handlers::conversation::create_conversation),
         )
+        .route(
+            "/api/v1/conversations/import",
+            axum::routing::post(handlers::conversation::import_conversations).layer(
+                axum::extract::DefaultBodyLimit::max(
+                    (config.import_max_size_mb * 1024 * 1024) as usize,
+                ),
+            ),
+        )
         .route(
             "/api/v1/conversations/:id",
             axum::routing::get(handlers::conversation::get_conversation),
@@ -235,6 +335,129 @@ async fn create_app(
             "/api/v1/conversations/:id/stats",
             axum::routing::get(handlers::conversation::get_conversation_stats),
         )
+        .route(
+            "/api/v1/conversations/:id/retrieval",
+            axum::routing::get(handlers::conversation::get_retrieval_settings)
+                .put(handlers::conversation::update_retrieval_settings),
+        )
+        .route(
+            "/api/v1/conversations/:id/related",
+            axum::routing::get(handlers::search::related_conversations),
+        )
+        .route(
+            "/api/v1/conversations/:id/export",
+            axum::routing::get(handlers::conversation::export_conversation),
+        )
+        .route(
+            "/api/v1/conversations/:id/pin",
+            axum::routing::post(handlers::conversation::pin_conversation)
+                .delete(handlers::conversation::unpin_conversation),
+        )
+        .route(
+            "/api/v1/conversations/:id/archive",
+            axum::routing::post(handlers::conversation::archive_conversation)
+                .delete(handlers::conversation::unarchive_conversation),
+        )
+        .route(
+            "/api/v1/conversations/:id/restore",
+            axum::routing::post(handlers::conversation::restore_conversation),
+        )
+        .route(
+            "/api/v1/conversations/:id/projects",
+            axum::routing::get(handlers::project::get_conversation_projects),
+        )
+        .route(
+            "/api/v1/conversations/:id/tags",
+            axum::routing::get(handlers::tag::get_conversation_tags)
+                .post(handlers::tag::tag_conversation),
+        )
+        .route(
+            "/api/v1/conversations/:id/tags/:tag_id",
+            axum::routing::delete(handlers::tag::untag_conversation),
+        )
+        .route(
+            "/api/v1/conversations/:id/members",
+            axum::routing::get(handlers::member::list_members).post(handlers::member::add_member),
+        )
+        .route(
+            "/api/v1/conversations/:id/members/:user_id",
+            axum::routing::patch(handlers::member::update_member)
+                .delete(handlers::member::remove_member),
+        )
+        .route(
+            "/api/v1/conversations/:id/share",
+            axum::routing::get(handlers::share::list_conversation_share_links)
+                .post(handlers::share::create_share_link),
+        )
+        // Share link endpoints (protected)
+        .route(
+            "/api/v1/shares",
+            axum::routing::get(handlers::share::list_share_links),
+        )
+        .route(
+            "/api/v1/shares/:id",
+            axum::routing::delete(handlers::share::revoke_share_link),
+        )
+        // Shared conversation snapshots (public - no auth needed)
+        .route(
+            "/api/v1/shared/:token",
+            axum::routing::get(handlers::share::get_shared_conversation),
+        )
+        // Project endpoints (protected)
+        .route(
+            "/api/v1/projects",
+            axum::routing::get(handlers::project::list_projects)
+                .post(handlers::project::create_project),
+        )
+        .route(
+            "/api/v1/projects/:id",
+            axum::routing::get(handlers::project::get_project)
+                .patch(handlers::project::update_project)
+                .delete(handlers::project::delete_project),
+        )
+        .route(
+            "/api/v1/projects/:id/conversations/:conversation_id",
+            axum::routing::put(handlers::project::add_conversation_to_project)
+                .delete(handlers::project::remove_conversation_from_project),
+        )
+        // Topic endpoints (protected)
+        .route(
+            "/api/v1/topics",
+            axum::routing::get(handlers::topic::list_topics).post(handlers::topic::refresh_topics),
+        )
+        .route(
+            "/api/v1/topics/map",
+            axum::routing::get(handlers::topic::topic_map),
+        )
+        // Saved search endpoints (protected)
+        .route(
+            "/api/v1/saved-searches",
+            axum::routing::get(handlers::saved_search::list_saved_searches)
+                .post(handlers::saved_search::create_saved_search),
+        )
+        .route(
+            "/api/v1/saved-searches/events",
+            axum::routing::get(handlers::saved_search::saved_search_events),
+        )
+        .route(
+            "/api/v1/saved-searches/:id",
+            axum::routing::get(handlers::saved_search::get_saved_search)
+                .put(handlers::saved_search::update_saved_search)
+                .delete(handlers::saved_search::delete_saved_search),
+        )
+        .route(
+            "/api/v1/saved-searches/:id/run",
+            axum::routing::post(handlers::saved_search::run_saved_search),
+        )
+        // Tag endpoints (protected)
+        .route(
+            "/api/v1/tags",
+            axum::routing::get(handlers::tag::list_tags).post(handlers::tag::create_tag),
+        )
+        .route(
+            "/api/v1/tags/:id",
+            axum::routing::patch(handlers::tag::update_tag).delete(handlers::tag::delete_tag),
+        )
         // Chat message endpoints (protected)
         .route(
             "/api/v1/conversations/:id/messages",
@@ -261,6 +484,14 @@ async fn create_app(
             "/api/v1/messages/:id/branches",
             axum::routing::get(handlers::message::get_message_branches),
         )
+        .route(
+            "/api/v1/messages/:id/history",
+            axum::routing::get(handlers::message::get_message_history),
+        )
+        .route(
+            "/api/v1/messages/:id/fork",
+            axum::routing::post(handlers::message::fork_message),
+        )
         .route(
             "/api/v1/conversations/:id/tree",
             axum::routing::get(handlers::message::get_conversation_tree),
@@ -269,6 +500,28 @@ async fn create_app(
             "/api/v1/conversations/:id/switch-branch",
             axum::routing::post(handlers::message::switch_branch),
         )
+        .route(
+            "/api/v1/conversations/:id/compare",
+            axum::routing::get(handlers::message::compare_branches),
+        )
+        // Annotation endpoints (protected)
+        .route(
+            "/api/v1/messages/:id/annotations",
+            axum::routing::post(handlers::annotation::create_annotation),
+        )
+        .route(
+            "/api/v1/conversations/:id/annotations",
+            axum::routing::get(handlers::annotation::list_conversation_annotations),
+        )
+        .route(
+            "/api/v1/annotations/:id",
+            axum::routing::patch(handlers::annotation::update_annotation)
+                .delete(handlers::annotation::delete_annotation),
+        )
+        .route(
+            "/api/v1/bookmarks",
+            axum::routing::get(handlers::annotation::list_bookmarks),
+        )
         // Search endpoints (protected)
         .route(
             "/api/v1/search",
@@ -286,15 +539,35 @@ async fn create_app(
             "/api/v1/search/stats",
             axum::routing::get(handlers::search::search_stats),
         )
+        .route(
+            "/api/v1/search/embedding-job",
+            axum::routing::get(handlers::search::embedding_job_status),
+        )
         .route(
             "/api/v1/search/embedding-job",
             axum::routing::post(handlers::search::trigger_embedding_job),
         )
-        // File attachment endpoints (temporarily disabled)
-        // .route("/api/upload", axum::routing::post(handlers::file::upload_file))
-        // .route("/api/files/:id", axum::routing::get(handlers::file::download_file))
-        // .route("/api/files/:id", axum::routing::delete(handlers::file::delete_file))
-        // .route("/api/messages/:id/attachments", axum::routing::get(handlers::file::get_message_attachments))
+        // File attachment endpoints (protected)
+        .route(
+            "/api/v1/files",
+            axum::routing::post(handlers::file::upload_file)
+                .layer(axum::extract::DefaultBodyLimit::max(
+                    // Room for the multipart framing around a maximum-size file
+                    ((config.rate_limit.max_file_size_mb + 1) * 1024 * 1024) as usize,
+                ))
+                .layer(axum_middleware::from_fn_with_state(
+                    app_state.clone(),
+                    upload_rate_limit_middleware,
+                )),
+        )
+        .route(
+            "/api/v1/files/:id",
+            axum::routing::get(handlers::file::download_file).delete(handlers::file::delete_file),
+        )
+        .route(
+            "/api/v1/messages/:id/attachments",
+            axum::routing::get(handlers::file::get_message_attachments),
+        )
         // Model endpoints
         .route("/api/v1/models", get(handlers::models::get_models))
         .route(
@@ -334,6 +607,7 @@ async fn create_app(
                     axum::http::Method::GET,
                     axum::http::Method::POST,
                     axum::http::Method::PUT,
+                    axum::http::Method::PATCH,
                     axum::http::Method::DELETE,
                     axum::http::Method::OPTIONS,
                 ])
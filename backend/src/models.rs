@@ -25,6 +25,12 @@ pub struct Conversation {
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub metadata: serde_json::Value,
+    pub pinned_at: Option<DateTime<Utc>>,
+    pub archived_at: Option<DateTime<Utc>>,
+    /// Set while the conversation is in the trash
+    pub deleted_at: Option<DateTime<Utc>>,
+    /// The last message of the selected branch; the active thread is the path to it
+    pub active_leaf_id: Option<Uuid>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
@@ -36,8 +42,25 @@ pub struct Message {
     pub content: String,
     pub tokens_used: Option<i32>,
     pub created_at: DateTime<Utc>,
+    /// Whether the message is on the selected branch, i.e. on the path to the conversation's
+    /// `active_leaf_id`. Derived when reading; on insert it asks for the message to be selected.
     pub is_active: bool,
     pub metadata: serde_json::Value,
+    /// The user who wrote the message; `None` for assistant and system messages
+    pub author_id: Option<Uuid>,
+    /// The message this one is an edited version of
+    pub edited_from: Option<Uuid>,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+impl Message {
+    /// Drop reasoning content from metadata for clients that did not request it
+    pub fn without_reasoning(mut self) -> Self {
+        if let Some(metadata) = self.metadata.as_object_mut() {
+            metadata.remove("reasoning");
+        }
+        self
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::Type)]
@@ -81,6 +104,9 @@ pub struct MessageEmbedding {
     pub id: Uuid,
     pub message_id: Uuid,
     pub embedding: Vec<f32>,
+    /// The embedding model that produced the vector
+    pub model: String,
+    pub dimensions: i32,
     pub created_at: DateTime<Utc>,
 }
 
@@ -92,6 +118,45 @@ impl sqlx::FromRow<'_, sqlx::postgres::PgRow> for MessageEmbedding {
             id: row.try_get("id")?,
             message_id: row.try_get("message_id")?,
             embedding: row.try_get("embedding")?,
+            model: row.try_get("model")?,
+            dimensions: row.try_get("dimensions")?,
+            created_at: row.try_get("created_at")?,
+        })
+    }
+}
+
+/// A span of a message embedded on its own. Offsets count characters into the
+/// message content, end exclusive. Only tests load whole chunks.
+#[cfg(test)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessageChunk {
+    pub id: Uuid,
+    pub message_id: Uuid,
+    pub chunk_index: i32,
+    pub start_offset: i32,
+    pub end_offset: i32,
+    pub content: String,
+    pub embedding: Vec<f32>,
+    pub model: String,
+    pub dimensions: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+#[cfg(test)]
+impl sqlx::FromRow<'_, sqlx::postgres::PgRow> for MessageChunk {
+    fn from_row(row: &sqlx::postgres::PgRow) -> Result<Self, sqlx::Error> {
+        use sqlx::Row;
+
+        Ok(MessageChunk {
+            id: row.try_get("id")?,
+            message_id: row.try_get("message_id")?,
+            chunk_index: row.try_get("chunk_index")?,
+            start_offset: row.try_get("start_offset")?,
+            end_offset: row.try_get("end_offset")?,
+            content: row.try_get("content")?,
+            embedding: row.try_get("embedding")?,
+            model: row.try_get("model")?,
+            dimensions: row.try_get("dimensions")?,
             created_at: row.try_get("created_at")?,
         })
     }
@@ -117,6 +182,8 @@ pub struct ApiUsage {
     pub tokens_prompt: Option<i32>,
     pub tokens_completion: Option<i32>,
     pub cost_cents: Option<i32>,
+    /// The assistant message this request produced
+    pub message_id: Option<Uuid>,
     pub created_at: DateTime<Utc>,
 }
 
@@ -132,6 +199,7 @@ impl sqlx::FromRow<'_, sqlx::postgres::PgRow> for ApiUsage {
             tokens_prompt: row.try_get("tokens_prompt")?,
             tokens_completion: row.try_get("tokens_completion")?,
             cost_cents: row.try_get("cost_cents")?,
+            message_id: row.try_get("message_id")?,
             created_at: row.try_get("created_at")?,
         })
     }
@@ -151,10 +219,12 @@ pub struct CreateUserRequest {
 #[derive(Debug, Deserialize, Validate)]
 pub struct CreateConversationRequest {
     pub title: Option<String>,
-    #[validate(length(min = 1))]
+    /// May be omitted when `project_id` points at a project with a default model
+    #[serde(default)]
     pub model: String,
     pub provider: Option<String>,
     pub metadata: Option<serde_json::Value>,
+    pub project_id: Option<Uuid>,
 }
 
 #[derive(Debug, Deserialize, Validate)]
@@ -165,6 +235,9 @@ pub struct CreateMessageRequest {
     #[validate(length(min = 1))]
     pub content: String,
     pub metadata: Option<serde_json::Value>,
+    /// Set by the server from the authenticated user, never by the client
+    #[serde(default, skip_deserializing)]
+    pub author_id: Option<Uuid>,
 }
 
 #[derive(Debug, Serialize)]
@@ -182,21 +255,83 @@ pub struct ConversationWithMessages {
     pub messages: Vec<Message>,
 }
 
-#[derive(Debug, Serialize)]
-pub struct PaginationParams {
-    pub page: Option<u32>,
-    pub limit: Option<u32>,
+/// Per-conversation retrieval: before each model call the user's other conversations
+/// are searched for the latest user turn and the best passages are added as context.
+/// Stored under `retrieval` in the conversation metadata.
+#[derive(Debug, Clone, Serialize, Deserialize, Validate, PartialEq)]
+#[serde(default)]
+pub struct RetrievalSettings {
+    pub enabled: bool,
+    /// Passages added to the prompt
+    #[validate(range(min = 1, max = 20))]
+    pub top_k: u32,
+    /// Passages less similar to the user turn than this are left out
+    #[validate(range(min = 0.0, max = 1.0))]
+    pub min_similarity: f32,
 }
 
-impl Default for PaginationParams {
+impl Default for RetrievalSettings {
     fn default() -> Self {
         Self {
-            page: Some(1),
-            limit: Some(20),
+            enabled: false,
+            top_k: 5,
+            min_similarity: 0.75,
         }
     }
 }
 
+impl RetrievalSettings {
+    /// The settings in a conversation's metadata, or the defaults
+    pub fn from_metadata(metadata: &serde_json::Value) -> Self {
+        metadata
+            .get("retrieval")
+            .and_then(|settings| serde_json::from_value(settings.clone()).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// Aggregates over a conversation's messages, usage, attachments and embeddings.
+/// Message counts skip deleted messages; tokens and cost cover every answer ever
+/// generated, since those were paid for.
+#[derive(Debug, Serialize)]
+pub struct ConversationStats {
+    pub conversation_id: Uuid,
+    /// Messages in the selected branch
+    pub message_count: i64,
+    /// Messages across all branches
+    pub total_messages: i64,
+    /// Distinct root-to-leaf threads
+    pub branch_count: i64,
+    /// Messages in the longest thread
+    pub max_depth: i32,
+    pub prompt_tokens: i64,
+    pub completion_tokens: i64,
+    pub total_tokens: i64,
+    pub cost_cents: i64,
+    /// Assistant messages per model, most used first
+    pub models: Vec<ModelMessageCount>,
+    pub average_latency_ms: Option<f64>,
+    pub attachment_count: i64,
+    pub attachment_bytes: i64,
+    pub embedded_messages: i64,
+    /// Share of messages with an embedding, from 0.0 to 1.0
+    pub embedding_coverage: f64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelMessageCount {
+    pub model: String,
+    pub message_count: i64,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ReasoningParams {
+    #[serde(default)]
+    pub include_reasoning: bool,
+}
+
 // Authentication DTOs
 #[derive(Debug, Deserialize, Validate)]
 pub struct LoginRequest {
@@ -291,7 +426,7 @@ impl From<Attachment> for FileUploadResponse {
             filename: attachment.filename,
             content_type: attachment.content_type,
             size_bytes: attachment.size_bytes,
-            upload_url: format!("/api/files/{}", attachment.id),
+            upload_url: format!("/api/v1/files/{}", attachment.id),
             created_at: attachment.created_at,
         }
     }
@@ -300,6 +435,7 @@ impl From<Attachment> for FileUploadResponse {
 #[derive(Debug, Serialize)]
 pub struct AttachmentResponse {
     pub id: Uuid,
+    pub message_id: Uuid,
     pub filename: String,
     pub content_type: Option<String>,
     pub size_bytes: Option<i64>,
@@ -311,10 +447,11 @@ impl From<Attachment> for AttachmentResponse {
     fn from(attachment: Attachment) -> Self {
         Self {
             id: attachment.id,
+            message_id: attachment.message_id,
             filename: attachment.filename,
             content_type: attachment.content_type,
             size_bytes: attachment.size_bytes,
-            download_url: format!("/api/files/{}", attachment.id),
+            download_url: format!("/api/v1/files/{}", attachment.id),
             created_at: attachment.created_at,
         }
     }
@@ -333,6 +470,46 @@ pub struct BranchOption {
     pub id: Uuid,
     pub preview: String,
     pub is_active: bool,
+    pub kind: BranchKind,
+}
+
+/// How a message relates to its siblings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BranchKind {
+    /// The first version written at this point
+    Original,
+    /// An edited version of a sibling
+    Edited,
+    /// Another take, such as a regenerated answer
+    Alternative,
+}
+
+impl BranchKind {
+    /// Classify siblings given oldest first
+    pub fn classify<'a>(siblings: impl IntoIterator<Item = &'a Message>) -> Vec<BranchKind> {
+        let mut seen_original = false;
+        siblings
+            .into_iter()
+            .map(|message| {
+                if message.edited_from.is_some() {
+                    BranchKind::Edited
+                } else if !seen_original {
+                    seen_original = true;
+                    BranchKind::Original
+                } else {
+                    BranchKind::Alternative
+                }
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct MessageBranch {
+    #[serde(flatten)]
+    pub message: Message,
+    pub kind: BranchKind,
 }
 
 #[derive(Debug, Deserialize, Validate)]
@@ -341,6 +518,17 @@ pub struct EditMessageRequest {
     pub content: String,
 }
 
+#[derive(Debug, Default, Deserialize, Validate)]
+pub struct ForkConversationRequest {
+    /// Defaults to the original title with " (fork)" appended
+    #[validate(length(min = 1, max = 255))]
+    pub title: Option<String>,
+    /// Continue the fork with a different model; defaults to the original one
+    pub model: Option<String>,
+    /// Defaults to the provider of the model
+    pub provider: Option<String>,
+}
+
 #[derive(Debug, Serialize)]
 pub struct EditMessageResponse {
     pub message: Message,
@@ -365,20 +553,612 @@ pub struct ConversationTreeResponse {
     pub active_thread: Vec<Uuid>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct CompareBranchesParams {
+    pub left: Uuid,
+    pub right: Uuid,
+}
+
+/// Two branches of a conversation laid side by side from where they diverge
+#[derive(Debug, Serialize)]
+pub struct BranchComparison {
+    pub conversation_id: Uuid,
+    /// The last message both paths share; `None` when they start from different roots
+    pub common_ancestor_id: Option<Uuid>,
+    /// Messages after the common ancestor, oldest first, ending at `left`
+    pub left: Vec<Message>,
+    /// Messages after the common ancestor, oldest first, ending at `right`
+    pub right: Vec<Message>,
+    /// Only present when `left` and `right` are sibling assistant answers
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub answers: Option<AnswerComparison>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AnswerComparison {
+    /// Word-level diff turning the left answer into the right one
+    pub diff: Vec<DiffSegment>,
+    pub left: AnswerStats,
+    pub right: AnswerStats,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffOp {
+    Equal,
+    Insert,
+    Delete,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DiffSegment {
+    pub op: DiffOp,
+    pub text: String,
+}
+
+/// Usage figures recorded with an assistant answer; missing when the provider did not report them
+#[derive(Debug, Default, Serialize)]
+pub struct AnswerStats {
+    pub model: Option<String>,
+    pub prompt_tokens: Option<u32>,
+    pub completion_tokens: Option<u32>,
+    pub total_tokens: Option<u32>,
+    pub cost_cents: Option<i32>,
+    pub latency_ms: Option<u64>,
+    pub word_count: usize,
+}
+
+// Project and tag DTOs
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Project {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub system_prompt: Option<String>,
+    pub default_model: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct ProjectSummary {
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    pub project: Project,
+    pub conversation_count: i64,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateProjectRequest {
+    #[validate(length(min = 1, max = 255))]
+    pub name: String,
+    pub description: Option<String>,
+    pub system_prompt: Option<String>,
+    #[validate(length(min = 1, max = 50))]
+    pub default_model: Option<String>,
+}
+
+/// Omitted fields are left unchanged; an empty string clears an optional field
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateProjectRequest {
+    #[validate(length(min = 1, max = 255))]
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub system_prompt: Option<String>,
+    #[validate(length(max = 50))]
+    pub default_model: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Tag {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    pub color: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct TagSummary {
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    pub tag: Tag,
+    pub conversation_count: i64,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateTagRequest {
+    #[validate(length(min = 1, max = 100))]
+    pub name: String,
+    #[validate(length(max = 20))]
+    pub color: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateTagRequest {
+    #[validate(length(min = 1, max = 100))]
+    pub name: Option<String>,
+    #[validate(length(max = 20))]
+    pub color: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConversationStatus {
+    #[default]
+    Active,
+    Archived,
+    Trashed,
+}
+
+impl ConversationStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConversationStatus::Active => "active",
+            ConversationStatus::Archived => "archived",
+            ConversationStatus::Trashed => "trashed",
+        }
+    }
+}
+
+/// Query parameters for the conversation list
+#[derive(Debug, Default, Deserialize)]
+pub struct ConversationListParams {
+    pub limit: Option<u32>,
+    /// Opaque `next_cursor` value from the previous page
+    pub cursor: Option<String>,
+    #[serde(default)]
+    pub status: ConversationStatus,
+    pub project_id: Option<Uuid>,
+    pub tag_id: Option<Uuid>,
+    /// Tag name, matched case-insensitively
+    pub tag: Option<String>,
+    /// Case-insensitive substring match on the title
+    pub q: Option<String>,
+    pub model: Option<String>,
+    pub provider: Option<String>,
+}
+
+/// Keyset position in the conversation list, which is ordered by
+/// pinned first, then `updated_at` and `id` descending
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConversationCursor {
+    pub pinned: bool,
+    pub updated_at: DateTime<Utc>,
+    pub id: Uuid,
+}
+
+impl ConversationCursor {
+    pub fn from_conversation(conversation: &Conversation) -> Self {
+        Self {
+            pinned: conversation.pinned_at.is_some(),
+            updated_at: conversation.updated_at,
+            id: conversation.id,
+        }
+    }
+
+    /// Encode as `<p|u>.<updated_at micros>.<id>`; Postgres timestamps have microsecond precision
+    pub fn encode(&self) -> String {
+        format!(
+            "{}.{}.{}",
+            if self.pinned { 'p' } else { 'u' },
+            self.updated_at.timestamp_micros(),
+            self.id.simple()
+        )
+    }
+
+    pub fn decode(cursor: &str) -> Option<Self> {
+        let mut parts = cursor.splitn(3, '.');
+        let pinned = match parts.next()? {
+            "p" => true,
+            "u" => false,
+            _ => return None,
+        };
+        let updated_at = DateTime::from_timestamp_micros(parts.next()?.parse().ok()?)?;
+        let id = Uuid::parse_str(parts.next()?).ok()?;
+
+        Some(Self {
+            pinned,
+            updated_at,
+            id,
+        })
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConversationPage {
+    pub conversations: Vec<Conversation>,
+    pub next_cursor: Option<String>,
+    /// Number of conversations matching the filters, across all pages
+    pub total: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MessageWindow {
+    pub conversation_id: Uuid,
+    pub messages: Vec<Message>,
+    /// Pass as `before` (or `after`, when paging forward) to load the next window
+    pub next_cursor: Option<Uuid>,
+    /// Number of messages in the active thread
+    pub total: i64,
+}
+
+/// Query parameters for loading a window of a conversation's active thread.
+/// Without `before` or `after` the whole thread is returned, or the latest `limit` messages.
+#[derive(Debug, Default, Deserialize)]
+pub struct MessageWindowParams {
+    pub before: Option<Uuid>,
+    pub after: Option<Uuid>,
+    pub limit: Option<u32>,
+    #[serde(default)]
+    pub include_reasoning: bool,
+}
+
+// Conversation membership models
+
+/// Access level on a conversation, ordered from least to most privileged.
+/// The owner is `conversations.user_id`; members are stored as editors or viewers.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, sqlx::Type,
+)]
+#[sqlx(type_name = "varchar")]
+#[serde(rename_all = "lowercase")]
+pub enum ConversationRole {
+    /// Can read the conversation
+    #[sqlx(rename = "viewer")]
+    Viewer,
+    /// Can also send messages, edit, branch and rename
+    #[sqlx(rename = "editor")]
+    Editor,
+    /// Can also share, organize, delete and manage members
+    #[sqlx(rename = "owner")]
+    Owner,
+}
+
+impl ConversationRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConversationRole::Viewer => "viewer",
+            ConversationRole::Editor => "editor",
+            ConversationRole::Owner => "owner",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct ConversationMember {
+    pub conversation_id: Uuid,
+    pub user_id: Uuid,
+    pub username: String,
+    pub email: String,
+    pub role: ConversationRole,
+    pub added_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct AddMemberRequest {
+    /// Email address of the user to add
+    #[validate(email)]
+    pub email: String,
+    pub role: ConversationRole,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateMemberRequest {
+    pub role: ConversationRole,
+}
+
+// Share link models
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct ShareLink {
+    pub id: Uuid,
+    /// Unguessable token used in the public URL
+    pub token: String,
+    pub conversation_id: Uuid,
+    pub user_id: Uuid,
+    pub include_branches: bool,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub view_count: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Default, Deserialize, Validate)]
+pub struct CreateShareLinkRequest {
+    /// Link lifetime; the link never expires when omitted
+    #[validate(range(min = 1, max = 8760))]
+    pub expires_in_hours: Option<u32>,
+    /// Also include the inactive alternative branches
+    #[serde(default)]
+    pub include_branches: bool,
+}
+
+/// Read-only copy of a conversation, frozen when the share link was created
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SharedConversation {
+    pub title: Option<String>,
+    pub model: String,
+    pub shared_at: DateTime<Utc>,
+    /// The active thread, oldest first
+    pub messages: Vec<SharedMessage>,
+    /// Inactive alternatives, only present when the link includes branches
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branches: Option<Vec<SharedMessage>>,
+}
+
+/// A message as shown to share link viewers; metadata such as usage and reasoning is left out
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SharedMessage {
+    pub id: Uuid,
+    pub parent_id: Option<Uuid>,
+    pub role: MessageRole,
+    pub content: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<Message> for SharedMessage {
+    fn from(message: Message) -> Self {
+        Self {
+            id: message.id,
+            parent_id: message.parent_id,
+            role: message.role,
+            content: message.content,
+            created_at: message.created_at,
+        }
+    }
+}
+
+// Annotation models
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct MessageAnnotation {
+    pub id: Uuid,
+    pub message_id: Uuid,
+    pub conversation_id: Uuid,
+    pub user_id: Uuid,
+    /// Highlighted character range `[start_offset, end_offset)`; `None` for the whole message
+    pub start_offset: Option<i32>,
+    pub end_offset: Option<i32>,
+    /// The highlighted text as it was when the annotation was made
+    pub quote: Option<String>,
+    pub note: Option<String>,
+    pub label: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// An annotation listed with enough context to find it again
+#[derive(Debug, Serialize, FromRow)]
+pub struct Bookmark {
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    pub annotation: MessageAnnotation,
+    pub conversation_title: Option<String>,
+    pub message_role: MessageRole,
+    pub message_preview: String,
+}
+
+/// Offsets count characters, not bytes. Omit both to annotate the whole message.
+#[derive(Debug, Default, Deserialize, Validate)]
+pub struct CreateAnnotationRequest {
+    pub start_offset: Option<u32>,
+    pub end_offset: Option<u32>,
+    #[validate(length(max = 10000))]
+    pub note: Option<String>,
+    /// Free-form, e.g. "key finding", "hallucination" or "follow up"
+    #[validate(length(min = 1, max = 50))]
+    pub label: Option<String>,
+}
+
+/// Omitted fields are left unchanged; an empty string clears them
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateAnnotationRequest {
+    #[validate(length(max = 10000))]
+    pub note: Option<String>,
+    #[validate(length(max = 50))]
+    pub label: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct BookmarkParams {
+    pub label: Option<String>,
+    /// Matched against notes and highlighted text
+    pub q: Option<String>,
+    pub conversation_id: Option<Uuid>,
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
+
 // Search-related DTOs
+/// Which index a search runs against. Hybrid merges both rankings.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "varchar", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum SearchMode {
+    Semantic,
+    Lexical,
+    #[default]
+    Hybrid,
+}
+
+/// Narrows a search to some of the user's messages. Unset filters match everything.
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[serde(default)]
+pub struct SearchFilters {
+    #[validate(length(max = 100))]
+    pub conversation_ids: Option<Vec<Uuid>>,
+    pub role: Option<MessageRole>,
+    /// Model that wrote the message, or the conversation's model
+    #[validate(length(max = 100))]
+    pub model: Option<String>,
+    #[validate(length(max = 50))]
+    pub provider: Option<String>,
+    pub created_after: Option<DateTime<Utc>>,
+    pub created_before: Option<DateTime<Utc>>,
+    /// Only messages on the selected branch of their conversation
+    pub active_branch_only: bool,
+    /// Only messages created or embedded since, so imported and re-embedded messages
+    /// count as new; set by saved-search checks, not by clients
+    #[serde(skip)]
+    pub indexed_after: Option<DateTime<Utc>>,
+}
+
+impl Default for SearchFilters {
+    fn default() -> Self {
+        Self {
+            conversation_ids: None,
+            role: None,
+            model: None,
+            provider: None,
+            created_after: None,
+            created_before: None,
+            active_branch_only: true,
+            indexed_after: None,
+        }
+    }
+}
+
 #[derive(Debug, Deserialize, Validate)]
 pub struct SearchRequest {
     #[validate(length(min = 1, max = 500))]
     pub query: String,
     pub limit: Option<i64>,
     pub similarity_threshold: Option<f32>,
+    #[serde(default)]
+    pub mode: SearchMode,
+    #[serde(default)]
+    #[validate(nested)]
+    pub filters: SearchFilters,
 }
 
 #[derive(Debug, Serialize)]
 pub struct SearchResponse {
     pub query: String,
+    /// The mode that produced the results; hybrid falls back to lexical
+    /// when embeddings are unavailable
+    pub mode: SearchMode,
     pub results: Vec<SearchResultResponse>,
     pub total_found: usize,
+    pub facets: SearchFacets,
+}
+
+/// Other conversations about the same things as `conversation_id`, closest first
+#[derive(Debug, Serialize)]
+pub struct RelatedConversationsResponse {
+    pub conversation_id: Uuid,
+    pub related: Vec<crate::repositories::embedding::RelatedConversation>,
+}
+
+/// A search kept to run again, watched for matches newer than `last_viewed_at`
+#[derive(Debug, Clone, Serialize)]
+pub struct SavedSearch {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    pub query: String,
+    pub mode: SearchMode,
+    pub filters: SearchFilters,
+    pub similarity_threshold: Option<f32>,
+    /// Matches created since the search was last viewed, as of `last_checked_at`
+    pub new_match_count: i32,
+    pub last_viewed_at: DateTime<Utc>,
+    pub last_checked_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl sqlx::FromRow<'_, sqlx::postgres::PgRow> for SavedSearch {
+    fn from_row(row: &sqlx::postgres::PgRow) -> Result<Self, sqlx::Error> {
+        use sqlx::Row;
+
+        let filters: sqlx::types::Json<SearchFilters> = row.try_get("filters")?;
+        Ok(SavedSearch {
+            id: row.try_get("id")?,
+            user_id: row.try_get("user_id")?,
+            name: row.try_get("name")?,
+            query: row.try_get("query")?,
+            mode: row.try_get("mode")?,
+            filters: filters.0,
+            similarity_threshold: row.try_get("similarity_threshold")?,
+            new_match_count: row.try_get("new_match_count")?,
+            last_viewed_at: row.try_get("last_viewed_at")?,
+            last_checked_at: row.try_get("last_checked_at")?,
+            created_at: row.try_get("created_at")?,
+            updated_at: row.try_get("updated_at")?,
+        })
+    }
+}
+
+/// Creates a saved search, or replaces one; a changed search starts counting afresh
+#[derive(Debug, Deserialize, Validate)]
+pub struct SaveSearchRequest {
+    #[validate(length(min = 1, max = 255))]
+    pub name: String,
+    #[validate(length(min = 1, max = 500))]
+    pub query: String,
+    #[serde(default)]
+    pub mode: SearchMode,
+    #[serde(default)]
+    #[validate(nested)]
+    pub filters: SearchFilters,
+    #[validate(range(min = 0.0, max = 1.0))]
+    pub similarity_threshold: Option<f32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RunSavedSearchParams {
+    pub limit: Option<i64>,
+}
+
+/// Results of running a saved search. `new_message_ids` are the matches created or
+/// embedded since `new_since`, the ones that were counted as new; running the search
+/// marks them seen.
+#[derive(Debug, Serialize)]
+pub struct SavedSearchRunResponse {
+    pub saved_search: SavedSearch,
+    pub new_since: DateTime<Utc>,
+    pub new_message_ids: Vec<Uuid>,
+    #[serde(flatten)]
+    pub search: SearchResponse,
+}
+
+/// New-match counts sent on the saved search notification stream
+#[derive(Debug, Clone, PartialEq, Serialize, FromRow)]
+pub struct SavedSearchMatches {
+    pub id: Uuid,
+    pub name: String,
+    pub new_match_count: i32,
+}
+
+/// How the matches considered by a search divide up, to refine it with filters.
+/// Counts cover every candidate ranked, not only the returned page.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SearchFacets {
+    pub conversations: Vec<ConversationFacet>,
+    pub roles: Vec<FacetCount>,
+    pub models: Vec<FacetCount>,
+    pub providers: Vec<FacetCount>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConversationFacet {
+    pub conversation_id: Uuid,
+    pub title: Option<String>,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FacetCount {
+    pub value: String,
+    pub count: usize,
+}
+
+/// A run of snippet text, highlighted where it matched the query terms
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SnippetPart {
+    pub text: String,
+    pub highlighted: bool,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -389,16 +1169,31 @@ pub struct SearchResultResponse {
     pub created_at: DateTime<Utc>,
     pub conversation_id: Uuid,
     pub conversation_title: Option<String>,
-    pub similarity: f32,
+    /// Cosine similarity, when the message matched semantically
+    pub similarity: Option<f32>,
+    /// Full-text rank, when the message matched lexically
+    pub text_rank: Option<f32>,
+    /// The score results are ordered by
+    pub score: f32,
+    /// The chunk of the message that matched, for semantic matches
+    pub matched_chunk: Option<crate::repositories::embedding::MatchedChunk>,
+    /// The matched chunk, or the start of the message
     pub preview: String,
+    /// The passage around the matched terms, split into highlighted and plain runs
+    pub snippet: Vec<SnippetPart>,
 }
 
+/// Characters of a search result shown as its preview
+const PREVIEW_CHARS: usize = 300;
+
 impl SearchResultResponse {
     pub fn from_search_result(result: crate::repositories::embedding::SearchResult) -> Self {
-        let preview = if result.content.len() > 150 {
-            format!("{}...", &result.content[..150])
-        } else {
-            result.content.clone()
+        let preview = match &result.chunk {
+            Some(chunk) => {
+                let prefix = if chunk.start_offset > 0 { "..." } else { "" };
+                format!("{}{}", prefix, preview_text(chunk.content.trim_start()))
+            }
+            None => preview_text(&result.content),
         };
 
         Self {
@@ -409,15 +1204,112 @@ impl SearchResultResponse {
             conversation_id: result.conversation_id,
             conversation_title: result.conversation_title,
             similarity: result.similarity,
+            text_rank: result.text_rank,
+            score: result.score,
+            matched_chunk: result.chunk,
             preview,
+            snippet: result
+                .snippet
+                .as_deref()
+                .map(snippet_parts)
+                .unwrap_or_default(),
         }
     }
 }
 
+/// Split a headline marked with `HIGHLIGHT_START` and `HIGHLIGHT_STOP` into runs
+fn snippet_parts(headline: &str) -> Vec<SnippetPart> {
+    use crate::repositories::embedding::{HIGHLIGHT_START, HIGHLIGHT_STOP};
+
+    let mut parts = Vec::new();
+    let mut rest = headline;
+    while !rest.is_empty() {
+        let (marker, highlighted) = match rest.find(HIGHLIGHT_START) {
+            Some(0) => (HIGHLIGHT_STOP, true),
+            _ => (HIGHLIGHT_START, false),
+        };
+        let text = rest.strip_prefix(HIGHLIGHT_START).unwrap_or(rest);
+        let end = text.find(marker).unwrap_or(text.len());
+        if end > 0 {
+            parts.push(SnippetPart {
+                text: text[..end].to_string(),
+                highlighted,
+            });
+        }
+        rest = &text[end..];
+        if highlighted {
+            rest = rest.strip_prefix(HIGHLIGHT_STOP).unwrap_or(rest);
+        }
+    }
+    parts
+}
+
+/// The first `PREVIEW_CHARS` characters of a text, marked when cut
+pub(crate) fn preview_text(text: &str) -> String {
+    match text.char_indices().nth(PREVIEW_CHARS) {
+        Some((end, _)) => format!("{}...", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
 #[derive(Debug, Serialize)]
 pub struct EmbeddingJobResponse {
     pub processed_count: usize,
     pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// The queue after the run
+    pub status: crate::repositories::embedding_job::EmbeddingQueueStatus,
+}
+
+/// A topic found by clustering a user's message embeddings
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct TopicCluster {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub cluster_index: i32,
+    /// Topic name from the LLM, or the keywords when no LLM is available
+    pub label: String,
+    /// Terms that set the cluster's messages apart from the others
+    pub keywords: Vec<String>,
+    pub size: i32,
+    /// Embedding model of the clustered vectors
+    pub model: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A cluster with its messages and the conversations they belong to; a conversation
+/// can appear under several topics
+#[derive(Debug, Serialize, FromRow)]
+pub struct TopicMembers {
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    pub cluster: TopicCluster,
+    pub message_ids: Vec<Uuid>,
+    pub conversation_ids: Vec<Uuid>,
+}
+
+/// A message placed on the 2-D map; coordinates are scaled into -1..=1
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct TopicPoint {
+    pub message_id: Uuid,
+    pub conversation_id: Uuid,
+    pub cluster_id: Uuid,
+    pub role: MessageRole,
+    pub x: f32,
+    pub y: f32,
+    pub preview: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TopicsResponse {
+    pub topics: Vec<TopicMembers>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TopicMapResponse {
+    pub topics: Vec<TopicCluster>,
+    pub points: Vec<TopicPoint>,
 }
 
 /// Custom password validator function for the validator crate
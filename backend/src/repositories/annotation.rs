@@ -0,0 +1,187 @@
+use crate::{
+    database::Database,
+    models::{Bookmark, BookmarkParams, MessageAnnotation, UpdateAnnotationRequest},
+    repositories::conversation::escape_like,
+};
+use anyhow::Result;
+use uuid::Uuid;
+
+const ANNOTATION_COLUMNS: &str = "a.id, a.message_id, m.conversation_id, a.user_id, \
+     a.start_offset, a.end_offset, a.quote, a.note, a.label, a.created_at, a.updated_at";
+
+#[derive(Debug, Clone)]
+pub struct AnnotationRepository {
+    database: Database,
+}
+
+impl AnnotationRepository {
+    pub fn new(database: Database) -> Self {
+        Self { database }
+    }
+
+    pub async fn create(
+        &self,
+        message_id: Uuid,
+        user_id: Uuid,
+        range: Option<(i32, i32)>,
+        quote: Option<&str>,
+        note: Option<&str>,
+        label: Option<&str>,
+    ) -> Result<MessageAnnotation> {
+        let annotation = sqlx::query_as::<_, MessageAnnotation>(&format!(
+            r#"
+            WITH a AS (
+                INSERT INTO message_annotations (message_id, user_id, start_offset, end_offset, quote, note, label)
+                VALUES ($1, $2, $3, $4, $5, $6, $7)
+                RETURNING *
+            )
+            SELECT {}
+            FROM a
+            INNER JOIN messages m ON m.id = a.message_id
+            "#,
+            ANNOTATION_COLUMNS
+        ))
+        .bind(message_id)
+        .bind(user_id)
+        .bind(range.map(|(start, _)| start))
+        .bind(range.map(|(_, end)| end))
+        .bind(quote)
+        .bind(note)
+        .bind(label)
+        .fetch_one(&mut *self.database.connection().await?)
+        .await?;
+
+        Ok(annotation)
+    }
+
+    pub async fn find_by_id(&self, id: Uuid) -> Result<Option<MessageAnnotation>> {
+        let annotation = sqlx::query_as::<_, MessageAnnotation>(&format!(
+            r#"
+            SELECT {}
+            FROM message_annotations a
+            INNER JOIN messages m ON m.id = a.message_id
+            WHERE a.id = $1
+            "#,
+            ANNOTATION_COLUMNS
+        ))
+        .bind(id)
+        .fetch_optional(&mut *self.database.connection().await?)
+        .await?;
+
+        Ok(annotation)
+    }
+
+    /// Every annotation on the conversation, whichever branch its message is on
+    pub async fn find_by_conversation_id(
+        &self,
+        conversation_id: Uuid,
+    ) -> Result<Vec<MessageAnnotation>> {
+        let annotations = sqlx::query_as::<_, MessageAnnotation>(&format!(
+            r#"
+            SELECT {}
+            FROM message_annotations a
+            INNER JOIN messages m ON m.id = a.message_id
+            WHERE m.conversation_id = $1 AND m.deleted_at IS NULL
+            ORDER BY m.created_at ASC, a.start_offset ASC NULLS FIRST, a.created_at ASC
+            "#,
+            ANNOTATION_COLUMNS
+        ))
+        .bind(conversation_id)
+        .fetch_all(&mut *self.database.connection().await?)
+        .await?;
+
+        Ok(annotations)
+    }
+
+    /// The user's annotations across all conversations they can still open, newest first
+    pub async fn find_bookmarks(
+        &self,
+        user_id: Uuid,
+        params: &BookmarkParams,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<Bookmark>> {
+        let label = params
+            .label
+            .as_deref()
+            .map(str::trim)
+            .filter(|l| !l.is_empty());
+        let pattern = params
+            .q
+            .as_deref()
+            .map(str::trim)
+            .filter(|q| !q.is_empty())
+            .map(|q| format!("%{}%", escape_like(q)));
+
+        let bookmarks = sqlx::query_as::<_, Bookmark>(&format!(
+            r#"
+            SELECT {}, c.title AS conversation_title, m.role AS message_role,
+                   LEFT(m.content, 200) AS message_preview
+            FROM message_annotations a
+            INNER JOIN messages m ON m.id = a.message_id
+            INNER JOIN conversations c ON c.id = m.conversation_id
+            WHERE a.user_id = $1
+              AND m.deleted_at IS NULL
+              AND c.deleted_at IS NULL
+              AND (c.user_id = $1 OR EXISTS (
+                  SELECT 1 FROM conversation_members cm
+                  WHERE cm.conversation_id = c.id AND cm.user_id = $1
+              ))
+              AND ($2::text IS NULL OR LOWER(a.label) = LOWER($2))
+              AND ($3::text IS NULL OR a.note ILIKE $3 OR a.quote ILIKE $3)
+              AND ($4::uuid IS NULL OR m.conversation_id = $4)
+            ORDER BY a.created_at DESC, a.id DESC
+            LIMIT $5 OFFSET $6
+            "#,
+            ANNOTATION_COLUMNS
+        ))
+        .bind(user_id)
+        .bind(label)
+        .bind(pattern)
+        .bind(params.conversation_id)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&mut *self.database.connection().await?)
+        .await?;
+
+        Ok(bookmarks)
+    }
+
+    pub async fn update(
+        &self,
+        id: Uuid,
+        request: &UpdateAnnotationRequest,
+    ) -> Result<Option<MessageAnnotation>> {
+        let annotation = sqlx::query_as::<_, MessageAnnotation>(&format!(
+            r#"
+            WITH a AS (
+                UPDATE message_annotations
+                SET note = CASE WHEN $2::text IS NULL THEN note ELSE NULLIF($2, '') END,
+                    label = CASE WHEN $3::text IS NULL THEN label ELSE NULLIF($3, '') END
+                WHERE id = $1
+                RETURNING *
+            )
+            SELECT {}
+            FROM a
+            INNER JOIN messages m ON m.id = a.message_id
+            "#,
+            ANNOTATION_COLUMNS
+        ))
+        .bind(id)
+        .bind(request.note.as_deref().map(str::trim))
+        .bind(request.label.as_deref().map(str::trim))
+        .fetch_optional(&mut *self.database.connection().await?)
+        .await?;
+
+        Ok(annotation)
+    }
+
+    pub async fn delete(&self, id: Uuid) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM message_annotations WHERE id = $1")
+            .bind(id)
+            .execute(&mut *self.database.connection().await?)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -20,9 +20,9 @@ impl ApiUsageRepository {
     pub async fn create(&self, api_usage: &ApiUsage) -> Result<ApiUsage, AppError> {
         let record = sqlx::query_as::<_, ApiUsage>(
             r#"
-            INSERT INTO api_usage (id, user_id, model, provider, tokens_prompt, tokens_completion, cost_cents, created_at)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
-            RETURNING id, user_id, model, provider, tokens_prompt, tokens_completion, cost_cents, created_at
+            INSERT INTO api_usage (id, user_id, model, provider, tokens_prompt, tokens_completion, cost_cents, message_id, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            RETURNING id, user_id, model, provider, tokens_prompt, tokens_completion, cost_cents, message_id, created_at
             "#
         )
         .bind(api_usage.id)
@@ -32,8 +32,9 @@ impl ApiUsageRepository {
         .bind(api_usage.tokens_prompt)
         .bind(api_usage.tokens_completion)
         .bind(api_usage.cost_cents)
+        .bind(api_usage.message_id)
         .bind(api_usage.created_at)
-        .fetch_one(&self.db.pool)
+        .fetch_one(&mut *self.db.connection().await?)
         .await
         .map_err(|e| AppError::Database(format!("Failed to create API usage record: {}", e)))?;
 
@@ -65,7 +66,7 @@ impl ApiUsageRepository {
         .bind(user_id)
         .bind(start)
         .bind(end)
-        .fetch_one(&self.db.pool)
+        .fetch_one(&mut *self.db.connection().await?)
         .await
         .map_err(|e| AppError::Database(format!("Failed to get usage stats: {}", e)))?;
 
@@ -113,7 +114,7 @@ impl ApiUsageRepository {
         .bind(user_id)
         .bind(start)
         .bind(end)
-        .fetch_all(&self.db.pool)
+        .fetch_all(&mut *self.db.connection().await?)
         .await
         .map_err(|e| AppError::Database(format!("Failed to get usage by model: {}", e)))?;
 
@@ -176,7 +177,7 @@ impl ApiUsageRepository {
         )
         .bind(user_id)
         .bind(start)
-        .fetch_all(&self.db.pool)
+        .fetch_all(&mut *self.db.connection().await?)
         .await
         .map_err(|e| AppError::Database(format!("Failed to get daily usage trends: {}", e)))?;
 
@@ -226,7 +227,7 @@ impl ApiUsageRepository {
 
         let records = sqlx::query_as::<_, ApiUsage>(
         r#"
-            SELECT id, user_id, model, provider, tokens_prompt, tokens_completion, cost_cents, created_at
+            SELECT id, user_id, model, provider, tokens_prompt, tokens_completion, cost_cents, message_id, created_at
             FROM api_usage
             WHERE user_id = $1 AND created_at >= $2 AND created_at <= $3
             ORDER BY created_at DESC
@@ -237,7 +238,7 @@ impl ApiUsageRepository {
         .bind(start)
         .bind(end)
         .bind(limit as i64)
-        .fetch_all(&self.db.pool)
+        .fetch_all(&mut *self.db.connection().await?)
         .await
         .map_err(|e| AppError::Database(format!("Failed to get usage for export: {}", e)))?;
 
@@ -276,7 +277,7 @@ impl ApiUsageRepository {
             )
             .bind(user_id)
             .bind(conv_id)
-            .fetch_all(&self.db.pool)
+            .fetch_all(&mut *self.db.connection().await?)
             .await
         } else {
             // Get usage for all conversations
@@ -304,7 +305,7 @@ impl ApiUsageRepository {
                 "#,
             )
             .bind(user_id)
-            .fetch_all(&self.db.pool)
+            .fetch_all(&mut *self.db.connection().await?)
             .await
         };
 
@@ -1,6 +1,7 @@
 use crate::database::Database;
 use crate::models::Attachment;
 use anyhow::Result;
+use chrono::{DateTime, Utc};
 use sqlx::Row;
 use uuid::Uuid;
 
@@ -27,7 +28,7 @@ impl AttachmentRepository {
             .bind(attachment.content_type.as_ref())
             .bind(attachment.size_bytes)
             .bind(&attachment.storage_path)
-            .fetch_one(&self.db.pool)
+            .fetch_one(&mut *self.db.connection().await?)
             .await?;
 
         Ok(Attachment {
@@ -50,7 +51,7 @@ impl AttachmentRepository {
 
         let result = sqlx::query_as::<_, Attachment>(query)
             .bind(id)
-            .fetch_optional(&self.db.pool)
+            .fetch_optional(&mut *self.db.connection().await?)
             .await?;
 
         Ok(result)
@@ -66,7 +67,24 @@ impl AttachmentRepository {
 
         let attachments = sqlx::query_as::<_, Attachment>(query)
             .bind(message_id)
-            .fetch_all(&self.db.pool)
+            .fetch_all(&mut *self.db.connection().await?)
+            .await?;
+
+        Ok(attachments)
+    }
+
+    pub async fn find_by_conversation_id(&self, conversation_id: Uuid) -> Result<Vec<Attachment>> {
+        let query = r#"
+            SELECT a.id, a.message_id, a.filename, a.content_type, a.size_bytes, a.storage_path, a.created_at
+            FROM attachments a
+            JOIN messages m ON a.message_id = m.id
+            WHERE m.conversation_id = $1
+            ORDER BY a.created_at ASC
+        "#;
+
+        let attachments = sqlx::query_as::<_, Attachment>(query)
+            .bind(conversation_id)
+            .fetch_all(&mut *self.db.connection().await?)
             .await?;
 
         Ok(attachments)
@@ -75,23 +93,92 @@ impl AttachmentRepository {
     pub async fn delete(&self, id: Uuid) -> Result<bool> {
         let query = "DELETE FROM attachments WHERE id = $1";
 
-        let result = sqlx::query(query).bind(id).execute(&self.db.pool).await?;
+        let result = sqlx::query(query)
+            .bind(id)
+            .execute(&mut *self.db.connection().await?)
+            .await?;
 
         Ok(result.rows_affected() > 0)
     }
 
-    pub async fn get_total_user_storage_size(&self, user_id: Uuid) -> Result<i64> {
+    /// Link the attachments of each source message to its copy. The new rows point
+    /// at the same stored file, so nothing is duplicated on disk.
+    pub async fn copy_to_messages(&self, message_map: &[(Uuid, Uuid)]) -> Result<u64> {
+        if message_map.is_empty() {
+            return Ok(0);
+        }
+
+        let source_ids: Vec<Uuid> = message_map.iter().map(|(source, _)| *source).collect();
+        let target_ids: Vec<Uuid> = message_map.iter().map(|(_, target)| *target).collect();
+
+        let query = r#"
+            INSERT INTO attachments (message_id, filename, content_type, size_bytes, storage_path)
+            SELECT map.target_id, a.filename, a.content_type, a.size_bytes, a.storage_path
+            FROM attachments a
+            INNER JOIN UNNEST($1::uuid[], $2::uuid[]) AS map(source_id, target_id)
+                ON a.message_id = map.source_id
+            ORDER BY a.created_at ASC
+        "#;
+
+        let result = sqlx::query(query)
+            .bind(&source_ids)
+            .bind(&target_ids)
+            .execute(&mut *self.db.connection().await?)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Stored files of the attachments in conversations trashed before `cutoff`
+    pub async fn find_storage_paths_trashed_before(
+        &self,
+        cutoff: DateTime<Utc>,
+    ) -> Result<Vec<String>> {
         let query = r#"
-            SELECT COALESCE(SUM(a.size_bytes), 0) as total_size
+            SELECT DISTINCT a.storage_path
             FROM attachments a
             JOIN messages m ON a.message_id = m.id
             JOIN conversations c ON m.conversation_id = c.id
-            WHERE c.user_id = $1
+            WHERE c.deleted_at IS NOT NULL AND c.deleted_at < $1
+        "#;
+
+        let paths = sqlx::query_scalar::<_, String>(query)
+            .bind(cutoff)
+            .fetch_all(&mut *self.db.connection().await?)
+            .await?;
+
+        Ok(paths)
+    }
+
+    /// Whether any attachment still links to the stored file
+    pub async fn is_storage_path_in_use(&self, storage_path: &str) -> Result<bool> {
+        let query = "SELECT EXISTS (SELECT 1 FROM attachments WHERE storage_path = $1)";
+
+        let in_use = sqlx::query_scalar::<_, bool>(query)
+            .bind(storage_path)
+            .fetch_one(&mut *self.db.connection().await?)
+            .await?;
+
+        Ok(in_use)
+    }
+
+    /// Bytes stored for the user's conversations; a file shared by forked copies of a
+    /// message counts once
+    pub async fn get_total_user_storage_size(&self, user_id: Uuid) -> Result<i64> {
+        let query = r#"
+            SELECT COALESCE(SUM(size_bytes), 0)::BIGINT as total_size
+            FROM (
+                SELECT DISTINCT ON (a.storage_path) a.size_bytes
+                FROM attachments a
+                JOIN messages m ON a.message_id = m.id
+                JOIN conversations c ON m.conversation_id = c.id
+                WHERE c.user_id = $1
+            ) stored
         "#;
 
         let row = sqlx::query(query)
             .bind(user_id)
-            .fetch_one(&self.db.pool)
+            .fetch_one(&mut *self.db.connection().await?)
             .await?;
 
         Ok(row.get("total_size"))
@@ -1,13 +1,89 @@
 use crate::{
     database::Database,
-    models::{Conversation, ConversationWithMessages, CreateConversationRequest, PaginationParams},
+    models::{
+        Conversation, ConversationCursor, ConversationListParams, ConversationStats,
+        ConversationWithMessages, CreateConversationRequest, ModelMessageCount,
+    },
     repositories::Repository,
 };
 use anyhow::Result;
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use sqlx::Row;
 use uuid::Uuid;
 
+/// Shared WHERE clause for listing and counting conversations; binds $1 through $8.
+/// Covers conversations the user owns and those shared with them (outside the trash).
+const LIST_FILTER: &str = r#"
+    (c.user_id = $1 OR (c.deleted_at IS NULL AND EXISTS (
+        SELECT 1 FROM conversation_members cm
+        WHERE cm.conversation_id = c.id AND cm.user_id = $1
+    )))
+    AND CASE $2
+        WHEN 'archived' THEN c.deleted_at IS NULL AND c.archived_at IS NOT NULL
+        WHEN 'trashed' THEN c.deleted_at IS NOT NULL
+        ELSE c.deleted_at IS NULL AND c.archived_at IS NULL
+    END
+    AND ($3::uuid IS NULL OR EXISTS (
+        SELECT 1 FROM conversation_projects cp
+        WHERE cp.conversation_id = c.id AND cp.project_id = $3
+    ))
+    AND ($4::uuid IS NULL OR EXISTS (
+        SELECT 1 FROM conversation_tags ct
+        WHERE ct.conversation_id = c.id AND ct.tag_id = $4
+    ))
+    AND ($5::text IS NULL OR EXISTS (
+        SELECT 1 FROM conversation_tags ct
+        INNER JOIN tags t ON t.id = ct.tag_id
+        WHERE ct.conversation_id = c.id AND LOWER(t.name) = LOWER($5)
+    ))
+    AND ($6::text IS NULL OR c.title ILIKE '%' || $6 || '%')
+    AND ($7::text IS NULL OR c.model = $7)
+    AND ($8::text IS NULL OR c.provider = $8)
+"#;
+
+/// Bind `$1..$8` of [`LIST_FILTER`] onto a query
+macro_rules! bind_list_filter {
+    ($query:expr, $user_id:expr, $params:expr) => {
+        $query
+            .bind($user_id)
+            .bind($params.status.as_str())
+            .bind($params.project_id)
+            .bind($params.tag_id)
+            .bind(not_blank(&$params.tag))
+            .bind(not_blank(&$params.q).map(escape_like))
+            .bind(not_blank(&$params.model))
+            .bind(not_blank(&$params.provider))
+    };
+}
+
+fn not_blank(value: &Option<String>) -> Option<&str> {
+    value.as_deref().map(str::trim).filter(|v| !v.is_empty())
+}
+
+/// Escape LIKE wildcards so user input is matched literally
+pub(crate) fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+/// Determine the provider that serves a model
+pub fn provider_for_model(model: &str) -> &'static str {
+    if model.starts_with("gpt-") {
+        "openai"
+    } else if model.starts_with("claude-") {
+        if model.contains("code") {
+            "claude_code"
+        } else {
+            "anthropic"
+        }
+    } else {
+        "openai" // default
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ConversationRepository {
     database: Database,
@@ -18,62 +94,81 @@ impl ConversationRepository {
         Self { database }
     }
 
+    /// One page of the user's conversations, pinned first and then most recently updated.
+    /// Pass the last row of the previous page as `cursor` to continue after it.
     pub async fn find_by_user_id(
         &self,
         user_id: Uuid,
-        pagination: PaginationParams,
+        params: &ConversationListParams,
+        cursor: Option<&ConversationCursor>,
+        limit: i64,
     ) -> Result<Vec<Conversation>> {
-        let offset = ((pagination.page.unwrap_or(1) - 1) * pagination.limit.unwrap_or(20)) as i64;
-        let limit = pagination.limit.unwrap_or(20) as i64;
-
-        let conversations = sqlx::query_as::<_, Conversation>(
+        let query = format!(
             r#"
-            SELECT id, user_id, title, model, provider, created_at, updated_at, metadata
-            FROM conversations
-            WHERE user_id = $1
-            ORDER BY updated_at DESC
-            LIMIT $2 OFFSET $3
-            "#,
-        )
-        .bind(user_id)
-        .bind(limit)
-        .bind(offset)
-        .fetch_all(&self.database.pool)
-        .await?;
+            SELECT c.id, c.user_id, c.title, c.model, c.provider, c.created_at, c.updated_at, c.metadata,
+                   c.pinned_at, c.archived_at, c.deleted_at, c.active_leaf_id
+            FROM conversations c
+            WHERE {LIST_FILTER}
+              AND ($9::bool IS NULL OR ((c.pinned_at IS NOT NULL), c.updated_at, c.id) < ($9, $10::timestamptz, $11::uuid))
+            ORDER BY (c.pinned_at IS NOT NULL) DESC, c.updated_at DESC, c.id DESC
+            LIMIT $12
+            "#
+        );
+
+        let conversations =
+            bind_list_filter!(sqlx::query_as::<_, Conversation>(&query), user_id, params)
+                .bind(cursor.map(|c| c.pinned))
+                .bind(cursor.map(|c| c.updated_at))
+                .bind(cursor.map(|c| c.id))
+                .bind(limit)
+                .fetch_all(&mut *self.database.connection().await?)
+                .await?;
 
         Ok(conversations)
     }
 
-    pub async fn find_with_messages(
+    /// Number of conversations matching the list filters, ignoring the cursor
+    pub async fn count_by_filter(
         &self,
-        id: Uuid,
         user_id: Uuid,
-    ) -> Result<Option<ConversationWithMessages>> {
+        params: &ConversationListParams,
+    ) -> Result<i64> {
+        let query = format!("SELECT COUNT(*) FROM conversations c WHERE {LIST_FILTER}");
+
+        let count = bind_list_filter!(sqlx::query_scalar::<_, i64>(&query), user_id, params)
+            .fetch_one(&mut *self.database.connection().await?)
+            .await?;
+
+        Ok(count)
+    }
+
+    /// A conversation with its active messages, unless it is in the trash. Access must be checked by the caller.
+    pub async fn find_with_messages(&self, id: Uuid) -> Result<Option<ConversationWithMessages>> {
         // First get the conversation
         let conversation = sqlx::query_as::<_, Conversation>(
             r#"
-            SELECT id, user_id, title, model, provider, created_at, updated_at, metadata
+            SELECT id, user_id, title, model, provider, created_at, updated_at, metadata,
+                   pinned_at, archived_at, deleted_at, active_leaf_id
             FROM conversations
-            WHERE id = $1 AND user_id = $2
+            WHERE id = $1 AND deleted_at IS NULL
             "#,
         )
         .bind(id)
-        .bind(user_id)
-        .fetch_optional(&self.database.pool)
+        .fetch_optional(&mut *self.database.connection().await?)
         .await?;
 
         if let Some(conv) = conversation {
             // Get all messages for this conversation
             let messages = sqlx::query_as(
                 r#"
-                SELECT id, conversation_id, parent_id, role, content, tokens_used, created_at, is_active, metadata
-                FROM messages
-                WHERE conversation_id = $1 AND is_active = true
-                ORDER BY created_at ASC
+                SELECT id, conversation_id, parent_id, role, content, tokens_used, created_at, is_active, metadata, author_id, edited_from, deleted_at
+                FROM message_nodes
+                WHERE conversation_id = $1 AND is_active = true AND deleted_at IS NULL
+                ORDER BY cardinality(path) ASC
                 "#,
             )
             .bind(id)
-            .fetch_all(&self.database.pool)
+            .fetch_all(&mut *self.database.connection().await?)
             .await?;
 
             Ok(Some(ConversationWithMessages {
@@ -93,24 +188,14 @@ impl ConversationRepository {
         let id = Uuid::new_v4();
         let metadata = request.metadata.unwrap_or_else(|| serde_json::json!({}));
 
-        // Determine provider based on model
-        let provider = if request.model.starts_with("gpt-") {
-            "openai"
-        } else if request.model.starts_with("claude-") {
-            if request.model.contains("code") {
-                "claude_code"
-            } else {
-                "anthropic"
-            }
-        } else {
-            "openai" // default
-        };
+        let provider = provider_for_model(&request.model);
 
         let conversation = sqlx::query_as::<_, Conversation>(
             r#"
             INSERT INTO conversations (id, user_id, title, model, provider, metadata)
             VALUES ($1, $2, $3, $4, $5, $6)
-            RETURNING id, user_id, title, model, provider, created_at, updated_at, metadata
+            RETURNING id, user_id, title, model, provider, created_at, updated_at, metadata,
+                   pinned_at, archived_at, deleted_at, active_leaf_id
             "#,
         )
         .bind(id)
@@ -119,38 +204,291 @@ impl ConversationRepository {
         .bind(&request.model)
         .bind(provider)
         .bind(&metadata)
-        .fetch_one(&self.database.pool)
+        .fetch_one(&mut *self.database.connection().await?)
         .await?;
 
         Ok(conversation)
     }
 
-    pub async fn update_title(&self, id: Uuid, user_id: Uuid, title: String) -> Result<bool> {
+    pub async fn set_pinned(&self, id: Uuid, user_id: Uuid, pinned: bool) -> Result<bool> {
+        let rows_affected = sqlx::query(
+            r#"
+            UPDATE conversations
+            SET pinned_at = CASE WHEN $3 THEN COALESCE(pinned_at, NOW()) ELSE NULL END
+            WHERE id = $1 AND user_id = $2 AND deleted_at IS NULL
+            "#,
+        )
+        .bind(id)
+        .bind(user_id)
+        .bind(pinned)
+        .execute(&mut *self.database.connection().await?)
+        .await?
+        .rows_affected();
+
+        Ok(rows_affected > 0)
+    }
+
+    pub async fn set_archived(&self, id: Uuid, user_id: Uuid, archived: bool) -> Result<bool> {
+        let rows_affected = sqlx::query(
+            r#"
+            UPDATE conversations
+            SET archived_at = CASE WHEN $3 THEN COALESCE(archived_at, NOW()) ELSE NULL END
+            WHERE id = $1 AND user_id = $2 AND deleted_at IS NULL
+            "#,
+        )
+        .bind(id)
+        .bind(user_id)
+        .bind(archived)
+        .execute(&mut *self.database.connection().await?)
+        .await?
+        .rows_affected();
+
+        Ok(rows_affected > 0)
+    }
+
+    /// Soft delete: the conversation stays restorable until the trash is purged
+    pub async fn move_to_trash(&self, id: Uuid, user_id: Uuid) -> Result<bool> {
+        let rows_affected = sqlx::query(
+            r#"
+            UPDATE conversations
+            SET deleted_at = NOW()
+            WHERE id = $1 AND user_id = $2 AND deleted_at IS NULL
+            "#,
+        )
+        .bind(id)
+        .bind(user_id)
+        .execute(&mut *self.database.connection().await?)
+        .await?
+        .rows_affected();
+
+        Ok(rows_affected > 0)
+    }
+
+    pub async fn restore_from_trash(&self, id: Uuid, user_id: Uuid) -> Result<bool> {
+        let rows_affected = sqlx::query(
+            r#"
+            UPDATE conversations
+            SET deleted_at = NULL
+            WHERE id = $1 AND user_id = $2 AND deleted_at IS NOT NULL
+            "#,
+        )
+        .bind(id)
+        .bind(user_id)
+        .execute(&mut *self.database.connection().await?)
+        .await?
+        .rows_affected();
+
+        Ok(rows_affected > 0)
+    }
+
+    /// Permanently delete conversations that have been in the trash since before `cutoff`
+    pub async fn purge_trashed_before(&self, cutoff: DateTime<Utc>) -> Result<u64> {
+        let rows_affected = sqlx::query(
+            "DELETE FROM conversations WHERE deleted_at IS NOT NULL AND deleted_at < $1",
+        )
+        .bind(cutoff)
+        .execute(&mut *self.database.connection().await?)
+        .await?
+        .rows_affected();
+
+        Ok(rows_affected)
+    }
+
+    pub async fn update_title(&self, id: Uuid, title: String) -> Result<bool> {
         let rows_affected = sqlx::query(
             r#"
             UPDATE conversations
             SET title = $1, updated_at = NOW()
-            WHERE id = $2 AND user_id = $3
+            WHERE id = $2
             "#,
         )
         .bind(&title)
         .bind(id)
-        .bind(user_id)
-        .execute(&self.database.pool)
+        .execute(&mut *self.database.connection().await?)
         .await?
         .rows_affected();
 
         Ok(rows_affected > 0)
     }
 
+    /// Set one top-level key of the conversation metadata, keeping the others
+    pub async fn set_metadata_key(
+        &self,
+        id: Uuid,
+        key: &str,
+        value: &serde_json::Value,
+    ) -> Result<bool> {
+        let rows_affected = sqlx::query(
+            r#"
+            UPDATE conversations
+            SET metadata = COALESCE(metadata, '{}'::jsonb) || jsonb_build_object($2::text, $3::jsonb),
+                updated_at = NOW()
+            WHERE id = $1 AND deleted_at IS NULL
+            "#,
+        )
+        .bind(id)
+        .bind(key)
+        .bind(value)
+        .execute(&mut *self.database.connection().await?)
+        .await?
+        .rows_affected();
+
+        Ok(rows_affected > 0)
+    }
+
+    /// Source ids of conversations previously imported from the given export source
+    pub async fn find_imported_source_ids(
+        &self,
+        user_id: Uuid,
+        source: &str,
+    ) -> Result<Vec<String>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT metadata->'import'->>'source_id' AS source_id
+            FROM conversations
+            WHERE user_id = $1
+              AND metadata->'import'->>'source' = $2
+              AND metadata->'import'->>'source_id' IS NOT NULL
+            "#,
+        )
+        .bind(user_id)
+        .bind(source)
+        .fetch_all(&mut *self.database.connection().await?)
+        .await?;
+
+        Ok(rows.iter().map(|row| row.get("source_id")).collect())
+    }
+
     pub async fn count_by_user(&self, user_id: Uuid) -> Result<i64> {
         let row = sqlx::query("SELECT COUNT(*) as count FROM conversations WHERE user_id = $1")
             .bind(user_id)
-            .fetch_one(&self.database.pool)
+            .fetch_one(&mut *self.database.connection().await?)
             .await?;
 
         Ok(row.get("count"))
     }
+
+    /// Compute the conversation's statistics in a single round trip. Tokens come from
+    /// the usage records linked to each answer, falling back to the usage the provider
+    /// reported in the message metadata for answers recorded before that link existed.
+    pub async fn find_stats(&self, id: Uuid) -> Result<Option<ConversationStats>> {
+        let row = sqlx::query(
+            r#"
+            WITH nodes AS (
+                SELECT id, parent_id, role, metadata, is_active, cardinality(path) AS depth
+                FROM message_nodes
+                WHERE conversation_id = $1 AND deleted_at IS NULL
+            ),
+            answers AS (
+                SELECT
+                    COALESCE(SUM(au.tokens_prompt),
+                             MAX((m.metadata->'usage'->>'prompt_tokens')::bigint), 0) AS prompt_tokens,
+                    COALESCE(SUM(au.tokens_completion),
+                             MAX((m.metadata->'usage'->>'completion_tokens')::bigint
+                                 + COALESCE((m.metadata->'usage'->>'reasoning_tokens')::bigint, 0)),
+                             0) AS completion_tokens,
+                    COALESCE(SUM(au.cost_cents), 0) AS cost_cents
+                FROM messages m
+                LEFT JOIN api_usage au ON au.message_id = m.id
+                WHERE m.conversation_id = $1 AND m.role = 'assistant'
+                GROUP BY m.id
+            )
+            SELECT
+                c.id, c.created_at, c.updated_at,
+                thread.message_count, thread.total_messages, thread.branch_count,
+                thread.max_depth, thread.average_latency_ms,
+                usage.prompt_tokens, usage.completion_tokens, usage.cost_cents,
+                models.models,
+                files.attachment_count, files.attachment_bytes,
+                embedded.embedded_messages
+            FROM conversations c
+            CROSS JOIN (
+                SELECT
+                    COUNT(*) FILTER (WHERE n.is_active) AS message_count,
+                    COUNT(*) AS total_messages,
+                    COUNT(*) FILTER (
+                        WHERE NOT EXISTS (SELECT 1 FROM nodes child WHERE child.parent_id = n.id)
+                    ) AS branch_count,
+                    COALESCE(MAX(n.depth), 0) AS max_depth,
+                    AVG((n.metadata->>'latency_ms')::double precision)
+                        FILTER (WHERE n.role = 'assistant') AS average_latency_ms
+                FROM nodes n
+            ) thread
+            CROSS JOIN (
+                SELECT
+                    COALESCE(SUM(prompt_tokens), 0)::bigint AS prompt_tokens,
+                    COALESCE(SUM(completion_tokens), 0)::bigint AS completion_tokens,
+                    COALESCE(SUM(cost_cents), 0)::bigint AS cost_cents
+                FROM answers
+            ) usage
+            CROSS JOIN (
+                SELECT COALESCE(
+                    jsonb_agg(
+                        jsonb_build_object('model', model, 'message_count', message_count)
+                        ORDER BY message_count DESC, model
+                    ),
+                    '[]'::jsonb
+                ) AS models
+                FROM (
+                    SELECT metadata->>'model' AS model, COUNT(*) AS message_count
+                    FROM nodes
+                    WHERE role = 'assistant' AND metadata->>'model' IS NOT NULL
+                    GROUP BY metadata->>'model'
+                ) per_model
+            ) models
+            CROSS JOIN (
+                SELECT COUNT(*) AS attachment_count,
+                       COALESCE(SUM(a.size_bytes), 0)::bigint AS attachment_bytes
+                FROM attachments a
+                INNER JOIN nodes n ON n.id = a.message_id
+            ) files
+            CROSS JOIN (
+                SELECT COUNT(*) AS embedded_messages
+                FROM message_embeddings me
+                INNER JOIN nodes n ON n.id = me.message_id
+            ) embedded
+            WHERE c.id = $1
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&mut *self.database.connection().await?)
+        .await?;
+
+        let Some(row) = row else {
+            return Ok(None);
+        };
+
+        let prompt_tokens: i64 = row.get("prompt_tokens");
+        let completion_tokens: i64 = row.get("completion_tokens");
+        let total_messages: i64 = row.get("total_messages");
+        let embedded_messages: i64 = row.get("embedded_messages");
+        let sqlx::types::Json(models) =
+            row.get::<sqlx::types::Json<Vec<ModelMessageCount>>, _>("models");
+
+        Ok(Some(ConversationStats {
+            conversation_id: row.get("id"),
+            message_count: row.get("message_count"),
+            total_messages,
+            branch_count: row.get("branch_count"),
+            max_depth: row.get("max_depth"),
+            prompt_tokens,
+            completion_tokens,
+            total_tokens: prompt_tokens + completion_tokens,
+            cost_cents: row.get("cost_cents"),
+            models,
+            average_latency_ms: row.get("average_latency_ms"),
+            attachment_count: row.get("attachment_count"),
+            attachment_bytes: row.get("attachment_bytes"),
+            embedded_messages,
+            embedding_coverage: if total_messages > 0 {
+                embedded_messages as f64 / total_messages as f64
+            } else {
+                0.0
+            },
+            created_at: row.get("created_at"),
+            updated_at: row.get("updated_at"),
+        }))
+    }
 }
 
 #[async_trait]
@@ -158,13 +496,14 @@ impl Repository<Conversation, Uuid> for ConversationRepository {
     async fn find_by_id(&self, id: Uuid) -> Result<Option<Conversation>> {
         let conversation = sqlx::query_as::<_, Conversation>(
             r#"
-            SELECT id, user_id, title, model, provider, created_at, updated_at, metadata
+            SELECT id, user_id, title, model, provider, created_at, updated_at, metadata,
+                   pinned_at, archived_at, deleted_at, active_leaf_id
             FROM conversations
             WHERE id = $1
             "#,
         )
         .bind(id)
-        .fetch_optional(&self.database.pool)
+        .fetch_optional(&mut *self.database.connection().await?)
         .await?;
 
         Ok(conversation)
@@ -173,9 +512,10 @@ impl Repository<Conversation, Uuid> for ConversationRepository {
     async fn create(&self, conversation: Conversation) -> Result<Conversation> {
         let created = sqlx::query_as::<_, Conversation>(
             r#"
-            INSERT INTO conversations (id, user_id, title, model, provider, metadata)
-            VALUES ($1, $2, $3, $4, $5, $6)
-            RETURNING id, user_id, title, model, provider, created_at, updated_at, metadata
+            INSERT INTO conversations (id, user_id, title, model, provider, metadata, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            RETURNING id, user_id, title, model, provider, created_at, updated_at, metadata,
+                   pinned_at, archived_at, deleted_at, active_leaf_id
             "#,
         )
         .bind(conversation.id)
@@ -184,7 +524,9 @@ impl Repository<Conversation, Uuid> for ConversationRepository {
         .bind(&conversation.model)
         .bind(&conversation.provider)
         .bind(&conversation.metadata)
-        .fetch_one(&self.database.pool)
+        .bind(conversation.created_at)
+        .bind(conversation.updated_at)
+        .fetch_one(&mut *self.database.connection().await?)
         .await?;
 
         Ok(created)
@@ -196,7 +538,8 @@ impl Repository<Conversation, Uuid> for ConversationRepository {
             UPDATE conversations
             SET title = $2, model = $3, provider = $4, metadata = $5, updated_at = NOW()
             WHERE id = $1
-            RETURNING id, user_id, title, model, provider, created_at, updated_at, metadata
+            RETURNING id, user_id, title, model, provider, created_at, updated_at, metadata,
+                   pinned_at, archived_at, deleted_at, active_leaf_id
             "#,
         )
         .bind(conversation.id)
@@ -204,7 +547,7 @@ impl Repository<Conversation, Uuid> for ConversationRepository {
         .bind(&conversation.model)
         .bind(&conversation.provider)
         .bind(&conversation.metadata)
-        .fetch_one(&self.database.pool)
+        .fetch_one(&mut *self.database.connection().await?)
         .await?;
 
         Ok(updated)
@@ -213,7 +556,7 @@ impl Repository<Conversation, Uuid> for ConversationRepository {
     async fn delete(&self, id: Uuid) -> Result<bool> {
         let rows_affected = sqlx::query("DELETE FROM conversations WHERE id = $1")
             .bind(id)
-            .execute(&self.database.pool)
+            .execute(&mut *self.database.connection().await?)
             .await?
             .rows_affected();
 
@@ -0,0 +1,109 @@
+use crate::{
+    database::Database,
+    models::{ConversationMember, ConversationRole},
+};
+use anyhow::Result;
+use uuid::Uuid;
+
+#[derive(Debug, Clone)]
+pub struct ConversationMemberRepository {
+    database: Database,
+}
+
+impl ConversationMemberRepository {
+    pub fn new(database: Database) -> Self {
+        Self { database }
+    }
+
+    /// The member's stored role, `None` if the user is not a member
+    pub async fn find_role(
+        &self,
+        conversation_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Option<ConversationRole>> {
+        let role = sqlx::query_scalar::<_, ConversationRole>(
+            "SELECT role FROM conversation_members WHERE conversation_id = $1 AND user_id = $2",
+        )
+        .bind(conversation_id)
+        .bind(user_id)
+        .fetch_optional(&mut *self.database.connection().await?)
+        .await?;
+
+        Ok(role)
+    }
+
+    pub async fn find_by_conversation_id(
+        &self,
+        conversation_id: Uuid,
+    ) -> Result<Vec<ConversationMember>> {
+        let members = sqlx::query_as::<_, ConversationMember>(
+            r#"
+            SELECT cm.conversation_id, cm.user_id, u.username, u.email, cm.role, cm.added_by, cm.created_at
+            FROM conversation_members cm
+            INNER JOIN users u ON u.id = cm.user_id
+            WHERE cm.conversation_id = $1
+            ORDER BY cm.created_at ASC
+            "#,
+        )
+        .bind(conversation_id)
+        .fetch_all(&mut *self.database.connection().await?)
+        .await?;
+
+        Ok(members)
+    }
+
+    /// Add a member, or change the role of an existing one
+    pub async fn upsert(
+        &self,
+        conversation_id: Uuid,
+        user_id: Uuid,
+        role: ConversationRole,
+        added_by: Uuid,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO conversation_members (conversation_id, user_id, role, added_by)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (conversation_id, user_id) DO UPDATE SET role = EXCLUDED.role
+            "#,
+        )
+        .bind(conversation_id)
+        .bind(user_id)
+        .bind(role.as_str())
+        .bind(added_by)
+        .execute(&mut *self.database.connection().await?)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn update_role(
+        &self,
+        conversation_id: Uuid,
+        user_id: Uuid,
+        role: ConversationRole,
+    ) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE conversation_members SET role = $3 WHERE conversation_id = $1 AND user_id = $2",
+        )
+        .bind(conversation_id)
+        .bind(user_id)
+        .bind(role.as_str())
+        .execute(&mut *self.database.connection().await?)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn remove(&self, conversation_id: Uuid, user_id: Uuid) -> Result<bool> {
+        let result = sqlx::query(
+            "DELETE FROM conversation_members WHERE conversation_id = $1 AND user_id = $2",
+        )
+        .bind(conversation_id)
+        .bind(user_id)
+        .execute(&mut *self.database.connection().await?)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -1,32 +1,58 @@
-use crate::{error::AppError, models::MessageEmbedding};
-use sqlx::{PgPool, Row};
+#[cfg(test)]
+use crate::models::MessageChunk;
+use crate::{
+    database::{Database, DbConnection},
+    error::AppError,
+    models::{MessageEmbedding, SearchFilters},
+};
+use serde::Serialize;
+use sqlx::{postgres::PgArguments, query::QueryAs, Postgres, Row};
 use uuid::Uuid;
 
+/// Chunks fetched per requested result in semantic search, before keeping the best
+/// chunk of each message
+const CHUNK_CANDIDATES_PER_RESULT: i64 = 4;
+
+/// Marks the start and end of a matched term in headlines; private use characters
+/// never appear in message text
+pub const HIGHLIGHT_START: &str = "\u{E000}";
+pub const HIGHLIGHT_STOP: &str = "\u{E001}";
+
 #[derive(Debug, Clone)]
 pub struct EmbeddingRepository {
-    db: PgPool,
+    db: Database,
 }
 
 impl EmbeddingRepository {
-    pub fn new(db: PgPool) -> Self {
+    pub fn new(db: Database) -> Self {
         Self { db }
     }
 
+    async fn connection(&self) -> Result<DbConnection<'_>, AppError> {
+        self.db
+            .connection()
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))
+    }
+
     pub async fn create(
         &self,
         message_id: Uuid,
         embedding: Vec<f32>,
+        model: &str,
     ) -> Result<MessageEmbedding, AppError> {
         let record = sqlx::query_as::<_, MessageEmbedding>(
             r#"
-            INSERT INTO message_embeddings (message_id, embedding)
-            VALUES ($1, $2)
-            RETURNING id, message_id, embedding, created_at
+            INSERT INTO message_embeddings (message_id, embedding, model, dimensions)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id, message_id, embedding, model, dimensions, created_at
             "#,
         )
         .bind(message_id)
         .bind(&embedding)
-        .fetch_one(&self.db)
+        .bind(model)
+        .bind(embedding.len() as i32)
+        .fetch_one(&mut *self.connection().await?)
         .await
         .map_err(|e| AppError::Database(e.to_string()))?;
 
@@ -39,13 +65,13 @@ impl EmbeddingRepository {
     ) -> Result<Option<MessageEmbedding>, AppError> {
         let record = sqlx::query_as::<_, MessageEmbedding>(
             r#"
-            SELECT id, message_id, embedding, created_at
+            SELECT id, message_id, embedding, model, dimensions, created_at
             FROM message_embeddings
             WHERE message_id = $1
             "#,
         )
         .bind(message_id)
-        .fetch_optional(&self.db)
+        .fetch_optional(&mut *self.connection().await?)
         .await
         .map_err(|e| AppError::Database(e.to_string()))?;
 
@@ -56,47 +82,167 @@ impl EmbeddingRepository {
         &self,
         message_id: Uuid,
         embedding: Vec<f32>,
+        model: &str,
     ) -> Result<MessageEmbedding, AppError> {
         let record = sqlx::query_as::<_, MessageEmbedding>(
             r#"
             UPDATE message_embeddings
-            SET embedding = $2, created_at = NOW()
+            SET embedding = $2, model = $3, dimensions = $4, created_at = NOW()
             WHERE message_id = $1
-            RETURNING id, message_id, embedding, created_at
+            RETURNING id, message_id, embedding, model, dimensions, created_at
             "#,
         )
         .bind(message_id)
         .bind(&embedding)
-        .fetch_one(&self.db)
+        .bind(model)
+        .bind(embedding.len() as i32)
+        .fetch_one(&mut *self.connection().await?)
         .await
         .map_err(|e| AppError::Database(e.to_string()))?;
 
         Ok(record)
     }
 
+    /// Store the embedding of a message, replacing one made by an earlier model
     pub async fn upsert_embedding(
         &self,
         message_id: Uuid,
         embedding: Vec<f32>,
+        model: &str,
     ) -> Result<MessageEmbedding, AppError> {
         let record = sqlx::query_as::<_, MessageEmbedding>(
             r#"
-            INSERT INTO message_embeddings (message_id, embedding)
-            VALUES ($1, $2)
+            INSERT INTO message_embeddings (message_id, embedding, model, dimensions)
+            VALUES ($1, $2, $3, $4)
             ON CONFLICT (message_id)
-            DO UPDATE SET embedding = EXCLUDED.embedding, created_at = NOW()
-            RETURNING id, message_id, embedding, created_at
+            DO UPDATE SET embedding = EXCLUDED.embedding, model = EXCLUDED.model,
+                          dimensions = EXCLUDED.dimensions, created_at = NOW()
+            RETURNING id, message_id, embedding, model, dimensions, created_at
             "#,
         )
         .bind(message_id)
         .bind(&embedding)
-        .fetch_one(&self.db)
+        .bind(model)
+        .bind(embedding.len() as i32)
+        .fetch_one(&mut *self.connection().await?)
         .await
         .map_err(|e| AppError::Database(e.to_string()))?;
 
         Ok(record)
     }
 
+    /// Store the chunk embeddings of a message, replacing its previous chunks
+    pub async fn replace_chunks(
+        &self,
+        message_id: Uuid,
+        model: &str,
+        chunks: &[ChunkEmbedding],
+    ) -> Result<u64, AppError> {
+        let mut conn = self.connection().await?;
+
+        sqlx::query("DELETE FROM message_chunks WHERE message_id = $1")
+            .bind(message_id)
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        for chunk in chunks {
+            sqlx::query(
+                r#"
+                INSERT INTO message_chunks (
+                    message_id, chunk_index, start_offset, end_offset, content,
+                    embedding, model, dimensions
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                "#,
+            )
+            .bind(message_id)
+            .bind(chunk.chunk_index)
+            .bind(chunk.start_offset)
+            .bind(chunk.end_offset)
+            .bind(&chunk.content)
+            .bind(&chunk.embedding)
+            .bind(model)
+            .bind(chunk.embedding.len() as i32)
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        }
+
+        Ok(chunks.len() as u64)
+    }
+
+    /// The stored chunks of a message; search reads chunks through its own queries
+    #[cfg(test)]
+    pub async fn find_chunks(&self, message_id: Uuid) -> Result<Vec<MessageChunk>, AppError> {
+        let chunks = sqlx::query_as::<_, MessageChunk>(
+            r#"
+            SELECT id, message_id, chunk_index, start_offset, end_offset, content,
+                   embedding, model, dimensions, created_at
+            FROM message_chunks
+            WHERE message_id = $1
+            ORDER BY chunk_index
+            "#,
+        )
+        .bind(message_id)
+        .fetch_all(&mut *self.connection().await?)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(chunks)
+    }
+
+    /// Copy the embeddings and chunks of each source message to its copy, skipping
+    /// messages that were never embedded
+    pub async fn copy_embeddings(&self, message_map: &[(Uuid, Uuid)]) -> Result<u64, AppError> {
+        if message_map.is_empty() {
+            return Ok(0);
+        }
+
+        let source_ids: Vec<Uuid> = message_map.iter().map(|(source, _)| *source).collect();
+        let target_ids: Vec<Uuid> = message_map.iter().map(|(_, target)| *target).collect();
+
+        let mut conn = self.connection().await?;
+
+        let result = sqlx::query(
+            r#"
+            INSERT INTO message_embeddings (message_id, embedding, model, dimensions)
+            SELECT map.target_id, me.embedding, me.model, me.dimensions
+            FROM message_embeddings me
+            INNER JOIN UNNEST($1::uuid[], $2::uuid[]) AS map(source_id, target_id)
+                ON me.message_id = map.source_id
+            ON CONFLICT (message_id) DO NOTHING
+            "#,
+        )
+        .bind(&source_ids)
+        .bind(&target_ids)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO message_chunks (
+                message_id, chunk_index, start_offset, end_offset, content,
+                embedding, model, dimensions
+            )
+            SELECT map.target_id, mc.chunk_index, mc.start_offset, mc.end_offset, mc.content,
+                   mc.embedding, mc.model, mc.dimensions
+            FROM message_chunks mc
+            INNER JOIN UNNEST($1::uuid[], $2::uuid[]) AS map(source_id, target_id)
+                ON mc.message_id = map.source_id
+            ON CONFLICT (message_id, chunk_index) DO NOTHING
+            "#,
+        )
+        .bind(&source_ids)
+        .bind(&target_ids)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(result.rows_affected())
+    }
+
     pub async fn find_messages_without_embeddings(
         &self,
         limit: i64,
@@ -104,15 +250,15 @@ impl EmbeddingRepository {
         let records = sqlx::query(
             r#"
             SELECT m.id
-            FROM messages m
+            FROM message_nodes m
             LEFT JOIN message_embeddings me ON m.id = me.message_id
-            WHERE me.id IS NULL AND m.is_active = true
+            WHERE me.id IS NULL AND m.is_active = true AND m.deleted_at IS NULL
             ORDER BY m.created_at ASC
             LIMIT $1
             "#,
         )
         .bind(limit)
-        .fetch_all(&self.db)
+        .fetch_all(&mut *self.connection().await?)
         .await
         .map_err(|e| AppError::Database(e.to_string()))?;
 
@@ -124,76 +270,340 @@ impl EmbeddingRepository {
         ids.map_err(|e| AppError::Database(e.to_string()))
     }
 
+    /// Rank messages by the cosine similarity of their best chunk embedded by `model`.
+    /// The cast to the query's dimension lets Postgres use that dimension's HNSW index;
+    /// several chunks per result are fetched so a message can be found through any of
+    /// its chunks.
     pub async fn search_similar_messages(
         &self,
         query_embedding: Vec<f32>,
+        model: &str,
         user_id: Option<Uuid>,
+        filters: &SearchFilters,
         limit: i64,
         similarity_threshold: Option<f32>,
     ) -> Result<Vec<SearchResult>, AppError> {
         let threshold = similarity_threshold.unwrap_or(0.7);
+        let distance = format!(
+            "(mc.embedding::vector({dims}) <=> $1::vector({dims}))",
+            dims = query_embedding.len()
+        );
 
-        let results = if let Some(user_id) = user_id {
-            sqlx::query_as::<_, SearchResult>(
-                r#"
+        let sql = format!(
+            r#"
+            WITH chunk_matches AS (
                 SELECT
-                    m.id as message_id,
-                    m.content,
-                    m.role,
-                    m.created_at,
-                    c.id as conversation_id,
-                    c.title as conversation_title,
-                    1 - (me.embedding <=> $1::vector) as similarity
-                FROM message_embeddings me
-                JOIN messages m ON me.message_id = m.id
+                    mc.message_id,
+                    mc.chunk_index,
+                    mc.start_offset,
+                    mc.end_offset,
+                    mc.content,
+                    (1 - {distance})::real AS similarity
+                FROM message_chunks mc
+                JOIN message_nodes m ON mc.message_id = m.id
                 JOIN conversations c ON m.conversation_id = c.id
-                WHERE c.user_id = $2 AND m.is_active = true
-                AND 1 - (me.embedding <=> $1::vector) >= $3
-                ORDER BY similarity DESC
-                LIMIT $4
-                "#,
+                WHERE mc.model = $2 AND mc.dimensions = {dims}
+                AND ($3::uuid IS NULL OR c.user_id = $3)
+                AND m.deleted_at IS NULL AND c.deleted_at IS NULL
+                {filters}
+                AND 1 - {distance} >= $4
+                ORDER BY {distance}
+                LIMIT $5 * {candidates}
+            ),
+            best_chunks AS (
+                SELECT DISTINCT ON (message_id) *
+                FROM chunk_matches
+                ORDER BY message_id, similarity DESC
             )
+            SELECT
+                m.id as message_id,
+                m.content,
+                m.role,
+                m.created_at,
+                c.id as conversation_id,
+                c.title as conversation_title,
+                bc.similarity,
+                NULL::real as text_rank,
+                bc.similarity as score,
+                COALESCE(m.metadata->>'model', c.model) as model,
+                COALESCE(m.metadata->>'provider', c.provider) as provider,
+                bc.chunk_index,
+                bc.start_offset as chunk_start,
+                bc.end_offset as chunk_end,
+                bc.content as chunk_content
+            FROM best_chunks bc
+            JOIN message_nodes m ON bc.message_id = m.id
+            JOIN conversations c ON m.conversation_id = c.id
+            ORDER BY bc.similarity DESC, m.created_at DESC
+            LIMIT $5
+            "#,
+            dims = query_embedding.len(),
+            candidates = CHUNK_CANDIDATES_PER_RESULT,
+            filters = filter_clause(6)
+        );
+        let query = sqlx::query_as::<_, SearchResult>(&sql)
             .bind(&query_embedding)
+            .bind(model)
             .bind(user_id)
             .bind(threshold)
+            .bind(limit);
+
+        let results = bind_filters(query, filters)
+            .fetch_all(&mut *self.connection().await?)
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(results)
+    }
+
+    /// The user's earlier questions closest to an embedding, each with the answer on its
+    /// selected branch. Searches message-level vectors, so the HNSW index applies.
+    pub async fn find_similar_questions(
+        &self,
+        query_embedding: Vec<f32>,
+        model: &str,
+        user_id: Uuid,
+        exclude_conversation_id: Uuid,
+        limit: i64,
+        similarity_threshold: f32,
+    ) -> Result<Vec<SimilarQuestion>, AppError> {
+        let distance = format!(
+            "(me.embedding::vector({dims}) <=> $1::vector({dims}))",
+            dims = query_embedding.len()
+        );
+
+        let sql = format!(
+            r#"
+            SELECT
+                q.id as question_id,
+                q.content as question,
+                q.created_at as asked_at,
+                c.id as conversation_id,
+                c.title as conversation_title,
+                (1 - {distance})::real as similarity,
+                a.id as answer_id,
+                a.content as answer
+            FROM message_embeddings me
+            JOIN messages q ON me.message_id = q.id
+            JOIN conversations c ON q.conversation_id = c.id
+            LEFT JOIN LATERAL (
+                SELECT m.id, m.content
+                FROM message_nodes m
+                WHERE m.parent_id = q.id AND m.role = 'assistant' AND m.deleted_at IS NULL
+                ORDER BY m.is_active DESC, m.created_at DESC
+                LIMIT 1
+            ) a ON true
+            WHERE me.model = $2 AND me.dimensions = {dims}
+            AND q.role = 'user'
+            AND c.user_id = $3 AND c.id <> $4
+            AND q.deleted_at IS NULL AND c.deleted_at IS NULL
+            AND 1 - {distance} >= $5
+            ORDER BY {distance}
+            LIMIT $6
+            "#,
+            dims = query_embedding.len()
+        );
+
+        let questions = sqlx::query_as::<_, SimilarQuestion>(&sql)
+            .bind(&query_embedding)
+            .bind(model)
+            .bind(user_id)
+            .bind(exclude_conversation_id)
+            .bind(similarity_threshold)
             .bind(limit)
-            .fetch_all(&self.db)
+            .fetch_all(&mut *self.connection().await?)
             .await
-        } else {
-            sqlx::query_as::<_, SearchResult>(
-                r#"
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(questions)
+    }
+
+    /// Conversations the user can open, ranked by the cosine similarity of their
+    /// centroid (mean message embedding) to the centroid of `conversation_id`
+    pub async fn find_related_conversations(
+        &self,
+        conversation_id: Uuid,
+        user_id: Uuid,
+        model: &str,
+        dimensions: usize,
+        limit: i64,
+    ) -> Result<Vec<RelatedConversation>, AppError> {
+        let sql = format!(
+            r#"
+            WITH centroids AS (
                 SELECT
-                    m.id as message_id,
-                    m.content,
-                    m.role,
-                    m.created_at,
-                    c.id as conversation_id,
-                    c.title as conversation_title,
-                    1 - (me.embedding <=> $1::vector) as similarity
+                    m.conversation_id,
+                    AVG(me.embedding::vector({dims})) as centroid,
+                    COUNT(*) as embedded_messages
                 FROM message_embeddings me
                 JOIN messages m ON me.message_id = m.id
                 JOIN conversations c ON m.conversation_id = c.id
-                WHERE m.is_active = true
-                AND 1 - (me.embedding <=> $1::vector) >= $2
-                ORDER BY similarity DESC
-                LIMIT $3
-                "#,
+                WHERE me.model = $1 AND me.dimensions = {dims}
+                AND m.deleted_at IS NULL AND c.deleted_at IS NULL
+                AND (c.id = $2 OR c.user_id = $3 OR EXISTS (
+                    SELECT 1 FROM conversation_members cm
+                    WHERE cm.conversation_id = c.id AND cm.user_id = $3
+                ))
+                GROUP BY m.conversation_id
             )
-            .bind(&query_embedding)
-            .bind(threshold)
+            SELECT
+                c.id as conversation_id,
+                c.title,
+                c.model,
+                c.updated_at,
+                other.embedded_messages,
+                (1 - (other.centroid <=> target.centroid))::real as similarity
+            FROM centroids target
+            JOIN centroids other ON other.conversation_id <> target.conversation_id
+            JOIN conversations c ON other.conversation_id = c.id
+            WHERE target.conversation_id = $2
+            ORDER BY similarity DESC, c.updated_at DESC
+            LIMIT $4
+            "#,
+            dims = dimensions
+        );
+
+        let related = sqlx::query_as::<_, RelatedConversation>(&sql)
+            .bind(model)
+            .bind(conversation_id)
+            .bind(user_id)
             .bind(limit)
-            .fetch_all(&self.db)
+            .fetch_all(&mut *self.connection().await?)
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(related)
+    }
+
+    /// Create the HNSW indexes for a dimension, so a model of that size can be searched
+    /// without scanning every embedding
+    pub async fn ensure_vector_index(&self, dimensions: usize) -> Result<(), AppError> {
+        let mut conn = self.connection().await?;
+
+        for table in ["message_embeddings", "message_chunks"] {
+            sqlx::query(&format!(
+                r#"
+                CREATE INDEX IF NOT EXISTS idx_{table}_hnsw_{dims}
+                ON {table}
+                USING hnsw ((embedding::vector({dims})) vector_cosine_ops)
+                WITH (m = 16, ef_construction = 64)
+                WHERE dimensions = {dims}
+                "#,
+                dims = dimensions
+            ))
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        }
+
+        Ok(())
+    }
+
+    /// Embedding counts per model, to follow a re-index
+    pub async fn count_by_model(&self) -> Result<Vec<(String, i64)>, AppError> {
+        let rows = sqlx::query_as::<_, (String, i64)>(
+            "SELECT model, COUNT(*) FROM message_embeddings GROUP BY model ORDER BY model",
+        )
+        .fetch_all(&mut *self.connection().await?)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(rows)
+    }
+
+    /// Full-text search over message content, ranked by cover density. The query uses
+    /// web search syntax: quoted phrases, `or` and `-excluded` terms.
+    pub async fn search_text_messages(
+        &self,
+        query: &str,
+        user_id: Option<Uuid>,
+        filters: &SearchFilters,
+        limit: i64,
+    ) -> Result<Vec<SearchResult>, AppError> {
+        let sql = format!(
+            r#"
+            SELECT
+                m.id as message_id,
+                m.content,
+                m.role,
+                m.created_at,
+                c.id as conversation_id,
+                c.title as conversation_title,
+                NULL::real as similarity,
+                ts_rank_cd(m.content_tsv, query) as text_rank,
+                ts_rank_cd(m.content_tsv, query) as score,
+                COALESCE(m.metadata->>'model', c.model) as model,
+                COALESCE(m.metadata->>'provider', c.provider) as provider,
+                NULL::integer as chunk_index,
+                NULL::integer as chunk_start,
+                NULL::integer as chunk_end,
+                NULL::text as chunk_content
+            FROM message_nodes m
+            JOIN conversations c ON m.conversation_id = c.id
+            CROSS JOIN websearch_to_tsquery('english', $1) query
+            WHERE m.content_tsv @@ query
+            AND ($2::uuid IS NULL OR c.user_id = $2)
+            AND m.deleted_at IS NULL AND c.deleted_at IS NULL
+            {filters}
+            ORDER BY text_rank DESC, m.created_at DESC
+            LIMIT $3
+            "#,
+            filters = filter_clause(4)
+        );
+        let search = sqlx::query_as::<_, SearchResult>(&sql)
+            .bind(query)
+            .bind(user_id)
+            .bind(limit);
+
+        let results = bind_filters(search, filters)
+            .fetch_all(&mut *self.connection().await?)
             .await
-        };
+            .map_err(|e| AppError::Database(e.to_string()))?;
 
-        let results = results.map_err(|e| AppError::Database(e.to_string()))?;
         Ok(results)
     }
 
+    /// Headlines of each text around the terms of a web search query, with matched
+    /// terms between `HIGHLIGHT_START` and `HIGHLIGHT_STOP`. Texts without a match
+    /// get their opening words.
+    pub async fn highlight(&self, query: &str, texts: &[String]) -> Result<Vec<String>, AppError> {
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let headlines = sqlx::query_scalar::<_, String>(
+            r#"
+            SELECT ts_headline(
+                'english', text, websearch_to_tsquery('english', $1),
+                format('StartSel=%s, StopSel=%s, MinWords=15, MaxWords=35, MaxFragments=2, FragmentDelimiter=" … "', $3, $4)
+            )
+            FROM UNNEST($2::text[]) WITH ORDINALITY AS texts(text, position)
+            ORDER BY position
+            "#,
+        )
+        .bind(query)
+        .bind(texts)
+        .bind(HIGHLIGHT_START)
+        .bind(HIGHLIGHT_STOP)
+        .fetch_all(&mut *self.connection().await?)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(headlines)
+    }
+
     pub async fn delete_by_message_id(&self, message_id: Uuid) -> Result<bool, AppError> {
+        let mut conn = self.connection().await?;
+
+        sqlx::query("DELETE FROM message_chunks WHERE message_id = $1")
+            .bind(message_id)
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
         let result = sqlx::query("DELETE FROM message_embeddings WHERE message_id = $1")
             .bind(message_id)
-            .execute(&self.db)
+            .execute(&mut *conn)
             .await
             .map_err(|e| AppError::Database(e.to_string()))?;
 
@@ -209,7 +619,107 @@ pub struct SearchResult {
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub conversation_id: Uuid,
     pub conversation_title: Option<String>,
+    /// Cosine similarity to the query embedding, for semantic matches
+    pub similarity: Option<f32>,
+    /// Full-text rank, for lexical matches
+    pub text_rank: Option<f32>,
+    /// The score the results are ordered by
+    pub score: f32,
+    /// Model that wrote the message, or the conversation's model
+    pub model: String,
+    pub provider: String,
+    /// The best-matching chunk, for semantic matches
+    pub chunk: Option<MatchedChunk>,
+    /// Headline around the matched terms, filled in for returned results
+    pub snippet: Option<String>,
+}
+
+/// An earlier question of the user's that is close to a new prompt
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct SimilarQuestion {
+    pub question_id: Uuid,
+    pub question: String,
+    pub asked_at: chrono::DateTime<chrono::Utc>,
+    pub conversation_id: Uuid,
+    pub conversation_title: Option<String>,
     pub similarity: f32,
+    /// The reply on the selected branch, or the latest one; none if it was never answered
+    pub answer_id: Option<Uuid>,
+    pub answer: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct RelatedConversation {
+    pub conversation_id: Uuid,
+    pub title: Option<String>,
+    pub model: String,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+    /// Messages that went into the conversation's centroid
+    pub embedded_messages: i64,
+    pub similarity: f32,
+}
+
+/// Conditions for `SearchFilters` on `m` (message_nodes) and `c` (conversations),
+/// numbered from parameter `first`; bind the values with `bind_filters`
+fn filter_clause(first: usize) -> String {
+    format!(
+        r#"
+        AND (${0}::uuid[] IS NULL OR m.conversation_id = ANY(${0}))
+        AND (${1}::varchar IS NULL OR m.role = ${1})
+        AND (${2}::text IS NULL OR COALESCE(m.metadata->>'model', c.model) = ${2})
+        AND (${3}::text IS NULL OR COALESCE(m.metadata->>'provider', c.provider) = ${3})
+        AND (${4}::timestamptz IS NULL OR m.created_at >= ${4})
+        AND (${5}::timestamptz IS NULL OR m.created_at < ${5})
+        AND (NOT ${6} OR m.is_active)
+        AND (${7}::timestamptz IS NULL OR m.created_at > ${7} OR EXISTS (
+            SELECT 1 FROM message_embeddings me
+            WHERE me.message_id = m.id AND me.created_at > ${7}
+        ))
+        "#,
+        first,
+        first + 1,
+        first + 2,
+        first + 3,
+        first + 4,
+        first + 5,
+        first + 6,
+        first + 7
+    )
+}
+
+fn bind_filters<'q, O>(
+    query: QueryAs<'q, Postgres, O, PgArguments>,
+    filters: &'q SearchFilters,
+) -> QueryAs<'q, Postgres, O, PgArguments> {
+    query
+        .bind(filters.conversation_ids.as_deref())
+        .bind(filters.role.as_ref().map(|role| role.to_string()))
+        .bind(filters.model.as_deref())
+        .bind(filters.provider.as_deref())
+        .bind(filters.created_after)
+        .bind(filters.created_before)
+        .bind(filters.active_branch_only)
+        .bind(filters.indexed_after)
+}
+
+/// The part of a message that matched a query. Offsets count characters into the
+/// message content, end exclusive.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MatchedChunk {
+    pub chunk_index: i32,
+    pub start_offset: i32,
+    pub end_offset: i32,
+    pub content: String,
+}
+
+/// A chunk of a message with its embedding, ready to be stored
+#[derive(Debug, Clone)]
+pub struct ChunkEmbedding {
+    pub chunk_index: i32,
+    pub start_offset: i32,
+    pub end_offset: i32,
+    pub content: String,
+    pub embedding: Vec<f32>,
 }
 
 impl sqlx::FromRow<'_, sqlx::postgres::PgRow> for SearchResult {
@@ -232,6 +742,20 @@ impl sqlx::FromRow<'_, sqlx::postgres::PgRow> for SearchResult {
             conversation_id: row.try_get("conversation_id")?,
             conversation_title: row.try_get("conversation_title")?,
             similarity: row.try_get("similarity")?,
+            text_rank: row.try_get("text_rank")?,
+            score: row.try_get("score")?,
+            model: row.try_get("model")?,
+            provider: row.try_get("provider")?,
+            chunk: match row.try_get::<Option<i32>, _>("chunk_index")? {
+                Some(chunk_index) => Some(MatchedChunk {
+                    chunk_index,
+                    start_offset: row.try_get("chunk_start")?,
+                    end_offset: row.try_get("chunk_end")?,
+                    content: row.try_get("chunk_content")?,
+                }),
+                None => None,
+            },
+            snippet: None,
         })
     }
 }
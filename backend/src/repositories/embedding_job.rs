@@ -0,0 +1,260 @@
+use crate::{
+    database::{Database, DbConnection},
+    error::AppError,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::Row;
+use uuid::Uuid;
+
+/// Jobs are given up after this many failed attempts
+pub const MAX_EMBEDDING_ATTEMPTS: i32 = 5;
+
+#[derive(Debug, Clone)]
+pub struct EmbeddingJobRepository {
+    db: Database,
+}
+
+impl EmbeddingJobRepository {
+    pub fn new(db: Database) -> Self {
+        Self { db }
+    }
+
+    async fn connection(&self) -> Result<DbConnection<'_>, AppError> {
+        self.db
+            .connection()
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    /// Queue every message that has no embedding from `model`, so a new model replaces
+    /// the old vectors. Jobs already waiting are left alone.
+    pub async fn enqueue_missing(&self, model: &str) -> Result<u64, AppError> {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO embedding_jobs (message_id)
+            SELECT m.id
+            FROM messages m
+            WHERE m.deleted_at IS NULL
+            AND NOT EXISTS (
+                SELECT 1 FROM message_chunks mc WHERE mc.message_id = m.id AND mc.model = $1
+            )
+            ON CONFLICT (message_id) DO UPDATE
+            SET status = 'pending', attempts = 0, last_error = NULL, run_after = NOW()
+            WHERE embedding_jobs.status IN ('completed', 'failed')
+            "#,
+        )
+        .bind(model)
+        .execute(&mut *self.connection().await?)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Claim up to `limit` due jobs for this worker. Jobs left processing for longer
+    /// than `stale_after_secs` by a worker that died are claimed again. `embedded`
+    /// tells whether the message already has chunks embedded by `model`.
+    pub async fn claim(
+        &self,
+        limit: i64,
+        stale_after_secs: i64,
+        model: &str,
+    ) -> Result<Vec<ClaimedEmbeddingJob>, AppError> {
+        let jobs = sqlx::query_as::<_, ClaimedEmbeddingJob>(
+            r#"
+            WITH claimed AS (
+                UPDATE embedding_jobs
+                SET status = 'processing', attempts = attempts + 1
+                WHERE id IN (
+                    SELECT id
+                    FROM embedding_jobs
+                    WHERE (status = 'pending' AND run_after <= NOW())
+                       OR (status = 'processing'
+                           AND updated_at < NOW() - make_interval(secs => $2))
+                    ORDER BY run_after, created_at
+                    LIMIT $1
+                    FOR UPDATE SKIP LOCKED
+                )
+                RETURNING id, message_id, attempts
+            )
+            SELECT
+                claimed.id,
+                claimed.message_id,
+                claimed.attempts,
+                m.content,
+                m.deleted_at IS NOT NULL AS deleted,
+                EXISTS (
+                    SELECT 1 FROM message_chunks mc
+                    WHERE mc.message_id = claimed.message_id AND mc.model = $3
+                ) AS embedded
+            FROM claimed
+            JOIN messages m ON m.id = claimed.message_id
+            "#,
+        )
+        .bind(limit)
+        .bind(stale_after_secs as f64)
+        .bind(model)
+        .fetch_all(&mut *self.connection().await?)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(jobs)
+    }
+
+    pub async fn complete(&self, job_ids: &[Uuid]) -> Result<u64, AppError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE embedding_jobs
+            SET status = 'completed', last_error = NULL
+            WHERE id = ANY($1)
+            "#,
+        )
+        .bind(job_ids)
+        .execute(&mut *self.connection().await?)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Record a failed attempt. Jobs with attempts left are retried after an
+    /// exponential backoff starting at 30 seconds and capped at an hour.
+    pub async fn fail(&self, job_ids: &[Uuid], error: &str) -> Result<u64, AppError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE embedding_jobs
+            SET status = CASE WHEN attempts >= $3 THEN 'failed' ELSE 'pending' END,
+                last_error = $2,
+                run_after = NOW() + LEAST(
+                    make_interval(secs => 30 * power(2, attempts - 1)),
+                    INTERVAL '1 hour'
+                )
+            WHERE id = ANY($1)
+            "#,
+        )
+        .bind(job_ids)
+        .bind(error)
+        .bind(MAX_EMBEDDING_ATTEMPTS)
+        .execute(&mut *self.connection().await?)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Give jobs that ran out of attempts a fresh set of retries
+    pub async fn retry_failed(&self) -> Result<u64, AppError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE embedding_jobs
+            SET status = 'pending', attempts = 0, run_after = NOW()
+            WHERE status = 'failed'
+            "#,
+        )
+        .execute(&mut *self.connection().await?)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Job counts per status with the most recent failures, limited to the messages
+    /// of `user_id`'s conversations when given
+    pub async fn status(
+        &self,
+        user_id: Option<Uuid>,
+        failure_limit: i64,
+    ) -> Result<EmbeddingQueueStatus, AppError> {
+        let mut conn = self.connection().await?;
+
+        let counts = sqlx::query(
+            r#"
+            SELECT
+                COUNT(*) FILTER (WHERE ej.status = 'pending') AS pending,
+                COUNT(*) FILTER (WHERE ej.status = 'pending' AND ej.attempts > 0) AS retrying,
+                COUNT(*) FILTER (WHERE ej.status = 'processing') AS processing,
+                COUNT(*) FILTER (WHERE ej.status = 'completed') AS completed,
+                COUNT(*) FILTER (WHERE ej.status = 'failed') AS failed
+            FROM embedding_jobs ej
+            JOIN messages m ON m.id = ej.message_id
+            JOIN conversations c ON c.id = m.conversation_id
+            WHERE ($1::uuid IS NULL OR c.user_id = $1)
+            "#,
+        )
+        .bind(user_id)
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        let failures = sqlx::query_as::<_, FailedEmbeddingJob>(
+            r#"
+            SELECT ej.message_id, ej.status, ej.attempts, ej.last_error, ej.run_after, ej.updated_at
+            FROM embedding_jobs ej
+            JOIN messages m ON m.id = ej.message_id
+            JOIN conversations c ON c.id = m.conversation_id
+            WHERE ej.last_error IS NOT NULL AND ej.status IN ('pending', 'failed')
+            AND ($1::uuid IS NULL OR c.user_id = $1)
+            ORDER BY ej.updated_at DESC
+            LIMIT $2
+            "#,
+        )
+        .bind(user_id)
+        .bind(failure_limit)
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        let count = |column: &str| -> Result<i64, AppError> {
+            counts
+                .try_get(column)
+                .map_err(|e| AppError::Database(e.to_string()))
+        };
+
+        Ok(EmbeddingQueueStatus {
+            pending: count("pending")?,
+            retrying: count("retrying")?,
+            processing: count("processing")?,
+            completed: count("completed")?,
+            failed: count("failed")?,
+            recent_failures: failures,
+        })
+    }
+}
+
+/// A job claimed by a worker, with what it needs to embed the message
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct ClaimedEmbeddingJob {
+    pub id: Uuid,
+    pub message_id: Uuid,
+    pub attempts: i32,
+    pub content: String,
+    /// The message was deleted after it was queued
+    pub deleted: bool,
+    /// The message already has chunks from the current model, e.g. copied by a fork
+    pub embedded: bool,
+}
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct FailedEmbeddingJob {
+    pub message_id: Uuid,
+    pub status: String,
+    pub attempts: i32,
+    pub last_error: Option<String>,
+    /// When the job is retried, if it has attempts left
+    pub run_after: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EmbeddingQueueStatus {
+    /// Jobs waiting for a worker, including the ones waiting to be retried
+    pub pending: i64,
+    /// Pending jobs that failed before
+    pub retrying: i64,
+    pub processing: i64,
+    pub completed: i64,
+    /// Jobs that ran out of attempts
+    pub failed: i64,
+    pub recent_failures: Vec<FailedEmbeddingJob>,
+}
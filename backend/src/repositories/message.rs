@@ -1,7 +1,8 @@
 use crate::{
     database::Database,
     models::{
-        BranchInfo, BranchOption, CreateMessageRequest, CreateMessageResponse, Message, MessageRole,
+        BranchInfo, BranchKind, BranchOption, CreateMessageRequest, CreateMessageResponse, Message,
+        MessageRole,
     },
     repositories::Repository,
 };
@@ -20,19 +21,75 @@ impl MessageRepository {
         Self { database }
     }
 
+    /// The messages of the active thread, root first
     pub async fn find_by_conversation_id(&self, conversation_id: Uuid) -> Result<Vec<Message>> {
-        let messages = sqlx::query_as::<_, Message>(
+        self.find_active_conversation_thread(conversation_id).await
+    }
+
+    /// The last message of the active thread, which new replies are added below
+    pub async fn find_active_leaf_id(&self, conversation_id: Uuid) -> Result<Option<Uuid>> {
+        let leaf_id = sqlx::query_scalar::<_, Uuid>(
             r#"
-            SELECT id, conversation_id, parent_id, role, content, tokens_used, created_at, is_active, metadata
-            FROM messages
-            WHERE conversation_id = $1 AND is_active = true
-            ORDER BY created_at ASC
+            SELECT id
+            FROM message_nodes
+            WHERE conversation_id = $1 AND is_active = true AND deleted_at IS NULL
+            ORDER BY cardinality(path) DESC
+            LIMIT 1
             "#,
         )
         .bind(conversation_id)
-        .fetch_all(&self.database.pool)
+        .fetch_optional(&mut *self.database.connection().await?)
         .await?;
 
+        Ok(leaf_id)
+    }
+
+    /// A window of the active thread in chronological order: the `limit` messages
+    /// right after `after`, right before `before`, or the latest ones without an anchor.
+    pub async fn find_window(
+        &self,
+        conversation_id: Uuid,
+        before: Option<Uuid>,
+        after: Option<Uuid>,
+        limit: i64,
+    ) -> Result<Vec<Message>> {
+        if let Some(after) = after {
+            let messages = sqlx::query_as::<_, Message>(
+                r#"
+                SELECT m.id, m.conversation_id, m.parent_id, m.role, m.content, m.tokens_used, m.created_at, m.is_active, m.metadata, m.author_id, m.edited_from, m.deleted_at
+                FROM message_nodes m
+                WHERE m.conversation_id = $1 AND m.is_active = true AND m.deleted_at IS NULL
+                  AND (m.created_at, m.id) > (SELECT created_at, id FROM messages WHERE id = $2)
+                ORDER BY m.created_at ASC, m.id ASC
+                LIMIT $3
+                "#,
+            )
+            .bind(conversation_id)
+            .bind(after)
+            .bind(limit)
+            .fetch_all(&mut *self.database.connection().await?)
+            .await?;
+
+            return Ok(messages);
+        }
+
+        let mut messages = sqlx::query_as::<_, Message>(
+            r#"
+            SELECT m.id, m.conversation_id, m.parent_id, m.role, m.content, m.tokens_used, m.created_at, m.is_active, m.metadata, m.author_id, m.edited_from, m.deleted_at
+            FROM message_nodes m
+            WHERE m.conversation_id = $1 AND m.is_active = true AND m.deleted_at IS NULL
+              AND ($2::uuid IS NULL OR (m.created_at, m.id) < (SELECT created_at, id FROM messages WHERE id = $2))
+            ORDER BY m.created_at DESC, m.id DESC
+            LIMIT $3
+            "#,
+        )
+        .bind(conversation_id)
+        .bind(before)
+        .bind(limit)
+        .fetch_all(&mut *self.database.connection().await?)
+        .await?;
+        messages.reverse();
+
         Ok(messages)
     }
 
@@ -44,11 +101,22 @@ impl MessageRepository {
         let metadata = request.metadata.unwrap_or_else(|| serde_json::json!({}));
         let now = Utc::now();
 
+        // The new message becomes the end of the selected branch in the same statement
         let message = sqlx::query_as::<_, Message>(
             r#"
-            INSERT INTO messages (id, conversation_id, parent_id, role, content, metadata, created_at)
-            VALUES ($1, $2, $3, $4, $5, $6, $7)
-            RETURNING id, conversation_id, parent_id, role, content, tokens_used, created_at, is_active, metadata
+            WITH inserted AS (
+                INSERT INTO messages (id, conversation_id, parent_id, role, content, metadata, created_at, author_id)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                RETURNING id, conversation_id, parent_id, role, content, tokens_used, created_at, metadata, author_id, edited_from, deleted_at
+            ),
+            selected AS (
+                UPDATE conversations c
+                SET active_leaf_id = inserted.id
+                FROM inserted
+                WHERE c.id = inserted.conversation_id
+            )
+            SELECT id, conversation_id, parent_id, role, content, tokens_used, created_at, true AS is_active, metadata, author_id, edited_from, deleted_at
+            FROM inserted
             "#,
         )
         .bind(id)
@@ -58,7 +126,8 @@ impl MessageRepository {
         .bind(&request.content)
         .bind(&metadata)
         .bind(now)
-        .fetch_one(&self.database.pool)
+        .bind(request.author_id)
+        .fetch_one(&mut *self.database.connection().await?)
         .await?;
 
         Ok(CreateMessageResponse {
@@ -70,6 +139,69 @@ impl MessageRepository {
         })
     }
 
+    /// Insert many messages in a single statement. Parents may appear in the same batch as
+    /// their children but must come before them. The deepest message marked active in each
+    /// conversation becomes its selected leaf.
+    pub async fn create_many(&self, messages: &[Message]) -> Result<u64> {
+        if messages.is_empty() {
+            return Ok(0);
+        }
+
+        let ids: Vec<Uuid> = messages.iter().map(|m| m.id).collect();
+        let conversation_ids: Vec<Uuid> = messages.iter().map(|m| m.conversation_id).collect();
+        let parent_ids: Vec<Option<Uuid>> = messages.iter().map(|m| m.parent_id).collect();
+        let roles: Vec<String> = messages.iter().map(|m| m.role.to_string()).collect();
+        let contents: Vec<&str> = messages.iter().map(|m| m.content.as_str()).collect();
+        let tokens: Vec<Option<i32>> = messages.iter().map(|m| m.tokens_used).collect();
+        let metadata: Vec<serde_json::Value> =
+            messages.iter().map(|m| m.metadata.clone()).collect();
+        let active: Vec<Uuid> = messages
+            .iter()
+            .filter(|m| m.is_active)
+            .map(|m| m.id)
+            .collect();
+        let created_at: Vec<chrono::DateTime<Utc>> =
+            messages.iter().map(|m| m.created_at).collect();
+        let author_ids: Vec<Option<Uuid>> = messages.iter().map(|m| m.author_id).collect();
+
+        let inserted = sqlx::query_scalar::<_, i64>(
+            r#"
+            WITH inserted AS (
+                INSERT INTO messages (id, conversation_id, parent_id, role, content, tokens_used, metadata, created_at, author_id)
+                SELECT * FROM UNNEST($1::uuid[], $2::uuid[], $3::uuid[], $4::varchar[], $5::text[], $6::int4[], $7::jsonb[], $8::timestamptz[], $9::uuid[])
+                RETURNING id, conversation_id, path
+            ),
+            leaves AS (
+                SELECT DISTINCT ON (conversation_id) conversation_id, id
+                FROM inserted
+                WHERE id = ANY($10::uuid[])
+                ORDER BY conversation_id, cardinality(path) DESC
+            ),
+            selected AS (
+                UPDATE conversations c
+                SET active_leaf_id = leaves.id
+                FROM leaves
+                WHERE c.id = leaves.conversation_id
+            )
+            SELECT COUNT(*) FROM inserted
+            "#,
+        )
+        .bind(&ids)
+        .bind(&conversation_ids)
+        .bind(&parent_ids)
+        .bind(&roles)
+        .bind(&contents)
+        .bind(&tokens)
+        .bind(&metadata)
+        .bind(&created_at)
+        .bind(&author_ids)
+        .bind(&active)
+        .fetch_one(&mut *self.database.connection().await?)
+        .await?;
+
+        Ok(inserted as u64)
+    }
+
     pub async fn update_tokens(&self, id: Uuid, tokens_used: i32) -> Result<bool> {
         let rows_affected = sqlx::query(
             r#"
@@ -80,7 +212,7 @@ impl MessageRepository {
         )
         .bind(tokens_used)
         .bind(id)
-        .execute(&self.database.pool)
+        .execute(&mut *self.database.connection().await?)
         .await?
         .rows_affected();
 
@@ -88,31 +220,18 @@ impl MessageRepository {
     }
 
     pub async fn find_conversation_thread(&self, message_id: Uuid) -> Result<Vec<Message>> {
-        // This function returns all messages in the conversation thread leading up to and including the specified message
+        // This function returns all messages in the conversation thread leading up to and including the specified message.
+        // The message's path lists its ancestors, whether or not they are on the selected branch.
         let messages = sqlx::query_as::<_, Message>(
             r#"
-            WITH RECURSIVE message_thread AS (
-                -- Base case: start with the target message
-                SELECT m.*, 0 as depth
-                FROM messages m
-                WHERE m.id = $1
-
-                UNION ALL
-
-                -- Recursive case: find parent messages
-                SELECT m.*, mt.depth + 1
-                FROM messages m
-                INNER JOIN message_thread mt ON m.id = mt.parent_id
-                WHERE mt.depth < 100  -- Prevent infinite recursion
-            )
-            SELECT id, conversation_id, parent_id, role, content, tokens_used, created_at, is_active, metadata
-            FROM message_thread
-            WHERE is_active = true
-            ORDER BY depth DESC, created_at ASC
+            SELECT id, conversation_id, parent_id, role, content, tokens_used, created_at, is_active, metadata, author_id, edited_from, deleted_at
+            FROM message_nodes
+            WHERE id = ANY((SELECT path FROM messages WHERE id = $1)::uuid[])
+            ORDER BY cardinality(path) ASC
             "#,
         )
         .bind(message_id)
-        .fetch_all(&self.database.pool)
+        .fetch_all(&mut *self.database.connection().await?)
         .await?;
 
         Ok(messages)
@@ -123,45 +242,57 @@ impl MessageRepository {
         parent_id: Uuid,
         content: String,
         role: MessageRole,
+        author_id: Option<Uuid>,
     ) -> Result<Message> {
-        let parent = self
-            .find_by_id(parent_id)
-            .await?
-            .ok_or_else(|| anyhow::anyhow!("Parent message not found"))?;
+        self.database
+            .transaction(|database| async move {
+                let messages = MessageRepository::new(database);
+                if !messages.lock_live(parent_id).await? {
+                    return Err(anyhow::anyhow!("Parent message not found"));
+                }
+                let parent = messages
+                    .find_by_id(parent_id)
+                    .await?
+                    .ok_or_else(|| anyhow::anyhow!("Parent message not found"))?;
+
+                // Creating the message also selects it, which moves its siblings off the active path
+                let request = CreateMessageRequest {
+                    conversation_id: parent.conversation_id,
+                    parent_id: Some(parent_id),
+                    role,
+                    content,
+                    metadata: None,
+                    author_id,
+                };
+
+                let response = messages.create_from_request(request).await?;
+                messages
+                    .find_by_id(response.id)
+                    .await?
+                    .ok_or_else(|| anyhow::anyhow!("Created message not found"))
+            })
+            .await
+    }
 
-        // Deactivate sibling messages (other messages with the same parent)
-        sqlx::query(
-            r#"
-            UPDATE messages
-            SET is_active = false
-            WHERE parent_id = $1 AND is_active = true
-            "#,
+    /// Lock a message that is not deleted until the surrounding transaction ends,
+    /// so it cannot be deleted while a reply or a new version is added next to it
+    async fn lock_live(&self, id: Uuid) -> Result<bool> {
+        let locked = sqlx::query_scalar::<_, Uuid>(
+            "SELECT id FROM messages WHERE id = $1 AND deleted_at IS NULL FOR SHARE",
         )
-        .bind(parent_id)
-        .execute(&self.database.pool)
+        .bind(id)
+        .fetch_optional(&mut *self.database.connection().await?)
         .await?;
 
-        // Create new message
-        let request = CreateMessageRequest {
-            conversation_id: parent.conversation_id,
-            parent_id: Some(parent_id),
-            role,
-            content,
-            metadata: None,
-        };
-
-        let response = self.create_from_request(request).await?;
-        self.find_by_id(response.id)
-            .await?
-            .ok_or_else(|| anyhow::anyhow!("Created message not found"))
+        Ok(locked.is_some())
     }
 
     pub async fn count_by_conversation(&self, conversation_id: Uuid) -> Result<i64> {
         let count = sqlx::query_scalar::<_, i64>(
-            "SELECT COUNT(*) FROM messages WHERE conversation_id = $1 AND is_active = true",
+            "SELECT COUNT(*) FROM message_nodes WHERE conversation_id = $1 AND is_active = true AND deleted_at IS NULL",
         )
         .bind(conversation_id)
-        .fetch_one(&self.database.pool)
+        .fetch_one(&mut *self.database.connection().await?)
         .await?;
 
         Ok(count)
@@ -171,187 +302,178 @@ impl MessageRepository {
     pub async fn find_message_branches(&self, message_id: Uuid) -> Result<Vec<Message>> {
         let branches = sqlx::query_as::<_, Message>(
             r#"
-            SELECT id, conversation_id, parent_id, role, content, tokens_used, created_at, is_active, metadata
-            FROM messages
-            WHERE parent_id = $1
+            SELECT id, conversation_id, parent_id, role, content, tokens_used, created_at, is_active, metadata, author_id, edited_from, deleted_at
+            FROM message_nodes
+            WHERE parent_id = $1 AND deleted_at IS NULL
             ORDER BY created_at ASC
             "#,
         )
         .bind(message_id)
-        .fetch_all(&self.database.pool)
+        .fetch_all(&mut *self.database.connection().await?)
         .await?;
 
         Ok(branches)
     }
 
-    /// Get the full tree structure for a conversation with active branch paths
+    /// Get the full tree structure for a conversation with active branch paths.
+    /// Replies to a deleted message are deleted with it, so the tree stays connected.
     pub async fn find_conversation_tree(&self, conversation_id: Uuid) -> Result<Vec<Message>> {
         let messages = sqlx::query_as::<_, Message>(
             r#"
-            WITH RECURSIVE conversation_tree AS (
-                -- Base case: root messages (no parent)
-                SELECT m.*, 0 as depth, ARRAY[m.id] as path
-                FROM messages m
-                WHERE m.conversation_id = $1 AND m.parent_id IS NULL
-
-                UNION ALL
-
-                -- Recursive case: find child messages
-                SELECT m.*, ct.depth + 1, ct.path || m.id
-                FROM messages m
-                INNER JOIN conversation_tree ct ON m.parent_id = ct.id
-                WHERE m.conversation_id = $1 AND ct.depth < 100
-            )
-            SELECT id, conversation_id, parent_id, role, content, tokens_used, created_at, is_active, metadata
-            FROM conversation_tree
-            ORDER BY depth ASC, created_at ASC
+            SELECT id, conversation_id, parent_id, role, content, tokens_used, created_at, is_active, metadata, author_id, edited_from, deleted_at
+            FROM message_nodes
+            WHERE conversation_id = $1 AND deleted_at IS NULL
+            ORDER BY cardinality(path) ASC, created_at ASC
             "#,
         )
         .bind(conversation_id)
-        .fetch_all(&self.database.pool)
+        .fetch_all(&mut *self.database.connection().await?)
         .await?;
 
         Ok(messages)
     }
 
-    /// Get the current active conversation thread (following is_active flags)
+    /// Get the current active conversation thread: the path from the root to the conversation's selected leaf
     pub async fn find_active_conversation_thread(
         &self,
         conversation_id: Uuid,
     ) -> Result<Vec<Message>> {
         let messages = sqlx::query_as::<_, Message>(
             r#"
-            WITH RECURSIVE active_thread AS (
-                -- Base case: root message that's active
-                SELECT m.*, 0 as depth
-                FROM messages m
-                WHERE m.conversation_id = $1 AND m.parent_id IS NULL AND m.is_active = true
-
-                UNION ALL
-
-                -- Recursive case: find the active child message
-                SELECT m.*, at.depth + 1
-                FROM messages m
-                INNER JOIN active_thread at ON m.parent_id = at.id
-                WHERE m.conversation_id = $1 AND m.is_active = true AND at.depth < 100
-            )
-            SELECT id, conversation_id, parent_id, role, content, tokens_used, created_at, is_active, metadata
-            FROM active_thread
-            ORDER BY depth ASC
+            SELECT id, conversation_id, parent_id, role, content, tokens_used, created_at, is_active, metadata, author_id, edited_from, deleted_at
+            FROM message_nodes
+            WHERE conversation_id = $1 AND is_active = true AND deleted_at IS NULL
+            ORDER BY cardinality(path) ASC
             "#,
         )
         .bind(conversation_id)
-        .fetch_all(&self.database.pool)
+        .fetch_all(&mut *self.database.connection().await?)
         .await?;
 
         Ok(messages)
     }
 
-    /// Edit a message and create a new branch from that point
+    /// Edit a message by adding a new version next to it, linked through `edited_from`.
+    /// The new version becomes the selected branch; the original and its replies stay in the tree.
     pub async fn edit_message_and_branch(
         &self,
         message_id: Uuid,
         new_content: String,
+        author_id: Option<Uuid>,
     ) -> Result<Message> {
-        let original_message = self
-            .find_by_id(message_id)
-            .await?
-            .ok_or_else(|| anyhow::anyhow!("Original message not found"))?;
+        self.database
+            .transaction(|database| async move {
+                let messages = MessageRepository::new(database);
+                if !messages.lock_live(message_id).await? {
+                    return Err(anyhow::anyhow!("Original message not found"));
+                }
+                let original_message = messages
+                    .find_by_id(message_id)
+                    .await?
+                    .ok_or_else(|| anyhow::anyhow!("Original message not found"))?;
+
+                // Selecting the new version moves the original and everything after it off the active path
+                let edited = sqlx::query_as::<_, Message>(
+                    r#"
+                    WITH inserted AS (
+                        INSERT INTO messages (id, conversation_id, parent_id, role, content, metadata, created_at, author_id, edited_from)
+                        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+                        RETURNING id, conversation_id, parent_id, role, content, tokens_used, created_at, metadata, author_id, edited_from, deleted_at
+                    ),
+                    selected AS (
+                        UPDATE conversations c
+                        SET active_leaf_id = inserted.id
+                        FROM inserted
+                        WHERE c.id = inserted.conversation_id
+                    )
+                    SELECT id, conversation_id, parent_id, role, content, tokens_used, created_at, true AS is_active, metadata, author_id, edited_from, deleted_at
+                    FROM inserted
+                    "#,
+                )
+                .bind(Uuid::new_v4())
+                .bind(original_message.conversation_id)
+                .bind(original_message.parent_id)
+                .bind(original_message.role.to_string())
+                .bind(&new_content)
+                .bind(&original_message.metadata)
+                .bind(Utc::now())
+                .bind(author_id)
+                .bind(original_message.id)
+                .fetch_one(&mut *messages.database.connection().await?)
+                .await?;
 
-        // Deactivate all messages from this point forward in the current thread
-        sqlx::query(
+                Ok(edited)
+            })
+            .await
+    }
+
+    /// Every version of a message, oldest first, following `edited_from` in both directions
+    pub async fn find_edit_history(&self, message_id: Uuid) -> Result<Vec<Message>> {
+        let versions = sqlx::query_as::<_, Message>(
             r#"
-            WITH RECURSIVE downstream_messages AS (
-                -- Base case: start with the message being edited
-                SELECT id, parent_id
-                FROM messages
-                WHERE id = $1
+            WITH RECURSIVE earlier AS (
+                SELECT m.id, m.edited_from, 0 AS depth
+                FROM messages m
+                WHERE m.id = $1
 
                 UNION ALL
 
-                -- Recursive case: find all downstream messages
-                SELECT m.id, m.parent_id
+                SELECT m.id, m.edited_from, e.depth + 1
                 FROM messages m
-                INNER JOIN downstream_messages dm ON m.parent_id = dm.id
-            )
-            UPDATE messages
-            SET is_active = false
-            WHERE id IN (SELECT id FROM downstream_messages)
-            "#,
-        )
-        .bind(message_id)
-        .execute(&self.database.pool)
-        .await?;
-
-        // Create new message with edited content
-        let request = CreateMessageRequest {
-            conversation_id: original_message.conversation_id,
-            parent_id: original_message.parent_id,
-            role: original_message.role,
-            content: new_content,
-            metadata: Some(original_message.metadata),
-        };
-
-        let response = self.create_from_request(request).await?;
-        self.find_by_id(response.id)
-            .await?
-            .ok_or_else(|| anyhow::anyhow!("Created message not found"))
-    }
-
-    /// Switch to a different branch by activating a specific message and its thread
-    pub async fn switch_to_branch(&self, message_id: Uuid) -> Result<Vec<Message>> {
-        let target_message = self
-            .find_by_id(message_id)
-            .await?
-            .ok_or_else(|| anyhow::anyhow!("Target message not found"))?;
-
-        // First, deactivate all messages in the conversation
-        sqlx::query(
-            r#"
-            UPDATE messages
-            SET is_active = false
-            WHERE conversation_id = $1
-            "#,
-        )
-        .bind(target_message.conversation_id)
-        .execute(&self.database.pool)
-        .await?;
-
-        // Find and activate the complete path from root to target message
-        let active_path = sqlx::query_as::<_, Message>(
-            r#"
-            WITH RECURSIVE path_to_message AS (
-                -- Base case: start with target message
-                SELECT m.*, 0 as depth
+                INNER JOIN earlier e ON m.id = e.edited_from
+                WHERE e.depth < 100
+            ),
+            versions AS (
+                SELECT m.id, 0 AS depth
                 FROM messages m
-                WHERE m.id = $1
+                WHERE m.id = (SELECT id FROM earlier ORDER BY depth DESC LIMIT 1)
 
                 UNION ALL
 
-                -- Recursive case: walk up the parent chain
-                SELECT m.*, ptm.depth + 1
+                SELECT m.id, v.depth + 1
                 FROM messages m
-                INNER JOIN path_to_message ptm ON m.id = ptm.parent_id
-                WHERE ptm.depth < 100
+                INNER JOIN versions v ON m.edited_from = v.id
+                WHERE v.depth < 100
             )
-            SELECT id, conversation_id, parent_id, role, content, tokens_used, created_at, is_active, metadata
-            FROM path_to_message
-            ORDER BY depth DESC
+            SELECT id, conversation_id, parent_id, role, content, tokens_used, created_at, is_active, metadata, author_id, edited_from, deleted_at
+            FROM message_nodes
+            WHERE id IN (SELECT id FROM versions)
+            ORDER BY created_at ASC
             "#,
         )
         .bind(message_id)
-        .fetch_all(&self.database.pool)
+        .fetch_all(&mut *self.database.connection().await?)
         .await?;
 
-        // Activate all messages in the path
-        for message in &active_path {
-            sqlx::query("UPDATE messages SET is_active = true WHERE id = $1")
-                .bind(message.id)
-                .execute(&self.database.pool)
-                .await?;
-        }
+        Ok(versions)
+    }
 
-        Ok(active_path)
+    /// Switch to a different branch by selecting `message_id` as the conversation's leaf.
+    /// Returns the now active thread, which ends at the message.
+    pub async fn switch_to_branch(&self, message_id: Uuid) -> Result<Vec<Message>> {
+        self.database
+            .transaction(|database| async move {
+                let messages = MessageRepository::new(database);
+                let rows_affected = sqlx::query(
+                    r#"
+                    UPDATE conversations c
+                    SET active_leaf_id = m.id
+                    FROM messages m
+                    WHERE m.id = $1 AND m.deleted_at IS NULL AND c.id = m.conversation_id
+                    "#,
+                )
+                .bind(message_id)
+                .execute(&mut *messages.database.connection().await?)
+                .await?
+                .rows_affected();
+
+                if rows_affected == 0 {
+                    return Err(anyhow::anyhow!("Target message not found"));
+                }
+
+                messages.find_conversation_thread(message_id).await
+            })
+            .await
     }
 
     /// Get branch information for a conversation including alternatives at each decision point
@@ -379,12 +501,15 @@ impl MessageRepository {
         let mut branches = Vec::new();
         for (parent_id, children) in parent_map {
             if children.len() > 1 {
+                let kinds = BranchKind::classify(children.iter().copied());
                 let branch_options = children
                     .iter()
-                    .map(|msg| BranchOption {
+                    .zip(kinds)
+                    .map(|(msg, kind)| BranchOption {
                         id: msg.id,
                         preview: msg.content.chars().take(50).collect(),
                         is_active: msg.is_active,
+                        kind,
                     })
                     .collect();
 
@@ -405,24 +530,35 @@ impl Repository<Message, Uuid> for MessageRepository {
     async fn find_by_id(&self, id: Uuid) -> Result<Option<Message>> {
         let message = sqlx::query_as::<_, Message>(
             r#"
-            SELECT id, conversation_id, parent_id, role, content, tokens_used, created_at, is_active, metadata
-            FROM messages
+            SELECT id, conversation_id, parent_id, role, content, tokens_used, created_at, is_active, metadata, author_id, edited_from, deleted_at
+            FROM message_nodes
             WHERE id = $1
             "#,
         )
         .bind(id)
-        .fetch_optional(&self.database.pool)
+        .fetch_optional(&mut *self.database.connection().await?)
         .await?;
 
         Ok(message)
     }
 
+    /// Insert a message. An active message also becomes the conversation's selected leaf.
     async fn create(&self, message: Message) -> Result<Message> {
         let created = sqlx::query_as::<_, Message>(
             r#"
-            INSERT INTO messages (id, conversation_id, parent_id, role, content, tokens_used, metadata, is_active)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
-            RETURNING id, conversation_id, parent_id, role, content, tokens_used, created_at, is_active, metadata
+            WITH inserted AS (
+                INSERT INTO messages (id, conversation_id, parent_id, role, content, tokens_used, metadata, created_at, author_id, edited_from, deleted_at)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $9, $10, $11, $12)
+                RETURNING id, conversation_id, parent_id, role, content, tokens_used, created_at, metadata, author_id, edited_from, deleted_at
+            ),
+            selected AS (
+                UPDATE conversations c
+                SET active_leaf_id = inserted.id
+                FROM inserted
+                WHERE $8 AND c.id = inserted.conversation_id
+            )
+            SELECT id, conversation_id, parent_id, role, content, tokens_used, created_at, $8 AS is_active, metadata, author_id, edited_from, deleted_at
+            FROM inserted
             "#,
         )
         .bind(message.id)
@@ -433,39 +569,53 @@ impl Repository<Message, Uuid> for MessageRepository {
         .bind(message.tokens_used)
         .bind(&message.metadata)
         .bind(message.is_active)
-        .fetch_one(&self.database.pool)
+        .bind(message.created_at)
+        .bind(message.author_id)
+        .bind(message.edited_from)
+        .bind(message.deleted_at)
+        .fetch_one(&mut *self.database.connection().await?)
         .await?;
 
         Ok(created)
     }
 
+    /// Update a message's content, token count and metadata. Branch selection is
+    /// changed through `switch_to_branch`, so `is_active` is ignored.
     async fn update(&self, message: Message) -> Result<Message> {
-        let updated = sqlx::query_as::<_, Message>(
+        sqlx::query(
             r#"
             UPDATE messages
-            SET content = $2, tokens_used = $3, metadata = $4, is_active = $5
+            SET content = $2, tokens_used = $3, metadata = $4
             WHERE id = $1
-            RETURNING id, conversation_id, parent_id, role, content, tokens_used, created_at, is_active, metadata
             "#,
         )
         .bind(message.id)
         .bind(&message.content)
         .bind(message.tokens_used)
         .bind(&message.metadata)
-        .bind(message.is_active)
-        .fetch_one(&self.database.pool)
+        .execute(&mut *self.database.connection().await?)
         .await?;
 
-        Ok(updated)
+        self.find_by_id(message.id)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Message not found"))
     }
 
     async fn delete(&self, id: Uuid) -> Result<bool> {
-        // Soft delete by setting is_active to false
-        let rows_affected = sqlx::query("UPDATE messages SET is_active = false WHERE id = $1")
-            .bind(id)
-            .execute(&self.database.pool)
-            .await?
-            .rows_affected();
+        // Soft delete the message and the replies below it; branch selection is left alone
+        let rows_affected = sqlx::query(
+            r#"
+            UPDATE messages
+            SET deleted_at = NOW()
+            WHERE path @> ARRAY[$1::uuid]
+              AND deleted_at IS NULL
+              AND EXISTS (SELECT 1 FROM messages WHERE id = $1 AND deleted_at IS NULL)
+            "#,
+        )
+        .bind(id)
+        .execute(&mut *self.database.connection().await?)
+        .await?
+        .rows_affected();
 
         Ok(rows_affected > 0)
     }
@@ -1,8 +1,16 @@
+pub mod annotation;
 pub mod api_usage;
 pub mod attachment;
 pub mod conversation;
+pub mod conversation_member;
 pub mod embedding;
+pub mod embedding_job;
 pub mod message;
+pub mod project;
+pub mod saved_search;
+pub mod share_link;
+pub mod tag;
+pub mod topic;
 pub mod user;
 
 use crate::database::Database;
@@ -21,22 +29,40 @@ pub trait Repository<T, ID> {
 // Repository factory to create repository instances
 #[derive(Debug)]
 pub struct RepositoryManager {
+    pub annotations: annotation::AnnotationRepository,
     pub api_usage: api_usage::ApiUsageRepository,
     pub attachments: attachment::AttachmentRepository,
     pub conversations: conversation::ConversationRepository,
+    pub conversation_members: conversation_member::ConversationMemberRepository,
     pub embeddings: embedding::EmbeddingRepository,
+    pub embedding_jobs: embedding_job::EmbeddingJobRepository,
     pub messages: message::MessageRepository,
+    pub projects: project::ProjectRepository,
+    pub saved_searches: saved_search::SavedSearchRepository,
+    pub share_links: share_link::ShareLinkRepository,
+    pub tags: tag::TagRepository,
+    pub topics: topic::TopicRepository,
     pub users: user::UserRepository,
 }
 
 impl RepositoryManager {
     pub fn new(database: Database) -> Self {
         Self {
+            annotations: annotation::AnnotationRepository::new(database.clone()),
             api_usage: api_usage::ApiUsageRepository::new(database.clone()),
             attachments: attachment::AttachmentRepository::new(database.clone()),
             conversations: conversation::ConversationRepository::new(database.clone()),
-            embeddings: embedding::EmbeddingRepository::new(database.pool()),
+            conversation_members: conversation_member::ConversationMemberRepository::new(
+                database.clone(),
+            ),
+            embeddings: embedding::EmbeddingRepository::new(database.clone()),
+            embedding_jobs: embedding_job::EmbeddingJobRepository::new(database.clone()),
             messages: message::MessageRepository::new(database.clone()),
+            projects: project::ProjectRepository::new(database.clone()),
+            saved_searches: saved_search::SavedSearchRepository::new(database.clone()),
+            share_links: share_link::ShareLinkRepository::new(database.clone()),
+            tags: tag::TagRepository::new(database.clone()),
+            topics: topic::TopicRepository::new(database.clone()),
             users: user::UserRepository::new(database),
         }
     }
@@ -0,0 +1,238 @@
+use crate::{
+    database::Database,
+    models::{CreateProjectRequest, Project, ProjectSummary, UpdateProjectRequest},
+    repositories::Repository,
+};
+use anyhow::Result;
+use async_trait::async_trait;
+use uuid::Uuid;
+
+#[derive(Debug, Clone)]
+pub struct ProjectRepository {
+    database: Database,
+}
+
+impl ProjectRepository {
+    pub fn new(database: Database) -> Self {
+        Self { database }
+    }
+
+    pub async fn find_by_user_id(&self, user_id: Uuid) -> Result<Vec<ProjectSummary>> {
+        let projects = sqlx::query_as::<_, ProjectSummary>(
+            r#"
+            SELECT p.id, p.user_id, p.name, p.description, p.system_prompt, p.default_model,
+                   p.created_at, p.updated_at,
+                   COUNT(cp.conversation_id) AS conversation_count
+            FROM projects p
+            LEFT JOIN conversation_projects cp ON cp.project_id = p.id
+            WHERE p.user_id = $1
+            GROUP BY p.id
+            ORDER BY p.name ASC
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&mut *self.database.connection().await?)
+        .await?;
+
+        Ok(projects)
+    }
+
+    pub async fn find_by_conversation_id(&self, conversation_id: Uuid) -> Result<Vec<Project>> {
+        let projects = sqlx::query_as::<_, Project>(
+            r#"
+            SELECT p.id, p.user_id, p.name, p.description, p.system_prompt, p.default_model,
+                   p.created_at, p.updated_at
+            FROM projects p
+            INNER JOIN conversation_projects cp ON cp.project_id = p.id
+            WHERE cp.conversation_id = $1
+            ORDER BY p.name ASC
+            "#,
+        )
+        .bind(conversation_id)
+        .fetch_all(&mut *self.database.connection().await?)
+        .await?;
+
+        Ok(projects)
+    }
+
+    /// Whether the user already has a project with this name, ignoring `exclude_id`
+    pub async fn name_exists(
+        &self,
+        user_id: Uuid,
+        name: &str,
+        exclude_id: Option<Uuid>,
+    ) -> Result<bool> {
+        let exists: bool = sqlx::query_scalar(
+            r#"
+            SELECT EXISTS(
+                SELECT 1 FROM projects
+                WHERE user_id = $1 AND name = $2 AND ($3::uuid IS NULL OR id <> $3)
+            )
+            "#,
+        )
+        .bind(user_id)
+        .bind(name)
+        .bind(exclude_id)
+        .fetch_one(&mut *self.database.connection().await?)
+        .await?;
+
+        Ok(exists)
+    }
+
+    pub async fn create_from_request(
+        &self,
+        user_id: Uuid,
+        request: CreateProjectRequest,
+    ) -> Result<Project> {
+        let project = sqlx::query_as::<_, Project>(
+            r#"
+            INSERT INTO projects (id, user_id, name, description, system_prompt, default_model)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING id, user_id, name, description, system_prompt, default_model, created_at, updated_at
+            "#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(user_id)
+        .bind(request.name.trim())
+        .bind(&request.description)
+        .bind(&request.system_prompt)
+        .bind(&request.default_model)
+        .fetch_one(&mut *self.database.connection().await?)
+        .await?;
+
+        Ok(project)
+    }
+
+    /// Apply a partial update. Empty strings clear the optional columns.
+    pub async fn update_from_request(
+        &self,
+        id: Uuid,
+        request: UpdateProjectRequest,
+    ) -> Result<Option<Project>> {
+        let project = sqlx::query_as::<_, Project>(
+            r#"
+            UPDATE projects
+            SET name = COALESCE($2, name),
+                description = CASE WHEN $3::text IS NULL THEN description ELSE NULLIF($3, '') END,
+                system_prompt = CASE WHEN $4::text IS NULL THEN system_prompt ELSE NULLIF($4, '') END,
+                default_model = CASE WHEN $5::text IS NULL THEN default_model ELSE NULLIF($5, '') END
+            WHERE id = $1
+            RETURNING id, user_id, name, description, system_prompt, default_model, created_at, updated_at
+            "#,
+        )
+        .bind(id)
+        .bind(request.name.as_deref().map(str::trim))
+        .bind(&request.description)
+        .bind(&request.system_prompt)
+        .bind(&request.default_model)
+        .fetch_optional(&mut *self.database.connection().await?)
+        .await?;
+
+        Ok(project)
+    }
+
+    pub async fn add_conversation(&self, project_id: Uuid, conversation_id: Uuid) -> Result<bool> {
+        let rows_affected = sqlx::query(
+            r#"
+            INSERT INTO conversation_projects (conversation_id, project_id)
+            VALUES ($1, $2)
+            ON CONFLICT DO NOTHING
+            "#,
+        )
+        .bind(conversation_id)
+        .bind(project_id)
+        .execute(&mut *self.database.connection().await?)
+        .await?
+        .rows_affected();
+
+        Ok(rows_affected > 0)
+    }
+
+    pub async fn remove_conversation(
+        &self,
+        project_id: Uuid,
+        conversation_id: Uuid,
+    ) -> Result<bool> {
+        let rows_affected = sqlx::query(
+            "DELETE FROM conversation_projects WHERE conversation_id = $1 AND project_id = $2",
+        )
+        .bind(conversation_id)
+        .bind(project_id)
+        .execute(&mut *self.database.connection().await?)
+        .await?
+        .rows_affected();
+
+        Ok(rows_affected > 0)
+    }
+}
+
+#[async_trait]
+impl Repository<Project, Uuid> for ProjectRepository {
+    async fn find_by_id(&self, id: Uuid) -> Result<Option<Project>> {
+        let project = sqlx::query_as::<_, Project>(
+            r#"
+            SELECT id, user_id, name, description, system_prompt, default_model, created_at, updated_at
+            FROM projects
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&mut *self.database.connection().await?)
+        .await?;
+
+        Ok(project)
+    }
+
+    async fn create(&self, project: Project) -> Result<Project> {
+        let created = sqlx::query_as::<_, Project>(
+            r#"
+            INSERT INTO projects (id, user_id, name, description, system_prompt, default_model, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            RETURNING id, user_id, name, description, system_prompt, default_model, created_at, updated_at
+            "#,
+        )
+        .bind(project.id)
+        .bind(project.user_id)
+        .bind(&project.name)
+        .bind(&project.description)
+        .bind(&project.system_prompt)
+        .bind(&project.default_model)
+        .bind(project.created_at)
+        .bind(project.updated_at)
+        .fetch_one(&mut *self.database.connection().await?)
+        .await?;
+
+        Ok(created)
+    }
+
+    async fn update(&self, project: Project) -> Result<Project> {
+        let updated = sqlx::query_as::<_, Project>(
+            r#"
+            UPDATE projects
+            SET name = $2, description = $3, system_prompt = $4, default_model = $5
+            WHERE id = $1
+            RETURNING id, user_id, name, description, system_prompt, default_model, created_at, updated_at
+            "#,
+        )
+        .bind(project.id)
+        .bind(&project.name)
+        .bind(&project.description)
+        .bind(&project.system_prompt)
+        .bind(&project.default_model)
+        .fetch_one(&mut *self.database.connection().await?)
+        .await?;
+
+        Ok(updated)
+    }
+
+    async fn delete(&self, id: Uuid) -> Result<bool> {
+        // Conversations are unlinked by the cascade, not deleted
+        let rows_affected = sqlx::query("DELETE FROM projects WHERE id = $1")
+            .bind(id)
+            .execute(&mut *self.database.connection().await?)
+            .await?
+            .rows_affected();
+
+        Ok(rows_affected > 0)
+    }
+}
@@ -0,0 +1,204 @@
+use crate::{
+    database::Database,
+    models::{SaveSearchRequest, SavedSearch, SavedSearchMatches},
+};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::types::Json;
+use uuid::Uuid;
+
+const SAVED_SEARCH_COLUMNS: &str = r#"
+    id, user_id, name, query, mode, filters, similarity_threshold, new_match_count,
+    last_viewed_at, last_checked_at, created_at, updated_at
+"#;
+
+#[derive(Debug, Clone)]
+pub struct SavedSearchRepository {
+    database: Database,
+}
+
+impl SavedSearchRepository {
+    pub fn new(database: Database) -> Self {
+        Self { database }
+    }
+
+    pub async fn create(&self, user_id: Uuid, request: &SaveSearchRequest) -> Result<SavedSearch> {
+        let saved_search = sqlx::query_as::<_, SavedSearch>(&format!(
+            r#"
+            INSERT INTO saved_searches (user_id, name, query, mode, filters, similarity_threshold)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING {SAVED_SEARCH_COLUMNS}
+            "#
+        ))
+        .bind(user_id)
+        .bind(&request.name)
+        .bind(&request.query)
+        .bind(request.mode)
+        .bind(Json(&request.filters))
+        .bind(request.similarity_threshold)
+        .fetch_one(&mut *self.database.connection().await?)
+        .await?;
+
+        Ok(saved_search)
+    }
+
+    /// A saved search of the user's; other users' searches are not found
+    pub async fn find_for_user(&self, id: Uuid, user_id: Uuid) -> Result<Option<SavedSearch>> {
+        let saved_search = sqlx::query_as::<_, SavedSearch>(&format!(
+            "SELECT {SAVED_SEARCH_COLUMNS} FROM saved_searches WHERE id = $1 AND user_id = $2"
+        ))
+        .bind(id)
+        .bind(user_id)
+        .fetch_optional(&mut *self.database.connection().await?)
+        .await?;
+
+        Ok(saved_search)
+    }
+
+    /// The user's saved searches, those with new matches first
+    pub async fn find_by_user_id(&self, user_id: Uuid) -> Result<Vec<SavedSearch>> {
+        let saved_searches = sqlx::query_as::<_, SavedSearch>(&format!(
+            r#"
+            SELECT {SAVED_SEARCH_COLUMNS}
+            FROM saved_searches
+            WHERE user_id = $1
+            ORDER BY new_match_count > 0 DESC, LOWER(name) ASC
+            "#
+        ))
+        .bind(user_id)
+        .fetch_all(&mut *self.database.connection().await?)
+        .await?;
+
+        Ok(saved_searches)
+    }
+
+    pub async fn find_match_counts(&self, user_id: Uuid) -> Result<Vec<SavedSearchMatches>> {
+        let counts = sqlx::query_as::<_, SavedSearchMatches>(
+            r#"
+            SELECT id, name, new_match_count
+            FROM saved_searches
+            WHERE user_id = $1
+            ORDER BY created_at ASC
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&mut *self.database.connection().await?)
+        .await?;
+
+        Ok(counts)
+    }
+
+    /// Replace the search; its matches are counted from now on
+    pub async fn update(
+        &self,
+        id: Uuid,
+        user_id: Uuid,
+        request: &SaveSearchRequest,
+    ) -> Result<Option<SavedSearch>> {
+        let saved_search = sqlx::query_as::<_, SavedSearch>(&format!(
+            r#"
+            UPDATE saved_searches
+            SET name = $3, query = $4, mode = $5, filters = $6, similarity_threshold = $7,
+                new_match_count = 0, last_viewed_at = NOW(), last_checked_at = NOW(),
+                updated_at = NOW()
+            WHERE id = $1 AND user_id = $2
+            RETURNING {SAVED_SEARCH_COLUMNS}
+            "#
+        ))
+        .bind(id)
+        .bind(user_id)
+        .bind(&request.name)
+        .bind(&request.query)
+        .bind(request.mode)
+        .bind(Json(&request.filters))
+        .bind(request.similarity_threshold)
+        .fetch_optional(&mut *self.database.connection().await?)
+        .await?;
+
+        Ok(saved_search)
+    }
+
+    pub async fn delete(&self, id: Uuid, user_id: Uuid) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM saved_searches WHERE id = $1 AND user_id = $2")
+            .bind(id)
+            .bind(user_id)
+            .execute(&mut *self.database.connection().await?)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Searches whose owner has messages created or embedded since the last check,
+    /// longest unchecked first
+    pub async fn find_due(&self, limit: i64) -> Result<Vec<SavedSearch>> {
+        let saved_searches = sqlx::query_as::<_, SavedSearch>(&format!(
+            r#"
+            SELECT {SAVED_SEARCH_COLUMNS}
+            FROM saved_searches ss
+            WHERE EXISTS (
+                SELECT 1
+                FROM messages m
+                INNER JOIN conversations c ON c.id = m.conversation_id
+                WHERE c.user_id = ss.user_id AND m.created_at > ss.last_checked_at
+            ) OR EXISTS (
+                SELECT 1
+                FROM message_embeddings me
+                INNER JOIN messages m ON m.id = me.message_id
+                INNER JOIN conversations c ON c.id = m.conversation_id
+                WHERE c.user_id = ss.user_id AND me.created_at > ss.last_checked_at
+            )
+            ORDER BY ss.last_checked_at ASC
+            LIMIT $1
+            "#
+        ))
+        .bind(limit)
+        .fetch_all(&mut *self.database.connection().await?)
+        .await?;
+
+        Ok(saved_searches)
+    }
+
+    /// Store a check's count, unless the search was viewed or changed since it started;
+    /// the count would then be measured from the wrong point
+    pub async fn record_check(
+        &self,
+        id: Uuid,
+        last_viewed_at: DateTime<Utc>,
+        new_match_count: i32,
+        checked_at: DateTime<Utc>,
+    ) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            UPDATE saved_searches
+            SET new_match_count = $3, last_checked_at = $4
+            WHERE id = $1 AND last_viewed_at = $2
+            "#,
+        )
+        .bind(id)
+        .bind(last_viewed_at)
+        .bind(new_match_count)
+        .bind(checked_at)
+        .execute(&mut *self.database.connection().await?)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Mark every match seen
+    pub async fn mark_viewed(&self, id: Uuid, viewed_at: DateTime<Utc>) -> Result<SavedSearch> {
+        let saved_search = sqlx::query_as::<_, SavedSearch>(&format!(
+            r#"
+            UPDATE saved_searches
+            SET new_match_count = 0, last_viewed_at = $2, last_checked_at = $2
+            WHERE id = $1
+            RETURNING {SAVED_SEARCH_COLUMNS}
+            "#
+        ))
+        .bind(id)
+        .bind(viewed_at)
+        .fetch_one(&mut *self.database.connection().await?)
+        .await?;
+
+        Ok(saved_search)
+    }
+}
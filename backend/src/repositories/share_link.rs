@@ -0,0 +1,123 @@
+use crate::{
+    database::Database,
+    models::{ShareLink, SharedConversation},
+};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::types::Json;
+use uuid::Uuid;
+
+const SHARE_LINK_COLUMNS: &str = "id, token, conversation_id, user_id, include_branches, \
+     expires_at, revoked_at, view_count, created_at";
+
+#[derive(Debug, Clone)]
+pub struct ShareLinkRepository {
+    database: Database,
+}
+
+impl ShareLinkRepository {
+    pub fn new(database: Database) -> Self {
+        Self { database }
+    }
+
+    pub async fn create(
+        &self,
+        conversation_id: Uuid,
+        user_id: Uuid,
+        token: &str,
+        include_branches: bool,
+        snapshot: &SharedConversation,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<ShareLink> {
+        let link = sqlx::query_as::<_, ShareLink>(&format!(
+            r#"
+            INSERT INTO share_links (id, token, conversation_id, user_id, include_branches, snapshot, expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING {}
+            "#,
+            SHARE_LINK_COLUMNS
+        ))
+        .bind(Uuid::new_v4())
+        .bind(token)
+        .bind(conversation_id)
+        .bind(user_id)
+        .bind(include_branches)
+        .bind(Json(snapshot))
+        .bind(expires_at)
+        .fetch_one(&mut *self.database.connection().await?)
+        .await?;
+
+        Ok(link)
+    }
+
+    pub async fn find_by_id(&self, id: Uuid) -> Result<Option<ShareLink>> {
+        let link = sqlx::query_as::<_, ShareLink>(&format!(
+            "SELECT {} FROM share_links WHERE id = $1",
+            SHARE_LINK_COLUMNS
+        ))
+        .bind(id)
+        .fetch_optional(&mut *self.database.connection().await?)
+        .await?;
+
+        Ok(link)
+    }
+
+    pub async fn find_by_conversation_id(&self, conversation_id: Uuid) -> Result<Vec<ShareLink>> {
+        let links = sqlx::query_as::<_, ShareLink>(&format!(
+            "SELECT {} FROM share_links WHERE conversation_id = $1 ORDER BY created_at DESC",
+            SHARE_LINK_COLUMNS
+        ))
+        .bind(conversation_id)
+        .fetch_all(&mut *self.database.connection().await?)
+        .await?;
+
+        Ok(links)
+    }
+
+    pub async fn find_by_user_id(&self, user_id: Uuid) -> Result<Vec<ShareLink>> {
+        let links = sqlx::query_as::<_, ShareLink>(&format!(
+            "SELECT {} FROM share_links WHERE user_id = $1 ORDER BY created_at DESC",
+            SHARE_LINK_COLUMNS
+        ))
+        .bind(user_id)
+        .fetch_all(&mut *self.database.connection().await?)
+        .await?;
+
+        Ok(links)
+    }
+
+    /// Snapshot behind a token, counting the view. Revoked and expired links, and links
+    /// to conversations in the trash, resolve to nothing.
+    pub async fn view_snapshot(&self, token: &str) -> Result<Option<SharedConversation>> {
+        let snapshot = sqlx::query_scalar::<_, Json<SharedConversation>>(
+            r#"
+            UPDATE share_links s
+            SET view_count = s.view_count + 1
+            FROM conversations c
+            WHERE s.token = $1
+              AND c.id = s.conversation_id
+              AND c.deleted_at IS NULL
+              AND s.revoked_at IS NULL
+              AND (s.expires_at IS NULL OR s.expires_at > NOW())
+            RETURNING s.snapshot
+            "#,
+        )
+        .bind(token)
+        .fetch_optional(&mut *self.database.connection().await?)
+        .await?;
+
+        Ok(snapshot.map(|Json(snapshot)| snapshot))
+    }
+
+    /// Revoke a link; returns false if it was already revoked
+    pub async fn revoke(&self, id: Uuid) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE share_links SET revoked_at = NOW() WHERE id = $1 AND revoked_at IS NULL",
+        )
+        .bind(id)
+        .execute(&mut *self.database.connection().await?)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
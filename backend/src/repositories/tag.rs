@@ -0,0 +1,242 @@
+use crate::{
+    database::Database,
+    models::{CreateTagRequest, Tag, TagSummary, UpdateTagRequest},
+    repositories::Repository,
+};
+use anyhow::Result;
+use async_trait::async_trait;
+use uuid::Uuid;
+
+#[derive(Debug, Clone)]
+pub struct TagRepository {
+    database: Database,
+}
+
+impl TagRepository {
+    pub fn new(database: Database) -> Self {
+        Self { database }
+    }
+
+    pub async fn find_by_user_id(&self, user_id: Uuid) -> Result<Vec<TagSummary>> {
+        let tags = sqlx::query_as::<_, TagSummary>(
+            r#"
+            SELECT t.id, t.user_id, t.name, t.color, t.created_at,
+                   COUNT(ct.conversation_id) AS conversation_count
+            FROM tags t
+            LEFT JOIN conversation_tags ct ON ct.tag_id = t.id
+            WHERE t.user_id = $1
+            GROUP BY t.id
+            ORDER BY LOWER(t.name) ASC
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&mut *self.database.connection().await?)
+        .await?;
+
+        Ok(tags)
+    }
+
+    pub async fn find_by_conversation_id(&self, conversation_id: Uuid) -> Result<Vec<Tag>> {
+        let tags = sqlx::query_as::<_, Tag>(
+            r#"
+            SELECT t.id, t.user_id, t.name, t.color, t.created_at
+            FROM tags t
+            INNER JOIN conversation_tags ct ON ct.tag_id = t.id
+            WHERE ct.conversation_id = $1
+            ORDER BY LOWER(t.name) ASC
+            "#,
+        )
+        .bind(conversation_id)
+        .fetch_all(&mut *self.database.connection().await?)
+        .await?;
+
+        Ok(tags)
+    }
+
+    /// Whether the user already has a tag with this name (case-insensitive), ignoring `exclude_id`
+    pub async fn name_exists(
+        &self,
+        user_id: Uuid,
+        name: &str,
+        exclude_id: Option<Uuid>,
+    ) -> Result<bool> {
+        let exists: bool = sqlx::query_scalar(
+            r#"
+            SELECT EXISTS(
+                SELECT 1 FROM tags
+                WHERE user_id = $1 AND LOWER(name) = LOWER($2) AND ($3::uuid IS NULL OR id <> $3)
+            )
+            "#,
+        )
+        .bind(user_id)
+        .bind(name)
+        .bind(exclude_id)
+        .fetch_one(&mut *self.database.connection().await?)
+        .await?;
+
+        Ok(exists)
+    }
+
+    pub async fn create_from_request(
+        &self,
+        user_id: Uuid,
+        request: CreateTagRequest,
+    ) -> Result<Tag> {
+        let tag = sqlx::query_as::<_, Tag>(
+            r#"
+            INSERT INTO tags (id, user_id, name, color)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id, user_id, name, color, created_at
+            "#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(user_id)
+        .bind(request.name.trim())
+        .bind(&request.color)
+        .fetch_one(&mut *self.database.connection().await?)
+        .await?;
+
+        Ok(tag)
+    }
+
+    /// Look up a tag by name, creating it on first use
+    pub async fn find_or_create_by_name(&self, user_id: Uuid, name: &str) -> Result<Tag> {
+        let tag = sqlx::query_as::<_, Tag>(
+            r#"
+            INSERT INTO tags (id, user_id, name)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (user_id, (LOWER(name))) DO UPDATE SET name = tags.name
+            RETURNING id, user_id, name, color, created_at
+            "#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(user_id)
+        .bind(name.trim())
+        .fetch_one(&mut *self.database.connection().await?)
+        .await?;
+
+        Ok(tag)
+    }
+
+    /// Apply a partial update. An empty color clears it.
+    pub async fn update_from_request(
+        &self,
+        id: Uuid,
+        request: UpdateTagRequest,
+    ) -> Result<Option<Tag>> {
+        let tag = sqlx::query_as::<_, Tag>(
+            r#"
+            UPDATE tags
+            SET name = COALESCE($2, name),
+                color = CASE WHEN $3::text IS NULL THEN color ELSE NULLIF($3, '') END
+            WHERE id = $1
+            RETURNING id, user_id, name, color, created_at
+            "#,
+        )
+        .bind(id)
+        .bind(request.name.as_deref().map(str::trim))
+        .bind(&request.color)
+        .fetch_optional(&mut *self.database.connection().await?)
+        .await?;
+
+        Ok(tag)
+    }
+
+    pub async fn add_to_conversation(&self, tag_id: Uuid, conversation_id: Uuid) -> Result<bool> {
+        let rows_affected = sqlx::query(
+            r#"
+            INSERT INTO conversation_tags (conversation_id, tag_id)
+            VALUES ($1, $2)
+            ON CONFLICT DO NOTHING
+            "#,
+        )
+        .bind(conversation_id)
+        .bind(tag_id)
+        .execute(&mut *self.database.connection().await?)
+        .await?
+        .rows_affected();
+
+        Ok(rows_affected > 0)
+    }
+
+    pub async fn remove_from_conversation(
+        &self,
+        tag_id: Uuid,
+        conversation_id: Uuid,
+    ) -> Result<bool> {
+        let rows_affected =
+            sqlx::query("DELETE FROM conversation_tags WHERE conversation_id = $1 AND tag_id = $2")
+                .bind(conversation_id)
+                .bind(tag_id)
+                .execute(&mut *self.database.connection().await?)
+                .await?
+                .rows_affected();
+
+        Ok(rows_affected > 0)
+    }
+}
+
+#[async_trait]
+impl Repository<Tag, Uuid> for TagRepository {
+    async fn find_by_id(&self, id: Uuid) -> Result<Option<Tag>> {
+        let tag = sqlx::query_as::<_, Tag>(
+            r#"
+            SELECT id, user_id, name, color, created_at
+            FROM tags
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&mut *self.database.connection().await?)
+        .await?;
+
+        Ok(tag)
+    }
+
+    async fn create(&self, tag: Tag) -> Result<Tag> {
+        let created = sqlx::query_as::<_, Tag>(
+            r#"
+            INSERT INTO tags (id, user_id, name, color, created_at)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING id, user_id, name, color, created_at
+            "#,
+        )
+        .bind(tag.id)
+        .bind(tag.user_id)
+        .bind(&tag.name)
+        .bind(&tag.color)
+        .bind(tag.created_at)
+        .fetch_one(&mut *self.database.connection().await?)
+        .await?;
+
+        Ok(created)
+    }
+
+    async fn update(&self, tag: Tag) -> Result<Tag> {
+        let updated = sqlx::query_as::<_, Tag>(
+            r#"
+            UPDATE tags
+            SET name = $2, color = $3
+            WHERE id = $1
+            RETURNING id, user_id, name, color, created_at
+            "#,
+        )
+        .bind(tag.id)
+        .bind(&tag.name)
+        .bind(&tag.color)
+        .fetch_one(&mut *self.database.connection().await?)
+        .await?;
+
+        Ok(updated)
+    }
+
+    async fn delete(&self, id: Uuid) -> Result<bool> {
+        let rows_affected = sqlx::query("DELETE FROM tags WHERE id = $1")
+            .bind(id)
+            .execute(&mut *self.database.connection().await?)
+            .await?
+            .rows_affected();
+
+        Ok(rows_affected > 0)
+    }
+}
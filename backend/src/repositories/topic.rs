@@ -0,0 +1,219 @@
+use crate::{
+    database::Database,
+    models::{TopicCluster, TopicMembers, TopicPoint},
+};
+use anyhow::Result;
+use uuid::Uuid;
+
+/// A message embedding to cluster
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct TopicSource {
+    pub message_id: Uuid,
+    pub content: String,
+    pub embedding: Vec<f32>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TopicRepository {
+    database: Database,
+}
+
+impl TopicRepository {
+    pub fn new(database: Database) -> Self {
+        Self { database }
+    }
+
+    /// The user's most recent message embeddings from one model, outside the trash.
+    /// System prompts are left out; they say little about what was explored.
+    pub async fn find_sources(
+        &self,
+        user_id: Uuid,
+        model: &str,
+        limit: i64,
+    ) -> Result<Vec<TopicSource>> {
+        let sources = sqlx::query_as::<_, TopicSource>(
+            r#"
+            SELECT me.message_id, m.content, me.embedding::real[] AS embedding
+            FROM message_embeddings me
+            INNER JOIN messages m ON m.id = me.message_id
+            INNER JOIN conversations c ON c.id = m.conversation_id
+            WHERE c.user_id = $1 AND me.model = $2 AND m.role <> 'system'
+            AND m.deleted_at IS NULL AND c.deleted_at IS NULL
+            ORDER BY m.created_at DESC
+            LIMIT $3
+            "#,
+        )
+        .bind(user_id)
+        .bind(model)
+        .bind(limit)
+        .fetch_all(&mut *self.database.connection().await?)
+        .await?;
+
+        Ok(sources)
+    }
+
+    /// Users with message embeddings from `model`, for clustering everyone in one run
+    pub async fn find_user_ids_with_embeddings(&self, model: &str) -> Result<Vec<Uuid>> {
+        let user_ids = sqlx::query_scalar::<_, Uuid>(
+            r#"
+            SELECT DISTINCT c.user_id
+            FROM message_embeddings me
+            INNER JOIN messages m ON m.id = me.message_id
+            INNER JOIN conversations c ON c.id = m.conversation_id
+            WHERE me.model = $1
+            "#,
+        )
+        .bind(model)
+        .fetch_all(&mut *self.database.connection().await?)
+        .await?;
+
+        Ok(user_ids)
+    }
+
+    /// Remove the user's clusters and, through the cascade, their message assignments
+    /// Make other runs for the user wait until this transaction ends, so two runs
+    /// can't both replace the topics
+    pub async fn lock_for_user(&self, user_id: Uuid) -> Result<()> {
+        sqlx::query("SELECT pg_advisory_xact_lock(hashtextextended('topics:' || $1::text, 0))")
+            .bind(user_id)
+            .execute(&mut *self.database.connection().await?)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn delete_for_user(&self, user_id: Uuid) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM topic_clusters WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&mut *self.database.connection().await?)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    pub async fn create_cluster(
+        &self,
+        user_id: Uuid,
+        cluster_index: i32,
+        label: &str,
+        keywords: &[String],
+        size: i32,
+        model: &str,
+    ) -> Result<TopicCluster> {
+        let cluster = sqlx::query_as::<_, TopicCluster>(
+            r#"
+            INSERT INTO topic_clusters (user_id, cluster_index, label, keywords, size, model)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING id, user_id, cluster_index, label, keywords, size, model, created_at
+            "#,
+        )
+        .bind(user_id)
+        .bind(cluster_index)
+        .bind(label)
+        .bind(keywords)
+        .bind(size)
+        .bind(model)
+        .fetch_one(&mut *self.database.connection().await?)
+        .await?;
+
+        Ok(cluster)
+    }
+
+    /// Put messages in a cluster at their map coordinates
+    pub async fn assign_messages(
+        &self,
+        cluster_id: Uuid,
+        message_ids: &[Uuid],
+        coordinates: &[[f32; 2]],
+    ) -> Result<u64> {
+        let xs: Vec<f32> = coordinates.iter().map(|point| point[0]).collect();
+        let ys: Vec<f32> = coordinates.iter().map(|point| point[1]).collect();
+
+        let result = sqlx::query(
+            r#"
+            INSERT INTO message_topics (message_id, cluster_id, x, y)
+            SELECT message_id, $1, x, y
+            FROM UNNEST($2::uuid[], $3::real[], $4::real[]) AS t(message_id, x, y)
+            ON CONFLICT (message_id) DO UPDATE
+            SET cluster_id = EXCLUDED.cluster_id, x = EXCLUDED.x, y = EXCLUDED.y
+            "#,
+        )
+        .bind(cluster_id)
+        .bind(message_ids)
+        .bind(&xs)
+        .bind(&ys)
+        .execute(&mut *self.database.connection().await?)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// The user's clusters, largest first
+    pub async fn find_clusters(&self, user_id: Uuid) -> Result<Vec<TopicCluster>> {
+        let clusters = sqlx::query_as::<_, TopicCluster>(
+            r#"
+            SELECT id, user_id, cluster_index, label, keywords, size, model, created_at
+            FROM topic_clusters
+            WHERE user_id = $1
+            ORDER BY size DESC, cluster_index ASC
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&mut *self.database.connection().await?)
+        .await?;
+
+        Ok(clusters)
+    }
+
+    /// The user's clusters with their messages, leaving out messages deleted since the run
+    pub async fn find_members(&self, user_id: Uuid) -> Result<Vec<TopicMembers>> {
+        let members = sqlx::query_as::<_, TopicMembers>(
+            r#"
+            SELECT tc.id, tc.user_id, tc.cluster_index, tc.label, tc.keywords, tc.size,
+                   tc.model, tc.created_at,
+                   COALESCE(
+                       array_agg(m.id ORDER BY m.created_at) FILTER (WHERE m.id IS NOT NULL),
+                       '{}'
+                   ) AS message_ids,
+                   COALESCE(
+                       array_agg(DISTINCT m.conversation_id) FILTER (WHERE m.id IS NOT NULL),
+                       '{}'
+                   ) AS conversation_ids
+            FROM topic_clusters tc
+            LEFT JOIN message_topics mt ON mt.cluster_id = tc.id
+            LEFT JOIN messages m ON m.id = mt.message_id AND m.deleted_at IS NULL
+            WHERE tc.user_id = $1
+            GROUP BY tc.id
+            ORDER BY tc.size DESC, tc.cluster_index ASC
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&mut *self.database.connection().await?)
+        .await?;
+
+        Ok(members)
+    }
+
+    /// Every clustered message of the user with its map coordinates
+    pub async fn find_points(&self, user_id: Uuid) -> Result<Vec<TopicPoint>> {
+        let points = sqlx::query_as::<_, TopicPoint>(
+            r#"
+            SELECT mt.message_id, m.conversation_id, mt.cluster_id, m.role, mt.x, mt.y,
+                   CASE WHEN char_length(m.content) > 300
+                        THEN LEFT(m.content, 300) || '...'
+                        ELSE m.content
+                   END AS preview
+            FROM message_topics mt
+            INNER JOIN topic_clusters tc ON tc.id = mt.cluster_id
+            INNER JOIN messages m ON m.id = mt.message_id
+            WHERE tc.user_id = $1 AND m.deleted_at IS NULL
+            ORDER BY tc.cluster_index ASC, m.created_at ASC
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&mut *self.database.connection().await?)
+        .await?;
+
+        Ok(points)
+    }
+}
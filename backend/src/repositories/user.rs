@@ -30,7 +30,7 @@ impl UserRepository {
             "#,
         )
         .bind(email)
-        .fetch_optional(&self.database.pool)
+        .fetch_optional(&mut *self.database.connection().await?)
         .await?;
 
         Ok(user)
@@ -45,7 +45,7 @@ impl UserRepository {
             "#,
         )
         .bind(username)
-        .fetch_optional(&self.database.pool)
+        .fetch_optional(&mut *self.database.connection().await?)
         .await?;
 
         Ok(user)
@@ -72,7 +72,7 @@ impl UserRepository {
         .bind(&request.email)
         .bind(&request.username)
         .bind(&password_hash)
-        .fetch_one(&self.database.pool)
+        .fetch_one(&mut *self.database.connection().await?)
         .await?;
 
         Ok(user)
@@ -104,7 +104,7 @@ impl UserRepository {
         )
         .bind(&password_hash)
         .bind(user_id)
-        .execute(&self.database.pool)
+        .execute(&mut *self.database.connection().await?)
         .await?
         .rows_affected();
 
@@ -114,7 +114,7 @@ impl UserRepository {
     pub async fn email_exists(&self, email: &str) -> Result<bool> {
         let count = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM users WHERE email = $1")
             .bind(email)
-            .fetch_one(&self.database.pool)
+            .fetch_one(&mut *self.database.connection().await?)
             .await?;
 
         Ok(count > 0)
@@ -123,7 +123,7 @@ impl UserRepository {
     pub async fn username_exists(&self, username: &str) -> Result<bool> {
         let count = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM users WHERE username = $1")
             .bind(username)
-            .fetch_one(&self.database.pool)
+            .fetch_one(&mut *self.database.connection().await?)
             .await?;
 
         Ok(count > 0)
@@ -141,7 +141,7 @@ impl Repository<User, Uuid> for UserRepository {
             "#,
         )
         .bind(id)
-        .fetch_optional(&self.database.pool)
+        .fetch_optional(&mut *self.database.connection().await?)
         .await?;
 
         Ok(user)
@@ -159,7 +159,7 @@ impl Repository<User, Uuid> for UserRepository {
         .bind(&user.email)
         .bind(&user.username)
         .bind(&user.password_hash)
-        .fetch_one(&self.database.pool)
+        .fetch_one(&mut *self.database.connection().await?)
         .await?;
 
         Ok(created)
@@ -178,7 +178,7 @@ impl Repository<User, Uuid> for UserRepository {
         .bind(&user.email)
         .bind(&user.username)
         .bind(&user.password_hash)
-        .fetch_one(&self.database.pool)
+        .fetch_one(&mut *self.database.connection().await?)
         .await?;
 
         Ok(updated)
@@ -187,7 +187,7 @@ impl Repository<User, Uuid> for UserRepository {
     async fn delete(&self, id: Uuid) -> Result<bool> {
         let rows_affected = sqlx::query("DELETE FROM users WHERE id = $1")
             .bind(id)
-            .execute(&self.database.pool)
+            .execute(&mut *self.database.connection().await?)
             .await?
             .rows_affected();
 
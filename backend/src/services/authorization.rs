@@ -0,0 +1,90 @@
+use crate::{
+    error::AppError,
+    models::{Conversation, ConversationRole, Message},
+    repositories::Repository,
+    services::DataAccessLayer,
+};
+use uuid::Uuid;
+
+/// Decides what a user may do with a conversation. Handlers and services ask here
+/// instead of comparing `conversation.user_id` themselves.
+#[derive(Debug, Clone)]
+pub struct AuthorizationService {
+    dal: DataAccessLayer,
+}
+
+impl AuthorizationService {
+    pub fn new(dal: DataAccessLayer) -> Self {
+        Self { dal }
+    }
+
+    /// The user's role on the conversation, `None` if they have no access.
+    /// Conversations in the trash are only visible to their owner.
+    pub async fn role(
+        &self,
+        conversation: &Conversation,
+        user_id: Uuid,
+    ) -> Result<Option<ConversationRole>, AppError> {
+        if conversation.user_id == user_id {
+            return Ok(Some(ConversationRole::Owner));
+        }
+        if conversation.deleted_at.is_some() {
+            return Ok(None);
+        }
+
+        Ok(self
+            .dal
+            .conversation_members()
+            .find_role(conversation.id, user_id)
+            .await?)
+    }
+
+    /// Load a conversation, checking the user has at least the `required` role
+    pub async fn require(
+        &self,
+        conversation_id: Uuid,
+        user_id: Uuid,
+        required: ConversationRole,
+    ) -> Result<Conversation, AppError> {
+        let conversation = self
+            .dal
+            .conversations()
+            .find_by_id(conversation_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Conversation not found".to_string()))?;
+
+        match self.role(&conversation, user_id).await? {
+            Some(role) if role >= required => Ok(conversation),
+            Some(_) => Err(AppError::Forbidden(format!(
+                "Requires {} access",
+                required.as_str()
+            ))),
+            None if conversation.deleted_at.is_some() => {
+                Err(AppError::NotFound("Conversation not found".to_string()))
+            }
+            None => Err(AppError::Forbidden("Access denied".to_string())),
+        }
+    }
+
+    /// Load a message and its conversation, checking the user has at least the `required` role.
+    /// Deleted messages are not found.
+    pub async fn require_for_message(
+        &self,
+        message_id: Uuid,
+        user_id: Uuid,
+        required: ConversationRole,
+    ) -> Result<(Message, Conversation), AppError> {
+        let message = self
+            .dal
+            .messages()
+            .find_by_id(message_id)
+            .await?
+            .filter(|m| m.deleted_at.is_none())
+            .ok_or_else(|| AppError::NotFound("Message not found".to_string()))?;
+
+        let conversation = self
+            .require(message.conversation_id, user_id, required)
+            .await?;
+        Ok((message, conversation))
+    }
+}
@@ -1,19 +1,27 @@
 use crate::{
-    models::{CreateMessageRequest, CreateMessageResponse, Message, MessageRole},
-    repositories::Repository,
-    services::DataAccessLayer,
+    llm::{self, LLMServiceFactory},
+    models::{
+        ApiUsage, ConversationRole, CreateMessageRequest, CreateMessageResponse, Message,
+        MessageRole, MessageWindow, MessageWindowParams,
+    },
+    services::{authorization::AuthorizationService, retrieval::Citation, DataAccessLayer},
 };
 use anyhow::Result;
+use chrono::Utc;
 use uuid::Uuid;
 
 #[derive(Debug, Clone)]
 pub struct ChatService {
     dal: DataAccessLayer,
+    authorization: AuthorizationService,
 }
 
 impl ChatService {
     pub fn new(dal: DataAccessLayer) -> Self {
-        Self { dal }
+        Self {
+            authorization: AuthorizationService::new(dal.clone()),
+            dal,
+        }
     }
 
     pub async fn send_message(
@@ -22,24 +30,36 @@ impl ChatService {
         conversation_id: Uuid,
         content: String,
     ) -> Result<CreateMessageResponse> {
-        // Verify the conversation belongs to the user
-        let conversation = self.dal.conversations().find_by_id(conversation_id).await?;
-
-        match conversation {
-            Some(conv) if conv.user_id == user_id => {
-                // Create the user message
-                let request = CreateMessageRequest {
-                    conversation_id,
-                    parent_id: None, // For now, we'll handle threading later
-                    role: MessageRole::User,
-                    content,
-                    metadata: None,
-                };
-
-                self.dal.messages().create_from_request(request).await
+        self.authorization
+            .require(conversation_id, user_id, ConversationRole::Editor)
+            .await?;
+
+        let request = CreateMessageRequest {
+            conversation_id,
+            parent_id: self.reply_parent(conversation_id, None).await?,
+            role: MessageRole::User,
+            content,
+            metadata: None,
+            author_id: Some(user_id),
+        };
+
+        self.dal.messages().create_from_request(request).await
+    }
+
+    /// Messages without an explicit parent continue the active thread
+    async fn reply_parent(
+        &self,
+        conversation_id: Uuid,
+        parent_id: Option<Uuid>,
+    ) -> Result<Option<Uuid>> {
+        match parent_id {
+            Some(parent_id) => Ok(Some(parent_id)),
+            None => {
+                self.dal
+                    .messages()
+                    .find_active_leaf_id(conversation_id)
+                    .await
             }
-            Some(_) => Err(anyhow::anyhow!("Conversation not found or access denied")),
-            None => Err(anyhow::anyhow!("Conversation not found")),
         }
     }
 
@@ -48,19 +68,74 @@ impl ChatService {
         user_id: Uuid,
         conversation_id: Uuid,
     ) -> Result<Vec<Message>> {
-        // Verify the conversation belongs to the user
-        let conversation = self.dal.conversations().find_by_id(conversation_id).await?;
+        self.authorization
+            .require(conversation_id, user_id, ConversationRole::Viewer)
+            .await?;
 
-        match conversation {
-            Some(conv) if conv.user_id == user_id => {
-                self.dal
-                    .messages()
-                    .find_by_conversation_id(conversation_id)
-                    .await
-            }
-            Some(_) => Err(anyhow::anyhow!("Conversation not found or access denied")),
-            None => Err(anyhow::anyhow!("Conversation not found")),
+        self.dal
+            .messages()
+            .find_by_conversation_id(conversation_id)
+            .await
+    }
+
+    /// Load part of the active thread. Without an anchor or limit the whole thread is returned.
+    pub async fn get_message_window(
+        &self,
+        user_id: Uuid,
+        conversation_id: Uuid,
+        params: &MessageWindowParams,
+    ) -> Result<MessageWindow> {
+        self.authorization
+            .require(conversation_id, user_id, ConversationRole::Viewer)
+            .await?;
+
+        let total = self
+            .dal
+            .messages()
+            .count_by_conversation(conversation_id)
+            .await?;
+
+        if params.before.is_none() && params.after.is_none() && params.limit.is_none() {
+            let messages = self
+                .dal
+                .messages()
+                .find_by_conversation_id(conversation_id)
+                .await?;
+            return Ok(MessageWindow {
+                conversation_id,
+                messages,
+                next_cursor: None,
+                total,
+            });
         }
+
+        let limit = params.limit.unwrap_or(50).clamp(1, 500) as i64;
+        // Fetch one extra message to learn whether the window can be extended
+        let mut messages = self
+            .dal
+            .messages()
+            .find_window(conversation_id, params.before, params.after, limit + 1)
+            .await?;
+
+        let has_more = messages.len() as i64 > limit;
+        let next_cursor = if params.after.is_some() {
+            if has_more {
+                messages.truncate(limit as usize);
+            }
+            messages.last().filter(|_| has_more).map(|m| m.id)
+        } else {
+            if has_more {
+                messages.remove(0);
+            }
+            messages.first().filter(|_| has_more).map(|m| m.id)
+        };
+
+        Ok(MessageWindow {
+            conversation_id,
+            messages,
+            next_cursor,
+            total,
+        })
     }
 
     pub async fn send_assistant_message(
@@ -81,7 +156,7 @@ impl ChatService {
     ) -> Result<CreateMessageResponse> {
         let request = CreateMessageRequest {
             conversation_id,
-            parent_id,
+            parent_id: self.reply_parent(conversation_id, parent_id).await?,
             role: MessageRole::Assistant,
             content,
             metadata: Some(
@@ -89,6 +164,7 @@ impl ChatService {
                     .map(|t| serde_json::json!({"tokens_used": t}))
                     .unwrap_or_else(|| serde_json::json!({})),
             ),
+            author_id: None,
         };
 
         let response = self.dal.messages().create_from_request(request).await?;
@@ -104,6 +180,77 @@ impl ChatService {
         Ok(response)
     }
 
+    /// Persist an LLM response as an assistant message and record its API usage.
+    ///
+    /// Reasoning content is stored under `metadata.reasoning`, separate from the answer.
+    pub async fn save_llm_response(
+        &self,
+        user_id: Uuid,
+        conversation_id: Uuid,
+        response: &llm::ChatResponse,
+        parent_id: Option<Uuid>,
+        citations: &[Citation],
+    ) -> Result<CreateMessageResponse> {
+        let mut metadata = serde_json::json!({
+            "model": response.model,
+            "provider": response.provider,
+        });
+        if let Some(usage) = &response.usage {
+            metadata["tokens_used"] = serde_json::json!(usage.total_tokens);
+            metadata["usage"] = serde_json::to_value(usage)?;
+        }
+        if let Some(latency_ms) = response.latency_ms {
+            metadata["latency_ms"] = serde_json::json!(latency_ms);
+        }
+        if let Some(reasoning) = &response.reasoning {
+            metadata["reasoning"] = serde_json::json!(reasoning);
+        }
+        if !citations.is_empty() {
+            metadata["citations"] = serde_json::to_value(citations)?;
+        }
+
+        let request = CreateMessageRequest {
+            conversation_id,
+            parent_id: self.reply_parent(conversation_id, parent_id).await?,
+            role: MessageRole::Assistant,
+            content: response.message.content.clone(),
+            metadata: Some(metadata),
+            author_id: None,
+        };
+
+        let message = self.dal.messages().create_from_request(request).await?;
+
+        if let Some(usage) = &response.usage {
+            self.dal
+                .messages()
+                .update_tokens(message.id, usage.total_tokens as i32)
+                .await?;
+
+            let record = ApiUsage {
+                id: Uuid::new_v4(),
+                user_id,
+                model: response.model.clone(),
+                provider: response.provider.clone(),
+                tokens_prompt: Some(usage.prompt_tokens as i32),
+                tokens_completion: Some(usage.output_tokens() as i32),
+                cost_cents: LLMServiceFactory::calculate_cost_cents(&response.model, usage),
+                message_id: Some(message.id),
+                created_at: Utc::now(),
+            };
+
+            // Usage tracking must not fail the chat request
+            if let Err(e) = self.dal.api_usage().create(&record).await {
+                tracing::warn!(
+                    "Failed to record API usage for message {}: {}",
+                    message.id,
+                    e
+                );
+            }
+        }
+
+        Ok(message)
+    }
+
     pub async fn create_message_branch(
         &self,
         user_id: Uuid,
@@ -111,28 +258,14 @@ impl ChatService {
         content: String,
         role: MessageRole,
     ) -> Result<Message> {
-        // Verify the parent message exists and belongs to a conversation owned by the user
-        let parent = self
-            .dal
-            .messages()
-            .find_by_id(parent_id)
-            .await?
-            .ok_or_else(|| anyhow::anyhow!("Parent message not found"))?;
-
-        let conversation = self
-            .dal
-            .conversations()
-            .find_by_id(parent.conversation_id)
-            .await?
-            .ok_or_else(|| anyhow::anyhow!("Conversation not found"))?;
-
-        if conversation.user_id != user_id {
-            return Err(anyhow::anyhow!("Access denied"));
-        }
+        self.authorization
+            .require_for_message(parent_id, user_id, ConversationRole::Editor)
+            .await?;
 
+        let author_id = matches!(role, MessageRole::User).then_some(user_id);
         self.dal
             .messages()
-            .create_branch(parent_id, content, role)
+            .create_branch(parent_id, content, role, author_id)
             .await
     }
 
@@ -141,24 +274,9 @@ impl ChatService {
         user_id: Uuid,
         message_id: Uuid,
     ) -> Result<Vec<Message>> {
-        // Get the message to verify ownership
-        let message = self
-            .dal
-            .messages()
-            .find_by_id(message_id)
-            .await?
-            .ok_or_else(|| anyhow::anyhow!("Message not found"))?;
-
-        let conversation = self
-            .dal
-            .conversations()
-            .find_by_id(message.conversation_id)
-            .await?
-            .ok_or_else(|| anyhow::anyhow!("Conversation not found"))?;
-
-        if conversation.user_id != user_id {
-            return Err(anyhow::anyhow!("Access denied"));
-        }
+        self.authorization
+            .require_for_message(message_id, user_id, ConversationRole::Viewer)
+            .await?;
 
         self.dal
             .messages()
@@ -178,6 +296,9 @@ impl ChatService {
 pub struct SendMessageRequest {
     pub content: String,
     pub parent_id: Option<Uuid>,
+    /// Return the model's reasoning alongside the answer (hidden by default)
+    #[serde(default)]
+    pub include_reasoning: bool,
 }
 
 #[derive(Debug, serde::Serialize)]
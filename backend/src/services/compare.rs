@@ -0,0 +1,327 @@
+use crate::{
+    error::AppError,
+    llm::{LLMServiceFactory, Usage},
+    models::{
+        AnswerComparison, AnswerStats, BranchComparison, ConversationRole, DiffOp, DiffSegment,
+        Message, MessageRole,
+    },
+    services::{authorization::AuthorizationService, DataAccessLayer},
+};
+use anyhow::Result;
+use serde_json::Value;
+use uuid::Uuid;
+
+/// Above this many LCS cells the differing middle is reported as one delete and one insert
+const MAX_DIFF_CELLS: usize = 4_000_000;
+
+#[derive(Debug, Clone)]
+pub struct CompareService {
+    dal: DataAccessLayer,
+    authorization: AuthorizationService,
+}
+
+impl CompareService {
+    pub fn new(dal: DataAccessLayer) -> Self {
+        Self {
+            authorization: AuthorizationService::new(dal.clone()),
+            dal,
+        }
+    }
+
+    /// Find where the paths to `left_id` and `right_id` diverge and return both halves.
+    /// Sibling assistant answers additionally get a word diff and their usage stats.
+    pub async fn compare_branches(
+        &self,
+        user_id: Uuid,
+        conversation_id: Uuid,
+        left_id: Uuid,
+        right_id: Uuid,
+    ) -> Result<BranchComparison> {
+        if left_id == right_id {
+            return Err(
+                AppError::BadRequest("Pick two different messages to compare".to_string()).into(),
+            );
+        }
+
+        self.authorization
+            .require(conversation_id, user_id, ConversationRole::Viewer)
+            .await?;
+
+        let left_path = self.thread_in(conversation_id, left_id).await?;
+        let right_path = self.thread_in(conversation_id, right_id).await?;
+
+        let shared = left_path
+            .iter()
+            .zip(&right_path)
+            .take_while(|(l, r)| l.id == r.id)
+            .count();
+        let common_ancestor_id = shared.checked_sub(1).map(|i| left_path[i].id);
+        let left: Vec<Message> = left_path.into_iter().skip(shared).collect();
+        let right: Vec<Message> = right_path.into_iter().skip(shared).collect();
+
+        let answers = match (left.as_slice(), right.as_slice()) {
+            ([l], [r])
+                if matches!(l.role, MessageRole::Assistant)
+                    && matches!(r.role, MessageRole::Assistant) =>
+            {
+                Some(AnswerComparison {
+                    diff: word_diff(&l.content, &r.content),
+                    left: answer_stats(l),
+                    right: answer_stats(r),
+                })
+            }
+            _ => None,
+        };
+
+        Ok(BranchComparison {
+            conversation_id,
+            common_ancestor_id,
+            left,
+            right,
+            answers,
+        })
+    }
+
+    /// The root-to-message path, provided the message belongs to the conversation
+    async fn thread_in(&self, conversation_id: Uuid, message_id: Uuid) -> Result<Vec<Message>> {
+        let thread = self
+            .dal
+            .messages()
+            .find_conversation_thread(message_id)
+            .await?;
+
+        let ends_at_message = thread.last().map(|m| m.id) == Some(message_id);
+        if !ends_at_message || thread.iter().any(|m| m.conversation_id != conversation_id) {
+            return Err(AppError::NotFound("Message not found".to_string()).into());
+        }
+        Ok(thread)
+    }
+}
+
+/// Pull the usage recorded by `ChatService::save_llm_response` back out of the metadata
+fn answer_stats(message: &Message) -> AnswerStats {
+    let metadata = &message.metadata;
+    let model = metadata
+        .get("model")
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    let usage = metadata
+        .get("usage")
+        .and_then(|usage| serde_json::from_value::<Usage>(usage.clone()).ok());
+    let cost_cents = match (&model, &usage) {
+        (Some(model), Some(usage)) => LLMServiceFactory::calculate_cost_cents(model, usage),
+        _ => None,
+    };
+
+    AnswerStats {
+        prompt_tokens: usage.as_ref().map(|u| u.prompt_tokens),
+        completion_tokens: usage.as_ref().map(|u| u.completion_tokens),
+        total_tokens: usage
+            .as_ref()
+            .map(|u| u.total_tokens)
+            .or(message.tokens_used.map(|t| t as u32)),
+        cost_cents,
+        latency_ms: metadata.get("latency_ms").and_then(Value::as_u64),
+        word_count: message.content.split_whitespace().count(),
+        model,
+    }
+}
+
+/// Word-level diff from `left` to `right`. Words and whitespace runs are separate
+/// tokens, so concatenating the equal and delete segments rebuilds `left` exactly,
+/// and the equal and insert segments rebuild `right`.
+pub fn word_diff(left: &str, right: &str) -> Vec<DiffSegment> {
+    let a = tokenize(left);
+    let b = tokenize(right);
+
+    // Only the middle between a common prefix and suffix needs the quadratic pass
+    let prefix = a.iter().zip(&b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let a_mid = &a[prefix..a.len() - suffix];
+    let b_mid = &b[prefix..b.len() - suffix];
+
+    let mut ops: Vec<(DiffOp, &str)> = a[..prefix].iter().map(|t| (DiffOp::Equal, *t)).collect();
+    if a_mid.len().saturating_mul(b_mid.len()) > MAX_DIFF_CELLS {
+        ops.extend(a_mid.iter().map(|t| (DiffOp::Delete, *t)));
+        ops.extend(b_mid.iter().map(|t| (DiffOp::Insert, *t)));
+    } else {
+        ops.extend(lcs_ops(a_mid, b_mid));
+    }
+    ops.extend(a[a.len() - suffix..].iter().map(|t| (DiffOp::Equal, *t)));
+
+    let mut segments: Vec<DiffSegment> = Vec::new();
+    for (op, text) in ops {
+        match segments.last_mut() {
+            Some(last) if last.op == op => last.text.push_str(text),
+            _ => segments.push(DiffSegment {
+                op,
+                text: text.to_string(),
+            }),
+        }
+    }
+    segments
+}
+
+/// Split text into alternating runs of whitespace and non-whitespace
+fn tokenize(text: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    let mut previous: Option<bool> = None;
+
+    for (i, c) in text.char_indices() {
+        let is_space = c.is_whitespace();
+        if previous.is_some_and(|p| p != is_space) {
+            tokens.push(&text[start..i]);
+            start = i;
+        }
+        previous = Some(is_space);
+    }
+    if start < text.len() {
+        tokens.push(&text[start..]);
+    }
+    tokens
+}
+
+fn lcs_ops<'a>(a: &[&'a str], b: &[&'a str]) -> Vec<(DiffOp, &'a str)> {
+    let (n, m) = (a.len(), b.len());
+    let width = m + 1;
+
+    // lengths[i * width + j] is the LCS length of a[i..] and b[j..]
+    let mut lengths = vec![0u32; (n + 1) * width];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lengths[i * width + j] = if a[i] == b[j] {
+                lengths[(i + 1) * width + j + 1] + 1
+            } else {
+                lengths[(i + 1) * width + j].max(lengths[i * width + j + 1])
+            };
+        }
+    }
+
+    let mut ops = Vec::with_capacity(n + m);
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if a[i] == b[j] {
+            ops.push((DiffOp::Equal, a[i]));
+            i += 1;
+            j += 1;
+        } else if lengths[(i + 1) * width + j] >= lengths[i * width + j + 1] {
+            ops.push((DiffOp::Delete, a[i]));
+            i += 1;
+        } else {
+            ops.push((DiffOp::Insert, b[j]));
+            j += 1;
+        }
+    }
+    ops.extend(a[i..].iter().map(|t| (DiffOp::Delete, *t)));
+    ops.extend(b[j..].iter().map(|t| (DiffOp::Insert, *t)));
+    ops
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rebuild(segments: &[DiffSegment], skip: DiffOp) -> String {
+        segments
+            .iter()
+            .filter(|s| s.op != skip)
+            .map(|s| s.text.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_word_diff_marks_changed_words() {
+        let diff = word_diff("The cache is cold today", "The cache is warm today");
+        assert_eq!(
+            diff,
+            vec![
+                DiffSegment {
+                    op: DiffOp::Equal,
+                    text: "The cache is ".to_string()
+                },
+                DiffSegment {
+                    op: DiffOp::Delete,
+                    text: "cold".to_string()
+                },
+                DiffSegment {
+                    op: DiffOp::Insert,
+                    text: "warm".to_string()
+                },
+                DiffSegment {
+                    op: DiffOp::Equal,
+                    text: " today".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_word_diff_rebuilds_both_sides() {
+        let left = "First line.\n\nSecond  line with   spacing — and ünïcode";
+        let right = "First line!\nSecond line with spacing — and more ünïcode\n";
+        let diff = word_diff(left, right);
+
+        assert_eq!(rebuild(&diff, DiffOp::Insert), left);
+        assert_eq!(rebuild(&diff, DiffOp::Delete), right);
+    }
+
+    #[test]
+    fn test_word_diff_identical_and_empty() {
+        assert!(word_diff("", "").is_empty());
+        assert_eq!(
+            word_diff("same text", "same text"),
+            vec![DiffSegment {
+                op: DiffOp::Equal,
+                text: "same text".to_string()
+            }]
+        );
+        assert_eq!(
+            word_diff("", "added"),
+            vec![DiffSegment {
+                op: DiffOp::Insert,
+                text: "added".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_answer_stats_reads_usage_metadata() {
+        let message = Message {
+            id: Uuid::new_v4(),
+            conversation_id: Uuid::new_v4(),
+            parent_id: None,
+            role: MessageRole::Assistant,
+            content: "Three short words".to_string(),
+            tokens_used: Some(30),
+            created_at: chrono::Utc::now(),
+            is_active: true,
+            metadata: serde_json::json!({
+                "model": "gpt-4",
+                "latency_ms": 850,
+                "usage": {
+                    "prompt_tokens": 10,
+                    "completion_tokens": 20,
+                    "total_tokens": 30
+                }
+            }),
+            author_id: None,
+            edited_from: None,
+            deleted_at: None,
+        };
+
+        let stats = answer_stats(&message);
+        assert_eq!(stats.model.as_deref(), Some("gpt-4"));
+        assert_eq!(stats.prompt_tokens, Some(10));
+        assert_eq!(stats.completion_tokens, Some(20));
+        assert_eq!(stats.total_tokens, Some(30));
+        assert_eq!(stats.latency_ms, Some(850));
+        assert_eq!(stats.word_count, 3);
+        assert!(stats.cost_cents.is_some());
+    }
+}
@@ -1,46 +1,144 @@
 use crate::{
-    models::{Conversation, ConversationWithMessages, CreateConversationRequest, PaginationParams},
-    repositories::Repository,
-    services::DataAccessLayer,
+    error::AppError,
+    models::{
+        Conversation, ConversationCursor, ConversationListParams, ConversationPage,
+        ConversationRole, ConversationStats, ConversationWithMessages, CreateConversationRequest,
+        CreateMessageRequest, ForkConversationRequest, Message, MessageRole, RetrievalSettings,
+    },
+    repositories::{conversation::provider_for_model, Repository},
+    services::{authorization::AuthorizationService, file::remove_unlinked_files, DataAccessLayer},
 };
 use anyhow::Result;
+use chrono::{Duration, Utc};
+use std::collections::HashMap;
 use uuid::Uuid;
+use validator::Validate;
 
 #[derive(Debug, Clone)]
 pub struct ConversationService {
     dal: DataAccessLayer,
+    authorization: AuthorizationService,
 }
 
 impl ConversationService {
     pub fn new(dal: DataAccessLayer) -> Self {
-        Self { dal }
+        Self {
+            authorization: AuthorizationService::new(dal.clone()),
+            dal,
+        }
     }
 
     pub async fn create_conversation(
         &self,
         user_id: Uuid,
-        request: CreateConversationRequest,
+        mut request: CreateConversationRequest,
     ) -> Result<Conversation> {
+        // Conversations created inside a project inherit its defaults; other users'
+        // projects are reported as missing
+        let project = match request.project_id {
+            Some(project_id) => Some(
+                self.dal
+                    .projects()
+                    .find_by_id(project_id)
+                    .await?
+                    .filter(|project| project.user_id == user_id)
+                    .ok_or_else(|| AppError::NotFound("Project not found".to_string()))?,
+            ),
+            None => None,
+        };
+
+        if request.model.trim().is_empty() {
+            match project.as_ref().and_then(|p| p.default_model.clone()) {
+                Some(model) => request.model = model,
+                None if project.is_some() => {
+                    return Err(AppError::BadRequest(
+                        "A model is required when the project has no default model".to_string(),
+                    )
+                    .into())
+                }
+                None => return Err(AppError::BadRequest("A model is required".to_string()).into()),
+            }
+        }
+
         // Validate that the model is supported
         if !self.is_model_supported(&request.model) {
-            return Err(anyhow::anyhow!("Unsupported model: {}", request.model));
+            return Err(
+                AppError::BadRequest(format!("Unsupported model: {}", request.model)).into(),
+            );
         }
 
-        self.dal
-            .conversations()
-            .create_from_request(user_id, request)
-            .await
+        // The conversation, its project link and the project's system prompt are
+        // written together so a failed step leaves no orphaned conversation
+        let conversation = self
+            .dal
+            .transaction(|dal| async move {
+                let conversation = dal
+                    .conversations()
+                    .create_from_request(user_id, request)
+                    .await?;
+
+                if let Some(project) = project {
+                    dal.projects()
+                        .add_conversation(project.id, conversation.id)
+                        .await?;
+
+                    if let Some(system_prompt) =
+                        project.system_prompt.filter(|p| !p.trim().is_empty())
+                    {
+                        dal.messages()
+                            .create_from_request(CreateMessageRequest {
+                                conversation_id: conversation.id,
+                                parent_id: None,
+                                role: MessageRole::System,
+                                content: system_prompt,
+                                metadata: Some(serde_json::json!({ "project_id": project.id })),
+                                author_id: None,
+                            })
+                            .await?;
+                    }
+                }
+
+                Ok(conversation)
+            })
+            .await?;
+
+        Ok(conversation)
     }
 
     pub async fn get_user_conversations(
         &self,
         user_id: Uuid,
-        pagination: PaginationParams,
-    ) -> Result<Vec<Conversation>> {
-        self.dal
+        params: &ConversationListParams,
+        cursor: Option<&ConversationCursor>,
+    ) -> Result<ConversationPage> {
+        let limit = params.limit.unwrap_or(20).clamp(1, 100) as i64;
+
+        // Fetch one extra row to learn whether another page follows
+        let mut conversations = self
+            .dal
             .conversations()
-            .find_by_user_id(user_id, pagination)
-            .await
+            .find_by_user_id(user_id, params, cursor, limit + 1)
+            .await?;
+        let next_cursor = if conversations.len() as i64 > limit {
+            conversations.truncate(limit as usize);
+            conversations
+                .last()
+                .map(|c| ConversationCursor::from_conversation(c).encode())
+        } else {
+            None
+        };
+
+        let total = self
+            .dal
+            .conversations()
+            .count_by_filter(user_id, params)
+            .await?;
+
+        Ok(ConversationPage {
+            conversations,
+            next_cursor,
+            total,
+        })
     }
 
     pub async fn get_conversation_with_messages(
@@ -48,9 +146,21 @@ impl ConversationService {
         conversation_id: Uuid,
         user_id: Uuid,
     ) -> Result<Option<ConversationWithMessages>> {
+        let Some(conversation) = self.dal.conversations().find_by_id(conversation_id).await? else {
+            return Ok(None);
+        };
+        if self
+            .authorization
+            .role(&conversation, user_id)
+            .await?
+            .is_none()
+        {
+            return Ok(None);
+        }
+
         self.dal
             .conversations()
-            .find_with_messages(conversation_id, user_id)
+            .find_with_messages(conversation_id)
             .await
     }
 
@@ -67,29 +177,214 @@ impl ConversationService {
             ));
         }
 
+        self.authorization
+            .require(conversation_id, user_id, ConversationRole::Editor)
+            .await?;
+
+        self.dal
+            .conversations()
+            .update_title(conversation_id, title.trim().to_string())
+            .await
+    }
+
+    /// Move a conversation to the trash. It can be restored until the purge job removes it.
+    pub async fn trash_conversation(&self, conversation_id: Uuid, user_id: Uuid) -> Result<bool> {
         self.dal
             .conversations()
-            .update_title(conversation_id, user_id, title.trim().to_string())
+            .move_to_trash(conversation_id, user_id)
             .await
     }
 
+    pub async fn restore_conversation(&self, conversation_id: Uuid, user_id: Uuid) -> Result<bool> {
+        self.dal
+            .conversations()
+            .restore_from_trash(conversation_id, user_id)
+            .await
+    }
+
+    /// Hard delete a conversation and, through the cascade, all of its messages. Files
+    /// of its attachments are removed from storage unless a fork still links to them.
     pub async fn delete_conversation(&self, conversation_id: Uuid, user_id: Uuid) -> Result<bool> {
-        // First verify the conversation belongs to the user
-        let conversation = self
+        let storage_paths = self
             .dal
+            .transaction(|dal| async move {
+                // Only the owner may delete; anyone else sees nothing to delete
+                let Some(conversation) = dal.conversations().find_by_id(conversation_id).await?
+                else {
+                    return Ok(None);
+                };
+                if self.authorization.role(&conversation, user_id).await?
+                    != Some(ConversationRole::Owner)
+                {
+                    return Ok(None);
+                }
+
+                let storage_paths: Vec<String> = dal
+                    .attachments()
+                    .find_by_conversation_id(conversation_id)
+                    .await?
+                    .into_iter()
+                    .map(|attachment| attachment.storage_path)
+                    .collect();
+                if !dal.conversations().delete(conversation_id).await? {
+                    return Ok(None);
+                }
+                Ok(Some(storage_paths))
+            })
+            .await?;
+
+        let Some(storage_paths) = storage_paths else {
+            return Ok(false);
+        };
+        remove_unlinked_files(&self.dal, &storage_paths).await?;
+        Ok(true)
+    }
+
+    pub async fn set_pinned(
+        &self,
+        conversation_id: Uuid,
+        user_id: Uuid,
+        pinned: bool,
+    ) -> Result<bool> {
+        self.dal
             .conversations()
-            .find_with_messages(conversation_id, user_id)
+            .set_pinned(conversation_id, user_id, pinned)
+            .await
+    }
+
+    pub async fn set_archived(
+        &self,
+        conversation_id: Uuid,
+        user_id: Uuid,
+        archived: bool,
+    ) -> Result<bool> {
+        self.dal
+            .conversations()
+            .set_archived(conversation_id, user_id, archived)
+            .await
+    }
+
+    /// Copy the thread from the root down to `message_id` into a new conversation owned by the user.
+    /// The fork records where it came from in its metadata; attachments and embeddings come along.
+    pub async fn fork_from_message(
+        &self,
+        user_id: Uuid,
+        message_id: Uuid,
+        request: ForkConversationRequest,
+    ) -> Result<Conversation> {
+        let (_, source) = self
+            .authorization
+            .require_for_message(message_id, user_id, ConversationRole::Viewer)
             .await?;
 
-        if conversation.is_some() {
-            self.dal
-                .repositories
-                .conversations
-                .delete(conversation_id)
-                .await
-        } else {
-            Ok(false)
+        let model = match request.model.as_deref().map(str::trim) {
+            Some(model) if !model.is_empty() => model.to_string(),
+            _ => source.model.clone(),
+        };
+        if !self.is_model_supported(&model) {
+            return Err(AppError::BadRequest(format!("Unsupported model: {}", model)).into());
         }
+
+        let provider = match request.provider.as_deref().map(str::trim) {
+            Some(provider) if !provider.is_empty() => normalize_provider(provider)
+                .ok_or_else(|| AppError::BadRequest(format!("Invalid provider: {}", provider)))?
+                .to_string(),
+            _ if model == source.model => source.provider.clone(),
+            _ => provider_for_model(&model).to_string(),
+        };
+
+        let title = match request.title {
+            Some(title) => Some(title.trim().to_string()),
+            None => source
+                .title
+                .as_ref()
+                .map(|title| format!("{} (fork)", title))
+                .filter(|title| title.chars().count() <= 255)
+                .or_else(|| source.title.clone()),
+        };
+
+        let thread = self
+            .dal
+            .messages()
+            .find_conversation_thread(message_id)
+            .await?;
+
+        // Everything is written in one transaction so a failed copy leaves no half-made fork behind
+        self.dal
+            .transaction(|dal| async move {
+                let now = Utc::now();
+                let conversation = dal
+                    .conversations()
+                    .create(Conversation {
+                        id: Uuid::new_v4(),
+                        user_id,
+                        title,
+                        model,
+                        provider,
+                        created_at: now,
+                        updated_at: now,
+                        metadata: serde_json::json!({
+                            "fork": {
+                                "conversation_id": source.id,
+                                "message_id": message_id,
+                                "forked_at": now,
+                            }
+                        }),
+                        pinned_at: None,
+                        archived_at: None,
+                        deleted_at: None,
+                        active_leaf_id: None,
+                    })
+                    .await?;
+
+                // The copied thread is a single path, so every message in it is active and
+                // edit links to versions left behind are dropped
+                let message_map: Vec<(Uuid, Uuid)> =
+                    thread.iter().map(|m| (m.id, Uuid::new_v4())).collect();
+                let new_ids: HashMap<Uuid, Uuid> = message_map.iter().copied().collect();
+                let messages: Vec<Message> = thread
+                    .into_iter()
+                    .map(|m| Message {
+                        id: new_ids[&m.id],
+                        conversation_id: conversation.id,
+                        parent_id: m.parent_id.and_then(|p| new_ids.get(&p).copied()),
+                        is_active: true,
+                        edited_from: None,
+                        ..m
+                    })
+                    .collect();
+
+                dal.messages().create_many(&messages).await?;
+                dal.attachments().copy_to_messages(&message_map).await?;
+                dal.embeddings().copy_embeddings(&message_map).await?;
+
+                // Read back so active_leaf_id reflects the copied thread
+                dal.conversations()
+                    .find_by_id(conversation.id)
+                    .await?
+                    .ok_or_else(|| anyhow::anyhow!("Forked conversation not found"))
+            })
+            .await
+    }
+
+    /// Permanently remove conversations that have been in the trash longer than the
+    /// retention period, with the stored files of their attachments
+    pub async fn purge_trash(&self, retention_days: u32) -> Result<u64> {
+        let cutoff = Utc::now() - Duration::days(retention_days as i64);
+        let (purged, storage_paths) = self
+            .dal
+            .transaction(|dal| async move {
+                let storage_paths = dal
+                    .attachments()
+                    .find_storage_paths_trashed_before(cutoff)
+                    .await?;
+                let purged = dal.conversations().purge_trashed_before(cutoff).await?;
+                Ok((purged, storage_paths))
+            })
+            .await?;
+
+        remove_unlinked_files(&self.dal, &storage_paths).await?;
+        Ok(purged)
     }
 
     pub async fn get_conversation_stats(
@@ -97,30 +392,62 @@ impl ConversationService {
         conversation_id: Uuid,
         user_id: Uuid,
     ) -> Result<ConversationStats> {
-        // Verify conversation belongs to user
-        let conversation = self.dal.conversations().find_by_id(conversation_id).await?;
-
-        match conversation {
-            Some(conv) if conv.user_id == user_id => {
-                let message_count = self
-                    .dal
-                    .messages()
-                    .count_by_conversation(conversation_id)
-                    .await?;
+        self.authorization
+            .require(conversation_id, user_id, ConversationRole::Viewer)
+            .await?;
 
-                Ok(ConversationStats {
-                    conversation_id,
-                    message_count,
-                    created_at: conv.created_at,
-                    updated_at: conv.updated_at,
-                })
-            }
-            Some(_) => Err(anyhow::anyhow!("Conversation not found or access denied")),
-            None => Err(anyhow::anyhow!("Conversation not found")),
+        self.dal
+            .conversations()
+            .find_stats(conversation_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Conversation not found".to_string()).into())
+    }
+
+    pub async fn get_retrieval_settings(
+        &self,
+        conversation_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<RetrievalSettings> {
+        let conversation = self
+            .authorization
+            .require(conversation_id, user_id, ConversationRole::Viewer)
+            .await?;
+
+        Ok(RetrievalSettings::from_metadata(&conversation.metadata))
+    }
+
+    /// Retrieval changes what the model sees, so it takes an editor
+    pub async fn update_retrieval_settings(
+        &self,
+        conversation_id: Uuid,
+        user_id: Uuid,
+        settings: RetrievalSettings,
+    ) -> Result<RetrievalSettings> {
+        settings
+            .validate()
+            .map_err(|e| AppError::BadRequest(format!("Validation error: {e}")))?;
+
+        self.authorization
+            .require(conversation_id, user_id, ConversationRole::Editor)
+            .await?;
+
+        let updated = self
+            .dal
+            .conversations()
+            .set_metadata_key(
+                conversation_id,
+                "retrieval",
+                &serde_json::to_value(&settings)?,
+            )
+            .await?;
+        if !updated {
+            return Err(AppError::NotFound("Conversation not found".to_string()).into());
         }
+
+        Ok(settings)
     }
 
-    fn is_model_supported(&self, model: &str) -> bool {
+    pub fn is_model_supported(&self, model: &str) -> bool {
         // List of supported models - this could be moved to configuration
         matches!(
             model,
@@ -168,10 +495,12 @@ impl ConversationService {
     }
 }
 
-#[derive(Debug, serde::Serialize)]
-pub struct ConversationStats {
-    pub conversation_id: Uuid,
-    pub message_count: i64,
-    pub created_at: chrono::DateTime<chrono::Utc>,
-    pub updated_at: chrono::DateTime<chrono::Utc>,
+/// Map the provider spellings the clients send to the stored name
+fn normalize_provider(provider: &str) -> Option<&'static str> {
+    match provider {
+        "open_a_i" | "openai" | "OpenAI" => Some("openai"),
+        "anthropic" | "Anthropic" => Some("anthropic"),
+        "claude_code" | "ClaudeCode" => Some("claude_code"),
+        _ => None,
+    }
 }
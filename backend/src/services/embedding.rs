@@ -1,80 +1,92 @@
-use async_openai::{
-    types::{CreateEmbeddingRequest, EmbeddingInput},
-    Client as OpenAIClient,
-};
-use std::time::Duration;
+use std::{collections::HashMap, sync::Arc};
 use uuid::Uuid;
 
 use crate::{
     config::AppConfig,
+    embeddings::{create_embedding_provider, EmbeddingProvider},
     error::AppError,
-    repositories::embedding::{EmbeddingRepository, SearchResult},
+    models::{
+        preview_text, ConversationFacet, FacetCount, SearchFacets, SearchFilters, SearchMode,
+    },
+    repositories::{
+        embedding::{ChunkEmbedding, RelatedConversation, SearchResult, SimilarQuestion},
+        embedding_job::{ClaimedEmbeddingJob, EmbeddingQueueStatus, MAX_EMBEDDING_ATTEMPTS},
+    },
+    services::DataAccessLayer,
 };
 
+/// Dampens the weight of top ranks in reciprocal rank fusion; 60 is the usual choice
+const RRF_K: f32 = 60.0;
+
+/// How many candidates each ranking contributes per requested result
+const FUSION_CANDIDATES_PER_RESULT: i64 = 3;
+
+/// Messages claimed from the queue per batch
+const EMBEDDING_BATCH_SIZE: i64 = 50;
+
+/// Chunks sent per provider request
+const CHUNKS_PER_REQUEST: usize = 64;
+
+/// Longest chunk in characters, roughly 500 tokens; well below every model's limit
+const CHUNK_CHARS: usize = 2000;
+
+/// Characters shared by consecutive chunks, so text at a boundary keeps its context
+const CHUNK_OVERLAP_CHARS: usize = 200;
+
+/// Earlier questions suggested for a new prompt, and how close they must be
+const SUGGESTION_LIMIT: i64 = 3;
+const SUGGESTION_SIMILARITY: f32 = 0.9;
+
+/// Jobs a worker has held this long are assumed abandoned and claimed again
+const STALE_JOB_SECS: i64 = 600;
+
+/// Results of a message search with the mode that produced them
+#[derive(Debug, Clone)]
+pub struct MessageSearch {
+    pub results: Vec<SearchResult>,
+    pub mode: SearchMode,
+    pub facets: SearchFacets,
+}
+
 #[derive(Debug, Clone)]
 pub struct EmbeddingService {
-    client: OpenAIClient<async_openai::config::OpenAIConfig>,
-    repository: EmbeddingRepository,
-    config: AppConfig,
+    provider: Arc<dyn EmbeddingProvider>,
+    dal: DataAccessLayer,
 }
 
 impl EmbeddingService {
-    pub fn new(config: AppConfig, repository: EmbeddingRepository) -> Result<Self, AppError> {
-        let openai_config =
-            async_openai::config::OpenAIConfig::new().with_api_key(&config.openai_api_key);
+    pub fn new(config: AppConfig, dal: DataAccessLayer) -> Result<Self, AppError> {
+        let provider = create_embedding_provider(&config.embedding, &config.openai_api_key)?;
 
-        let client = OpenAIClient::with_config(openai_config);
+        Ok(Self { provider, dal })
+    }
 
-        Ok(Self {
-            client,
-            repository,
-            config,
-        })
+    /// The provider that embeds messages and queries
+    pub fn provider(&self) -> &dyn EmbeddingProvider {
+        self.provider.as_ref()
+    }
+
+    fn ensure_configured(&self) -> Result<(), AppError> {
+        if self.provider.is_configured() {
+            Ok(())
+        } else {
+            Err(AppError::Embedding(
+                "Embedding provider is not configured".to_string(),
+            ))
+        }
     }
 
-    /// Generate embeddings for text using OpenAI's text-embedding-3-small model
+    /// Generate the embedding of one text with the configured provider
     pub async fn generate_embedding(&self, text: &str) -> Result<Vec<f32>, AppError> {
         tracing::info!("Generating embedding for text of length: {}", text.len());
 
-        let request = CreateEmbeddingRequest {
-            model: "text-embedding-3-small".to_string(),
-            input: EmbeddingInput::String(text.to_string()),
-            encoding_format: None,
-            dimensions: Some(1536),
-            user: None,
-        };
-
-        let response = self
-            .client
-            .embeddings()
-            .create(request)
-            .await
-            .map_err(|e| AppError::OpenAI(format!("Failed to generate embedding: {}", e)))?;
-
-        let embedding = response
-            .data
+        self.ensure_configured()?;
+        self.provider
+            .embed(vec![text.to_string()])
+            .await?
             .into_iter()
             .next()
-            .ok_or_else(|| AppError::OpenAI("No embedding returned from OpenAI".to_string()))?
-            .embedding;
-
-        Ok(embedding)
-    }
-
-    /// Store embeddings for a message
-    pub async fn store_message_embedding(
-        &self,
-        message_id: Uuid,
-        content: &str,
-    ) -> Result<(), AppError> {
-        let embedding = self.generate_embedding(content).await?;
-
-        self.repository
-            .upsert_embedding(message_id, embedding)
-            .await?;
-
-        tracing::info!("Stored embedding for message {}", message_id);
-        Ok(())
+            .ok_or_else(|| AppError::Embedding("No embedding returned".to_string()))
     }
 
     /// Find messages similar to the given query text
@@ -82,6 +94,7 @@ impl EmbeddingService {
         &self,
         query: &str,
         user_id: Option<Uuid>,
+        filters: &SearchFilters,
         limit: Option<i64>,
         similarity_threshold: Option<f32>,
     ) -> Result<Vec<SearchResult>, AppError> {
@@ -91,116 +104,360 @@ impl EmbeddingService {
         );
 
         let query_embedding = self.generate_embedding(query).await?;
+        self.search_similar_to_embedding(
+            query_embedding,
+            user_id,
+            filters,
+            limit,
+            similarity_threshold,
+        )
+        .await
+    }
+
+    /// Find messages similar to an embedding of the current model
+    pub async fn search_similar_to_embedding(
+        &self,
+        query_embedding: Vec<f32>,
+        user_id: Option<Uuid>,
+        filters: &SearchFilters,
+        limit: Option<i64>,
+        similarity_threshold: Option<f32>,
+    ) -> Result<Vec<SearchResult>, AppError> {
         let limit = limit.unwrap_or(10);
 
         let results = self
-            .repository
-            .search_similar_messages(query_embedding, user_id, limit, similarity_threshold)
+            .dal
+            .embeddings()
+            .search_similar_messages(
+                query_embedding,
+                self.provider.model(),
+                user_id,
+                filters,
+                limit,
+                similarity_threshold,
+            )
             .await?;
 
         tracing::info!("Found {} similar messages", results.len());
         Ok(results)
     }
 
-    /// Process messages without embeddings in batches
-    pub async fn process_pending_embeddings(&self, batch_size: i64) -> Result<usize, AppError> {
-        tracing::info!(
-            "Processing pending embeddings with batch size: {}",
-            batch_size
-        );
-
-        let message_ids = self
-            .repository
-            .find_messages_without_embeddings(batch_size)
+    /// Earlier questions from the user's other conversations that are close to a new
+    /// prompt, with a preview of their answers
+    pub async fn find_similar_questions(
+        &self,
+        prompt_embedding: Vec<f32>,
+        user_id: Uuid,
+        conversation_id: Uuid,
+    ) -> Result<Vec<SimilarQuestion>, AppError> {
+        let mut questions = self
+            .dal
+            .embeddings()
+            .find_similar_questions(
+                prompt_embedding,
+                self.provider.model(),
+                user_id,
+                conversation_id,
+                SUGGESTION_LIMIT,
+                SUGGESTION_SIMILARITY,
+            )
             .await?;
-
-        if message_ids.is_empty() {
-            tracing::info!("No messages found without embeddings");
-            return Ok(0);
+        for question in &mut questions {
+            question.answer = question.answer.as_deref().map(preview_text);
         }
 
-        let mut processed_count = 0;
+        Ok(questions)
+    }
 
-        for message_id in &message_ids {
-            match self.generate_embedding_for_message(*message_id).await {
-                Ok(_) => {
-                    processed_count += 1;
-                    tracing::debug!("Generated embedding for message {}", message_id);
-                }
-                Err(e) => {
-                    tracing::error!(
-                        "Failed to generate embedding for message {}: {}",
-                        message_id,
-                        e
-                    );
-                    // Continue processing other messages even if one fails
+    /// Other conversations ranked by how close their centroid is to this one's. Only
+    /// embeddings of the current model count; a conversation without any has no
+    /// related conversations.
+    pub async fn find_related_conversations(
+        &self,
+        conversation_id: Uuid,
+        user_id: Uuid,
+        limit: Option<i64>,
+    ) -> Result<Vec<RelatedConversation>, AppError> {
+        self.dal
+            .embeddings()
+            .find_related_conversations(
+                conversation_id,
+                user_id,
+                self.provider.model(),
+                self.provider.dimensions(),
+                limit.unwrap_or(10).clamp(1, 50),
+            )
+            .await
+    }
+
+    /// Search messages lexically, semantically or both. Hybrid search merges the two
+    /// rankings with reciprocal rank fusion and falls back to lexical results when no
+    /// query embedding can be generated. Facets are counted over every candidate
+    /// ranked; the returned results get highlighted snippets.
+    pub async fn search_messages(
+        &self,
+        query: &str,
+        user_id: Option<Uuid>,
+        mode: SearchMode,
+        filters: &SearchFilters,
+        limit: Option<i64>,
+        similarity_threshold: Option<f32>,
+    ) -> Result<MessageSearch, AppError> {
+        if let (Some(after), Some(before)) = (filters.created_after, filters.created_before) {
+            if after >= before {
+                return Err(AppError::BadRequest(
+                    "created_after must be before created_before".to_string(),
+                ));
+            }
+        }
+
+        let limit = limit.unwrap_or(10);
+        let candidates = limit * FUSION_CANDIDATES_PER_RESULT;
+
+        let (ranked, mode) = match mode {
+            SearchMode::Semantic => {
+                let results = self
+                    .search_similar_messages(
+                        query,
+                        user_id,
+                        filters,
+                        Some(candidates),
+                        similarity_threshold,
+                    )
+                    .await?;
+                (results, SearchMode::Semantic)
+            }
+            SearchMode::Lexical => {
+                let results = self
+                    .dal
+                    .embeddings()
+                    .search_text_messages(query, user_id, filters, candidates)
+                    .await?;
+                (results, SearchMode::Lexical)
+            }
+            SearchMode::Hybrid => {
+                let lexical = self
+                    .dal
+                    .embeddings()
+                    .search_text_messages(query, user_id, filters, candidates)
+                    .await?;
+
+                let embedding = if !self.provider.is_configured() {
+                    None
+                } else {
+                    match self.generate_embedding(query).await {
+                        Ok(embedding) => Some(embedding),
+                        Err(e) => {
+                            tracing::warn!("Falling back to lexical search: {}", e);
+                            None
+                        }
+                    }
+                };
+
+                match embedding {
+                    Some(embedding) => {
+                        let semantic = self
+                            .dal
+                            .embeddings()
+                            .search_similar_messages(
+                                embedding,
+                                self.provider.model(),
+                                user_id,
+                                filters,
+                                candidates,
+                                similarity_threshold,
+                            )
+                            .await?;
+                        (
+                            reciprocal_rank_fusion(vec![semantic, lexical], 2 * candidates),
+                            SearchMode::Hybrid,
+                        )
+                    }
+                    None => (lexical, SearchMode::Lexical),
                 }
             }
+        };
 
-            // Add small delay to avoid rate limiting
-            tokio::time::sleep(Duration::from_millis(100)).await;
+        let facets = search_facets(&ranked);
+        let mut results = truncate(ranked, limit);
+
+        let texts: Vec<String> = results
+            .iter()
+            .map(|result| match &result.chunk {
+                Some(chunk) => chunk.content.clone(),
+                None => result.content.clone(),
+            })
+            .collect();
+        let snippets = self.dal.embeddings().highlight(query, &texts).await?;
+        for (result, snippet) in results.iter_mut().zip(snippets) {
+            result.snippet = Some(snippet);
         }
 
-        tracing::info!(
-            "Processed {} out of {} pending embeddings",
-            processed_count,
-            message_ids.len()
-        );
-        Ok(processed_count)
+        Ok(MessageSearch {
+            results,
+            mode,
+            facets,
+        })
     }
 
-    /// Generate embedding for a specific message by fetching its content
-    async fn generate_embedding_for_message(&self, _message_id: Uuid) -> Result<(), AppError> {
-        // This would require access to the message repository to fetch content
-        // For now, we'll implement this as a placeholder that could be called
-        // from a handler that has access to the message content
+    /// Generate embeddings for several texts with one provider request, in input order
+    pub async fn generate_embeddings(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>, AppError> {
+        tracing::info!("Generating embeddings for {} texts", texts.len());
+
+        self.ensure_configured()?;
+        self.provider.embed(texts).await
+    }
+
+    /// Split each text into chunks and embed them, a few requests' worth at a time.
+    /// Returns the chunks of every text in input order.
+    pub(crate) async fn embed_chunks(
+        &self,
+        texts: &[&str],
+    ) -> Result<Vec<Vec<ChunkEmbedding>>, AppError> {
+        let chunked: Vec<Vec<TextChunk>> = texts.iter().map(|text| chunk_text(text)).collect();
+        let inputs: Vec<String> = chunked
+            .iter()
+            .flatten()
+            .map(|chunk| chunk.content.clone())
+            .collect();
+
+        let mut embeddings = Vec::with_capacity(inputs.len());
+        for batch in inputs.chunks(CHUNKS_PER_REQUEST) {
+            embeddings.extend(self.generate_embeddings(batch.to_vec()).await?);
+        }
 
-        // In practice, this would be called from a background job or webhook
-        // where the message content is already available
-        tracing::warn!("generate_embedding_for_message called with just message_id - content needed from caller");
-        Err(AppError::BadRequest(
-            "Message content required for embedding generation".to_string(),
-        ))
+        let mut embeddings = embeddings.into_iter();
+        Ok(chunked
+            .into_iter()
+            .map(|chunks| {
+                chunks
+                    .into_iter()
+                    .zip(embeddings.by_ref())
+                    .enumerate()
+                    .map(|(index, (chunk, embedding))| ChunkEmbedding {
+                        chunk_index: index as i32,
+                        start_offset: chunk.start as i32,
+                        end_offset: chunk.end as i32,
+                        content: chunk.content,
+                        embedding,
+                    })
+                    .collect()
+            })
+            .collect())
     }
 
-    /// Delete embedding for a message
-    pub async fn delete_message_embedding(&self, message_id: Uuid) -> Result<bool, AppError> {
-        let deleted = self.repository.delete_by_message_id(message_id).await?;
+    /// Embed one batch of queued messages. Returns how many jobs were finished; a
+    /// failed provider request is recorded on the jobs for a later retry and returned.
+    pub async fn process_pending_embeddings(&self, batch_size: i64) -> Result<usize, AppError> {
+        self.ensure_configured()?;
+
+        let jobs = self
+            .dal
+            .embedding_jobs()
+            .claim(batch_size, STALE_JOB_SECS, self.provider.model())
+            .await?;
+        if jobs.is_empty() {
+            return Ok(0);
+        }
 
-        if deleted {
-            tracing::info!("Deleted embedding for message {}", message_id);
+        // Deleted, blank and already embedded messages need no API call
+        let (to_embed, skipped): (Vec<ClaimedEmbeddingJob>, Vec<ClaimedEmbeddingJob>) = jobs
+            .into_iter()
+            .partition(|job| !job.deleted && !job.embedded && !job.content.trim().is_empty());
+        if !skipped.is_empty() {
+            let ids: Vec<Uuid> = skipped.iter().map(|job| job.id).collect();
+            self.dal.embedding_jobs().complete(&ids).await?;
         }
+        if to_embed.is_empty() {
+            return Ok(skipped.len());
+        }
+
+        let to_embed_count = to_embed.len();
+        let ids: Vec<Uuid> = to_embed.iter().map(|job| job.id).collect();
+        let texts: Vec<&str> = to_embed.iter().map(|job| job.content.as_str()).collect();
+
+        let embeddings = match self.embed_chunks(&texts).await {
+            Ok(embeddings) => embeddings,
+            Err(e) => {
+                tracing::error!("Failed to embed {} messages: {}", ids.len(), e);
+                for job in to_embed
+                    .iter()
+                    .filter(|job| job.attempts >= MAX_EMBEDDING_ATTEMPTS)
+                {
+                    tracing::warn!(
+                        "Giving up on embedding message {} after {} attempts",
+                        job.message_id,
+                        job.attempts
+                    );
+                }
+                self.dal.embedding_jobs().fail(&ids, &e.to_string()).await?;
+                return Err(e);
+            }
+        };
 
-        Ok(deleted)
+        let model = self.provider.model().to_string();
+        self.dal
+            .transaction(|dal| async move {
+                for (job, chunks) in to_embed.iter().zip(embeddings) {
+                    store_chunks(&dal, job.message_id, &model, &chunks).await?;
+                }
+                dal.embedding_jobs().complete(&ids).await?;
+                Ok(())
+            })
+            .await?;
+
+        let processed = skipped.len() + to_embed_count;
+        tracing::info!("Embedded {} queued messages", processed);
+        Ok(processed)
     }
 
-    /// Check if a message has an embedding
-    pub async fn has_embedding(&self, message_id: Uuid) -> Result<bool, AppError> {
-        let embedding = self.repository.find_by_message_id(message_id).await?;
+    /// Prepare the vector index for the current model and queue every message that
+    /// has no embedding from it. Returns how many messages were queued; the worker
+    /// replaces their old embeddings as it works through the queue.
+    pub async fn reindex(&self) -> Result<u64, AppError> {
+        self.ensure_configured()?;
+
+        self.dal
+            .embeddings()
+            .ensure_vector_index(self.provider.dimensions())
+            .await?;
+        let queued = self
+            .dal
+            .embedding_jobs()
+            .enqueue_missing(self.provider.model())
+            .await?;
 
-        Ok(embedding.is_some())
+        tracing::info!(
+            "Queued {} messages for re-embedding with {} ({} dimensions)",
+            queued,
+            self.provider.model(),
+            self.provider.dimensions()
+        );
+        Ok(queued)
+    }
+
+    /// Job counts per status with the most recent failures, for the messages of the
+    /// user's own conversations
+    pub async fn queue_status(&self, user_id: Uuid) -> Result<EmbeddingQueueStatus, AppError> {
+        self.dal.embedding_jobs().status(Some(user_id), 20).await
     }
 }
 
 // Background job functions that can be called by external schedulers
 impl EmbeddingService {
-    /// Background job to process all pending embeddings
+    /// Background job to work through the queue until no job is due
     pub async fn background_embedding_job(&self) -> Result<usize, AppError> {
         tracing::info!("Starting background embedding job");
 
         let mut total_processed = 0;
-        let batch_size = 50; // Process in batches of 50
-
         loop {
-            let batch_processed = self.process_pending_embeddings(batch_size).await?;
-            total_processed += batch_processed;
-
+            let batch_processed = self
+                .process_pending_embeddings(EMBEDDING_BATCH_SIZE)
+                .await?;
             if batch_processed == 0 {
-                break; // No more messages to process
+                break;
             }
-
-            // Add delay between batches to respect rate limits
-            tokio::time::sleep(Duration::from_secs(1)).await;
+            total_processed += batch_processed;
         }
 
         tracing::info!(
@@ -211,8 +468,364 @@ impl EmbeddingService {
     }
 }
 
+/// Store the chunks of a message and, as its message-level embedding, their mean
+pub(crate) async fn store_chunks(
+    dal: &DataAccessLayer,
+    message_id: Uuid,
+    model: &str,
+    chunks: &[ChunkEmbedding],
+) -> Result<(), AppError> {
+    dal.embeddings()
+        .replace_chunks(message_id, model, chunks)
+        .await?;
+    dal.embeddings()
+        .upsert_embedding(message_id, mean_embedding(chunks), model)
+        .await?;
+    Ok(())
+}
+
+/// Normalized mean of the chunk vectors
+fn mean_embedding(chunks: &[ChunkEmbedding]) -> Vec<f32> {
+    let dimensions = chunks.first().map_or(0, |chunk| chunk.embedding.len());
+    let mut mean = vec![0.0f32; dimensions];
+    for chunk in chunks {
+        for (sum, value) in mean.iter_mut().zip(&chunk.embedding) {
+            *sum += value;
+        }
+    }
+
+    let norm = mean.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        mean.iter_mut().for_each(|v| *v /= norm);
+    }
+    mean
+}
+
+/// A span of a message; offsets count characters, end exclusive
+#[derive(Debug, Clone, PartialEq)]
+struct TextChunk {
+    start: usize,
+    end: usize,
+    content: String,
+}
+
+/// Split text into chunks of at most `CHUNK_CHARS` characters that overlap by up to
+/// `CHUNK_OVERLAP_CHARS`. Chunks end at a paragraph, line, sentence or word break
+/// when there is one in their second half, and start at a word. Blank chunks are
+/// dropped.
+fn chunk_text(text: &str) -> Vec<TextChunk> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut chunks = Vec::new();
+    let mut start = 0;
+
+    while start < chars.len() {
+        let mut end = (start + CHUNK_CHARS).min(chars.len());
+        if end < chars.len() {
+            end = chunk_break(&chars, start + CHUNK_CHARS / 2, end);
+        }
+
+        let content: String = chars[start..end].iter().collect();
+        if !content.trim().is_empty() {
+            chunks.push(TextChunk {
+                start,
+                end,
+                content,
+            });
+        }
+        if end == chars.len() {
+            break;
+        }
+
+        let overlap_start = end.saturating_sub(CHUNK_OVERLAP_CHARS).max(start + 1);
+        start = (overlap_start..end)
+            .find(|&i| chars[i - 1].is_whitespace() && !chars[i].is_whitespace())
+            .unwrap_or(overlap_start);
+    }
+
+    chunks
+}
+
+/// The best place in `min..=max` to end a chunk, as an exclusive end offset
+fn chunk_break(chars: &[char], min: usize, max: usize) -> usize {
+    let paragraph = |i: usize| chars[i - 1] == '\n' && chars[i - 2] == '\n';
+    let line = |i: usize| chars[i - 1] == '\n';
+    let sentence =
+        |i: usize| chars[i - 1].is_whitespace() && matches!(chars[i - 2], '.' | '!' | '?');
+    let word = |i: usize| chars[i - 1].is_whitespace();
+    let breaks: [&dyn Fn(usize) -> bool; 4] = [&paragraph, &line, &sentence, &word];
+
+    breaks
+        .iter()
+        .find_map(|is_break| (min.max(2)..=max).rev().find(|&i| is_break(i)))
+        .unwrap_or(max)
+}
+
+/// Count results per conversation, role, model and provider, most frequent first
+fn search_facets(results: &[SearchResult]) -> SearchFacets {
+    let mut conversations: Vec<ConversationFacet> = Vec::new();
+    let mut roles: HashMap<String, usize> = HashMap::new();
+    let mut models: HashMap<String, usize> = HashMap::new();
+    let mut providers: HashMap<String, usize> = HashMap::new();
+
+    for result in results {
+        match conversations
+            .iter_mut()
+            .find(|facet| facet.conversation_id == result.conversation_id)
+        {
+            Some(facet) => facet.count += 1,
+            None => conversations.push(ConversationFacet {
+                conversation_id: result.conversation_id,
+                title: result.conversation_title.clone(),
+                count: 1,
+            }),
+        }
+        *roles.entry(result.role.to_string()).or_default() += 1;
+        *models.entry(result.model.clone()).or_default() += 1;
+        *providers.entry(result.provider.clone()).or_default() += 1;
+    }
+
+    // Stable, so conversations with equal counts keep the order of their best match
+    conversations.sort_by_key(|facet| std::cmp::Reverse(facet.count));
+
+    SearchFacets {
+        conversations,
+        roles: facet_counts(roles),
+        models: facet_counts(models),
+        providers: facet_counts(providers),
+    }
+}
+
+fn facet_counts(counts: HashMap<String, usize>) -> Vec<FacetCount> {
+    let mut facets: Vec<FacetCount> = counts
+        .into_iter()
+        .map(|(value, count)| FacetCount { value, count })
+        .collect();
+    facets.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.value.cmp(&b.value)));
+    facets
+}
+
+fn truncate(mut results: Vec<SearchResult>, limit: i64) -> Vec<SearchResult> {
+    results.truncate(limit.max(0) as usize);
+    results
+}
+
+/// Merge rankings by summing 1 / (k + rank) for every list a message appears in, so
+/// messages ranked well by several searches rise to the top. Each merged result keeps
+/// the similarity and text rank it was found with.
+fn reciprocal_rank_fusion(rankings: Vec<Vec<SearchResult>>, limit: i64) -> Vec<SearchResult> {
+    let mut fused: HashMap<Uuid, SearchResult> = HashMap::new();
+
+    for ranking in rankings {
+        for (rank, result) in ranking.into_iter().enumerate() {
+            let contribution = 1.0 / (RRF_K + rank as f32 + 1.0);
+            fused
+                .entry(result.message_id)
+                .and_modify(|existing| {
+                    existing.score += contribution;
+                    existing.similarity = existing.similarity.or(result.similarity);
+                    existing.text_rank = existing.text_rank.or(result.text_rank);
+                    if existing.chunk.is_none() {
+                        existing.chunk = result.chunk.clone();
+                    }
+                })
+                .or_insert(SearchResult {
+                    score: contribution,
+                    ..result
+                });
+        }
+    }
+
+    let mut results: Vec<SearchResult> = fused.into_values().collect();
+    results.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then_with(|| b.created_at.cmp(&a.created_at))
+    });
+    truncate(results, limit)
+}
+
 #[cfg(test)]
 mod tests {
+    use super::*;
+    use crate::models::MessageRole;
+
+    fn result(id: Uuid, similarity: Option<f32>, text_rank: Option<f32>) -> SearchResult {
+        SearchResult {
+            message_id: id,
+            content: "content".to_string(),
+            role: MessageRole::User,
+            created_at: chrono::Utc::now(),
+            conversation_id: Uuid::nil(),
+            conversation_title: None,
+            similarity,
+            text_rank,
+            score: similarity.or(text_rank).unwrap_or_default(),
+            model: "gpt-4".to_string(),
+            provider: "openai".to_string(),
+            chunk: None,
+            snippet: None,
+        }
+    }
+
+    #[test]
+    fn test_reciprocal_rank_fusion_favours_messages_found_by_both() {
+        let (both, semantic_only, lexical_only) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let semantic = vec![
+            result(semantic_only, Some(0.95), None),
+            result(both, Some(0.9), None),
+        ];
+        let lexical = vec![
+            result(lexical_only, None, Some(0.4)),
+            result(both, None, Some(0.3)),
+        ];
+
+        let fused = reciprocal_rank_fusion(vec![semantic, lexical], 10);
+        assert_eq!(fused.len(), 3);
+        assert_eq!(fused[0].message_id, both);
+        assert_eq!(fused[0].similarity, Some(0.9));
+        assert_eq!(fused[0].text_rank, Some(0.3));
+        assert!((fused[0].score - 2.0 / 62.0).abs() < f32::EPSILON);
+        // Top ranks of a single list tie; the score no longer mixes units
+        assert_eq!(fused[1].score, fused[2].score);
+
+        let truncated = reciprocal_rank_fusion(vec![fused, Vec::new()], 1);
+        assert_eq!(truncated.len(), 1);
+        assert_eq!(truncated[0].message_id, both);
+    }
+
+    #[test]
+    fn test_search_facets_count_candidates_most_frequent_first() {
+        let (first, second) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut results = vec![
+            result(Uuid::new_v4(), Some(0.9), None),
+            result(Uuid::new_v4(), Some(0.8), None),
+            result(Uuid::new_v4(), None, Some(0.2)),
+        ];
+        results[0].conversation_id = first;
+        results[1].conversation_id = second;
+        results[1].role = MessageRole::Assistant;
+        results[1].model = "claude-3-opus".to_string();
+        results[2].conversation_id = second;
+
+        let facets = search_facets(&results);
+        assert_eq!(
+            facets
+                .conversations
+                .iter()
+                .map(|facet| (facet.conversation_id, facet.count))
+                .collect::<Vec<_>>(),
+            vec![(second, 2), (first, 1)]
+        );
+        assert_eq!(
+            facets.roles,
+            vec![
+                FacetCount {
+                    value: "user".to_string(),
+                    count: 2
+                },
+                FacetCount {
+                    value: "assistant".to_string(),
+                    count: 1
+                },
+            ]
+        );
+        assert_eq!(facets.models[0].value, "gpt-4");
+        assert_eq!(facets.models[1].value, "claude-3-opus");
+        assert_eq!(facets.providers.len(), 1);
+    }
+
+    #[test]
+    fn test_chunk_text_splits_long_text_into_overlapping_chunks() {
+        assert_eq!(
+            chunk_text("A short message."),
+            vec![TextChunk {
+                start: 0,
+                end: 16,
+                content: "A short message.".to_string(),
+            }]
+        );
+        assert!(chunk_text(" \n ").is_empty());
+
+        let sentence = "Ünïcode sentences keep their characters intact. ";
+        let text = sentence.repeat(200);
+        let chars: Vec<char> = text.chars().collect();
+        let chunks = chunk_text(&text);
+
+        assert!(chunks.len() > 1);
+        assert_eq!(chunks[0].start, 0);
+        assert_eq!(chunks.last().unwrap().end, chars.len());
+        for chunk in &chunks {
+            assert!(chunk.end - chunk.start <= CHUNK_CHARS);
+            assert_eq!(
+                chunk.content,
+                chars[chunk.start..chunk.end].iter().collect::<String>()
+            );
+            assert!(!chunk.content.starts_with(char::is_whitespace));
+        }
+        for chunk in &chunks[..chunks.len() - 1] {
+            assert!(chunk.content.ends_with(". "));
+        }
+        for pair in chunks.windows(2) {
+            assert!(pair[1].start < pair[0].end);
+            assert!(pair[0].end - pair[1].start <= CHUNK_OVERLAP_CHARS);
+        }
+
+        // Text without breaks is cut at the chunk size
+        let chunks = chunk_text(&"x".repeat(CHUNK_CHARS + 10));
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].end, CHUNK_CHARS);
+    }
+
+    #[test]
+    fn test_mean_embedding_is_normalized() {
+        let chunk = |embedding: Vec<f32>| ChunkEmbedding {
+            chunk_index: 0,
+            start_offset: 0,
+            end_offset: 1,
+            content: "x".to_string(),
+            embedding,
+        };
+
+        let mean = mean_embedding(&[chunk(vec![1.0, 0.0]), chunk(vec![0.0, 1.0])]);
+        assert!((mean[0] - std::f32::consts::FRAC_1_SQRT_2).abs() < 1e-6);
+        assert_eq!(mean[0], mean[1]);
+    }
+
+    #[test]
+    fn test_local_embeddings_rank_shared_vocabulary_higher() {
+        let provider = crate::embeddings::local::LocalEmbeddingProvider::new(384);
+        let cosine = |a: &[f32], b: &[f32]| a.iter().zip(b).map(|(x, y)| x * y).sum::<f32>();
+
+        let query = provider.embed_text("How do I configure the database connection pool?");
+        let related = provider.embed_text("Configuring database connections and pooling");
+        let unrelated = provider.embed_text("A recipe for banana bread with walnuts");
+
+        assert_eq!(query.len(), 384);
+        assert!((cosine(&query, &query) - 1.0).abs() < 1e-5);
+        assert!(cosine(&query, &related) > cosine(&query, &unrelated));
+        assert_eq!(
+            query,
+            provider.embed_text("How do I configure the database connection pool?")
+        );
+        assert!(provider.embed_text("  ").iter().all(|v| *v == 0.0));
+    }
+
+    #[test]
+    fn test_local_provider_rejects_other_model_names() {
+        let mut config = crate::config::EmbeddingConfig {
+            provider: "local".to_string(),
+            model_path: None,
+            model: "all-MiniLM-L6-v2".to_string(),
+            dimensions: 384,
+            api_base: None,
+            api_key: String::new(),
+        };
+        assert!(create_embedding_provider(&config, "").is_err());
+
+        config.model = crate::embeddings::local::HASHED_NGRAMS_MODEL.to_string();
+        let provider = create_embedding_provider(&config, "").unwrap();
+        assert_eq!(provider.model(), "hashed-ngrams-v1");
+    }
 
     // Note: These would be integration tests requiring database setup
     // For now, we'll add unit test stubs
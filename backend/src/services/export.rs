@@ -0,0 +1,516 @@
+use crate::{
+    models::{AttachmentResponse, Conversation, Message, MessageRole},
+    services::DataAccessLayer,
+};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    #[serde(alias = "markdown")]
+    Md,
+    Json,
+    Html,
+}
+
+impl ExportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Md => "text/markdown; charset=utf-8",
+            ExportFormat::Json => "application/json",
+            ExportFormat::Html => "text/html; charset=utf-8",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Md => "md",
+            ExportFormat::Json => "json",
+            ExportFormat::Html => "html",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportScope {
+    /// Only the currently active thread
+    #[default]
+    Active,
+    /// The full branch tree, including inactive alternatives
+    Tree,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExportedMessage {
+    #[serde(flatten)]
+    pub message: Message,
+    pub attachments: Vec<AttachmentResponse>,
+}
+
+/// A snapshot of a conversation ready to be rendered in any export format
+#[derive(Debug, Serialize)]
+pub struct ConversationExport {
+    pub conversation: Conversation,
+    pub scope: ExportScope,
+    pub exported_at: DateTime<Utc>,
+    pub active_thread: Vec<Uuid>,
+    pub messages: Vec<ExportedMessage>,
+    pub total_tokens: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExportService {
+    dal: DataAccessLayer,
+}
+
+impl ExportService {
+    pub fn new(dal: DataAccessLayer) -> Self {
+        Self { dal }
+    }
+
+    /// Build an export of a conversation. Access must be verified by the caller.
+    /// Reasoning is left out of message metadata unless `include_reasoning` is set.
+    pub async fn export_conversation(
+        &self,
+        conversation: Conversation,
+        scope: ExportScope,
+        include_reasoning: bool,
+    ) -> Result<ConversationExport> {
+        let active_thread = self
+            .dal
+            .messages()
+            .find_active_conversation_thread(conversation.id)
+            .await?;
+        let active_ids: Vec<Uuid> = active_thread.iter().map(|m| m.id).collect();
+
+        let messages = match scope {
+            ExportScope::Active => active_thread,
+            ExportScope::Tree => {
+                self.dal
+                    .messages()
+                    .find_conversation_tree(conversation.id)
+                    .await?
+            }
+        };
+
+        let mut attachments: HashMap<Uuid, Vec<AttachmentResponse>> = HashMap::new();
+        for attachment in self
+            .dal
+            .attachments()
+            .find_by_conversation_id(conversation.id)
+            .await?
+        {
+            attachments
+                .entry(attachment.message_id)
+                .or_default()
+                .push(attachment.into());
+        }
+
+        let total_tokens = messages
+            .iter()
+            .filter_map(|m| m.tokens_used)
+            .map(i64::from)
+            .sum();
+
+        let messages = messages
+            .into_iter()
+            .map(|message| ExportedMessage {
+                attachments: attachments.remove(&message.id).unwrap_or_default(),
+                message: if include_reasoning {
+                    message
+                } else {
+                    message.without_reasoning()
+                },
+            })
+            .collect();
+
+        Ok(ConversationExport {
+            conversation,
+            scope,
+            exported_at: Utc::now(),
+            active_thread: active_ids,
+            messages,
+            total_tokens,
+        })
+    }
+}
+
+impl ConversationExport {
+    pub fn render(&self, format: ExportFormat) -> Result<String> {
+        match format {
+            ExportFormat::Md => Ok(self.to_markdown()),
+            ExportFormat::Html => Ok(self.to_html()),
+            ExportFormat::Json => Ok(serde_json::to_string_pretty(self)?),
+        }
+    }
+
+    /// File name for the Content-Disposition header, derived from the title
+    pub fn filename(&self, format: ExportFormat) -> String {
+        let slug: String = self
+            .title()
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() {
+                    c.to_ascii_lowercase()
+                } else {
+                    '-'
+                }
+            })
+            .collect();
+        let slug = slug
+            .split('-')
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join("-");
+        let slug = if slug.is_empty() {
+            "conversation".to_string()
+        } else {
+            slug.chars().take(80).collect()
+        };
+
+        format!("{}.{}", slug, format.extension())
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut out = format!("# {}\n\n", self.title());
+        out.push_str(&format!(
+            "- **Model:** {} ({})\n- **Created:** {}\n- **Exported:** {}\n\n---\n\n",
+            self.conversation.model,
+            self.conversation.provider,
+            self.conversation.created_at.to_rfc3339(),
+            self.exported_at.to_rfc3339()
+        ));
+        out.push_str(&self.render_transcript(&MarkdownStyle));
+        out
+    }
+
+    pub fn to_html(&self) -> String {
+        let title = escape_html(self.title());
+        format!(
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n<style>\n\
+             body {{ font-family: sans-serif; max-width: 50rem; margin: 2rem auto; line-height: 1.5; }}\n\
+             .message {{ border-left: 3px solid #ccc; padding: 0.25rem 1rem; margin: 1rem 0; }}\n\
+             .message.user {{ border-color: #3b82f6; }}\n\
+             .message.assistant {{ border-color: #10b981; }}\n\
+             .content {{ white-space: pre-wrap; }}\n\
+             details {{ margin-left: 1.5rem; }}\n\
+             </style>\n</head>\n<body>\n<h1>{title}</h1>\n\
+             <p>Model: {model} ({provider})<br>Created: {created}<br>Exported: {exported}</p>\n\
+             <hr>\n{transcript}</body>\n</html>\n",
+            title = title,
+            model = escape_html(&self.conversation.model),
+            provider = escape_html(&self.conversation.provider),
+            created = self.conversation.created_at.to_rfc3339(),
+            exported = self.exported_at.to_rfc3339(),
+            transcript = self.render_transcript(&HtmlStyle),
+        )
+    }
+
+    fn title(&self) -> &str {
+        self.conversation
+            .title
+            .as_deref()
+            .filter(|t| !t.trim().is_empty())
+            .unwrap_or("Untitled conversation")
+    }
+
+    /// Render the active thread, with sibling branches as collapsible alternatives
+    fn render_transcript(&self, style: &dyn TranscriptStyle) -> String {
+        let mut children: HashMap<Option<Uuid>, Vec<&ExportedMessage>> = HashMap::new();
+        for message in &self.messages {
+            children
+                .entry(message.message.parent_id)
+                .or_default()
+                .push(message);
+        }
+        for siblings in children.values_mut() {
+            siblings.sort_by_key(|m| m.message.created_at);
+        }
+
+        let by_id: HashMap<Uuid, &ExportedMessage> =
+            self.messages.iter().map(|m| (m.message.id, m)).collect();
+
+        let mut out = String::new();
+        for id in &self.active_thread {
+            let Some(message) = by_id.get(id) else {
+                continue;
+            };
+            out.push_str(&style.message(message));
+
+            let alternatives = children
+                .get(&message.message.parent_id)
+                .into_iter()
+                .flatten()
+                .filter(|sibling| sibling.message.id != message.message.id);
+            for alternative in alternatives {
+                let branch = render_branch(alternative, &children, style);
+                out.push_str(&style.alternative(&alternative.message, branch));
+            }
+        }
+        out
+    }
+}
+
+/// Render a branch following its earliest child, nesting any further alternatives
+fn render_branch(
+    root: &ExportedMessage,
+    children: &HashMap<Option<Uuid>, Vec<&ExportedMessage>>,
+    style: &dyn TranscriptStyle,
+) -> String {
+    let mut out = style.message(root);
+    let mut current = root;
+
+    while let Some(kids) = children.get(&Some(current.message.id)) {
+        let Some((first, rest)) = kids.split_first() else {
+            break;
+        };
+        out.push_str(&style.message(first));
+        for alternative in rest {
+            let branch = render_branch(alternative, children, style);
+            out.push_str(&style.alternative(&alternative.message, branch));
+        }
+        current = first;
+    }
+
+    out
+}
+
+trait TranscriptStyle {
+    fn message(&self, message: &ExportedMessage) -> String;
+    fn alternative(&self, root: &Message, body: String) -> String;
+}
+
+struct MarkdownStyle;
+
+impl TranscriptStyle for MarkdownStyle {
+    fn message(&self, exported: &ExportedMessage) -> String {
+        let message = &exported.message;
+        let mut out = format!(
+            "### {} · {}\n\n{}\n\n",
+            role_label(&message.role),
+            message.created_at.format("%Y-%m-%d %H:%M UTC"),
+            message.content.trim_end()
+        );
+        if !exported.attachments.is_empty() {
+            let links: Vec<String> = exported
+                .attachments
+                .iter()
+                .map(|a| format!("[{}]({})", a.filename, a.download_url))
+                .collect();
+            out.push_str(&format!("_Attachments: {}_\n\n", links.join(", ")));
+        }
+        out
+    }
+
+    fn alternative(&self, root: &Message, body: String) -> String {
+        format!(
+            "<details>\n<summary>Alternative branch: {}</summary>\n\n{}</details>\n\n",
+            escape_html(&preview(&root.content)),
+            body
+        )
+    }
+}
+
+struct HtmlStyle;
+
+impl TranscriptStyle for HtmlStyle {
+    fn message(&self, exported: &ExportedMessage) -> String {
+        let message = &exported.message;
+        let mut out = format!(
+            "<div class=\"message {}\">\n<h3>{} <small>{}</small></h3>\n<div class=\"content\">{}</div>\n",
+            message.role,
+            role_label(&message.role),
+            message.created_at.format("%Y-%m-%d %H:%M UTC"),
+            escape_html(message.content.trim_end())
+        );
+        if !exported.attachments.is_empty() {
+            let links: Vec<String> = exported
+                .attachments
+                .iter()
+                .map(|a| {
+                    format!(
+                        "<a href=\"{}\">{}</a>",
+                        escape_html(&a.download_url),
+                        escape_html(&a.filename)
+                    )
+                })
+                .collect();
+            out.push_str(&format!("<p>Attachments: {}</p>\n", links.join(", ")));
+        }
+        out.push_str("</div>\n");
+        out
+    }
+
+    fn alternative(&self, root: &Message, body: String) -> String {
+        format!(
+            "<details>\n<summary>Alternative branch: {}</summary>\n{}</details>\n",
+            escape_html(&preview(&root.content)),
+            body
+        )
+    }
+}
+
+fn role_label(role: &MessageRole) -> &'static str {
+    match role {
+        MessageRole::User => "User",
+        MessageRole::Assistant => "Assistant",
+        MessageRole::System => "System",
+    }
+}
+
+fn preview(content: &str) -> String {
+    let line = content.lines().next().unwrap_or_default();
+    let mut preview: String = line.chars().take(50).collect();
+    if line.chars().count() > 50 {
+        preview.push_str("...");
+    }
+    preview
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn message(id: Uuid, parent_id: Option<Uuid>, role: MessageRole, content: &str) -> Message {
+        Message {
+            id,
+            conversation_id: Uuid::nil(),
+            parent_id,
+            role,
+            content: content.to_string(),
+            tokens_used: Some(5),
+            created_at: Utc::now(),
+            is_active: true,
+            metadata: serde_json::json!({}),
+            author_id: None,
+            edited_from: None,
+            deleted_at: None,
+        }
+    }
+
+    fn sample_export(scope: ExportScope) -> ConversationExport {
+        let root = Uuid::new_v4();
+        let answer = Uuid::new_v4();
+        let alternative = Uuid::new_v4();
+
+        let mut messages = vec![
+            message(root, None, MessageRole::User, "What is <b>2+2</b>?"),
+            message(answer, Some(root), MessageRole::Assistant, "It is 4."),
+        ];
+        if scope == ExportScope::Tree {
+            let mut alt = message(
+                alternative,
+                Some(root),
+                MessageRole::Assistant,
+                "Four, obviously.",
+            );
+            alt.is_active = false;
+            alt.created_at += Duration::seconds(1);
+            messages.push(alt);
+        }
+
+        ConversationExport {
+            conversation: Conversation {
+                id: Uuid::nil(),
+                user_id: Uuid::nil(),
+                title: Some("Arithmetic: basics!".to_string()),
+                model: "gpt-4".to_string(),
+                provider: "openai".to_string(),
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+                metadata: serde_json::json!({}),
+                pinned_at: None,
+                archived_at: None,
+                deleted_at: None,
+                active_leaf_id: None,
+            },
+            scope,
+            exported_at: Utc::now(),
+            active_thread: vec![root, answer],
+            total_tokens: messages
+                .iter()
+                .filter_map(|m| m.tokens_used)
+                .map(i64::from)
+                .sum(),
+            messages: messages
+                .into_iter()
+                .map(|message| ExportedMessage {
+                    message,
+                    attachments: Vec::new(),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_markdown_active_thread() {
+        let markdown = sample_export(ExportScope::Active).to_markdown();
+        assert!(markdown.starts_with("# Arithmetic: basics!"));
+        assert!(markdown.contains("### User"));
+        assert!(markdown.contains("It is 4."));
+        assert!(!markdown.contains("<details>"));
+    }
+
+    #[test]
+    fn test_markdown_tree_shows_alternatives_collapsed() {
+        let markdown = sample_export(ExportScope::Tree).to_markdown();
+        let answer = markdown.find("It is 4.").unwrap();
+        let details = markdown.find("<details>").unwrap();
+        assert!(
+            answer < details,
+            "Alternative should follow the active answer"
+        );
+        assert!(markdown.contains("Alternative branch: Four, obviously."));
+    }
+
+    #[test]
+    fn test_html_escapes_content() {
+        let html = sample_export(ExportScope::Tree).to_html();
+        assert!(html.contains("What is &lt;b&gt;2+2&lt;/b&gt;?"));
+        assert!(!html.contains("<b>2+2</b>"));
+        assert!(html.contains("<details>"));
+    }
+
+    #[test]
+    fn test_json_is_lossless() {
+        let export = sample_export(ExportScope::Tree);
+        let json: serde_json::Value =
+            serde_json::from_str(&export.render(ExportFormat::Json).unwrap()).unwrap();
+        assert_eq!(json["messages"].as_array().unwrap().len(), 3);
+        assert_eq!(json["total_tokens"], 15);
+        assert!(json["messages"][0]["attachments"].is_array());
+        assert!(json["messages"][0]["metadata"].is_object());
+    }
+
+    #[test]
+    fn test_filename_is_sanitized() {
+        let export = sample_export(ExportScope::Active);
+        assert_eq!(export.filename(ExportFormat::Md), "arithmetic-basics.md");
+        assert_eq!(
+            export.filename(ExportFormat::Html),
+            "arithmetic-basics.html"
+        );
+    }
+}
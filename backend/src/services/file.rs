@@ -0,0 +1,222 @@
+use crate::config::AppConfig;
+use crate::error::AppError;
+use crate::models::{Attachment, AttachmentResponse, FileUploadResponse};
+use crate::repositories::attachment::CreateAttachment;
+use crate::services::DataAccessLayer;
+use anyhow::{Context, Result};
+use axum_typed_multipart::{FieldData, TryFromMultipart};
+use mime_guess::MimeGuess;
+use std::path::{Path, PathBuf};
+use tempfile::NamedTempFile;
+use tokio::fs;
+use uuid::Uuid;
+
+const ALLOWED_EXTENSIONS: &[&str] = &[
+    "png", "jpg", "jpeg", "gif", "pdf", "txt", "md", "doc", "docx",
+];
+
+#[derive(Debug, Clone)]
+pub struct FileService {
+    dal: DataAccessLayer,
+    storage_path: PathBuf,
+    max_file_size: u64,
+    storage_quota: i64,
+}
+
+impl FileService {
+    pub fn new(config: AppConfig, dal: DataAccessLayer) -> Self {
+        Self {
+            dal,
+            storage_path: PathBuf::from(config.storage_path),
+            max_file_size: config.rate_limit.max_file_size_mb * 1024 * 1024,
+            storage_quota: (config.storage_quota_mb * 1024 * 1024) as i64,
+        }
+    }
+
+    /// Store an upload and attach it to a message. The size counts towards the quota
+    /// of `owner_id`, the owner of the message's conversation. Access must be verified
+    /// by the caller.
+    pub async fn upload_file(
+        &self,
+        owner_id: Uuid,
+        message_id: Uuid,
+        file_data: FieldData<NamedTempFile>,
+    ) -> Result<FileUploadResponse> {
+        let file_size = file_data.contents.as_file().metadata()?.len();
+        if file_size > self.max_file_size {
+            return Err(AppError::BadRequest(format!(
+                "File size {} bytes exceeds maximum allowed size {} bytes",
+                file_size, self.max_file_size
+            ))
+            .into());
+        }
+
+        // Only the final path component of the client's filename is kept
+        let filename = file_data
+            .metadata
+            .file_name
+            .as_deref()
+            .and_then(|name| Path::new(name).file_name())
+            .and_then(|name| name.to_str())
+            .map(str::to_string)
+            .ok_or_else(|| AppError::BadRequest("No filename provided".to_string()))?;
+        let extension = validate_file_extension(&filename)?;
+
+        let current_usage = self.get_user_storage_usage(owner_id).await?;
+        if current_usage + file_size as i64 > self.storage_quota {
+            return Err(AppError::BadRequest(format!(
+                "Upload would exceed storage quota. Current usage: {}MB, Max: {}MB",
+                current_usage / (1024 * 1024),
+                self.storage_quota / (1024 * 1024)
+            ))
+            .into());
+        }
+
+        let content_type = MimeGuess::from_path(&filename)
+            .first()
+            .map(|mime| mime.to_string())
+            .or(file_data.metadata.content_type);
+
+        // Stored under a fresh name so client filenames never reach the file system
+        let storage_dir = self.storage_path.join("attachments");
+        fs::create_dir_all(&storage_dir)
+            .await
+            .context("Failed to create storage directory")?;
+        let file_path = storage_dir.join(format!("{}.{}", Uuid::new_v4(), extension));
+
+        fs::copy(file_data.contents.path(), &file_path)
+            .await
+            .context("Failed to save file to storage")?;
+
+        let created = self
+            .dal
+            .attachments()
+            .create(CreateAttachment {
+                message_id,
+                filename,
+                content_type,
+                size_bytes: Some(file_size as i64),
+                storage_path: file_path.to_string_lossy().to_string(),
+            })
+            .await;
+
+        match created {
+            Ok(attachment) => Ok(attachment.into()),
+            Err(e) => {
+                if let Err(remove_error) = fs::remove_file(&file_path).await {
+                    tracing::warn!(
+                        "Failed to remove orphaned upload {}: {}",
+                        file_path.display(),
+                        remove_error
+                    );
+                }
+                Err(e)
+            }
+        }
+    }
+
+    pub async fn get_attachment(&self, attachment_id: Uuid) -> Result<Attachment> {
+        self.dal
+            .attachments()
+            .find_by_id(attachment_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Attachment not found".to_string()).into())
+    }
+
+    /// Open the stored file of an attachment for streaming
+    pub async fn open_file(&self, attachment: &Attachment) -> Result<fs::File> {
+        match fs::File::open(&attachment.storage_path).await {
+            Ok(file) => Ok(file),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                tracing::error!(
+                    "Attachment {} is missing from storage: {}",
+                    attachment.id,
+                    attachment.storage_path
+                );
+                Err(AppError::NotFound("Attachment file not found".to_string()).into())
+            }
+            Err(e) => Err(anyhow::Error::new(e).context("Failed to read file from storage")),
+        }
+    }
+
+    /// Delete an attachment. Its file is removed from storage unless a forked copy of
+    /// the message still links to it.
+    pub async fn delete_file(&self, attachment: &Attachment) -> Result<()> {
+        if !self.dal.attachments().delete(attachment.id).await? {
+            return Err(AppError::NotFound("Attachment not found".to_string()).into());
+        }
+        remove_unlinked_files(&self.dal, std::slice::from_ref(&attachment.storage_path)).await
+    }
+
+    pub async fn get_message_attachments(
+        &self,
+        message_id: Uuid,
+    ) -> Result<Vec<AttachmentResponse>> {
+        let attachments = self
+            .dal
+            .attachments()
+            .find_by_message_id(message_id)
+            .await?;
+        Ok(attachments.into_iter().map(|att| att.into()).collect())
+    }
+
+    pub async fn get_user_storage_usage(&self, user_id: Uuid) -> Result<i64> {
+        self.dal
+            .attachments()
+            .get_total_user_storage_size(user_id)
+            .await
+    }
+}
+
+/// Remove the stored files that no attachment links to any more. Call after the
+/// attachment rows are deleted and committed; files shared by forks are kept.
+pub async fn remove_unlinked_files(dal: &DataAccessLayer, storage_paths: &[String]) -> Result<()> {
+    for storage_path in storage_paths {
+        if dal
+            .attachments()
+            .is_storage_path_in_use(storage_path)
+            .await?
+        {
+            continue;
+        }
+
+        match fs::remove_file(storage_path).await {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => tracing::warn!(
+                "Failed to remove file from storage: {} - {}",
+                storage_path,
+                e
+            ),
+        }
+    }
+    Ok(())
+}
+
+/// The lowercased extension of an allowed filename
+fn validate_file_extension(filename: &str) -> Result<String, AppError> {
+    let extension = Path::new(filename)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .ok_or_else(|| AppError::BadRequest("File has no extension".to_string()))?
+        .to_lowercase();
+
+    if !ALLOWED_EXTENSIONS.contains(&extension.as_str()) {
+        return Err(AppError::BadRequest(format!(
+            "File extension '{}' is not allowed. Allowed extensions: {}",
+            extension,
+            ALLOWED_EXTENSIONS.join(", ")
+        )));
+    }
+
+    Ok(extension)
+}
+
+// Multipart form data structure for file upload. The file size is limited by the
+// route's body limit and checked against the configured maximum on upload.
+#[derive(TryFromMultipart)]
+pub struct FileUploadForm {
+    #[form_data(limit = "unlimited")]
+    pub file: FieldData<NamedTempFile>,
+    pub message_id: String, // Will be parsed to UUID
+}
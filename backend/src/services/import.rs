@@ -0,0 +1,994 @@
+use crate::{
+    error::AppError,
+    models::{Conversation, Message, MessageRole},
+    repositories::Repository,
+    services::DataAccessLayer,
+};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::io::{Cursor, Read};
+use uuid::Uuid;
+
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+const CONVERSATIONS_FILE: &str = "conversations.json";
+const MAX_TITLE_CHARS: usize = 255;
+const MAX_MODEL_CHARS: usize = 50;
+const CHATGPT_FALLBACK_MODEL: &str = "gpt-4";
+const CLAUDE_FALLBACK_MODEL: &str = "claude";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportSource {
+    #[serde(alias = "openai")]
+    ChatGpt,
+    #[serde(alias = "anthropic")]
+    Claude,
+}
+
+impl ImportSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ImportSource::ChatGpt => "chatgpt",
+            ImportSource::Claude => "claude",
+        }
+    }
+
+    fn provider(&self) -> &'static str {
+        match self {
+            ImportSource::ChatGpt => "openai",
+            ImportSource::Claude => "anthropic",
+        }
+    }
+
+    /// Guess the export format from the shape of the first conversation
+    pub fn detect(conversations: &[Value]) -> Option<Self> {
+        let first = conversations.first()?;
+        if first.get("mapping").is_some() {
+            Some(ImportSource::ChatGpt)
+        } else if first.get("chat_messages").is_some() {
+            Some(ImportSource::Claude)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SkippedKind {
+    Conversation,
+    Message,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SkippedItem {
+    pub kind: SkippedKind,
+    pub source_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    pub reason: String,
+}
+
+impl SkippedItem {
+    fn conversation(
+        source_id: Option<String>,
+        title: Option<String>,
+        reason: impl Into<String>,
+    ) -> Self {
+        Self {
+            kind: SkippedKind::Conversation,
+            source_id,
+            title,
+            reason: reason.into(),
+        }
+    }
+
+    fn message(source_id: &str, reason: impl Into<String>) -> Self {
+        Self {
+            kind: SkippedKind::Message,
+            source_id: Some(source_id.to_string()),
+            title: None,
+            reason: reason.into(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportReport {
+    pub source: ImportSource,
+    pub conversations_imported: usize,
+    pub messages_imported: usize,
+    /// Messages on the active path of each conversation, which the embedding job picks up
+    pub embeddings_queued: usize,
+    pub conversation_ids: Vec<Uuid>,
+    pub skipped: Vec<SkippedItem>,
+}
+
+/// A message extracted from an export, still keyed by the source tool's ids
+#[derive(Debug, Clone)]
+pub struct ParsedMessage {
+    pub source_id: String,
+    /// Nearest ancestor that is itself imported (skipped nodes are collapsed)
+    pub parent: Option<String>,
+    pub role: MessageRole,
+    pub content: String,
+    pub created_at: Option<DateTime<Utc>>,
+    pub model: Option<String>,
+    pub is_active: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct ParsedConversation {
+    pub source_id: Option<String>,
+    pub title: Option<String>,
+    pub model: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+    /// Ordered so that every parent precedes its children
+    pub messages: Vec<ParsedMessage>,
+    pub skipped: Vec<SkippedItem>,
+}
+
+/// Content of a node in the source tree before it is flattened
+enum NodeContent {
+    Message(ParsedMessage),
+    Skip(String),
+    /// Structural nodes such as ChatGPT's empty root
+    Empty,
+}
+
+struct SourceNode {
+    id: String,
+    parent: Option<String>,
+    content: NodeContent,
+}
+
+pub struct ImportService {
+    dal: DataAccessLayer,
+}
+
+impl ImportService {
+    pub fn new(dal: DataAccessLayer) -> Self {
+        Self { dal }
+    }
+
+    /// Import an uploaded export. Accepts either the zip archive downloaded from
+    /// ChatGPT / Claude or the bare `conversations.json` inside it.
+    pub async fn import(
+        &self,
+        user_id: Uuid,
+        data: &[u8],
+        source: Option<ImportSource>,
+        max_bytes: u64,
+    ) -> Result<ImportReport, AppError> {
+        let json = extract_conversations_json(data, max_bytes)
+            .map_err(|e| AppError::BadRequest(e.to_string()))?;
+        let conversations: Vec<Value> = serde_json::from_slice(&json).map_err(|e| {
+            AppError::BadRequest(format!(
+                "{} is not a conversation list: {}",
+                CONVERSATIONS_FILE, e
+            ))
+        })?;
+
+        let source = match source.or_else(|| ImportSource::detect(&conversations)) {
+            Some(source) => source,
+            None if conversations.is_empty() => ImportSource::ChatGpt,
+            None => {
+                return Err(AppError::BadRequest(
+                    "Unrecognised export format, expected a ChatGPT or Claude export".to_string(),
+                ))
+            }
+        };
+
+        let already_imported: HashSet<String> = self
+            .dal
+            .conversations()
+            .find_imported_source_ids(user_id, source.as_str())
+            .await?
+            .into_iter()
+            .collect();
+
+        let mut report = ImportReport {
+            source,
+            conversations_imported: 0,
+            messages_imported: 0,
+            embeddings_queued: 0,
+            conversation_ids: Vec::new(),
+            skipped: Vec::new(),
+        };
+
+        for raw in &conversations {
+            let parsed = match source {
+                ImportSource::ChatGpt => parse_chatgpt_conversation(raw),
+                ImportSource::Claude => parse_claude_conversation(raw),
+            };
+            let mut parsed = match parsed {
+                Ok(parsed) => parsed,
+                Err(e) => {
+                    report.skipped.push(SkippedItem::conversation(
+                        source_id_of(raw),
+                        None,
+                        e.to_string(),
+                    ));
+                    continue;
+                }
+            };
+
+            if let Some(source_id) = &parsed.source_id {
+                if already_imported.contains(source_id) {
+                    report.skipped.push(SkippedItem::conversation(
+                        parsed.source_id.clone(),
+                        parsed.title.clone(),
+                        "Already imported",
+                    ));
+                    continue;
+                }
+            }
+
+            report.skipped.append(&mut parsed.skipped);
+
+            if parsed.messages.is_empty() {
+                report.skipped.push(SkippedItem::conversation(
+                    parsed.source_id.clone(),
+                    parsed.title.clone(),
+                    "No importable messages",
+                ));
+                continue;
+            }
+
+            match self.store_conversation(user_id, source, &parsed).await {
+                Ok((conversation_id, active)) => {
+                    report.conversations_imported += 1;
+                    report.messages_imported += parsed.messages.len();
+                    report.embeddings_queued += active;
+                    report.conversation_ids.push(conversation_id);
+                }
+                Err(e) => {
+                    tracing::warn!(
+                        "Failed to import conversation {:?}: {}",
+                        parsed.source_id,
+                        e
+                    );
+                    report.skipped.push(SkippedItem::conversation(
+                        parsed.source_id.clone(),
+                        parsed.title.clone(),
+                        format!("Database error: {}", e),
+                    ));
+                }
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Persist one parsed conversation, returning its new id and the number of active messages
+    async fn store_conversation(
+        &self,
+        user_id: Uuid,
+        source: ImportSource,
+        parsed: &ParsedConversation,
+    ) -> Result<(Uuid, usize)> {
+        let now = Utc::now();
+        let first_message_at = parsed.messages.iter().filter_map(|m| m.created_at).min();
+        let last_message_at = parsed.messages.iter().filter_map(|m| m.created_at).max();
+        let created_at = parsed.created_at.or(first_message_at).unwrap_or(now);
+        let updated_at = parsed
+            .updated_at
+            .or(last_message_at)
+            .unwrap_or(created_at)
+            .max(created_at);
+
+        let model = parsed
+            .model
+            .clone()
+            .or_else(|| parsed.messages.iter().rev().find_map(|m| m.model.clone()))
+            .unwrap_or_else(|| match source {
+                ImportSource::ChatGpt => CHATGPT_FALLBACK_MODEL.to_string(),
+                ImportSource::Claude => CLAUDE_FALLBACK_MODEL.to_string(),
+            });
+
+        let conversation = Conversation {
+            id: Uuid::new_v4(),
+            user_id,
+            title: parsed
+                .title
+                .as_deref()
+                .map(|t| truncate_chars(t, MAX_TITLE_CHARS)),
+            model: truncate_chars(&model, MAX_MODEL_CHARS),
+            provider: source.provider().to_string(),
+            created_at,
+            updated_at,
+            metadata: serde_json::json!({
+                "import": {
+                    "source": source.as_str(),
+                    "source_id": parsed.source_id,
+                    "source_model": model,
+                    "imported_at": now,
+                }
+            }),
+            pinned_at: None,
+            archived_at: None,
+            deleted_at: None,
+            active_leaf_id: None,
+        };
+        let ids: HashMap<&str, Uuid> = parsed
+            .messages
+            .iter()
+            .map(|m| (m.source_id.as_str(), Uuid::new_v4()))
+            .collect();
+
+        let mut last_created_at = created_at;
+        let messages: Vec<Message> = parsed
+            .messages
+            .iter()
+            .map(|m| {
+                // Exports sometimes omit timestamps; keep such messages in order after their predecessor
+                let message_created_at = m.created_at.unwrap_or(last_created_at);
+                last_created_at = message_created_at;
+
+                let mut metadata = serde_json::json!({
+                    "import": {
+                        "source": source.as_str(),
+                        "source_id": m.source_id,
+                    }
+                });
+                if let Some(model) = &m.model {
+                    metadata["model"] = Value::String(model.clone());
+                    metadata["provider"] = Value::String(source.provider().to_string());
+                }
+
+                Message {
+                    id: ids[m.source_id.as_str()],
+                    conversation_id: conversation.id,
+                    parent_id: m.parent.as_deref().and_then(|p| ids.get(p).copied()),
+                    role: m.role.clone(),
+                    content: m.content.clone(),
+                    tokens_used: None,
+                    created_at: message_created_at,
+                    is_active: m.is_active,
+                    metadata,
+                    author_id: matches!(m.role, MessageRole::User).then_some(user_id),
+                    edited_from: None,
+                    deleted_at: None,
+                }
+            })
+            .collect();
+
+        // Don't leave an empty shell behind if the messages could not be written
+        let conversation_id = conversation.id;
+        let written = &messages;
+        self.dal
+            .transaction(|dal| async move {
+                dal.conversations().create(conversation).await?;
+                dal.messages().create_many(written).await?;
+                Ok(())
+            })
+            .await?;
+
+        let active = messages.iter().filter(|m| m.is_active).count();
+        Ok((conversation_id, active))
+    }
+}
+
+/// Pull `conversations.json` out of an export archive, or pass raw JSON through
+pub fn extract_conversations_json(data: &[u8], max_bytes: u64) -> Result<Vec<u8>> {
+    if !data.starts_with(ZIP_MAGIC) {
+        return Ok(data.to_vec());
+    }
+
+    let mut archive = zip::ZipArchive::new(Cursor::new(data))
+        .map_err(|e| anyhow!("Invalid export archive: {}", e))?;
+
+    // Prefer the shallowest match in case the archive was re-zipped inside a folder
+    let name = archive
+        .file_names()
+        .filter(|name| {
+            name.rsplit('/').next() == Some(CONVERSATIONS_FILE) && !name.starts_with("__MACOSX")
+        })
+        .min_by_key(|name| name.len())
+        .map(|name| name.to_string())
+        .ok_or_else(|| anyhow!("Export archive does not contain {}", CONVERSATIONS_FILE))?;
+
+    let file = archive.by_name(&name)?;
+    let mut json = Vec::new();
+    file.take(max_bytes + 1).read_to_end(&mut json)?;
+    if json.len() as u64 > max_bytes {
+        return Err(anyhow!(
+            "{} exceeds the import size limit",
+            CONVERSATIONS_FILE
+        ));
+    }
+
+    Ok(json)
+}
+
+fn source_id_of(raw: &Value) -> Option<String> {
+    ["id", "conversation_id", "uuid"]
+        .iter()
+        .find_map(|key| raw.get(*key).and_then(Value::as_str))
+        .map(str::to_string)
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatGptConversation {
+    title: Option<String>,
+    create_time: Option<f64>,
+    update_time: Option<f64>,
+    #[serde(default)]
+    mapping: HashMap<String, ChatGptNode>,
+    current_node: Option<String>,
+    default_model_slug: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatGptNode {
+    id: Option<String>,
+    message: Option<ChatGptMessage>,
+    parent: Option<String>,
+    #[serde(default)]
+    children: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatGptMessage {
+    author: ChatGptAuthor,
+    create_time: Option<f64>,
+    content: Option<ChatGptContent>,
+    #[serde(default)]
+    metadata: Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatGptAuthor {
+    role: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatGptContent {
+    content_type: Option<String>,
+    #[serde(default)]
+    parts: Vec<Value>,
+    text: Option<String>,
+}
+
+impl ChatGptContent {
+    fn text(&self) -> String {
+        let parts: Vec<&str> = self
+            .parts
+            .iter()
+            .filter_map(|part| match part {
+                Value::String(text) => Some(text.as_str()),
+                // Multimodal parts carry text alongside asset pointers
+                Value::Object(obj) => obj.get("text").and_then(Value::as_str),
+                _ => None,
+            })
+            .filter(|text| !text.is_empty())
+            .collect();
+
+        if parts.is_empty() {
+            self.text.clone().unwrap_or_default()
+        } else {
+            parts.join("\n")
+        }
+    }
+}
+
+pub fn parse_chatgpt_conversation(raw: &Value) -> Result<ParsedConversation> {
+    let conversation: ChatGptConversation = serde_json::from_value(raw.clone())
+        .map_err(|e| anyhow!("Malformed ChatGPT conversation: {}", e))?;
+    let source_id = source_id_of(raw);
+
+    // Walk the mapping so that children follow the order ChatGPT recorded them in
+    let mut order: Vec<&str> = Vec::with_capacity(conversation.mapping.len());
+    let mut seen: HashSet<&str> = HashSet::new();
+    let mut roots: Vec<&str> = conversation
+        .mapping
+        .iter()
+        .filter(|(_, node)| {
+            node.parent
+                .as_deref()
+                .is_none_or(|p| !conversation.mapping.contains_key(p))
+        })
+        .map(|(id, _)| id.as_str())
+        .collect();
+    roots.sort_by(|a, b| {
+        let time = |id: &str| node_time(&conversation.mapping[id]).unwrap_or(f64::MAX);
+        time(a).total_cmp(&time(b))
+    });
+    let mut stack: Vec<&str> = roots.into_iter().rev().collect();
+    while let Some(id) = stack.pop() {
+        if !seen.insert(id) {
+            continue;
+        }
+        order.push(id);
+        if let Some(node) = conversation.mapping.get(id) {
+            for child in node.children.iter().rev() {
+                if conversation.mapping.contains_key(child.as_str()) {
+                    stack.push(child.as_str());
+                }
+            }
+        }
+    }
+
+    let nodes = order
+        .into_iter()
+        .map(|key| {
+            let node = &conversation.mapping[key];
+            let id = node.id.clone().unwrap_or_else(|| key.to_string());
+            SourceNode {
+                content: chatgpt_node_content(&id, node),
+                id,
+                parent: node.parent.clone(),
+            }
+        })
+        .collect();
+
+    let (messages, skipped) = flatten_tree(nodes, conversation.current_node.as_deref());
+
+    Ok(ParsedConversation {
+        source_id,
+        title: conversation.title.filter(|t| !t.trim().is_empty()),
+        model: conversation.default_model_slug,
+        created_at: conversation.create_time.and_then(from_unix_seconds),
+        updated_at: conversation.update_time.and_then(from_unix_seconds),
+        messages,
+        skipped,
+    })
+}
+
+fn node_time(node: &ChatGptNode) -> Option<f64> {
+    node.message.as_ref().and_then(|m| m.create_time)
+}
+
+fn chatgpt_node_content(id: &str, node: &ChatGptNode) -> NodeContent {
+    let Some(message) = &node.message else {
+        return NodeContent::Empty;
+    };
+
+    let role = match message.author.role.as_str() {
+        "user" => MessageRole::User,
+        "assistant" => MessageRole::Assistant,
+        "system" => MessageRole::System,
+        other => return NodeContent::Skip(format!("Unsupported author role '{}'", other)),
+    };
+
+    let hidden = message
+        .metadata
+        .get("is_visually_hidden_from_conversation")
+        .and_then(Value::as_bool)
+        .unwrap_or(false);
+    let content = message
+        .content
+        .as_ref()
+        .map(|c| c.text())
+        .unwrap_or_default();
+
+    if content.trim().is_empty() {
+        // ChatGPT stores an empty system prompt at the top of nearly every conversation
+        if matches!(role, MessageRole::System) || hidden {
+            return NodeContent::Empty;
+        }
+        let content_type = message
+            .content
+            .as_ref()
+            .and_then(|c| c.content_type.as_deref())
+            .unwrap_or("unknown");
+        return NodeContent::Skip(format!("No text content ({})", content_type));
+    }
+    if hidden {
+        return NodeContent::Empty;
+    }
+
+    let model = message
+        .metadata
+        .get("model_slug")
+        .and_then(Value::as_str)
+        .map(str::to_string);
+
+    NodeContent::Message(ParsedMessage {
+        source_id: id.to_string(),
+        parent: None,
+        role,
+        content,
+        created_at: message.create_time.and_then(from_unix_seconds),
+        model,
+        is_active: false,
+    })
+}
+
+#[derive(Debug, Deserialize)]
+struct ClaudeConversation {
+    name: Option<String>,
+    created_at: Option<DateTime<Utc>>,
+    updated_at: Option<DateTime<Utc>>,
+    model: Option<String>,
+    current_leaf_message_uuid: Option<String>,
+    #[serde(default)]
+    chat_messages: Vec<ClaudeMessage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ClaudeMessage {
+    uuid: String,
+    sender: String,
+    #[serde(default)]
+    text: String,
+    #[serde(default)]
+    content: Vec<ClaudeContentBlock>,
+    created_at: Option<DateTime<Utc>>,
+    parent_message_uuid: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ClaudeContentBlock {
+    #[serde(rename = "type")]
+    block_type: String,
+    text: Option<String>,
+}
+
+impl ClaudeMessage {
+    fn text(&self) -> String {
+        if !self.text.trim().is_empty() {
+            return self.text.clone();
+        }
+        self.content
+            .iter()
+            .filter(|block| block.block_type == "text")
+            .filter_map(|block| block.text.as_deref())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+pub fn parse_claude_conversation(raw: &Value) -> Result<ParsedConversation> {
+    let conversation: ClaudeConversation = serde_json::from_value(raw.clone())
+        .map_err(|e| anyhow!("Malformed Claude conversation: {}", e))?;
+    let source_id = source_id_of(raw);
+
+    // Older exports are strictly linear; newer ones carry explicit parent pointers
+    let has_parents = conversation
+        .chat_messages
+        .iter()
+        .any(|m| m.parent_message_uuid.is_some());
+
+    let mut previous: Option<String> = None;
+    let nodes = conversation
+        .chat_messages
+        .iter()
+        .map(|message| {
+            let parent = if has_parents {
+                message.parent_message_uuid.clone()
+            } else {
+                previous.clone()
+            };
+            previous = Some(message.uuid.clone());
+
+            let content = message.text();
+            let node_content = match message.sender.as_str() {
+                _ if content.trim().is_empty() => NodeContent::Skip("No text content".to_string()),
+                "human" => {
+                    NodeContent::Message(claude_message(message, MessageRole::User, content))
+                }
+                "assistant" => {
+                    NodeContent::Message(claude_message(message, MessageRole::Assistant, content))
+                }
+                other => NodeContent::Skip(format!("Unsupported sender '{}'", other)),
+            };
+
+            SourceNode {
+                id: message.uuid.clone(),
+                parent,
+                content: node_content,
+            }
+        })
+        .collect();
+
+    let (messages, skipped) =
+        flatten_tree(nodes, conversation.current_leaf_message_uuid.as_deref());
+
+    Ok(ParsedConversation {
+        source_id,
+        title: conversation.name.filter(|t| !t.trim().is_empty()),
+        model: conversation.model,
+        created_at: conversation.created_at,
+        updated_at: conversation.updated_at,
+        messages,
+        skipped,
+    })
+}
+
+fn claude_message(message: &ClaudeMessage, role: MessageRole, content: String) -> ParsedMessage {
+    ParsedMessage {
+        source_id: message.uuid.clone(),
+        parent: None,
+        role,
+        content,
+        created_at: message.created_at,
+        model: None,
+        is_active: false,
+    }
+}
+
+/// Turn a source tree into an ordered message list. Skipped nodes are removed and
+/// their children re-attached to the nearest kept ancestor. The path from `leaf` to
+/// the root becomes the active thread; without a leaf the last message is used.
+fn flatten_tree(
+    nodes: Vec<SourceNode>,
+    leaf: Option<&str>,
+) -> (Vec<ParsedMessage>, Vec<SkippedItem>) {
+    let index: HashMap<&str, usize> = nodes
+        .iter()
+        .enumerate()
+        .map(|(i, node)| (node.id.as_str(), i))
+        .collect();
+
+    let mut children: HashMap<usize, Vec<usize>> = HashMap::new();
+    let mut roots = Vec::new();
+    for (i, node) in nodes.iter().enumerate() {
+        match node.parent.as_deref().and_then(|p| index.get(p)) {
+            Some(&parent) if parent != i => children.entry(parent).or_default().push(i),
+            _ => roots.push(i),
+        }
+    }
+
+    let leaf = leaf.and_then(|id| index.get(id).copied()).or_else(|| {
+        nodes
+            .iter()
+            .rposition(|node| matches!(node.content, NodeContent::Message(_)))
+    });
+
+    let mut active: HashSet<usize> = HashSet::new();
+    let mut cursor = leaf;
+    while let Some(i) = cursor {
+        if !active.insert(i) {
+            break;
+        }
+        cursor = nodes[i]
+            .parent
+            .as_deref()
+            .and_then(|p| index.get(p))
+            .copied();
+    }
+
+    let mut messages = Vec::new();
+    let mut skipped = Vec::new();
+    let mut visited: HashSet<usize> = HashSet::new();
+    let mut stack: Vec<(usize, Option<String>)> =
+        roots.into_iter().rev().map(|i| (i, None)).collect();
+
+    let mut nodes: Vec<Option<SourceNode>> = nodes.into_iter().map(Some).collect();
+    while let Some((i, ancestor)) = stack.pop() {
+        if !visited.insert(i) {
+            continue;
+        }
+        let Some(node) = nodes[i].take() else {
+            continue;
+        };
+
+        let next_ancestor = match node.content {
+            NodeContent::Message(mut message) => {
+                message.parent = ancestor;
+                message.is_active = active.contains(&i);
+                let id = message.source_id.clone();
+                messages.push(message);
+                Some(id)
+            }
+            NodeContent::Skip(reason) => {
+                skipped.push(SkippedItem::message(&node.id, reason));
+                ancestor
+            }
+            NodeContent::Empty => ancestor,
+        };
+
+        if let Some(kids) = children.get(&i) {
+            for &child in kids.iter().rev() {
+                stack.push((child, next_ancestor.clone()));
+            }
+        }
+    }
+
+    (messages, skipped)
+}
+
+fn from_unix_seconds(seconds: f64) -> Option<DateTime<Utc>> {
+    if !seconds.is_finite() {
+        return None;
+    }
+    let secs = seconds.trunc() as i64;
+    let nanos = ((seconds.fract()) * 1e9).round().clamp(0.0, 999_999_999.0) as u32;
+    DateTime::from_timestamp(secs, nanos)
+}
+
+fn truncate_chars(value: &str, max: usize) -> String {
+    value.chars().take(max).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn chatgpt_fixture() -> Value {
+        json!({
+            "id": "conv-1",
+            "title": "Rust lifetimes",
+            "create_time": 1700000000.5,
+            "update_time": 1700000100.0,
+            "current_node": "a2",
+            "default_model_slug": "gpt-4o",
+            "mapping": {
+                "root": { "id": "root", "message": null, "parent": null, "children": ["sys"] },
+                "sys": {
+                    "id": "sys",
+                    "message": {
+                        "author": { "role": "system" },
+                        "create_time": null,
+                        "content": { "content_type": "text", "parts": [""] },
+                        "metadata": { "is_visually_hidden_from_conversation": true }
+                    },
+                    "parent": "root",
+                    "children": ["u1"]
+                },
+                "u1": {
+                    "id": "u1",
+                    "message": {
+                        "author": { "role": "user" },
+                        "create_time": 1700000001.0,
+                        "content": { "content_type": "text", "parts": ["What is 'a?"] },
+                        "metadata": {}
+                    },
+                    "parent": "sys",
+                    "children": ["a1", "a2"]
+                },
+                "a1": {
+                    "id": "a1",
+                    "message": {
+                        "author": { "role": "assistant" },
+                        "create_time": 1700000002.0,
+                        "content": { "content_type": "text", "parts": ["A lifetime."] },
+                        "metadata": { "model_slug": "gpt-4" }
+                    },
+                    "parent": "u1",
+                    "children": []
+                },
+                "a2": {
+                    "id": "a2",
+                    "message": {
+                        "author": { "role": "assistant" },
+                        "create_time": 1700000003.0,
+                        "content": { "content_type": "text", "parts": ["A named lifetime parameter."] },
+                        "metadata": { "model_slug": "gpt-4o" }
+                    },
+                    "parent": "u1",
+                    "children": ["t1"]
+                },
+                "t1": {
+                    "id": "t1",
+                    "message": {
+                        "author": { "role": "tool" },
+                        "create_time": 1700000004.0,
+                        "content": { "content_type": "text", "parts": ["search results"] },
+                        "metadata": {}
+                    },
+                    "parent": "a2",
+                    "children": []
+                }
+            }
+        })
+    }
+
+    #[test]
+    fn test_detect_source() {
+        assert_eq!(
+            ImportSource::detect(&[chatgpt_fixture()]),
+            Some(ImportSource::ChatGpt)
+        );
+        assert_eq!(
+            ImportSource::detect(&[json!({ "uuid": "x", "chat_messages": [] })]),
+            Some(ImportSource::Claude)
+        );
+        assert_eq!(ImportSource::detect(&[json!({ "foo": 1 })]), None);
+    }
+
+    #[test]
+    fn test_parse_chatgpt_branches_and_active_path() {
+        let parsed = parse_chatgpt_conversation(&chatgpt_fixture()).unwrap();
+
+        assert_eq!(parsed.source_id.as_deref(), Some("conv-1"));
+        assert_eq!(parsed.model.as_deref(), Some("gpt-4o"));
+        let ids: Vec<&str> = parsed
+            .messages
+            .iter()
+            .map(|m| m.source_id.as_str())
+            .collect();
+        assert_eq!(ids, vec!["u1", "a1", "a2"]);
+
+        // The hidden system node is collapsed, so the user message becomes a root
+        assert_eq!(parsed.messages[0].parent, None);
+        assert_eq!(parsed.messages[1].parent.as_deref(), Some("u1"));
+        assert_eq!(parsed.messages[2].parent.as_deref(), Some("u1"));
+
+        let active: Vec<bool> = parsed.messages.iter().map(|m| m.is_active).collect();
+        assert_eq!(active, vec![true, false, true]);
+        assert_eq!(parsed.messages[1].model.as_deref(), Some("gpt-4"));
+        assert_eq!(
+            parsed.messages[0].created_at,
+            DateTime::from_timestamp(1700000001, 0)
+        );
+
+        assert_eq!(parsed.skipped.len(), 1);
+        assert_eq!(parsed.skipped[0].source_id.as_deref(), Some("t1"));
+    }
+
+    #[test]
+    fn test_parse_claude_linear() {
+        let raw = json!({
+            "uuid": "c-1",
+            "name": "Trip plan",
+            "created_at": "2024-05-01T10:00:00Z",
+            "updated_at": "2024-05-01T10:05:00Z",
+            "chat_messages": [
+                { "uuid": "m1", "sender": "human", "text": "Plan a trip", "created_at": "2024-05-01T10:00:00Z" },
+                { "uuid": "m2", "sender": "assistant", "text": "", "content": [{ "type": "text", "text": "Sure." }], "created_at": "2024-05-01T10:00:05Z" },
+                { "uuid": "m3", "sender": "human", "text": "", "created_at": "2024-05-01T10:01:00Z" },
+                { "uuid": "m4", "sender": "human", "text": "To Lisbon", "created_at": "2024-05-01T10:02:00Z" }
+            ]
+        });
+
+        let parsed = parse_claude_conversation(&raw).unwrap();
+        assert_eq!(parsed.title.as_deref(), Some("Trip plan"));
+        let parents: Vec<Option<&str>> = parsed
+            .messages
+            .iter()
+            .map(|m| m.parent.as_deref())
+            .collect();
+        // The empty message is skipped and its child re-attached to the assistant reply
+        assert_eq!(parents, vec![None, Some("m1"), Some("m2")]);
+        assert_eq!(parsed.messages[1].content, "Sure.");
+        assert!(parsed.messages.iter().all(|m| m.is_active));
+        assert_eq!(parsed.skipped.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_claude_branches_use_leaf() {
+        let raw = json!({
+            "uuid": "c-2",
+            "name": "Branches",
+            "current_leaf_message_uuid": "m2",
+            "chat_messages": [
+                { "uuid": "m1", "sender": "human", "text": "Hi", "parent_message_uuid": "00000000-0000-4000-8000-000000000000" },
+                { "uuid": "m2", "sender": "assistant", "text": "Hello", "parent_message_uuid": "m1" },
+                { "uuid": "m3", "sender": "assistant", "text": "Hey", "parent_message_uuid": "m1" }
+            ]
+        });
+
+        let parsed = parse_claude_conversation(&raw).unwrap();
+        let active: Vec<(&str, bool)> = parsed
+            .messages
+            .iter()
+            .map(|m| (m.source_id.as_str(), m.is_active))
+            .collect();
+        assert_eq!(active, vec![("m1", true), ("m2", true), ("m3", false)]);
+    }
+
+    #[test]
+    fn test_extract_conversations_json_from_zip() {
+        let mut buffer = Cursor::new(Vec::new());
+        {
+            let mut writer = zip::ZipWriter::new(&mut buffer);
+            writer
+                .start_file("export/conversations.json", Default::default())
+                .unwrap();
+            std::io::Write::write_all(&mut writer, b"[]").unwrap();
+            writer.finish().unwrap();
+        }
+        let bytes = buffer.into_inner();
+
+        assert_eq!(extract_conversations_json(&bytes, 1024).unwrap(), b"[]");
+        assert_eq!(extract_conversations_json(b"[1]", 1024).unwrap(), b"[1]");
+        assert!(extract_conversations_json(&bytes, 1).is_err());
+    }
+}
@@ -1,36 +1,92 @@
 pub mod auth;
+pub mod authorization;
 pub mod chat;
+pub mod compare;
 pub mod conversation;
 pub mod embedding;
-// pub mod file;  // Temporarily disabled
+pub mod export;
+pub mod file;
+pub mod import;
 pub mod password;
 pub mod redis_session_store;
+pub mod retrieval;
+pub mod saved_search;
 pub mod session;
+pub mod share;
+pub mod topic;
 
 use crate::{database::Database, repositories::RepositoryManager};
-use std::sync::Arc;
+use anyhow::Result;
+use std::{future::Future, sync::Arc};
 
 // Data Access Layer - combines repositories and provides business logic
 #[derive(Debug, Clone)]
 pub struct DataAccessLayer {
     pub repositories: Arc<RepositoryManager>,
+    database: Database,
 }
 
 impl DataAccessLayer {
     pub fn new(database: Database) -> Self {
-        let repositories = Arc::new(RepositoryManager::new(database));
-        Self { repositories }
+        let repositories = Arc::new(RepositoryManager::new(database.clone()));
+        Self {
+            repositories,
+            database,
+        }
+    }
+
+    /// Run `work` as one unit: every repository of the `DataAccessLayer` it is given
+    /// shares a single transaction, committed when `work` returns `Ok` and rolled back
+    /// otherwise. Nested calls join the outer transaction.
+    pub async fn transaction<T, F, Fut>(&self, work: F) -> Result<T>
+    where
+        F: FnOnce(DataAccessLayer) -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        self.database
+            .transaction(|database| work(DataAccessLayer::new(database)))
+            .await
     }
 
     // Convenience methods to access repositories
+    pub fn annotations(&self) -> &crate::repositories::annotation::AnnotationRepository {
+        &self.repositories.annotations
+    }
+
     pub fn conversations(&self) -> &crate::repositories::conversation::ConversationRepository {
         &self.repositories.conversations
     }
 
+    pub fn conversation_members(
+        &self,
+    ) -> &crate::repositories::conversation_member::ConversationMemberRepository {
+        &self.repositories.conversation_members
+    }
+
     pub fn messages(&self) -> &crate::repositories::message::MessageRepository {
         &self.repositories.messages
     }
 
+    pub fn projects(&self) -> &crate::repositories::project::ProjectRepository {
+        &self.repositories.projects
+    }
+
+    pub fn saved_searches(&self) -> &crate::repositories::saved_search::SavedSearchRepository {
+        &self.repositories.saved_searches
+    }
+
+    pub fn share_links(&self) -> &crate::repositories::share_link::ShareLinkRepository {
+        &self.repositories.share_links
+    }
+
+    pub fn tags(&self) -> &crate::repositories::tag::TagRepository {
+        &self.repositories.tags
+    }
+
+    pub fn topics(&self) -> &crate::repositories::topic::TopicRepository {
+        &self.repositories.topics
+    }
+
     pub fn users(&self) -> &crate::repositories::user::UserRepository {
         &self.repositories.users
     }
@@ -39,7 +95,15 @@ impl DataAccessLayer {
         &self.repositories.attachments
     }
 
+    pub fn api_usage(&self) -> &crate::repositories::api_usage::ApiUsageRepository {
+        &self.repositories.api_usage
+    }
+
     pub fn embeddings(&self) -> &crate::repositories::embedding::EmbeddingRepository {
         &self.repositories.embeddings
     }
+
+    pub fn embedding_jobs(&self) -> &crate::repositories::embedding_job::EmbeddingJobRepository {
+        &self.repositories.embedding_jobs
+    }
 }
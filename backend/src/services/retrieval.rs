@@ -0,0 +1,252 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    error::AppError,
+    llm::ChatMessage,
+    models::{Conversation, RetrievalSettings, SearchFilters},
+    repositories::embedding::SearchResult,
+    services::embedding::EmbeddingService,
+};
+
+/// Matches fetched per passage used; matches from the current conversation are dropped
+const CANDIDATES_PER_PASSAGE: i64 = 3;
+
+/// Longest passage or query in characters; matched chunks are shorter already
+const MAX_PASSAGE_CHARS: usize = 2000;
+
+/// A passage given to the model, recorded on the answer it informed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Citation {
+    /// The number the passage had in the prompt, as in `[1]`
+    pub marker: usize,
+    pub message_id: Uuid,
+    pub conversation_id: Uuid,
+    pub conversation_title: Option<String>,
+    /// Character offsets of the passage in the message
+    pub start_offset: Option<i32>,
+    pub end_offset: Option<i32>,
+    pub similarity: Option<f32>,
+}
+
+/// Adds passages from a user's earlier conversations to the prompt of conversations
+/// that have retrieval turned on
+#[derive(Debug, Clone)]
+pub struct RetrievalService {
+    embedding: EmbeddingService,
+}
+
+impl RetrievalService {
+    pub fn new(embedding: EmbeddingService) -> Self {
+        Self { embedding }
+    }
+
+    /// Search for the latest user turn and insert the best passages as a system
+    /// message just before it. Returns the citations to save with the answer. A
+    /// failed search is logged and the chat goes on without context. Passages come
+    /// from the conversation owner's history whoever sends the message, so members
+    /// never pull their own conversations into a shared one. Callers that already
+    /// embedded the latest user turn pass `query_embedding` so it isn't embedded twice.
+    pub async fn augment(
+        &self,
+        conversation: &Conversation,
+        messages: &mut Vec<ChatMessage>,
+        query_embedding: Option<Vec<f32>>,
+    ) -> Vec<Citation> {
+        let settings = RetrievalSettings::from_metadata(&conversation.metadata);
+        if !settings.enabled {
+            return Vec::new();
+        }
+        let Some(position) = messages.iter().rposition(|message| message.role == "user") else {
+            return Vec::new();
+        };
+
+        let query = truncate_chars(&messages[position].content, MAX_PASSAGE_CHARS);
+        let results = async {
+            let query_embedding = match query_embedding {
+                Some(query_embedding) => query_embedding,
+                None => self.embedding.generate_embedding(query).await?,
+            };
+            self.retrieve(
+                conversation.user_id,
+                conversation.id,
+                query_embedding,
+                &settings,
+            )
+            .await
+        }
+        .await;
+        match results {
+            Ok(results) if !results.is_empty() => {
+                let (context, citations) = build_context(&results);
+                messages.insert(position, context);
+                tracing::info!(
+                    "Added {} retrieved passages to conversation {}",
+                    citations.len(),
+                    conversation.id
+                );
+                citations
+            }
+            Ok(_) => Vec::new(),
+            Err(e) => {
+                tracing::warn!(
+                    "Retrieval failed for conversation {}, answering without it: {}",
+                    conversation.id,
+                    e
+                );
+                Vec::new()
+            }
+        }
+    }
+
+    /// The user's messages most similar to the query, outside the current conversation
+    pub async fn retrieve(
+        &self,
+        user_id: Uuid,
+        conversation_id: Uuid,
+        query_embedding: Vec<f32>,
+        settings: &RetrievalSettings,
+    ) -> Result<Vec<SearchResult>, AppError> {
+        let top_k = settings.top_k as usize;
+        let results = self
+            .embedding
+            .search_similar_to_embedding(
+                query_embedding,
+                Some(user_id),
+                &SearchFilters::default(),
+                Some(top_k as i64 * CANDIDATES_PER_PASSAGE),
+                Some(settings.min_similarity),
+            )
+            .await?;
+
+        Ok(results
+            .into_iter()
+            .filter(|result| result.conversation_id != conversation_id)
+            .take(top_k)
+            .collect())
+    }
+}
+
+/// A system message listing the passages under numbered markers, with their citations
+fn build_context(results: &[SearchResult]) -> (ChatMessage, Vec<Citation>) {
+    let mut content = String::from(
+        "Passages from the user's earlier conversations that may help with their next \
+         message. When you use one, cite its marker, e.g. [1]. Ignore passages that are \
+         not relevant.\n",
+    );
+    let mut citations = Vec::with_capacity(results.len());
+
+    for (index, result) in results.iter().enumerate() {
+        let marker = index + 1;
+        let passage = match &result.chunk {
+            Some(chunk) => chunk.content.as_str(),
+            None => result.content.as_str(),
+        };
+        content.push_str(&format!(
+            "\n[{}] {} message in \"{}\", {}:\n{}\n",
+            marker,
+            result.role,
+            result
+                .conversation_title
+                .as_deref()
+                .unwrap_or("Untitled conversation"),
+            result.created_at.format("%Y-%m-%d"),
+            truncate_chars(passage.trim(), MAX_PASSAGE_CHARS)
+        ));
+
+        citations.push(Citation {
+            marker,
+            message_id: result.message_id,
+            conversation_id: result.conversation_id,
+            conversation_title: result.conversation_title.clone(),
+            start_offset: result.chunk.as_ref().map(|chunk| chunk.start_offset),
+            end_offset: result.chunk.as_ref().map(|chunk| chunk.end_offset),
+            similarity: result.similarity,
+        });
+    }
+
+    (
+        ChatMessage {
+            role: "system".to_string(),
+            content,
+        },
+        citations,
+    )
+}
+
+fn truncate_chars(text: &str, max_chars: usize) -> &str {
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => &text[..end],
+        None => text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{models::MessageRole, repositories::embedding::MatchedChunk};
+
+    fn result(title: Option<&str>, content: &str, chunk: Option<MatchedChunk>) -> SearchResult {
+        SearchResult {
+            message_id: Uuid::new_v4(),
+            content: content.to_string(),
+            role: MessageRole::Assistant,
+            created_at: chrono::Utc::now(),
+            conversation_id: Uuid::new_v4(),
+            conversation_title: title.map(str::to_string),
+            similarity: Some(0.82),
+            text_rank: None,
+            score: 0.82,
+            model: "gpt-4".to_string(),
+            provider: "openai".to_string(),
+            chunk,
+            snippet: None,
+        }
+    }
+
+    #[test]
+    fn test_build_context_numbers_passages_and_cites_their_chunks() {
+        let chunk = MatchedChunk {
+            chunk_index: 3,
+            start_offset: 5400,
+            end_offset: 7200,
+            content: "Retry with exponential backoff capped at an hour.".to_string(),
+        };
+        let results = vec![
+            result(
+                Some("Queue design"),
+                "A long answer about queues",
+                Some(chunk),
+            ),
+            result(None, "Use SKIP LOCKED to claim jobs.", None),
+        ];
+
+        let (message, citations) = build_context(&results);
+
+        assert_eq!(message.role, "system");
+        assert!(message
+            .content
+            .contains("[1] assistant message in \"Queue design\""));
+        assert!(message
+            .content
+            .contains("Retry with exponential backoff capped at an hour."));
+        assert!(!message.content.contains("A long answer about queues"));
+        assert!(message
+            .content
+            .contains("[2] assistant message in \"Untitled conversation\""));
+
+        assert_eq!(citations.len(), 2);
+        assert_eq!(citations[0].marker, 1);
+        assert_eq!(citations[0].message_id, results[0].message_id);
+        assert_eq!(citations[0].start_offset, Some(5400));
+        assert_eq!(citations[0].end_offset, Some(7200));
+        assert_eq!(citations[1].marker, 2);
+        assert_eq!(citations[1].start_offset, None);
+    }
+
+    #[test]
+    fn test_truncate_chars_keeps_whole_characters() {
+        assert_eq!(truncate_chars("héllo", 2), "hé");
+        assert_eq!(truncate_chars("héllo", 10), "héllo");
+    }
+}
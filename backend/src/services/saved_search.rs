@@ -0,0 +1,258 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    config::AppConfig,
+    error::AppError,
+    models::{SaveSearchRequest, SavedSearch, SearchFilters},
+    services::{
+        embedding::{EmbeddingService, MessageSearch},
+        DataAccessLayer,
+    },
+};
+
+/// Most new matches counted per check
+const MAX_NEW_MATCHES: i64 = 100;
+
+/// Saved searches re-run per background pass
+const CHECK_BATCH_SIZE: i64 = 100;
+
+/// A saved search after running it, with the point its new matches were counted from
+#[derive(Debug, Clone)]
+pub struct SavedSearchRun {
+    pub saved_search: SavedSearch,
+    pub new_since: DateTime<Utc>,
+    /// Matches created or embedded since `new_since`, as the checks count them
+    pub new_message_ids: Vec<Uuid>,
+    pub search: MessageSearch,
+}
+
+#[derive(Debug, Clone)]
+pub struct SavedSearchService {
+    dal: DataAccessLayer,
+    embedding: EmbeddingService,
+}
+
+impl SavedSearchService {
+    pub fn new(config: AppConfig, dal: DataAccessLayer) -> Result<Self, AppError> {
+        let embedding = EmbeddingService::new(config, dal.clone())?;
+        Ok(Self { dal, embedding })
+    }
+
+    pub async fn list(&self, user_id: Uuid) -> Result<Vec<SavedSearch>> {
+        self.dal.saved_searches().find_by_user_id(user_id).await
+    }
+
+    pub async fn get(&self, id: Uuid, user_id: Uuid) -> Result<SavedSearch> {
+        self.dal
+            .saved_searches()
+            .find_for_user(id, user_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Saved search not found".to_string()).into())
+    }
+
+    pub async fn create(&self, user_id: Uuid, request: SaveSearchRequest) -> Result<SavedSearch> {
+        validate(&request)?;
+        self.dal.saved_searches().create(user_id, &request).await
+    }
+
+    pub async fn update(
+        &self,
+        id: Uuid,
+        user_id: Uuid,
+        request: SaveSearchRequest,
+    ) -> Result<SavedSearch> {
+        validate(&request)?;
+        self.dal
+            .saved_searches()
+            .update(id, user_id, &request)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Saved search not found".to_string()).into())
+    }
+
+    pub async fn delete(&self, id: Uuid, user_id: Uuid) -> Result<()> {
+        if !self.dal.saved_searches().delete(id, user_id).await? {
+            return Err(AppError::NotFound("Saved search not found".to_string()).into());
+        }
+        Ok(())
+    }
+
+    /// Run the search now and mark its matches seen
+    pub async fn run(&self, id: Uuid, user_id: Uuid, limit: Option<i64>) -> Result<SavedSearchRun> {
+        let saved_search = self.get(id, user_id).await?;
+        let viewed_at = Utc::now();
+
+        let search = self
+            .embedding
+            .search_messages(
+                &saved_search.query,
+                Some(user_id),
+                saved_search.mode,
+                &saved_search.filters,
+                limit,
+                saved_search.similarity_threshold,
+            )
+            .await?;
+        let new_message_ids = self
+            .embedding
+            .search_messages(
+                &saved_search.query,
+                Some(user_id),
+                saved_search.mode,
+                &new_match_filters(&saved_search),
+                Some(MAX_NEW_MATCHES),
+                saved_search.similarity_threshold,
+            )
+            .await?
+            .results
+            .into_iter()
+            .map(|result| result.message_id)
+            .collect();
+        let new_since = saved_search.last_viewed_at;
+        let saved_search = self
+            .dal
+            .saved_searches()
+            .mark_viewed(saved_search.id, viewed_at)
+            .await?;
+
+        Ok(SavedSearchRun {
+            saved_search,
+            new_since,
+            new_message_ids,
+            search,
+        })
+    }
+
+    /// Re-run the saved searches of users with new messages or embeddings since their
+    /// last check and store their new-match counts. A failing search is logged and
+    /// tried again on the next pass. Returns the searches checked.
+    pub async fn check_due(&self) -> Result<usize> {
+        let due = self.dal.saved_searches().find_due(CHECK_BATCH_SIZE).await?;
+
+        let mut checked = 0;
+        for saved_search in &due {
+            match self.check(saved_search).await {
+                Ok(_) => checked += 1,
+                Err(e) => tracing::warn!("Failed to check saved search {}: {}", saved_search.id, e),
+            }
+        }
+
+        Ok(checked)
+    }
+
+    /// Count the matches created since the search was last viewed
+    pub async fn check(&self, saved_search: &SavedSearch) -> Result<i32> {
+        let checked_at = Utc::now();
+        let new_match_count = self
+            .embedding
+            .search_messages(
+                &saved_search.query,
+                Some(saved_search.user_id),
+                saved_search.mode,
+                &new_match_filters(saved_search),
+                Some(MAX_NEW_MATCHES),
+                saved_search.similarity_threshold,
+            )
+            .await?
+            .results
+            .len() as i32;
+
+        self.dal
+            .saved_searches()
+            .record_check(
+                saved_search.id,
+                saved_search.last_viewed_at,
+                new_match_count,
+                checked_at,
+            )
+            .await?;
+
+        Ok(new_match_count)
+    }
+}
+
+fn validate(request: &SaveSearchRequest) -> Result<()> {
+    request
+        .validate()
+        .map_err(|e| AppError::BadRequest(format!("Validation error: {e}")))?;
+    if let (Some(after), Some(before)) = (
+        request.filters.created_after,
+        request.filters.created_before,
+    ) {
+        if after >= before {
+            return Err(AppError::BadRequest(
+                "created_after must be before created_before".to_string(),
+            )
+            .into());
+        }
+    }
+    Ok(())
+}
+
+/// The search's filters narrowed to messages created or embedded since its last view,
+/// as `find_due` counts them, so imported and re-embedded history shows up as new
+fn new_match_filters(saved_search: &SavedSearch) -> SearchFilters {
+    SearchFilters {
+        indexed_after: Some(saved_search.last_viewed_at),
+        ..saved_search.filters.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::SearchMode;
+    use chrono::Duration;
+
+    fn saved_search(filters: SearchFilters) -> SavedSearch {
+        let now = Utc::now();
+        SavedSearch {
+            id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            name: "Ingress".to_string(),
+            query: "ingress".to_string(),
+            mode: SearchMode::Lexical,
+            filters,
+            similarity_threshold: None,
+            new_match_count: 0,
+            last_viewed_at: now,
+            last_checked_at: now,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    #[test]
+    fn test_new_match_filters_start_at_the_last_view() {
+        let search = saved_search(SearchFilters {
+            provider: Some("openai".to_string()),
+            ..SearchFilters::default()
+        });
+
+        let filters = new_match_filters(&search);
+
+        assert_eq!(filters.indexed_after, Some(search.last_viewed_at));
+        assert_eq!(filters.created_after, None);
+        assert_eq!(filters.provider.as_deref(), Some("openai"));
+    }
+
+    #[test]
+    fn test_new_match_filters_keep_the_date_range() {
+        // Messages imported into a past range are new once they are indexed
+        let after = Utc::now() - Duration::days(30);
+        let before = Utc::now() - Duration::days(1);
+        let search = saved_search(SearchFilters {
+            created_after: Some(after),
+            created_before: Some(before),
+            ..SearchFilters::default()
+        });
+
+        let filters = new_match_filters(&search);
+
+        assert_eq!(filters.created_after, Some(after));
+        assert_eq!(filters.created_before, Some(before));
+        assert_eq!(filters.indexed_after, Some(search.last_viewed_at));
+    }
+}
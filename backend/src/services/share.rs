@@ -0,0 +1,91 @@
+use crate::{
+    models::{Conversation, CreateShareLinkRequest, ShareLink, SharedConversation, SharedMessage},
+    services::DataAccessLayer,
+};
+use anyhow::Result;
+use chrono::{Duration, Utc};
+use rand::{rngs::OsRng, RngCore};
+use std::collections::HashSet;
+use uuid::Uuid;
+
+#[derive(Debug, Clone)]
+pub struct ShareService {
+    dal: DataAccessLayer,
+}
+
+impl ShareService {
+    pub fn new(dal: DataAccessLayer) -> Self {
+        Self { dal }
+    }
+
+    /// Create a share link with a snapshot of the conversation as it is now.
+    /// Ownership must be verified by the caller.
+    pub async fn create_share_link(
+        &self,
+        conversation: &Conversation,
+        request: &CreateShareLinkRequest,
+    ) -> Result<ShareLink> {
+        let snapshot = self
+            .snapshot(conversation, request.include_branches)
+            .await?;
+        let expires_at = request
+            .expires_in_hours
+            .map(|hours| Utc::now() + Duration::hours(i64::from(hours)));
+
+        self.dal
+            .share_links()
+            .create(
+                conversation.id,
+                conversation.user_id,
+                &generate_token(),
+                request.include_branches,
+                &snapshot,
+                expires_at,
+            )
+            .await
+    }
+
+    async fn snapshot(
+        &self,
+        conversation: &Conversation,
+        include_branches: bool,
+    ) -> Result<SharedConversation> {
+        let messages = self
+            .dal
+            .messages()
+            .find_active_conversation_thread(conversation.id)
+            .await?;
+
+        let branches = if include_branches {
+            let active: HashSet<Uuid> = messages.iter().map(|m| m.id).collect();
+            let tree = self
+                .dal
+                .messages()
+                .find_conversation_tree(conversation.id)
+                .await?;
+            Some(
+                tree.into_iter()
+                    .filter(|m| !active.contains(&m.id))
+                    .map(SharedMessage::from)
+                    .collect(),
+            )
+        } else {
+            None
+        };
+
+        Ok(SharedConversation {
+            title: conversation.title.clone(),
+            model: conversation.model.clone(),
+            shared_at: Utc::now(),
+            messages: messages.into_iter().map(SharedMessage::from).collect(),
+            branches,
+        })
+    }
+}
+
+/// 256 random bits, hex encoded
+fn generate_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
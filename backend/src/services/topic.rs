@@ -0,0 +1,656 @@
+use anyhow::Result;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+use crate::{
+    config::AppConfig,
+    error::AppError,
+    llm::{ChatMessage, ChatRequest, LLMServiceFactory, Provider},
+    models::{preview_text, TopicCluster},
+    services::{embedding::EmbeddingService, DataAccessLayer},
+};
+
+/// Most recent messages clustered per user; k-means time grows with points times topics
+const MAX_TOPIC_MESSAGES: i64 = 2000;
+
+/// Users with fewer embedded messages than this get no topics
+const MIN_TOPIC_MESSAGES: usize = 10;
+
+const MAX_TOPICS: usize = 12;
+
+const KMEANS_ITERATIONS: usize = 30;
+
+const PCA_ITERATIONS: usize = 100;
+
+/// Fixed so clustering the same messages again gives the same topics
+const CLUSTER_SEED: u64 = 0x70_91c5;
+
+const KEYWORDS_PER_TOPIC: usize = 3;
+
+/// Messages nearest a topic's centre that the LLM sees when naming it
+const LABEL_SAMPLES: usize = 5;
+
+const MAX_LABEL_CHARS: usize = 60;
+
+/// Common words that say nothing about a topic; shorter words are dropped anyway
+const STOPWORDS: &[&str] = &[
+    "about", "after", "again", "also", "because", "been", "before", "being", "between", "both",
+    "could", "does", "doing", "done", "each", "even", "from", "have", "here", "into", "just",
+    "know", "like", "make", "many", "more", "most", "much", "need", "only", "other", "over",
+    "same", "should", "some", "such", "sure", "than", "that", "their", "them", "then", "there",
+    "these", "they", "thing", "think", "this", "those", "through", "using", "very", "want", "well",
+    "were", "what", "when", "where", "which", "while", "will", "with", "would", "your",
+];
+
+/// Clusters a user's message embeddings into topics and lays the messages out on a
+/// 2-D map
+#[derive(Debug, Clone)]
+pub struct TopicService {
+    config: AppConfig,
+    dal: DataAccessLayer,
+    /// Only embeddings of the current model are clustered together
+    model: String,
+}
+
+/// Where k-means and the projection put the messages
+struct TopicLayout {
+    assignments: Vec<usize>,
+    clusters: usize,
+    /// Each message's distance to the centre of its cluster
+    distances: Vec<f32>,
+    coordinates: Vec<[f32; 2]>,
+}
+
+/// A cluster ready to be stored
+struct NewTopic {
+    label: String,
+    keywords: Vec<String>,
+    message_ids: Vec<Uuid>,
+    coordinates: Vec<[f32; 2]>,
+}
+
+impl TopicService {
+    pub fn new(config: AppConfig, dal: DataAccessLayer) -> Result<Self, AppError> {
+        let model = EmbeddingService::new(config.clone(), dal.clone())?
+            .provider()
+            .model()
+            .to_string();
+
+        Ok(Self { config, dal, model })
+    }
+
+    /// Cluster one user, or every user with embeddings from the current model when
+    /// `user_id` is `None`. A failure for one user is logged and the others still run.
+    /// Returns the users clustered. This is the job of the `cluster-topics` command
+    /// and of the refresh endpoint, which runs it in the background.
+    pub async fn refresh_users(&self, user_id: Option<Uuid>) -> Result<usize> {
+        let user_ids = match user_id {
+            Some(user_id) => vec![user_id],
+            None => {
+                self.dal
+                    .topics()
+                    .find_user_ids_with_embeddings(&self.model)
+                    .await?
+            }
+        };
+
+        let mut refreshed = 0;
+        for user_id in user_ids {
+            match self.refresh(user_id).await {
+                Ok(clusters) => {
+                    tracing::info!("Clustered user {} into {} topics", user_id, clusters.len());
+                    refreshed += 1;
+                }
+                Err(e) => tracing::warn!("Failed to cluster topics for user {}: {}", user_id, e),
+            }
+        }
+
+        Ok(refreshed)
+    }
+
+    /// Cluster the user's recent message embeddings, name the clusters and store them
+    /// with map coordinates, replacing the previous run. Returns the clusters, largest
+    /// first; none when there are too few messages.
+    pub async fn refresh(&self, user_id: Uuid) -> Result<Vec<TopicCluster>> {
+        let mut sources = self
+            .dal
+            .topics()
+            .find_sources(user_id, &self.model, MAX_TOPIC_MESSAGES)
+            .await?;
+        // Vectors from before a change of dimensions cannot be compared with the rest
+        if let Some(dims) = sources.first().map(|source| source.embedding.len()) {
+            sources.retain(|source| source.embedding.len() == dims);
+        }
+
+        if sources.len() < MIN_TOPIC_MESSAGES {
+            self.dal.topics().delete_for_user(user_id).await?;
+            return Ok(Vec::new());
+        }
+
+        let vectors: Vec<Vec<f32>> = sources
+            .iter()
+            .map(|source| normalized(&source.embedding))
+            .collect();
+        let layout = tokio::task::spawn_blocking(move || layout_topics(&vectors)).await?;
+
+        // Group the messages, largest topic first
+        let mut groups: Vec<Vec<usize>> = vec![Vec::new(); layout.clusters];
+        for (index, &cluster) in layout.assignments.iter().enumerate() {
+            groups[cluster].push(index);
+        }
+        let mut order: Vec<usize> = (0..groups.len())
+            .filter(|&cluster| !groups[cluster].is_empty())
+            .collect();
+        order.sort_by_key(|&cluster| std::cmp::Reverse(groups[cluster].len()));
+
+        let texts: Vec<Vec<&str>> = order
+            .iter()
+            .map(|&cluster| {
+                groups[cluster]
+                    .iter()
+                    .map(|&index| sources[index].content.as_str())
+                    .collect()
+            })
+            .collect();
+        let keywords = topic_keywords(&texts);
+
+        let mut topics = Vec::with_capacity(order.len());
+        for (position, (&cluster, keywords)) in order.iter().zip(keywords).enumerate() {
+            let members = &groups[cluster];
+            let mut nearest = members.clone();
+            nearest.sort_by(|&a, &b| layout.distances[a].total_cmp(&layout.distances[b]));
+            let samples: Vec<String> = nearest
+                .iter()
+                .take(LABEL_SAMPLES)
+                .map(|&index| preview_text(&sources[index].content))
+                .collect();
+
+            let label = match self.llm_label(&samples).await {
+                Some(label) => label,
+                None if keywords.is_empty() => format!("Topic {}", position + 1),
+                None => keywords.join(", "),
+            };
+
+            topics.push(NewTopic {
+                label,
+                keywords,
+                message_ids: members
+                    .iter()
+                    .map(|&index| sources[index].message_id)
+                    .collect(),
+                coordinates: members
+                    .iter()
+                    .map(|&index| layout.coordinates[index])
+                    .collect(),
+            });
+        }
+
+        let model = self.model.clone();
+        self.dal
+            .transaction(|dal| async move {
+                dal.topics().lock_for_user(user_id).await?;
+                dal.topics().delete_for_user(user_id).await?;
+
+                let mut clusters = Vec::with_capacity(topics.len());
+                for (index, topic) in topics.into_iter().enumerate() {
+                    let cluster = dal
+                        .topics()
+                        .create_cluster(
+                            user_id,
+                            index as i32,
+                            &topic.label,
+                            &topic.keywords,
+                            topic.message_ids.len() as i32,
+                            &model,
+                        )
+                        .await?;
+                    dal.topics()
+                        .assign_messages(cluster.id, &topic.message_ids, &topic.coordinates)
+                        .await?;
+                    clusters.push(cluster);
+                }
+                Ok(clusters)
+            })
+            .await
+    }
+
+    /// Ask the configured LLM for a short name for the topic of `samples`. `None`
+    /// when LLM labels are turned off, no provider has a key or the call fails; the
+    /// keywords are used instead.
+    async fn llm_label(&self, samples: &[String]) -> Option<String> {
+        let (provider, model) = label_model(&self.config)?;
+
+        let excerpts = samples
+            .iter()
+            .map(|sample| format!("- {}", sample.replace('\n', " ")))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let request = ChatRequest {
+            model,
+            messages: vec![
+                ChatMessage {
+                    role: "system".to_string(),
+                    content: "You name topics. Reply with a topic name of two to five words \
+                              and nothing else."
+                        .to_string(),
+                },
+                ChatMessage {
+                    role: "user".to_string(),
+                    content: format!("Name the topic these messages share:\n{excerpts}"),
+                },
+            ],
+            temperature: Some(0.2),
+            max_tokens: Some(20),
+            stream: Some(false),
+        };
+
+        let response = match LLMServiceFactory::create_service(&provider, &self.config) {
+            Ok(service) => service.chat_completion(request).await,
+            Err(e) => Err(e),
+        };
+        match response {
+            Ok(response) => clean_label(&response.message.content),
+            Err(e) => {
+                tracing::warn!("Failed to name topic, using keywords: {}", e);
+                None
+            }
+        }
+    }
+}
+
+/// The provider and model that name topics, when LLM labels are on and one has a key
+fn label_model(config: &AppConfig) -> Option<(Provider, String)> {
+    if !config.topic_llm_labels {
+        None
+    } else if !config.openai_api_key.is_empty() {
+        Some((Provider::OpenAI, config.openai_model.clone()))
+    } else if !config.anthropic_api_key.is_empty() {
+        Some((Provider::Anthropic, config.anthropic_model.clone()))
+    } else {
+        None
+    }
+}
+
+fn layout_topics(vectors: &[Vec<f32>]) -> TopicLayout {
+    let mut rng = StdRng::seed_from_u64(CLUSTER_SEED);
+    let (assignments, centroids) = kmeans(vectors, topic_count(vectors.len()), &mut rng);
+    let coordinates = project_2d(vectors, &mut rng);
+    let distances = vectors
+        .iter()
+        .zip(&assignments)
+        .map(|(vector, &cluster)| squared_distance(vector, &centroids[cluster]))
+        .collect();
+
+    TopicLayout {
+        assignments,
+        clusters: centroids.len(),
+        distances,
+        coordinates,
+    }
+}
+
+/// About sqrt(n / 2) topics, the usual rule of thumb, within 2..=MAX_TOPICS
+fn topic_count(points: usize) -> usize {
+    ((points as f64 / 2.0).sqrt().round() as usize).clamp(2, MAX_TOPICS)
+}
+
+/// k-means++ seeding followed by Lloyd iterations. On unit vectors squared distance
+/// ranks like cosine distance. Returns each point's cluster and the centroids.
+fn kmeans(points: &[Vec<f32>], k: usize, rng: &mut StdRng) -> (Vec<usize>, Vec<Vec<f32>>) {
+    let k = k.min(points.len());
+    let mut centroids = vec![points[rng.gen_range(0..points.len())].clone()];
+    let mut nearest: Vec<f32> = points
+        .iter()
+        .map(|point| squared_distance(point, &centroids[0]))
+        .collect();
+
+    // Each further seed is drawn with probability proportional to its squared distance
+    while centroids.len() < k {
+        let total: f32 = nearest.iter().sum();
+        let next = if total > 0.0 {
+            let mut target = rng.gen::<f32>() * total;
+            nearest
+                .iter()
+                .position(|&distance| {
+                    target -= distance;
+                    target <= 0.0
+                })
+                .unwrap_or(points.len() - 1)
+        } else {
+            rng.gen_range(0..points.len())
+        };
+        centroids.push(points[next].clone());
+        for (distance, point) in nearest.iter_mut().zip(points) {
+            *distance = distance.min(squared_distance(point, &points[next]));
+        }
+    }
+
+    let dims = points[0].len();
+    let mut assignments = vec![0; points.len()];
+    for iteration in 0..KMEANS_ITERATIONS {
+        let mut changed = false;
+        for (assignment, point) in assignments.iter_mut().zip(points) {
+            let closest = nearest_centroid(point, &centroids);
+            changed |= closest != *assignment;
+            *assignment = closest;
+        }
+        if iteration > 0 && !changed {
+            break;
+        }
+
+        let mut sums = vec![vec![0.0f32; dims]; k];
+        let mut counts = vec![0usize; k];
+        for (&cluster, point) in assignments.iter().zip(points) {
+            counts[cluster] += 1;
+            for (sum, value) in sums[cluster].iter_mut().zip(point) {
+                *sum += value;
+            }
+        }
+        // An emptied cluster keeps its centroid
+        for ((centroid, sum), count) in centroids.iter_mut().zip(sums).zip(counts) {
+            if count > 0 {
+                *centroid = sum.into_iter().map(|value| value / count as f32).collect();
+            }
+        }
+    }
+
+    (assignments, centroids)
+}
+
+fn nearest_centroid(point: &[f32], centroids: &[Vec<f32>]) -> usize {
+    centroids
+        .iter()
+        .map(|centroid| squared_distance(point, centroid))
+        .enumerate()
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map_or(0, |(index, _)| index)
+}
+
+/// Coordinates on the first two principal components, found by power iteration and
+/// scaled together into -1..=1 so distances on the map stay comparable
+fn project_2d(points: &[Vec<f32>], rng: &mut StdRng) -> Vec<[f32; 2]> {
+    let dims = points[0].len();
+    let mut mean = vec![0.0f32; dims];
+    for point in points {
+        for (sum, value) in mean.iter_mut().zip(point) {
+            *sum += value;
+        }
+    }
+    for value in &mut mean {
+        *value /= points.len() as f32;
+    }
+    let centered: Vec<Vec<f32>> = points
+        .iter()
+        .map(|point| point.iter().zip(&mean).map(|(x, m)| x - m).collect())
+        .collect();
+
+    let first = principal_component(&centered, &[], rng);
+    let second = principal_component(&centered, &[&first], rng);
+    let mut coordinates: Vec<[f32; 2]> = centered
+        .iter()
+        .map(|point| [dot(point, &first), dot(point, &second)])
+        .collect();
+
+    let scale = coordinates
+        .iter()
+        .flatten()
+        .fold(0.0f32, |max, value| max.max(value.abs()));
+    if scale > 0.0 {
+        for point in &mut coordinates {
+            point[0] /= scale;
+            point[1] /= scale;
+        }
+    }
+    coordinates
+}
+
+/// The direction of greatest variance orthogonal to `previous`; all zeros when the
+/// points do not vary in any such direction
+fn principal_component(centered: &[Vec<f32>], previous: &[&[f32]], rng: &mut StdRng) -> Vec<f32> {
+    let dims = centered[0].len();
+    let mut component: Vec<f32> = (0..dims).map(|_| rng.gen::<f32>() - 0.5).collect();
+
+    for _ in 0..PCA_ITERATIONS {
+        orthogonalize(&mut component, previous);
+        component = normalized(&component);
+        // Multiply by the covariance matrix without building it: Xᵀ(Xv)
+        let mut next = vec![0.0f32; dims];
+        for point in centered {
+            let projection = dot(point, &component);
+            for (sum, value) in next.iter_mut().zip(point) {
+                *sum += projection * value;
+            }
+        }
+        component = next;
+    }
+
+    orthogonalize(&mut component, previous);
+    normalized(&component)
+}
+
+fn orthogonalize(vector: &mut [f32], basis: &[&[f32]]) {
+    for direction in basis {
+        let projection = dot(vector, direction);
+        for (value, d) in vector.iter_mut().zip(direction.iter()) {
+            *value -= projection * d;
+        }
+    }
+}
+
+/// Terms that set each group of texts apart: how many of the group's texts use a term,
+/// weighted down when other groups use it too
+fn topic_keywords(groups: &[Vec<&str>]) -> Vec<Vec<String>> {
+    let counts: Vec<HashMap<String, usize>> = groups
+        .iter()
+        .map(|texts| {
+            let mut counts = HashMap::new();
+            for text in texts {
+                for term in terms(text) {
+                    *counts.entry(term).or_insert(0) += 1;
+                }
+            }
+            counts
+        })
+        .collect();
+
+    let mut groups_using: HashMap<&str, usize> = HashMap::new();
+    for group in &counts {
+        for term in group.keys() {
+            *groups_using.entry(term.as_str()).or_insert(0) += 1;
+        }
+    }
+
+    counts
+        .iter()
+        .map(|group| {
+            let mut scored: Vec<(&String, f32)> = group
+                .iter()
+                .map(|(term, &count)| {
+                    let spread = groups.len() as f32 / groups_using[term.as_str()] as f32;
+                    (term, count as f32 * (1.0 + spread).ln())
+                })
+                .collect();
+            scored.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+            scored
+                .into_iter()
+                .take(KEYWORDS_PER_TOPIC)
+                .map(|(term, _)| term.clone())
+                .collect()
+        })
+        .collect()
+}
+
+/// The distinct lowercase words of a text that can name a topic
+fn terms(text: &str) -> HashSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.chars().count() >= 4 && !word.chars().all(|c| c.is_numeric()))
+        .map(str::to_lowercase)
+        .filter(|word| !STOPWORDS.contains(&word.as_str()))
+        .collect()
+}
+
+/// The first line of an LLM reply without quotes, a label prefix or a final period
+fn clean_label(reply: &str) -> Option<String> {
+    let line = reply.lines().map(str::trim).find(|line| !line.is_empty())?;
+    let line = line
+        .strip_prefix("Topic:")
+        .or_else(|| line.strip_prefix("topic:"))
+        .unwrap_or(line);
+    let label = line
+        .trim()
+        .trim_end_matches('.')
+        .trim_matches(|c| matches!(c, '"' | '\'' | '*' | '`'))
+        .trim_end_matches('.')
+        .trim();
+    if label.is_empty() {
+        return None;
+    }
+
+    Some(label.chars().take(MAX_LABEL_CHARS).collect())
+}
+
+fn normalized(vector: &[f32]) -> Vec<f32> {
+    let norm = dot(vector, vector).sqrt();
+    if norm > 0.0 {
+        vector.iter().map(|value| value / norm).collect()
+    } else {
+        vector.to_vec()
+    }
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+fn squared_distance(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| (x - y) * (x - y)).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Two tight groups around orthogonal directions
+    fn two_groups() -> Vec<Vec<f32>> {
+        let mut rng = StdRng::seed_from_u64(7);
+        (0..20)
+            .map(|i| {
+                let mut point = vec![0.0f32; 8];
+                point[if i < 10 { 0 } else { 4 }] = 1.0;
+                for value in &mut point {
+                    *value += rng.gen::<f32>() * 0.05;
+                }
+                normalized(&point)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_topic_count_grows_with_messages_within_bounds() {
+        assert_eq!(topic_count(10), 2);
+        assert_eq!(topic_count(50), 5);
+        assert_eq!(topic_count(2000), MAX_TOPICS);
+    }
+
+    #[test]
+    fn test_kmeans_separates_groups() {
+        let points = two_groups();
+        let mut rng = StdRng::seed_from_u64(CLUSTER_SEED);
+        let (assignments, centroids) = kmeans(&points, 2, &mut rng);
+
+        assert_eq!(centroids.len(), 2);
+        assert!(assignments[..10].iter().all(|&a| a == assignments[0]));
+        assert!(assignments[10..].iter().all(|&a| a == assignments[10]));
+        assert_ne!(assignments[0], assignments[10]);
+    }
+
+    #[test]
+    fn test_kmeans_handles_identical_points() {
+        let points = vec![vec![1.0, 0.0]; 5];
+        let mut rng = StdRng::seed_from_u64(CLUSTER_SEED);
+        let (assignments, centroids) = kmeans(&points, 3, &mut rng);
+
+        assert_eq!(assignments.len(), 5);
+        assert_eq!(centroids.len(), 3);
+    }
+
+    #[test]
+    fn test_project_2d_keeps_groups_apart_within_unit_range() {
+        let points = two_groups();
+        let mut rng = StdRng::seed_from_u64(CLUSTER_SEED);
+        let coordinates = project_2d(&points, &mut rng);
+
+        assert_eq!(coordinates.len(), points.len());
+        assert!(coordinates
+            .iter()
+            .flatten()
+            .all(|value| (-1.0..=1.0).contains(value)));
+        // The groups differ most along the first component, so it splits them by sign
+        let side = coordinates[0][0].signum();
+        assert!(coordinates[..10].iter().all(|c| c[0].signum() == side));
+        assert!(coordinates[10..].iter().all(|c| c[0].signum() == -side));
+    }
+
+    #[test]
+    fn test_project_2d_of_identical_points_is_the_origin() {
+        let points = vec![vec![0.6, 0.8]; 4];
+        let mut rng = StdRng::seed_from_u64(CLUSTER_SEED);
+
+        assert!(project_2d(&points, &mut rng)
+            .iter()
+            .all(|c| c[0] == 0.0 && c[1] == 0.0));
+    }
+
+    #[test]
+    fn test_topic_keywords_prefer_distinctive_terms() {
+        let groups = vec![
+            vec![
+                "Kubernetes ingress returns 404 with this config",
+                "How do I debug a Kubernetes ingress controller?",
+            ],
+            vec![
+                "My sourdough starter is not rising with this flour",
+                "Sourdough hydration for a denser crumb with rye flour",
+            ],
+        ];
+
+        let keywords = topic_keywords(&groups);
+
+        assert_eq!(keywords[0][..2], ["ingress", "kubernetes"]);
+        assert_eq!(keywords[1][..2], ["flour", "sourdough"]);
+        assert!(!keywords
+            .iter()
+            .flatten()
+            .any(|term| term == "with" || term == "this"));
+    }
+
+    #[test]
+    fn test_clean_label() {
+        assert_eq!(
+            clean_label("\"Kubernetes networking\".\n"),
+            Some("Kubernetes networking".to_string())
+        );
+        assert_eq!(
+            clean_label("\nTopic: Sourdough baking"),
+            Some("Sourdough baking".to_string())
+        );
+        assert_eq!(clean_label("  \n"), None);
+        assert_eq!(
+            clean_label(&"a".repeat(100)).map(|label| label.len()),
+            Some(MAX_LABEL_CHARS)
+        );
+    }
+
+    #[test]
+    fn test_llm_labels_can_be_turned_off() {
+        let mut config = AppConfig {
+            openai_api_key: "sk-test".to_string(),
+            ..AppConfig::default()
+        };
+        assert!(matches!(label_model(&config), Some((Provider::OpenAI, _))));
+
+        config.topic_llm_labels = false;
+        assert!(label_model(&config).is_none());
+    }
+}
@@ -0,0 +1,173 @@
+use anyhow::Result;
+
+use crate::{
+    models::{
+        BookmarkParams, ConversationRole, CreateConversationRequest, CreateMessageRequest,
+        MessageRole, UpdateAnnotationRequest,
+    },
+    repositories::Repository,
+    services::conversation::ConversationService,
+    tests::{create_user, test_dal},
+};
+
+#[tokio::test]
+#[ignore = "needs DATABASE_URL"]
+async fn test_annotations_survive_branch_switches_and_list_as_bookmarks() -> Result<()> {
+    let dal = test_dal().await?;
+    let conversation_service = ConversationService::new(dal.clone());
+
+    let researcher = create_user(&dal, "researcher").await?;
+    let colleague = create_user(&dal, "colleague").await?;
+
+    let conversation = conversation_service
+        .create_conversation(
+            researcher.id,
+            CreateConversationRequest {
+                title: Some("Interview transcript".to_string()),
+                model: "gpt-4".to_string(),
+                provider: None,
+                metadata: None,
+                project_id: None,
+            },
+        )
+        .await?;
+    dal.conversation_members()
+        .upsert(
+            conversation.id,
+            colleague.id,
+            ConversationRole::Viewer,
+            researcher.id,
+        )
+        .await?;
+
+    let question = dal
+        .messages()
+        .create_from_request(CreateMessageRequest {
+            conversation_id: conversation.id,
+            parent_id: None,
+            role: MessageRole::User,
+            content: "What causes the outage?".to_string(),
+            metadata: None,
+            author_id: Some(researcher.id),
+        })
+        .await?;
+    let answer = dal
+        .messages()
+        .create_from_request(CreateMessageRequest {
+            conversation_id: conversation.id,
+            parent_id: Some(question.id),
+            role: MessageRole::Assistant,
+            content: "A retry storm overloads the queue.".to_string(),
+            metadata: None,
+            author_id: None,
+        })
+        .await?;
+
+    let annotations = dal.annotations();
+    let highlight = annotations
+        .create(
+            answer.id,
+            researcher.id,
+            Some((2, 13)),
+            Some("retry storm"),
+            Some("Matches the postmortem"),
+            Some("key finding"),
+        )
+        .await?;
+    assert_eq!(highlight.conversation_id, conversation.id);
+    assert_eq!(highlight.quote.as_deref(), Some("retry storm"));
+    annotations
+        .create(
+            question.id,
+            colleague.id,
+            None,
+            None,
+            Some("Ask about the queue"),
+            Some("follow up"),
+        )
+        .await?;
+
+    // Switching to another branch leaves the annotation in place
+    let alternative = dal
+        .messages()
+        .create_branch(
+            question.id,
+            "A bad deploy.".to_string(),
+            MessageRole::Assistant,
+            None,
+        )
+        .await?;
+    dal.messages().switch_to_branch(alternative.id).await?;
+    dal.messages().switch_to_branch(answer.id).await?;
+    let listed = annotations.find_by_conversation_id(conversation.id).await?;
+    assert_eq!(listed.len(), 2);
+    assert_eq!(listed[0].message_id, question.id);
+    assert_eq!(listed[1].id, highlight.id);
+
+    // Bookmarks only list the user's own annotations, filtered by label or note text
+    let bookmarks = annotations
+        .find_bookmarks(researcher.id, &BookmarkParams::default(), 50, 0)
+        .await?;
+    assert_eq!(bookmarks.len(), 1);
+    assert_eq!(
+        bookmarks[0].conversation_title.as_deref(),
+        Some("Interview transcript")
+    );
+    let search = |q: &str| BookmarkParams {
+        q: Some(q.to_string()),
+        ..Default::default()
+    };
+    assert_eq!(
+        annotations
+            .find_bookmarks(researcher.id, &search("postmortem"), 50, 0)
+            .await?
+            .len(),
+        1
+    );
+    assert!(annotations
+        .find_bookmarks(researcher.id, &search("100%"), 50, 0)
+        .await?
+        .is_empty());
+    let by_label = BookmarkParams {
+        label: Some("Follow Up".to_string()),
+        ..Default::default()
+    };
+    assert_eq!(
+        annotations
+            .find_bookmarks(colleague.id, &by_label, 50, 0)
+            .await?
+            .len(),
+        1
+    );
+
+    // Clearing the note keeps the label and highlight
+    let updated = annotations
+        .update(
+            highlight.id,
+            &UpdateAnnotationRequest {
+                note: Some(String::new()),
+                label: None,
+            },
+        )
+        .await?
+        .unwrap();
+    assert_eq!(updated.note, None);
+    assert_eq!(updated.label.as_deref(), Some("key finding"));
+    assert_eq!(updated.start_offset, Some(2));
+
+    // Losing access to the conversation hides its bookmarks
+    dal.conversation_members()
+        .remove(conversation.id, colleague.id)
+        .await?;
+    assert!(annotations
+        .find_bookmarks(colleague.id, &BookmarkParams::default(), 50, 0)
+        .await?
+        .is_empty());
+
+    assert!(annotations.delete(highlight.id).await?);
+    assert!(annotations.find_by_id(highlight.id).await?.is_none());
+
+    dal.users().delete(researcher.id).await?;
+    dal.users().delete(colleague.id).await?;
+    Ok(())
+}
@@ -3,25 +3,40 @@ use uuid::Uuid;
 
 use crate::{
     database::Database,
-    models::{CreateMessageRequest, MessageRole},
-    repositories::{message::MessageRepository, Repository},
+    models::{BranchKind, CreateConversationRequest, CreateMessageRequest, Message, MessageRole},
+    repositories::{
+        conversation::ConversationRepository, message::MessageRepository, user::UserRepository,
+        Repository,
+    },
+    services::DataAccessLayer,
+    tests::{create_user, test_database},
 };
 
+/// A conversation owned by a fresh user. Deleting the returned user removes both.
+async fn create_conversation(database: &Database) -> Result<(Uuid, Uuid)> {
+    let user = create_user(&DataAccessLayer::new(database.clone()), "brancher").await?;
+    let conversation = ConversationRepository::new(database.clone())
+        .create_from_request(
+            user.id,
+            CreateConversationRequest {
+                title: Some("Branching".to_string()),
+                model: "gpt-4".to_string(),
+                provider: None,
+                metadata: None,
+                project_id: None,
+            },
+        )
+        .await?;
+    Ok((conversation.id, user.id))
+}
+
 #[tokio::test]
+#[ignore = "needs DATABASE_URL"]
 async fn test_message_tree_operations() -> Result<()> {
-    // Skip test if database is not available
-    if std::env::var("DATABASE_URL").is_err() {
-        return Ok(());
-    }
-
-    let database_url = std::env::var("DATABASE_URL")
-        .expect("DATABASE_URL environment variable is required for tests. Please set DATABASE_URL to a valid PostgreSQL connection string.");
-
-    let database = Database::new(&database_url).await?;
-    let message_repo = MessageRepository::new(database);
+    let database = test_database().await?;
+    let message_repo = MessageRepository::new(database.clone());
 
-    // Create a conversation ID (in real usage this would come from conversation table)
-    let conversation_id = Uuid::new_v4();
+    let (conversation_id, user_id) = create_conversation(&database).await?;
 
     // Create root message (user)
     let root_request = CreateMessageRequest {
@@ -30,6 +45,7 @@ async fn test_message_tree_operations() -> Result<()> {
         role: MessageRole::User,
         content: "What is the capital of France?".to_string(),
         metadata: None,
+        author_id: None,
     };
 
     let root_message = message_repo.create_from_request(root_request).await?;
@@ -42,6 +58,7 @@ async fn test_message_tree_operations() -> Result<()> {
         role: MessageRole::Assistant,
         content: "The capital of France is Paris.".to_string(),
         metadata: None,
+        author_id: None,
     };
 
     let assistant1_message = message_repo.create_from_request(assistant1_request).await?;
@@ -57,6 +74,7 @@ async fn test_message_tree_operations() -> Result<()> {
         role: MessageRole::User,
         content: "Tell me more about Paris.".to_string(),
         metadata: None,
+        author_id: None,
     };
 
     let user2_message = message_repo.create_from_request(user2_request).await?;
@@ -69,6 +87,7 @@ async fn test_message_tree_operations() -> Result<()> {
         role: MessageRole::Assistant,
         content: "Paris is the most populous city of France.".to_string(),
         metadata: None,
+        author_id: None,
     };
 
     let assistant2_message = message_repo.create_from_request(assistant2_request).await?;
@@ -82,6 +101,7 @@ async fn test_message_tree_operations() -> Result<()> {
         .edit_message_and_branch(
             user2_message.id,
             "What is the population of Paris?".to_string(),
+            None,
         )
         .await?;
 
@@ -151,23 +171,17 @@ async fn test_message_tree_operations() -> Result<()> {
     }
 
     println!("✅ All branching tests passed!");
+    UserRepository::new(database).delete(user_id).await?;
     Ok(())
 }
 
 #[tokio::test]
+#[ignore = "needs DATABASE_URL"]
 async fn test_message_thread_traversal() -> Result<()> {
-    // Skip test if database is not available
-    if std::env::var("DATABASE_URL").is_err() {
-        return Ok(());
-    }
-
-    let database_url = std::env::var("DATABASE_URL")
-        .expect("DATABASE_URL environment variable is required for tests. Please set DATABASE_URL to a valid PostgreSQL connection string.");
+    let database = test_database().await?;
+    let message_repo = MessageRepository::new(database.clone());
 
-    let database = Database::new(&database_url).await?;
-    let message_repo = MessageRepository::new(database);
-
-    let conversation_id = Uuid::new_v4();
+    let (conversation_id, user_id) = create_conversation(&database).await?;
 
     // Create a simple chain: User -> Assistant -> User
     let user1_request = CreateMessageRequest {
@@ -176,6 +190,7 @@ async fn test_message_thread_traversal() -> Result<()> {
         role: MessageRole::User,
         content: "Hello".to_string(),
         metadata: None,
+        author_id: None,
     };
 
     let user1_message = message_repo.create_from_request(user1_request).await?;
@@ -186,6 +201,7 @@ async fn test_message_thread_traversal() -> Result<()> {
         role: MessageRole::Assistant,
         content: "Hi there!".to_string(),
         metadata: None,
+        author_id: None,
     };
 
     let assistant1_message = message_repo.create_from_request(assistant1_request).await?;
@@ -196,6 +212,7 @@ async fn test_message_thread_traversal() -> Result<()> {
         role: MessageRole::User,
         content: "How are you?".to_string(),
         metadata: None,
+        author_id: None,
     };
 
     let user2_message = message_repo.create_from_request(user2_request).await?;
@@ -212,5 +229,222 @@ async fn test_message_thread_traversal() -> Result<()> {
     assert_eq!(thread[2].content, "How are you?");
 
     println!("✅ Thread traversal test passed!");
+    UserRepository::new(database).delete(user_id).await?;
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "needs DATABASE_URL"]
+async fn test_edit_history_and_soft_delete() -> Result<()> {
+    let database = test_database().await?;
+    let message_repo = MessageRepository::new(database.clone());
+
+    let (conversation_id, user_id) = create_conversation(&database).await?;
+    let message =
+        |parent_id: Option<Uuid>, role: MessageRole, content: &str| CreateMessageRequest {
+            conversation_id,
+            parent_id,
+            role,
+            content: content.to_string(),
+            metadata: None,
+            author_id: None,
+        };
+
+    let question = message_repo
+        .create_from_request(message(None, MessageRole::User, "Plan a trip"))
+        .await?;
+    let answer = message_repo
+        .create_from_request(message(
+            Some(question.id),
+            MessageRole::Assistant,
+            "Where to?",
+        ))
+        .await?;
+    let follow_up = message_repo
+        .create_from_request(message(Some(answer.id), MessageRole::User, "Lisbon"))
+        .await?;
+    let regenerated = message_repo
+        .create_branch(answer.id, "Porto".to_string(), MessageRole::User, None)
+        .await?;
+
+    // Editing twice builds a lineage back to the first version
+    let first_edit = message_repo
+        .edit_message_and_branch(follow_up.id, "Lisbon in May".to_string(), None)
+        .await?;
+    let second_edit = message_repo
+        .edit_message_and_branch(first_edit.id, "Lisbon in June".to_string(), None)
+        .await?;
+    assert_eq!(first_edit.edited_from, Some(follow_up.id));
+    assert_eq!(second_edit.edited_from, Some(first_edit.id));
+    assert!(second_edit.is_active);
+
+    let history = message_repo.find_edit_history(first_edit.id).await?;
+    assert_eq!(
+        history.iter().map(|m| m.id).collect::<Vec<_>>(),
+        vec![follow_up.id, first_edit.id, second_edit.id]
+    );
+
+    // The original is off the selected branch but neither deleted nor gone
+    let original = message_repo.find_by_id(follow_up.id).await?.unwrap();
+    assert!(!original.is_active);
+    assert!(original.deleted_at.is_none());
+
+    // Branch listings tell edits apart from alternatives
+    let siblings = message_repo.find_message_branches(answer.id).await?;
+    let kinds: Vec<(Uuid, BranchKind)> = siblings
+        .iter()
+        .map(|m| m.id)
+        .zip(BranchKind::classify(&siblings))
+        .collect();
+    assert_eq!(
+        kinds,
+        vec![
+            (follow_up.id, BranchKind::Original),
+            (regenerated.id, BranchKind::Alternative),
+            (first_edit.id, BranchKind::Edited),
+            (second_edit.id, BranchKind::Edited),
+        ]
+    );
+
+    // Deleting a message also deletes its replies, without touching branch selection
+    let reply = message_repo
+        .create_from_request(message(
+            Some(regenerated.id),
+            MessageRole::Assistant,
+            "Porto it is",
+        ))
+        .await?;
+    message_repo.switch_to_branch(second_edit.id).await?;
+    assert!(message_repo.delete(regenerated.id).await?);
+    assert!(!message_repo.delete(regenerated.id).await?);
+    let deleted_reply = message_repo.find_by_id(reply.id).await?.unwrap();
+    assert!(deleted_reply.deleted_at.is_some());
+    assert_eq!(
+        message_repo.find_message_branches(answer.id).await?.len(),
+        3
+    );
+    assert!(message_repo
+        .find_conversation_tree(conversation_id)
+        .await?
+        .iter()
+        .all(|m| m.id != regenerated.id && m.id != reply.id));
+    assert!(message_repo.switch_to_branch(regenerated.id).await.is_err());
+
+    let active_thread = message_repo
+        .find_active_conversation_thread(conversation_id)
+        .await?;
+    assert_eq!(active_thread.last().map(|m| m.id), Some(second_edit.id));
+
+    UserRepository::new(database).delete(user_id).await?;
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "needs DATABASE_URL"]
+async fn test_active_leaf_selects_thread() -> Result<()> {
+    let database = test_database().await?;
+    let message_repo = MessageRepository::new(database.clone());
+    let (conversation_id, user_id) = create_conversation(&database).await?;
+
+    // A thread deeper than the old recursion limit, written in one batch with the
+    // second to last message marked as the end of the selected branch
+    let mut parent_id = None;
+    let messages: Vec<Message> = (0..150)
+        .map(|i| {
+            let id = Uuid::new_v4();
+            let message = Message {
+                id,
+                conversation_id,
+                parent_id,
+                role: if i % 2 == 0 {
+                    MessageRole::User
+                } else {
+                    MessageRole::Assistant
+                },
+                content: format!("Message {}", i),
+                tokens_used: None,
+                created_at: chrono::Utc::now() + chrono::Duration::milliseconds(i),
+                is_active: i < 149,
+                metadata: serde_json::json!({}),
+                author_id: None,
+                edited_from: None,
+                deleted_at: None,
+            };
+            parent_id = Some(id);
+            message
+        })
+        .collect();
+    assert_eq!(message_repo.create_many(&messages).await?, 150);
+
+    let active_thread = message_repo
+        .find_active_conversation_thread(conversation_id)
+        .await?;
+    assert_eq!(active_thread.len(), 149);
+    assert_eq!(active_thread[0].id, messages[0].id);
+    assert_eq!(active_thread[148].id, messages[148].id);
+    assert_eq!(
+        message_repo.count_by_conversation(conversation_id).await?,
+        149
+    );
+    assert_eq!(
+        message_repo
+            .find_conversation_thread(messages[149].id)
+            .await?
+            .len(),
+        150
+    );
+    assert_eq!(
+        message_repo
+            .find_conversation_tree(conversation_id)
+            .await?
+            .len(),
+        150
+    );
+    let last = message_repo.find_by_id(messages[149].id).await?.unwrap();
+    assert!(!last.is_active);
+
+    // Replies without a parent given by the caller continue from the selected leaf
+    assert_eq!(
+        message_repo.find_active_leaf_id(conversation_id).await?,
+        Some(messages[148].id)
+    );
+
+    // Concurrent switches each replace the leaf in one write, so a full path always remains
+    let alternatives: Vec<Message> = futures::future::try_join_all((0..8).map(|i| {
+        message_repo.create_branch(
+            messages[99].id,
+            format!("Alternative {}", i),
+            MessageRole::User,
+            None,
+        )
+    }))
+    .await?;
+    futures::future::try_join_all(
+        alternatives
+            .iter()
+            .map(|alternative| message_repo.switch_to_branch(alternative.id)),
+    )
+    .await?;
+
+    let active_thread = message_repo
+        .find_active_conversation_thread(conversation_id)
+        .await?;
+    assert_eq!(active_thread.len(), 101);
+    assert!(active_thread
+        .windows(2)
+        .all(|pair| pair[1].parent_id == Some(pair[0].id)));
+    let leaf = active_thread.last().unwrap();
+    assert!(alternatives.iter().any(|a| a.id == leaf.id));
+    let siblings = message_repo.find_message_branches(messages[99].id).await?;
+    assert_eq!(siblings.iter().filter(|m| m.is_active).count(), 1);
+
+    // Deleting the selected leaf leaves its parent at the end of the active thread
+    assert!(message_repo.delete(leaf.id).await?);
+    assert_eq!(
+        message_repo.find_active_leaf_id(conversation_id).await?,
+        Some(messages[99].id)
+    );
+
+    UserRepository::new(database).delete(user_id).await?;
     Ok(())
 }
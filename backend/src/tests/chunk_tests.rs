@@ -0,0 +1,108 @@
+use anyhow::Result;
+
+use crate::{
+    config::{AppConfig, EmbeddingConfig},
+    models::{CreateConversationRequest, CreateMessageRequest, MessageRole},
+    repositories::Repository,
+    services::embedding::EmbeddingService,
+    tests::{create_user, embed_message, test_dal},
+};
+
+#[tokio::test]
+#[ignore = "needs DATABASE_URL"]
+async fn test_long_messages_are_embedded_in_overlapping_chunks() -> Result<()> {
+    let dal = test_dal().await?;
+    let user = create_user(&dal, "chunks").await?;
+    let conversation = dal
+        .conversations()
+        .create_from_request(
+            user.id,
+            CreateConversationRequest {
+                title: Some("Long answer".to_string()),
+                model: "gpt-4".to_string(),
+                provider: None,
+                metadata: None,
+                project_id: None,
+            },
+        )
+        .await?;
+
+    let content = [
+        "Connection pools keep database connections open between requests. ",
+        "Résumé parsing extracts skills from uploaded documents. ",
+        "Sourdough needs a mature starter and a long, cold proof. ",
+    ]
+    .map(|sentence| sentence.repeat(30))
+    .join("\n\n");
+    let message = dal
+        .messages()
+        .create_from_request(CreateMessageRequest {
+            conversation_id: conversation.id,
+            parent_id: None,
+            role: MessageRole::Assistant,
+            content: content.clone(),
+            metadata: None,
+            author_id: None,
+        })
+        .await?;
+
+    let service = EmbeddingService::new(
+        AppConfig {
+            embedding: EmbeddingConfig {
+                provider: "local".to_string(),
+                model_path: None,
+                model: "hashed-ngrams-v1".to_string(),
+                dimensions: 384,
+                api_base: None,
+                api_key: String::new(),
+            },
+            ..AppConfig::default()
+        },
+        dal.clone(),
+    )?;
+    embed_message(&service, &dal, message.id, &content).await?;
+
+    // Chunks cover the message in order, overlap, and point back into it
+    let chunks = dal.embeddings().find_chunks(message.id).await?;
+    let chars: Vec<char> = content.chars().collect();
+    assert!(chunks.len() > 2);
+    assert_eq!(chunks[0].start_offset, 0);
+    assert_eq!(chunks.last().unwrap().end_offset as usize, chars.len());
+    for (index, chunk) in chunks.iter().enumerate() {
+        assert_eq!(chunk.chunk_index, index as i32);
+        assert_eq!(chunk.model, "hashed-ngrams-v1");
+        assert_eq!(chunk.dimensions, 384);
+        assert_eq!(chunk.embedding.len(), 384);
+        assert_eq!(
+            chunk.content,
+            chars[chunk.start_offset as usize..chunk.end_offset as usize]
+                .iter()
+                .collect::<String>()
+        );
+    }
+    for pair in chunks.windows(2) {
+        assert!(pair[1].start_offset < pair[0].end_offset);
+    }
+
+    // The message keeps one vector of its own for message-level features
+    let embedding = dal
+        .embeddings()
+        .find_by_message_id(message.id)
+        .await?
+        .unwrap();
+    assert_eq!(embedding.model, "hashed-ngrams-v1");
+    assert_eq!(embedding.dimensions, 384);
+
+    // Re-embedding replaces the chunks instead of adding to them
+    embed_message(&service, &dal, message.id, &content).await?;
+    assert_eq!(
+        dal.embeddings().find_chunks(message.id).await?.len(),
+        chunks.len()
+    );
+
+    assert!(dal.embeddings().delete_by_message_id(message.id).await?);
+    assert!(dal.embeddings().find_chunks(message.id).await?.is_empty());
+
+    dal.users().delete(user.id).await?;
+    Ok(())
+}
@@ -0,0 +1,164 @@
+use anyhow::Result;
+
+use crate::{
+    error::AppError,
+    models::{ConversationListParams, ConversationRole, CreateConversationRequest, MessageRole},
+    repositories::Repository,
+    services::{
+        authorization::AuthorizationService, chat::ChatService, conversation::ConversationService,
+    },
+    tests::{create_user, test_dal},
+};
+
+#[tokio::test]
+#[ignore = "needs DATABASE_URL"]
+async fn test_conversation_members_and_roles() -> Result<()> {
+    let dal = test_dal().await?;
+    let authorization = AuthorizationService::new(dal.clone());
+    let conversation_service = ConversationService::new(dal.clone());
+    let chat_service = ChatService::new(dal.clone());
+
+    let owner = create_user(&dal, "owner").await?;
+    let editor = create_user(&dal, "editor").await?;
+    let viewer = create_user(&dal, "viewer").await?;
+    let stranger = create_user(&dal, "stranger").await?;
+
+    let conversation = conversation_service
+        .create_conversation(
+            owner.id,
+            CreateConversationRequest {
+                title: Some("Team notes".to_string()),
+                model: "gpt-4".to_string(),
+                provider: None,
+                metadata: None,
+                project_id: None,
+            },
+        )
+        .await?;
+
+    let members = dal.conversation_members();
+    members
+        .upsert(
+            conversation.id,
+            editor.id,
+            ConversationRole::Editor,
+            owner.id,
+        )
+        .await?;
+    members
+        .upsert(
+            conversation.id,
+            viewer.id,
+            ConversationRole::Viewer,
+            owner.id,
+        )
+        .await?;
+    assert_eq!(
+        members
+            .find_by_conversation_id(conversation.id)
+            .await?
+            .len(),
+        2
+    );
+
+    assert_eq!(
+        authorization.role(&conversation, owner.id).await?,
+        Some(ConversationRole::Owner)
+    );
+    assert_eq!(
+        authorization.role(&conversation, viewer.id).await?,
+        Some(ConversationRole::Viewer)
+    );
+    assert_eq!(authorization.role(&conversation, stranger.id).await?, None);
+    assert!(matches!(
+        authorization
+            .require(conversation.id, viewer.id, ConversationRole::Editor)
+            .await,
+        Err(AppError::Forbidden(_))
+    ));
+    assert!(matches!(
+        authorization
+            .require(conversation.id, stranger.id, ConversationRole::Viewer)
+            .await,
+        Err(AppError::Forbidden(_))
+    ));
+
+    // Editors can write, and their messages record who wrote them
+    let sent = chat_service
+        .send_message(editor.id, conversation.id, "Adding my notes".to_string())
+        .await?;
+    let message = dal.messages().find_by_id(sent.id).await?.unwrap();
+    assert_eq!(message.author_id, Some(editor.id));
+    let branch = chat_service
+        .create_message_branch(
+            editor.id,
+            message.id,
+            "A follow-up".to_string(),
+            MessageRole::User,
+        )
+        .await?;
+    assert_eq!(branch.author_id, Some(editor.id));
+
+    // Viewers can read but not write; the error keeps its status through the service
+    assert_eq!(
+        chat_service
+            .get_conversation_messages(viewer.id, conversation.id)
+            .await?
+            .len(),
+        2
+    );
+    let denied = chat_service
+        .send_message(viewer.id, conversation.id, "Let me in".to_string())
+        .await
+        .unwrap_err();
+    assert!(matches!(AppError::from(denied), AppError::Forbidden(_)));
+    assert!(conversation_service
+        .get_conversation_with_messages(conversation.id, stranger.id)
+        .await?
+        .is_none());
+
+    // Shared conversations show up in the member's list
+    let listed = conversation_service
+        .get_user_conversations(editor.id, &ConversationListParams::default(), None)
+        .await?;
+    assert_eq!(
+        listed
+            .conversations
+            .iter()
+            .map(|c| c.id)
+            .collect::<Vec<_>>(),
+        vec![conversation.id]
+    );
+    assert_eq!(listed.total, 1);
+
+    // Only the owner can delete, and a trashed conversation disappears for members
+    assert!(
+        !conversation_service
+            .delete_conversation(conversation.id, editor.id)
+            .await?
+    );
+    conversation_service
+        .trash_conversation(conversation.id, owner.id)
+        .await?;
+    let listed = conversation_service
+        .get_user_conversations(editor.id, &ConversationListParams::default(), None)
+        .await?;
+    assert_eq!(listed.total, 0);
+    assert!(matches!(
+        authorization
+            .require(conversation.id, editor.id, ConversationRole::Viewer)
+            .await,
+        Err(AppError::NotFound(_))
+    ));
+    conversation_service
+        .restore_conversation(conversation.id, owner.id)
+        .await?;
+
+    assert!(members.remove(conversation.id, viewer.id).await?);
+    assert_eq!(authorization.role(&conversation, viewer.id).await?, None);
+
+    for user in [owner, editor, viewer, stranger] {
+        dal.users().delete(user.id).await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,131 @@
+use anyhow::Result;
+
+use crate::{
+    error::AppError,
+    models::{CreateConversationRequest, CreateMessageRequest, DiffOp, MessageRole},
+    repositories::Repository,
+    services::{compare::CompareService, conversation::ConversationService},
+    tests::{create_user, test_dal},
+};
+
+#[tokio::test]
+#[ignore = "needs DATABASE_URL"]
+async fn test_compare_sibling_answers_and_divergent_paths() -> Result<()> {
+    let dal = test_dal().await?;
+    let conversation_service = ConversationService::new(dal.clone());
+    let compare_service = CompareService::new(dal.clone());
+
+    let user = create_user(&dal, "compare").await?;
+    let conversation = conversation_service
+        .create_conversation(
+            user.id,
+            CreateConversationRequest {
+                title: Some("Model bake-off".to_string()),
+                model: "gpt-4".to_string(),
+                provider: None,
+                metadata: None,
+                project_id: None,
+            },
+        )
+        .await?;
+
+    let question = dal
+        .messages()
+        .create_from_request(CreateMessageRequest {
+            conversation_id: conversation.id,
+            parent_id: None,
+            role: MessageRole::User,
+            content: "Summarise the incident".to_string(),
+            metadata: None,
+            author_id: Some(user.id),
+        })
+        .await?;
+    let gpt_answer = dal
+        .messages()
+        .create_from_request(CreateMessageRequest {
+            conversation_id: conversation.id,
+            parent_id: Some(question.id),
+            role: MessageRole::Assistant,
+            content: "The database ran out of connections".to_string(),
+            metadata: Some(serde_json::json!({
+                "model": "gpt-4",
+                "latency_ms": 1200,
+                "usage": { "prompt_tokens": 12, "completion_tokens": 7, "total_tokens": 19 }
+            })),
+            author_id: None,
+        })
+        .await?;
+    let claude_answer = dal
+        .messages()
+        .create_branch(
+            question.id,
+            "The database ran out of memory".to_string(),
+            MessageRole::Assistant,
+            None,
+        )
+        .await?;
+
+    let comparison = compare_service
+        .compare_branches(user.id, conversation.id, gpt_answer.id, claude_answer.id)
+        .await?;
+    assert_eq!(comparison.common_ancestor_id, Some(question.id));
+    assert_eq!(comparison.left.len(), 1);
+    assert_eq!(comparison.right.len(), 1);
+    let answers = comparison.answers.expect("sibling answers are diffed");
+    assert!(answers
+        .diff
+        .iter()
+        .any(|s| s.op == DiffOp::Delete && s.text == "connections"));
+    assert!(answers
+        .diff
+        .iter()
+        .any(|s| s.op == DiffOp::Insert && s.text == "memory"));
+    assert_eq!(answers.left.total_tokens, Some(19));
+    assert_eq!(answers.left.latency_ms, Some(1200));
+    assert!(answers.left.cost_cents.is_some());
+    assert_eq!(answers.right.total_tokens, None);
+
+    // Longer paths are returned side by side without an answer diff
+    let follow_up = dal
+        .messages()
+        .create_from_request(CreateMessageRequest {
+            conversation_id: conversation.id,
+            parent_id: Some(claude_answer.id),
+            role: MessageRole::User,
+            content: "Why memory?".to_string(),
+            metadata: None,
+            author_id: Some(user.id),
+        })
+        .await?;
+    let comparison = compare_service
+        .compare_branches(user.id, conversation.id, gpt_answer.id, follow_up.id)
+        .await?;
+    assert_eq!(comparison.common_ancestor_id, Some(question.id));
+    assert_eq!(
+        comparison.right.iter().map(|m| m.id).collect::<Vec<_>>(),
+        vec![claude_answer.id, follow_up.id]
+    );
+    assert!(comparison.answers.is_none());
+
+    // Messages from another conversation are not found
+    let other = conversation_service
+        .create_conversation(
+            user.id,
+            CreateConversationRequest {
+                title: None,
+                model: "gpt-4".to_string(),
+                provider: None,
+                metadata: None,
+                project_id: None,
+            },
+        )
+        .await?;
+    let err = compare_service
+        .compare_branches(user.id, other.id, gpt_answer.id, claude_answer.id)
+        .await
+        .unwrap_err();
+    assert!(matches!(AppError::from(err), AppError::NotFound(_)));
+
+    dal.users().delete(user.id).await?;
+    Ok(())
+}
@@ -0,0 +1,111 @@
+use anyhow::Result;
+use chrono::{Duration, Utc};
+
+use crate::{
+    models::{ConversationListParams, ConversationStatus, CreateConversationRequest},
+    repositories::Repository,
+    services::conversation::ConversationService,
+    tests::{create_user, test_dal},
+};
+
+fn list_params(status: ConversationStatus) -> ConversationListParams {
+    ConversationListParams {
+        status,
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+#[ignore = "needs DATABASE_URL"]
+async fn test_pin_archive_trash_and_restore() -> Result<()> {
+    let dal = test_dal().await?;
+    let service = ConversationService::new(dal.clone());
+
+    let user = create_user(&dal, "status").await?;
+
+    let mut ids = Vec::new();
+    for title in ["first", "second", "third"] {
+        let conversation = service
+            .create_conversation(
+                user.id,
+                CreateConversationRequest {
+                    title: Some(title.to_string()),
+                    model: "gpt-4".to_string(),
+                    provider: None,
+                    metadata: None,
+                    project_id: None,
+                },
+            )
+            .await?;
+        ids.push(conversation.id);
+    }
+    let (first, second, third) = (ids[0], ids[1], ids[2]);
+
+    // Pinned conversations sort ahead of more recently updated ones
+    assert!(service.set_pinned(first, user.id, true).await?);
+    service
+        .update_conversation_title(third, user.id, "third, edited".to_string())
+        .await?;
+    let active = service
+        .get_user_conversations(user.id, &list_params(ConversationStatus::Active), None)
+        .await?
+        .conversations;
+    assert_eq!(active[0].id, first);
+    assert!(active[0].pinned_at.is_some());
+
+    assert!(service.set_archived(second, user.id, true).await?);
+    assert!(service.trash_conversation(third, user.id).await?);
+
+    let active = service
+        .get_user_conversations(user.id, &list_params(ConversationStatus::Active), None)
+        .await?
+        .conversations;
+    assert_eq!(active.iter().map(|c| c.id).collect::<Vec<_>>(), vec![first]);
+    let archived = service
+        .get_user_conversations(user.id, &list_params(ConversationStatus::Archived), None)
+        .await?
+        .conversations;
+    assert_eq!(
+        archived.iter().map(|c| c.id).collect::<Vec<_>>(),
+        vec![second]
+    );
+    let trashed = service
+        .get_user_conversations(user.id, &list_params(ConversationStatus::Trashed), None)
+        .await?
+        .conversations;
+    assert_eq!(
+        trashed.iter().map(|c| c.id).collect::<Vec<_>>(),
+        vec![third]
+    );
+
+    // Trashed conversations can't be opened, pinned or trashed twice
+    assert!(service
+        .get_conversation_with_messages(third, user.id)
+        .await?
+        .is_none());
+    assert!(!service.set_pinned(third, user.id, true).await?);
+    assert!(!service.trash_conversation(third, user.id).await?);
+
+    assert!(service.restore_conversation(third, user.id).await?);
+    assert!(!service.restore_conversation(third, user.id).await?);
+    assert!(service.set_archived(second, user.id, false).await?);
+    let active = service
+        .get_user_conversations(user.id, &list_params(ConversationStatus::Active), None)
+        .await?
+        .conversations;
+    assert_eq!(active.len(), 3);
+
+    // Only conversations trashed before the cutoff are purged
+    service.trash_conversation(third, user.id).await?;
+    dal.conversations()
+        .purge_trashed_before(Utc::now() - Duration::days(1))
+        .await?;
+    assert!(dal.conversations().find_by_id(third).await?.is_some());
+    dal.conversations()
+        .purge_trashed_before(Utc::now() + Duration::seconds(1))
+        .await?;
+    assert!(dal.conversations().find_by_id(third).await?.is_none());
+
+    dal.users().delete(user.id).await?;
+    Ok(())
+}
@@ -0,0 +1,223 @@
+use anyhow::Result;
+use uuid::Uuid;
+
+use crate::{
+    config::{AppConfig, EmbeddingConfig},
+    database::Database,
+    error::AppError,
+    models::{CreateConversationRequest, CreateMessageRequest, MessageRole},
+    repositories::{embedding::ChunkEmbedding, embedding_job::MAX_EMBEDDING_ATTEMPTS, Repository},
+    services::{embedding::EmbeddingService, DataAccessLayer},
+    tests::{create_user, test_database},
+};
+
+async fn job_states(database: &Database, message_ids: &[Uuid]) -> Result<Vec<(String, i32)>> {
+    let mut states = Vec::new();
+    for message_id in message_ids {
+        let state: (String, i32) =
+            sqlx::query_as("SELECT status, attempts FROM embedding_jobs WHERE message_id = $1")
+                .bind(message_id)
+                .fetch_one(&database.pool)
+                .await?;
+        states.push(state);
+    }
+    Ok(states)
+}
+
+#[tokio::test]
+#[ignore = "needs DATABASE_URL"]
+async fn test_embedding_jobs_are_queued_claimed_and_retried() -> Result<()> {
+    let database = test_database().await?;
+    let dal = DataAccessLayer::new(database.clone());
+    let jobs = dal.embedding_jobs();
+
+    let user = create_user(&dal, "ejobs").await?;
+    let conversation = dal
+        .conversations()
+        .create_from_request(
+            user.id,
+            CreateConversationRequest {
+                title: Some("Queue".to_string()),
+                model: "gpt-4".to_string(),
+                provider: None,
+                metadata: None,
+                project_id: None,
+            },
+        )
+        .await?;
+
+    let mut message_ids = Vec::new();
+    for content in ["First", "Second", "Third"] {
+        let message = dal
+            .messages()
+            .create_from_request(CreateMessageRequest {
+                conversation_id: conversation.id,
+                parent_id: message_ids.last().copied(),
+                role: MessageRole::User,
+                content: content.to_string(),
+                metadata: None,
+                author_id: Some(user.id),
+            })
+            .await?;
+        message_ids.push(message.id);
+    }
+
+    // Creating a message queues it
+    assert_eq!(
+        job_states(&database, &message_ids).await?,
+        vec![("pending".to_string(), 0); 3]
+    );
+
+    // Without an API key nothing is claimed
+    let service = EmbeddingService::new(AppConfig::default(), dal.clone())?;
+    let unconfigured = service.process_pending_embeddings(10).await.unwrap_err();
+    assert!(matches!(unconfigured, AppError::Embedding(_)));
+    assert_eq!(
+        job_states(&database, &message_ids).await?,
+        vec![("pending".to_string(), 0); 3]
+    );
+
+    // Put these jobs ahead of everything else queued in the shared database
+    sqlx::query("UPDATE embedding_jobs SET run_after = '-infinity' WHERE message_id = ANY($1)")
+        .bind(&message_ids)
+        .execute(&database.pool)
+        .await?;
+    dal.embeddings()
+        .replace_chunks(
+            message_ids[1],
+            "text-embedding-3-small",
+            &[ChunkEmbedding {
+                chunk_index: 0,
+                start_offset: 0,
+                end_offset: 6,
+                content: "Second".to_string(),
+                embedding: vec![0.01; 1536],
+            }],
+        )
+        .await?;
+    dal.messages().delete(message_ids[2]).await?;
+
+    let mut claimed = jobs.claim(3, 600, "text-embedding-3-small").await?;
+    claimed.sort_by_key(|job| {
+        message_ids
+            .iter()
+            .position(|id| *id == job.message_id)
+            .unwrap()
+    });
+    assert_eq!(
+        claimed.iter().map(|job| job.message_id).collect::<Vec<_>>(),
+        message_ids
+    );
+    assert_eq!(claimed[0].content, "First");
+    assert!(claimed.iter().all(|job| job.attempts == 1));
+    assert!(!claimed[0].embedded && !claimed[0].deleted);
+    assert!(claimed[1].embedded);
+    assert!(claimed[2].deleted);
+    assert_eq!(
+        job_states(&database, &message_ids).await?,
+        vec![("processing".to_string(), 1); 3]
+    );
+
+    // A failed attempt is retried later; the last one gives up
+    jobs.fail(&[claimed[0].id], "rate limited").await?;
+    jobs.complete(&[claimed[1].id, claimed[2].id]).await?;
+    let (status, run_after_delay): (String, f64) = sqlx::query_as(
+        "SELECT status, EXTRACT(EPOCH FROM run_after - NOW())::float8 FROM embedding_jobs WHERE id = $1",
+    )
+    .bind(claimed[0].id)
+    .fetch_one(&database.pool)
+    .await?;
+    assert_eq!(status, "pending");
+    assert!(run_after_delay > 20.0 && run_after_delay <= 30.0);
+    let queue = jobs.status(Some(user.id), 50).await?;
+    assert_eq!(queue.retrying, 1);
+    assert_eq!(queue.completed, 2);
+    assert!(queue.recent_failures.iter().any(|failure| {
+        failure.message_id == message_ids[0]
+            && failure.last_error.as_deref() == Some("rate limited")
+    }));
+    // Other users see neither the counts nor the errors of these messages
+    let other = jobs.status(Some(Uuid::new_v4()), 50).await?;
+    assert_eq!(other.retrying + other.completed, 0);
+    assert!(other.recent_failures.is_empty());
+    assert!(jobs.status(None, 50).await?.retrying >= 1);
+
+    sqlx::query("UPDATE embedding_jobs SET attempts = $2 WHERE id = $1")
+        .bind(claimed[0].id)
+        .bind(MAX_EMBEDDING_ATTEMPTS)
+        .execute(&database.pool)
+        .await?;
+    jobs.fail(&[claimed[0].id], "rate limited").await?;
+    assert_eq!(
+        job_states(&database, &message_ids).await?,
+        vec![
+            ("failed".to_string(), MAX_EMBEDDING_ATTEMPTS),
+            ("completed".to_string(), 1),
+            ("completed".to_string(), 1),
+        ]
+    );
+
+    // Failed jobs can be retried
+    assert!(jobs.retry_failed().await? >= 1);
+    sqlx::query("UPDATE embedding_jobs SET status = 'pending', attempts = 0 WHERE message_id = $1")
+        .bind(message_ids[1])
+        .execute(&database.pool)
+        .await?;
+    assert_eq!(
+        job_states(&database, &message_ids).await?,
+        vec![
+            ("pending".to_string(), 0),
+            ("pending".to_string(), 0),
+            ("completed".to_string(), 1),
+        ]
+    );
+
+    // The in-process provider embeds the queue and stores its model with each vector
+    sqlx::query("UPDATE embedding_jobs SET run_after = '-infinity' WHERE message_id = ANY($1)")
+        .bind(&message_ids)
+        .execute(&database.pool)
+        .await?;
+    let local = EmbeddingService::new(
+        AppConfig {
+            embedding: EmbeddingConfig {
+                provider: "local".to_string(),
+                model_path: None,
+                model: "hashed-ngrams-v1".to_string(),
+                dimensions: 384,
+                api_base: None,
+                api_key: String::new(),
+            },
+            ..AppConfig::default()
+        },
+        dal.clone(),
+    )?;
+    assert_eq!(local.process_pending_embeddings(2).await?, 2);
+    for message_id in &message_ids[..2] {
+        let embedding = dal
+            .embeddings()
+            .find_by_message_id(*message_id)
+            .await?
+            .unwrap();
+        assert_eq!(embedding.model, "hashed-ngrams-v1");
+        assert_eq!(embedding.dimensions, 384);
+    }
+
+    // Switching models queues every message without an embedding from the new one
+    jobs.enqueue_missing("hashed-ngrams-v1").await?;
+    assert_eq!(
+        job_states(&database, &message_ids).await?,
+        vec![("completed".to_string(), 1); 3]
+    );
+    assert!(jobs.enqueue_missing("text-embedding-3-small").await? >= 2);
+    assert_eq!(
+        job_states(&database, &message_ids).await?,
+        vec![
+            ("pending".to_string(), 0),
+            ("pending".to_string(), 0),
+            ("completed".to_string(), 1),
+        ]
+    );
+
+    dal.users().delete(user.id).await?;
+    Ok(())
+}
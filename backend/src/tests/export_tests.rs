@@ -0,0 +1,72 @@
+use anyhow::Result;
+
+use crate::{
+    models::{CreateConversationRequest, CreateMessageRequest, MessageRole},
+    repositories::Repository,
+    services::export::{ExportFormat, ExportScope, ExportService},
+    tests::{create_user, test_dal},
+};
+
+#[tokio::test]
+#[ignore = "needs DATABASE_URL"]
+async fn test_export_strips_reasoning_unless_requested() -> Result<()> {
+    let dal = test_dal().await?;
+    let user = create_user(&dal, "export").await?;
+
+    let conversation = dal
+        .conversations()
+        .create_from_request(
+            user.id,
+            CreateConversationRequest {
+                title: Some("Exported reasoning".to_string()),
+                model: "o1".to_string(),
+                provider: None,
+                metadata: None,
+                project_id: None,
+            },
+        )
+        .await?;
+    let question = dal
+        .messages()
+        .create_from_request(CreateMessageRequest {
+            conversation_id: conversation.id,
+            parent_id: None,
+            role: MessageRole::User,
+            content: "What is 6 times 7?".to_string(),
+            metadata: None,
+            author_id: Some(user.id),
+        })
+        .await?;
+    dal.messages()
+        .create_from_request(CreateMessageRequest {
+            conversation_id: conversation.id,
+            parent_id: Some(question.id),
+            role: MessageRole::Assistant,
+            content: "42".to_string(),
+            metadata: Some(serde_json::json!({
+                "reasoning": "Six sevens are forty-two.",
+                "model": "o1"
+            })),
+            author_id: None,
+        })
+        .await?;
+
+    let exports = ExportService::new(dal.clone());
+    for format in [ExportFormat::Json, ExportFormat::Html, ExportFormat::Md] {
+        let body = exports
+            .export_conversation(conversation.clone(), ExportScope::Tree, false)
+            .await?
+            .render(format)?;
+        assert!(body.contains("42"));
+        assert!(!body.contains("Six sevens are forty-two."));
+    }
+
+    let json = exports
+        .export_conversation(conversation.clone(), ExportScope::Tree, true)
+        .await?
+        .render(ExportFormat::Json)?;
+    assert!(json.contains("Six sevens are forty-two."));
+
+    dal.users().delete(user.id).await?;
+    Ok(())
+}
@@ -0,0 +1,247 @@
+use anyhow::Result;
+use axum_typed_multipart::{FieldData, FieldMetadata};
+use std::io::Write;
+use tempfile::NamedTempFile;
+use tokio::io::AsyncReadExt;
+use uuid::Uuid;
+
+use crate::{
+    config::AppConfig,
+    error::AppError,
+    models::{
+        CreateConversationRequest, CreateMessageRequest, ForkConversationRequest, MessageRole,
+    },
+    repositories::Repository,
+    services::{conversation::ConversationService, file::FileService, DataAccessLayer},
+    tests::{create_user, test_dal, test_database},
+};
+
+fn upload(filename: &str, contents: &[u8]) -> Result<FieldData<NamedTempFile>> {
+    let mut file = NamedTempFile::new()?;
+    file.write_all(contents)?;
+    Ok(FieldData {
+        metadata: FieldMetadata {
+            name: Some("file".to_string()),
+            file_name: Some(filename.to_string()),
+            ..FieldMetadata::default()
+        },
+        contents: file,
+    })
+}
+
+async fn create_message(dal: &DataAccessLayer, conversation_id: Uuid) -> Result<Uuid> {
+    let message = dal
+        .messages()
+        .create_from_request(CreateMessageRequest {
+            conversation_id,
+            parent_id: None,
+            role: MessageRole::User,
+            content: "See the attached notes".to_string(),
+            metadata: None,
+            author_id: None,
+        })
+        .await?;
+    Ok(message.id)
+}
+
+#[tokio::test]
+#[ignore = "needs DATABASE_URL"]
+async fn test_file_upload_download_and_delete() -> Result<()> {
+    let dal = test_dal().await?;
+    let user = create_user(&dal, "files").await?;
+    let conversation = dal
+        .conversations()
+        .create_from_request(
+            user.id,
+            CreateConversationRequest {
+                title: Some("Attachments".to_string()),
+                model: "gpt-4".to_string(),
+                provider: None,
+                metadata: None,
+                project_id: None,
+            },
+        )
+        .await?;
+    let message_id = create_message(&dal, conversation.id).await?;
+
+    let storage = tempfile::tempdir()?;
+    let mut config = AppConfig {
+        storage_path: storage.path().to_string_lossy().to_string(),
+        storage_quota_mb: 1,
+        ..AppConfig::default()
+    };
+    config.rate_limit.max_file_size_mb = 1;
+    let file_service = FileService::new(config, dal.clone());
+
+    // Directories in the client's filename are dropped
+    let uploaded = file_service
+        .upload_file(
+            user.id,
+            message_id,
+            upload("../../reports/Notes.MD", b"# Notes\n")?,
+        )
+        .await?;
+    assert_eq!(uploaded.filename, "Notes.MD");
+    assert_eq!(uploaded.content_type.as_deref(), Some("text/markdown"));
+    assert_eq!(uploaded.size_bytes, Some(8));
+
+    let attachment = file_service.get_attachment(uploaded.id).await?;
+    assert!(attachment.storage_path.starts_with(
+        &storage
+            .path()
+            .join("attachments")
+            .to_string_lossy()
+            .to_string()
+    ));
+    let mut contents = String::new();
+    file_service
+        .open_file(&attachment)
+        .await?
+        .read_to_string(&mut contents)
+        .await?;
+    assert_eq!(contents, "# Notes\n");
+
+    let listed = file_service.get_message_attachments(message_id).await?;
+    assert_eq!(listed.len(), 1);
+    assert_eq!(
+        listed[0].download_url,
+        format!("/api/v1/files/{}", uploaded.id)
+    );
+
+    // Disallowed types, oversized files and uploads past the quota are rejected
+    let large = vec![b'a'; 700 * 1024];
+    for rejected in [
+        file_service
+            .upload_file(user.id, message_id, upload("setup.exe", b"MZ")?)
+            .await,
+        file_service
+            .upload_file(
+                user.id,
+                message_id,
+                upload("dump.txt", &vec![b'a'; 1024 * 1024 + 1])?,
+            )
+            .await,
+        async {
+            file_service
+                .upload_file(user.id, message_id, upload("first.txt", &large)?)
+                .await?;
+            file_service
+                .upload_file(user.id, message_id, upload("second.txt", &large)?)
+                .await
+        }
+        .await,
+    ] {
+        let error = AppError::from(rejected.unwrap_err());
+        assert!(matches!(error, AppError::BadRequest(_)));
+    }
+
+    // A forked copy of the message shares the stored file, which counts once
+    let usage = file_service.get_user_storage_usage(user.id).await?;
+    let copy_id = create_message(&dal, conversation.id).await?;
+    dal.attachments()
+        .copy_to_messages(&[(message_id, copy_id)])
+        .await?;
+    assert_eq!(file_service.get_user_storage_usage(user.id).await?, usage);
+
+    // The file stays while the copy links to it and goes with the last link
+    file_service.delete_file(&attachment).await?;
+    assert!(std::path::Path::new(&attachment.storage_path).exists());
+    let copied = dal
+        .attachments()
+        .find_by_message_id(copy_id)
+        .await?
+        .into_iter()
+        .find(|a| a.storage_path == attachment.storage_path)
+        .expect("the copy links to the same file");
+    file_service.delete_file(&copied).await?;
+    assert!(!std::path::Path::new(&attachment.storage_path).exists());
+    assert!(dal.attachments().find_by_id(attachment.id).await?.is_none());
+
+    let error = AppError::from(
+        file_service
+            .get_attachment(attachment.id)
+            .await
+            .unwrap_err(),
+    );
+    assert!(matches!(error, AppError::NotFound(_)));
+
+    dal.users().delete(user.id).await?;
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "needs DATABASE_URL"]
+async fn test_removing_conversations_removes_their_files() -> Result<()> {
+    let database = test_database().await?;
+    let dal = DataAccessLayer::new(database.clone());
+    let user = create_user(&dal, "fpurge").await?;
+
+    let storage = tempfile::tempdir()?;
+    let file_service = FileService::new(
+        AppConfig {
+            storage_path: storage.path().to_string_lossy().to_string(),
+            ..AppConfig::default()
+        },
+        dal.clone(),
+    );
+    let conversations = ConversationService::new(dal.clone());
+
+    let mut stored = Vec::new();
+    for title in ["Trashed", "Deleted"] {
+        let conversation = dal
+            .conversations()
+            .create_from_request(
+                user.id,
+                CreateConversationRequest {
+                    title: Some(title.to_string()),
+                    model: "gpt-4".to_string(),
+                    provider: None,
+                    metadata: None,
+                    project_id: None,
+                },
+            )
+            .await?;
+        let message_id = create_message(&dal, conversation.id).await?;
+        let uploaded = file_service
+            .upload_file(user.id, message_id, upload("notes.txt", b"notes")?)
+            .await?;
+        let path = file_service.get_attachment(uploaded.id).await?.storage_path;
+        assert!(std::path::Path::new(&path).exists());
+        stored.push((conversation.id, message_id, path));
+    }
+    let (trashed, trashed_message, trashed_path) = &stored[0];
+    let (deleted, _, deleted_path) = &stored[1];
+
+    // A fork shares the stored file, which survives until the fork is gone too
+    let fork = conversations
+        .fork_from_message(
+            user.id,
+            *trashed_message,
+            ForkConversationRequest {
+                title: None,
+                model: None,
+                provider: None,
+            },
+        )
+        .await?;
+
+    // Purging the trash removes the files of purged conversations
+    assert!(conversations.trash_conversation(*trashed, user.id).await?);
+    sqlx::query("UPDATE conversations SET deleted_at = NOW() - INTERVAL '400 days' WHERE id = $1")
+        .bind(trashed)
+        .execute(&database.pool)
+        .await?;
+    assert!(conversations.purge_trash(365).await? >= 1);
+    assert!(dal.conversations().find_by_id(*trashed).await?.is_none());
+    assert!(std::path::Path::new(trashed_path).exists());
+
+    assert!(conversations.delete_conversation(fork.id, user.id).await?);
+    assert!(!std::path::Path::new(trashed_path).exists());
+
+    // So does a permanent delete
+    assert!(conversations.delete_conversation(*deleted, user.id).await?);
+    assert!(!std::path::Path::new(deleted_path).exists());
+
+    dal.users().delete(user.id).await?;
+    Ok(())
+}
@@ -0,0 +1,168 @@
+use anyhow::Result;
+use uuid::Uuid;
+
+use crate::{
+    error::AppError,
+    models::{
+        CreateConversationRequest, CreateMessageRequest, ForkConversationRequest, MessageRole,
+    },
+    repositories::{attachment::CreateAttachment, Repository},
+    services::conversation::ConversationService,
+    tests::{create_user, test_dal},
+};
+
+#[tokio::test]
+#[ignore = "needs DATABASE_URL"]
+async fn test_fork_copies_thread_attachments_and_embeddings() -> Result<()> {
+    let dal = test_dal().await?;
+    let conversation_service = ConversationService::new(dal.clone());
+
+    let suffix = Uuid::new_v4().simple().to_string();
+    let user = create_user(&dal, "fork").await?;
+    let stranger = create_user(&dal, "forkx").await?;
+
+    let conversation = conversation_service
+        .create_conversation(
+            user.id,
+            CreateConversationRequest {
+                title: Some("Deep dive".to_string()),
+                model: "gpt-4".to_string(),
+                provider: None,
+                metadata: None,
+                project_id: None,
+            },
+        )
+        .await?;
+
+    let question = dal
+        .messages()
+        .create_from_request(CreateMessageRequest {
+            conversation_id: conversation.id,
+            parent_id: None,
+            role: MessageRole::User,
+            content: "Why is the cache cold?".to_string(),
+            metadata: None,
+            author_id: Some(user.id),
+        })
+        .await?;
+    let first_answer = dal
+        .messages()
+        .create_from_request(CreateMessageRequest {
+            conversation_id: conversation.id,
+            parent_id: Some(question.id),
+            role: MessageRole::Assistant,
+            content: "The TTL is too short.".to_string(),
+            metadata: None,
+            author_id: None,
+        })
+        .await?;
+    // A second answer takes over as the active branch, leaving the first one inactive
+    dal.messages()
+        .create_branch(
+            question.id,
+            "Eviction runs too often.".to_string(),
+            MessageRole::Assistant,
+            None,
+        )
+        .await?;
+
+    dal.attachments()
+        .create(CreateAttachment {
+            message_id: question.id,
+            filename: "trace.log".to_string(),
+            content_type: Some("text/plain".to_string()),
+            size_bytes: Some(42),
+            storage_path: format!("uploads/{}/trace.log", suffix),
+        })
+        .await?;
+    dal.embeddings()
+        .upsert_embedding(question.id, vec![0.01; 1536], "text-embedding-3-small")
+        .await?;
+
+    // Fork the inactive branch onto a different model
+    let fork = conversation_service
+        .fork_from_message(
+            user.id,
+            first_answer.id,
+            ForkConversationRequest {
+                model: Some("claude-3-opus".to_string()),
+                ..Default::default()
+            },
+        )
+        .await?;
+    assert_ne!(fork.id, conversation.id);
+    assert_eq!(fork.title.as_deref(), Some("Deep dive (fork)"));
+    assert_eq!(fork.model, "claude-3-opus");
+    assert_eq!(fork.provider, "anthropic");
+    assert_eq!(
+        fork.metadata["fork"]["conversation_id"],
+        serde_json::json!(conversation.id)
+    );
+    assert_eq!(
+        fork.metadata["fork"]["message_id"],
+        serde_json::json!(first_answer.id)
+    );
+
+    let messages = dal.messages().find_by_conversation_id(fork.id).await?;
+    assert_eq!(
+        messages
+            .iter()
+            .map(|m| m.content.as_str())
+            .collect::<Vec<_>>(),
+        vec!["Why is the cache cold?", "The TTL is too short."]
+    );
+    assert!(messages.iter().all(|m| m.is_active));
+    assert_eq!(messages[0].parent_id, None);
+    assert_eq!(messages[1].parent_id, Some(messages[0].id));
+    assert_eq!(messages[0].author_id, Some(user.id));
+
+    let attachments = dal.attachments().find_by_conversation_id(fork.id).await?;
+    assert_eq!(attachments.len(), 1);
+    assert_eq!(attachments[0].message_id, messages[0].id);
+    assert_eq!(
+        attachments[0].storage_path,
+        format!("uploads/{}/trace.log", suffix)
+    );
+    assert!(dal
+        .embeddings()
+        .find_by_message_id(messages[0].id)
+        .await?
+        .is_some());
+    assert!(dal
+        .embeddings()
+        .find_by_message_id(messages[1].id)
+        .await?
+        .is_none());
+
+    // The original conversation is left untouched
+    let original = dal.messages().find_by_id(first_answer.id).await?.unwrap();
+    assert_eq!(original.conversation_id, conversation.id);
+    assert!(!original.is_active);
+
+    // Forking needs read access to the source conversation
+    let denied = conversation_service
+        .fork_from_message(stranger.id, first_answer.id, Default::default())
+        .await
+        .unwrap_err();
+    assert!(matches!(AppError::from(denied), AppError::Forbidden(_)));
+
+    let unsupported = conversation_service
+        .fork_from_message(
+            user.id,
+            first_answer.id,
+            ForkConversationRequest {
+                model: Some("not-a-model".to_string()),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        AppError::from(unsupported),
+        AppError::BadRequest(_)
+    ));
+
+    dal.users().delete(user.id).await?;
+    dal.users().delete(stranger.id).await?;
+    Ok(())
+}
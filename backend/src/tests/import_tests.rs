@@ -0,0 +1,102 @@
+use anyhow::Result;
+
+use crate::{
+    repositories::Repository,
+    services::import::{ImportService, ImportSource},
+    tests::{create_user, test_dal},
+};
+
+const CHATGPT_EXPORT: &str = r#"[
+  {
+    "id": "chatgpt-conv-1",
+    "title": "Imported branches",
+    "create_time": 1700000000.0,
+    "update_time": 1700000300.0,
+    "current_node": "a2",
+    "mapping": {
+      "root": { "id": "root", "message": null, "parent": null, "children": ["u1"] },
+      "u1": {
+        "id": "u1",
+        "message": {
+          "author": { "role": "user" },
+          "create_time": 1700000100.0,
+          "content": { "content_type": "text", "parts": ["Hello"] },
+          "metadata": {}
+        },
+        "parent": "root",
+        "children": ["a1", "a2"]
+      },
+      "a1": {
+        "id": "a1",
+        "message": {
+          "author": { "role": "assistant" },
+          "create_time": 1700000200.0,
+          "content": { "content_type": "text", "parts": ["Hi!"] },
+          "metadata": { "model_slug": "gpt-4" }
+        },
+        "parent": "u1",
+        "children": []
+      },
+      "a2": {
+        "id": "a2",
+        "message": {
+          "author": { "role": "assistant" },
+          "create_time": 1700000300.0,
+          "content": { "content_type": "text", "parts": ["Hello there!"] },
+          "metadata": { "model_slug": "gpt-4o" }
+        },
+        "parent": "u1",
+        "children": []
+      }
+    }
+  }
+]"#;
+
+#[tokio::test]
+#[ignore = "needs DATABASE_URL"]
+async fn test_import_chatgpt_export() -> Result<()> {
+    let dal = test_dal().await?;
+
+    let user = create_user(&dal, "import").await?;
+
+    let service = ImportService::new(dal.clone());
+    let report = service
+        .import(user.id, CHATGPT_EXPORT.as_bytes(), None, 1024 * 1024)
+        .await?;
+
+    assert_eq!(report.source, ImportSource::ChatGpt);
+    assert_eq!(report.conversations_imported, 1);
+    assert_eq!(report.messages_imported, 3);
+    assert_eq!(report.embeddings_queued, 2);
+
+    let conversation_id = report.conversation_ids[0];
+    let conversation = dal
+        .conversations()
+        .find_by_id(conversation_id)
+        .await?
+        .expect("imported conversation");
+    assert_eq!(conversation.model, "gpt-4o");
+    assert_eq!(conversation.created_at.timestamp(), 1700000000);
+
+    let tree = dal
+        .messages()
+        .find_conversation_tree(conversation_id)
+        .await?;
+    assert_eq!(tree.len(), 3);
+    let root = tree.iter().find(|m| m.parent_id.is_none()).unwrap();
+    assert_eq!(root.created_at.timestamp(), 1700000100);
+    assert_eq!(
+        tree.iter().filter(|m| m.parent_id == Some(root.id)).count(),
+        2
+    );
+
+    // Importing the same export again is a no-op
+    let again = service
+        .import(user.id, CHATGPT_EXPORT.as_bytes(), None, 1024 * 1024)
+        .await?;
+    assert_eq!(again.conversations_imported, 0);
+    assert_eq!(again.skipped.len(), 1);
+
+    dal.users().delete(user.id).await?;
+    Ok(())
+}
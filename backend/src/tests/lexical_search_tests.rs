@@ -0,0 +1,133 @@
+use anyhow::Result;
+use uuid::Uuid;
+
+use crate::{
+    config::AppConfig,
+    models::{
+        CreateConversationRequest, CreateMessageRequest, MessageRole, SearchFilters, SearchMode,
+    },
+    repositories::Repository,
+    services::embedding::{EmbeddingService, MessageSearch},
+    tests::{create_user, test_dal},
+};
+
+#[tokio::test]
+#[ignore = "needs DATABASE_URL"]
+async fn test_search_finds_exact_terms_without_embeddings() -> Result<()> {
+    let dal = test_dal().await?;
+    let suffix = Uuid::new_v4().simple().to_string();
+    let user = create_user(&dal, "lex").await?;
+    let stranger = create_user(&dal, "lex-stranger").await?;
+
+    let conversation = dal
+        .conversations()
+        .create_from_request(
+            user.id,
+            CreateConversationRequest {
+                title: Some("Variants".to_string()),
+                model: "gpt-4".to_string(),
+                provider: None,
+                metadata: None,
+                project_id: None,
+            },
+        )
+        .await?;
+
+    // A unique marker keeps other test data out of the results
+    let marker = format!("x{}", &suffix[..10]);
+    let mut parent_id = None;
+    let mut messages = Vec::new();
+    for content in [
+        format!("Which {} variants affect BRCA1 splicing?", marker),
+        format!("The {} build fails with error E0308 in the parser", marker),
+        format!("Splicing of {} transcripts is tissue specific", marker),
+    ] {
+        let message = dal
+            .messages()
+            .create_from_request(CreateMessageRequest {
+                conversation_id: conversation.id,
+                parent_id,
+                role: MessageRole::User,
+                content,
+                metadata: None,
+                author_id: Some(user.id),
+            })
+            .await?;
+        parent_id = Some(message.id);
+        messages.push(message);
+    }
+
+    // Without an OpenAI key hybrid search still answers from the full-text index
+    let service = EmbeddingService::new(AppConfig::default(), dal.clone())?;
+    let MessageSearch { results, mode, .. } = service
+        .search_messages(
+            &format!("{} BRCA1", marker),
+            Some(user.id),
+            SearchMode::Hybrid,
+            &SearchFilters::default(),
+            None,
+            None,
+        )
+        .await?;
+    assert_eq!(mode, SearchMode::Lexical);
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].message_id, messages[0].id);
+    assert_eq!(results[0].similarity, None);
+    assert!(results[0].text_rank.is_some_and(|rank| rank > 0.0));
+
+    let MessageSearch { results, .. } = service
+        .search_messages(
+            &format!("{} e0308", marker),
+            Some(user.id),
+            SearchMode::Lexical,
+            &SearchFilters::default(),
+            None,
+            None,
+        )
+        .await?;
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].message_id, messages[1].id);
+
+    // Stemming matches other word forms; the limit caps the results
+    let MessageSearch { results, .. } = service
+        .search_messages(
+            &format!("{} spliced", marker),
+            Some(user.id),
+            SearchMode::Lexical,
+            &SearchFilters::default(),
+            Some(1),
+            None,
+        )
+        .await?;
+    assert_eq!(results.len(), 1);
+
+    let MessageSearch { results, .. } = service
+        .search_messages(
+            &format!("{} BRCA1", marker),
+            Some(stranger.id),
+            SearchMode::Lexical,
+            &SearchFilters::default(),
+            None,
+            None,
+        )
+        .await?;
+    assert!(results.is_empty());
+
+    // Deleted messages drop out of the index results
+    dal.messages().delete(messages[0].id).await?;
+    let MessageSearch { results, .. } = service
+        .search_messages(
+            &format!("{} BRCA1", marker),
+            Some(user.id),
+            SearchMode::Lexical,
+            &SearchFilters::default(),
+            None,
+            None,
+        )
+        .await?;
+    assert!(results.is_empty());
+
+    dal.users().delete(user.id).await?;
+    dal.users().delete(stranger.id).await?;
+    Ok(())
+}
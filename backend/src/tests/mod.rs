@@ -1,3 +1,77 @@
+use anyhow::{ensure, Context, Result};
+use uuid::Uuid;
+
+use crate::{
+    database::Database,
+    models::{CreateUserRequest, User},
+    services::{
+        embedding::{store_chunks, EmbeddingService},
+        DataAccessLayer,
+    },
+};
+
+pub mod annotation_tests;
 pub mod branching_tests;
+pub mod chunk_tests;
+pub mod collaboration_tests;
+pub mod compare_tests;
+pub mod conversation_status_tests;
+pub mod embedding_job_tests;
+pub mod export_tests;
+pub mod file_tests;
+pub mod fork_tests;
+pub mod import_tests;
+pub mod lexical_search_tests;
+pub mod pagination_tests;
+pub mod project_tests;
 pub mod rate_limit_tests;
+pub mod retrieval_tests;
+pub mod saved_search_tests;
+pub mod search_filter_tests;
 pub mod session_tests;
+pub mod share_tests;
+pub mod stats_tests;
+pub mod topic_tests;
+pub mod transaction_tests;
+
+/// Connect to the database of the database-backed tests. They are ignored by default;
+/// run them with `cargo test -- --include-ignored` and `DATABASE_URL` pointing at a
+/// migrated database.
+pub async fn test_database() -> Result<Database> {
+    let database_url =
+        std::env::var("DATABASE_URL").context("DATABASE_URL must be set for database tests")?;
+    Database::new(&database_url).await
+}
+
+pub async fn test_dal() -> Result<DataAccessLayer> {
+    Ok(DataAccessLayer::new(test_database().await?))
+}
+
+/// A fresh user named after `prefix`. Deleting it removes everything it owns.
+pub async fn create_user(dal: &DataAccessLayer, prefix: &str) -> Result<User> {
+    let suffix = Uuid::new_v4().simple().to_string();
+    dal.users()
+        .create_from_request(CreateUserRequest {
+            email: format!("{}-{}@example.com", prefix, suffix),
+            username: format!("{}-{}", prefix, &suffix[..12]),
+            password: "Test-User-Password-1!".to_string(),
+        })
+        .await
+}
+
+/// Embed a message right away instead of through the queue, which other tests drain
+pub async fn embed_message(
+    service: &EmbeddingService,
+    dal: &DataAccessLayer,
+    message_id: Uuid,
+    content: &str,
+) -> Result<()> {
+    let chunks = service
+        .embed_chunks(&[content])
+        .await?
+        .pop()
+        .unwrap_or_default();
+    ensure!(!chunks.is_empty(), "message has no text to embed");
+    store_chunks(dal, message_id, service.provider().model(), &chunks).await?;
+    Ok(())
+}
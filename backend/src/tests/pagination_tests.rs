@@ -0,0 +1,249 @@
+use std::collections::HashSet;
+
+use anyhow::Result;
+
+use crate::{
+    models::{
+        ConversationCursor, ConversationListParams, CreateConversationRequest,
+        CreateMessageRequest, MessageRole, MessageWindowParams,
+    },
+    repositories::Repository,
+    services::{chat::ChatService, conversation::ConversationService},
+    tests::{create_user, test_dal},
+};
+
+#[tokio::test]
+#[ignore = "needs DATABASE_URL"]
+async fn test_conversation_cursor_pagination_and_filters() -> Result<()> {
+    let dal = test_dal().await?;
+    let service = ConversationService::new(dal.clone());
+
+    let user = create_user(&dal, "pages").await?;
+
+    let mut created = Vec::new();
+    for i in 0..5 {
+        let conversation = service
+            .create_conversation(
+                user.id,
+                CreateConversationRequest {
+                    title: Some(format!("Trip {} 100%_done", i)),
+                    model: if i % 2 == 0 {
+                        "gpt-4"
+                    } else {
+                        "claude-3-haiku"
+                    }
+                    .to_string(),
+                    provider: Some(if i % 2 == 0 { "openai" } else { "anthropic" }.to_string()),
+                    metadata: None,
+                    project_id: None,
+                },
+            )
+            .await?;
+        created.push(conversation.id);
+    }
+
+    // Walk every page of two and check nothing is repeated or lost
+    let mut seen = Vec::new();
+    let mut cursor = None;
+    loop {
+        let page = service
+            .get_user_conversations(
+                user.id,
+                &ConversationListParams {
+                    limit: Some(2),
+                    ..Default::default()
+                },
+                cursor.as_ref(),
+            )
+            .await?;
+        assert_eq!(page.total, 5);
+        assert!(page.conversations.len() <= 2);
+        seen.extend(page.conversations.iter().map(|c| c.id));
+
+        match page.next_cursor {
+            Some(next) => {
+                let decoded = ConversationCursor::decode(&next).expect("valid cursor");
+                assert_eq!(decoded.encode(), next);
+                cursor = Some(decoded);
+            }
+            None => break,
+        }
+    }
+    assert_eq!(seen.len(), 5);
+    assert_eq!(seen.iter().collect::<HashSet<_>>().len(), 5);
+    // Newest first
+    assert_eq!(seen, created.iter().rev().copied().collect::<Vec<_>>());
+    assert!(ConversationCursor::decode("not-a-cursor").is_none());
+
+    let by_model = service
+        .get_user_conversations(
+            user.id,
+            &ConversationListParams {
+                model: Some("claude-3-haiku".to_string()),
+                ..Default::default()
+            },
+            None,
+        )
+        .await?;
+    assert_eq!(by_model.total, 2);
+
+    let by_provider = service
+        .get_user_conversations(
+            user.id,
+            &ConversationListParams {
+                provider: Some("openai".to_string()),
+                ..Default::default()
+            },
+            None,
+        )
+        .await?;
+    assert_eq!(by_provider.total, 3);
+
+    // Title search is case-insensitive and treats LIKE wildcards literally
+    let by_title = service
+        .get_user_conversations(
+            user.id,
+            &ConversationListParams {
+                q: Some("trip 3 100%_".to_string()),
+                ..Default::default()
+            },
+            None,
+        )
+        .await?;
+    assert_eq!(
+        by_title
+            .conversations
+            .iter()
+            .map(|c| c.id)
+            .collect::<Vec<_>>(),
+        vec![created[3]]
+    );
+    let no_match = service
+        .get_user_conversations(
+            user.id,
+            &ConversationListParams {
+                q: Some("trip%x".to_string()),
+                ..Default::default()
+            },
+            None,
+        )
+        .await?;
+    assert_eq!(no_match.total, 0);
+
+    dal.users().delete(user.id).await?;
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "needs DATABASE_URL"]
+async fn test_message_window_before_and_after() -> Result<()> {
+    let dal = test_dal().await?;
+    let chat_service = ChatService::new(dal.clone());
+
+    let user = create_user(&dal, "window").await?;
+    let conversation = ConversationService::new(dal.clone())
+        .create_conversation(
+            user.id,
+            CreateConversationRequest {
+                title: Some("Long thread".to_string()),
+                model: "gpt-4".to_string(),
+                provider: None,
+                metadata: None,
+                project_id: None,
+            },
+        )
+        .await?;
+
+    let mut ids = Vec::new();
+    let mut parent_id = None;
+    for i in 0..7 {
+        let message = dal
+            .messages()
+            .create_from_request(CreateMessageRequest {
+                conversation_id: conversation.id,
+                parent_id,
+                role: if i % 2 == 0 {
+                    MessageRole::User
+                } else {
+                    MessageRole::Assistant
+                },
+                content: format!("Message {}", i),
+                metadata: None,
+                author_id: None,
+            })
+            .await?;
+        parent_id = Some(message.id);
+        ids.push(message.id);
+    }
+
+    let window_ids = |window: &crate::models::MessageWindow| {
+        window.messages.iter().map(|m| m.id).collect::<Vec<_>>()
+    };
+
+    // Latest messages, oldest of them is the cursor for loading earlier ones
+    let latest = chat_service
+        .get_message_window(
+            user.id,
+            conversation.id,
+            &MessageWindowParams {
+                limit: Some(3),
+                ..Default::default()
+            },
+        )
+        .await?;
+    assert_eq!(latest.total, 7);
+    assert_eq!(window_ids(&latest), ids[4..].to_vec());
+    assert_eq!(latest.next_cursor, Some(ids[4]));
+
+    let earlier = chat_service
+        .get_message_window(
+            user.id,
+            conversation.id,
+            &MessageWindowParams {
+                before: latest.next_cursor,
+                limit: Some(3),
+                ..Default::default()
+            },
+        )
+        .await?;
+    assert_eq!(window_ids(&earlier), ids[1..4].to_vec());
+    assert_eq!(earlier.next_cursor, Some(ids[1]));
+
+    let first = chat_service
+        .get_message_window(
+            user.id,
+            conversation.id,
+            &MessageWindowParams {
+                before: earlier.next_cursor,
+                limit: Some(3),
+                ..Default::default()
+            },
+        )
+        .await?;
+    assert_eq!(window_ids(&first), vec![ids[0]]);
+    assert_eq!(first.next_cursor, None);
+
+    let after = chat_service
+        .get_message_window(
+            user.id,
+            conversation.id,
+            &MessageWindowParams {
+                after: Some(ids[1]),
+                limit: Some(2),
+                ..Default::default()
+            },
+        )
+        .await?;
+    assert_eq!(window_ids(&after), ids[2..4].to_vec());
+    assert_eq!(after.next_cursor, Some(ids[3]));
+
+    // Without a window the whole thread is returned
+    let all = chat_service
+        .get_message_window(user.id, conversation.id, &MessageWindowParams::default())
+        .await?;
+    assert_eq!(window_ids(&all), ids);
+    assert_eq!(all.next_cursor, None);
+
+    dal.users().delete(user.id).await?;
+    Ok(())
+}
@@ -0,0 +1,174 @@
+use anyhow::Result;
+use uuid::Uuid;
+
+use crate::{
+    error::AppError,
+    models::{
+        ConversationListParams, CreateConversationRequest, CreateProjectRequest, MessageRole,
+        UpdateProjectRequest,
+    },
+    repositories::Repository,
+    services::conversation::ConversationService,
+    tests::{create_user, test_dal},
+};
+
+#[tokio::test]
+#[ignore = "needs DATABASE_URL"]
+async fn test_projects_and_tags_organize_conversations() -> Result<()> {
+    let dal = test_dal().await?;
+    let conversation_service = ConversationService::new(dal.clone());
+
+    let user = create_user(&dal, "projects").await?;
+
+    let project = dal
+        .projects()
+        .create_from_request(
+            user.id,
+            CreateProjectRequest {
+                name: "Research".to_string(),
+                description: None,
+                system_prompt: Some("Answer like a librarian.".to_string()),
+                default_model: Some("claude-3-haiku".to_string()),
+            },
+        )
+        .await?;
+    assert!(
+        dal.projects()
+            .name_exists(user.id, "Research", None)
+            .await?
+    );
+
+    // A conversation created in the project takes its model and system prompt
+    let in_project = conversation_service
+        .create_conversation(
+            user.id,
+            CreateConversationRequest {
+                title: Some("Sources".to_string()),
+                model: String::new(),
+                provider: None,
+                metadata: None,
+                project_id: Some(project.id),
+            },
+        )
+        .await?;
+    assert_eq!(in_project.model, "claude-3-haiku");
+    let messages = dal
+        .messages()
+        .find_by_conversation_id(in_project.id)
+        .await?;
+    assert_eq!(messages.len(), 1);
+    assert!(matches!(messages[0].role, MessageRole::System));
+    assert_eq!(messages[0].content, "Answer like a librarian.");
+
+    let outside = conversation_service
+        .create_conversation(
+            user.id,
+            CreateConversationRequest {
+                title: Some("Loose".to_string()),
+                model: "gpt-4".to_string(),
+                provider: None,
+                metadata: None,
+                project_id: None,
+            },
+        )
+        .await?;
+
+    // Unknown projects and missing models are client errors, not server errors
+    for (model, project_id, not_found) in [
+        ("gpt-4", Some(Uuid::new_v4()), true),
+        ("", None, false),
+        ("not-a-model", None, false),
+    ] {
+        let error = conversation_service
+            .create_conversation(
+                user.id,
+                CreateConversationRequest {
+                    title: None,
+                    model: model.to_string(),
+                    provider: None,
+                    metadata: None,
+                    project_id,
+                },
+            )
+            .await
+            .unwrap_err();
+        match AppError::from(error) {
+            AppError::NotFound(_) => assert!(not_found),
+            AppError::BadRequest(_) => assert!(!not_found),
+            other => panic!("Unexpected error: {:?}", other),
+        }
+    }
+
+    // Tag names are free-form and case-insensitive
+    let tag = dal.tags().find_or_create_by_name(user.id, "Urgent").await?;
+    let same = dal.tags().find_or_create_by_name(user.id, "urgent").await?;
+    assert_eq!(tag.id, same.id);
+    dal.tags().add_to_conversation(tag.id, outside.id).await?;
+
+    let by_project = conversation_service
+        .get_user_conversations(
+            user.id,
+            &ConversationListParams {
+                project_id: Some(project.id),
+                ..Default::default()
+            },
+            None,
+        )
+        .await?
+        .conversations;
+    assert_eq!(
+        by_project.iter().map(|c| c.id).collect::<Vec<_>>(),
+        vec![in_project.id]
+    );
+
+    let by_tag = conversation_service
+        .get_user_conversations(
+            user.id,
+            &ConversationListParams {
+                tag: Some("URGENT".to_string()),
+                ..Default::default()
+            },
+            None,
+        )
+        .await?
+        .conversations;
+    assert_eq!(
+        by_tag.iter().map(|c| c.id).collect::<Vec<_>>(),
+        vec![outside.id]
+    );
+
+    let all = conversation_service
+        .get_user_conversations(user.id, &ConversationListParams::default(), None)
+        .await?
+        .conversations;
+    assert_eq!(all.len(), 2);
+
+    // Empty strings clear optional fields, omitted fields are left alone
+    let updated = dal
+        .projects()
+        .update_from_request(
+            project.id,
+            UpdateProjectRequest {
+                name: None,
+                description: None,
+                system_prompt: Some(String::new()),
+                default_model: None,
+            },
+        )
+        .await?
+        .expect("project exists");
+    assert_eq!(updated.name, "Research");
+    assert_eq!(updated.system_prompt, None);
+    assert_eq!(updated.default_model.as_deref(), Some("claude-3-haiku"));
+
+    // Deleting the project keeps its conversations
+    dal.projects().delete(project.id).await?;
+    assert!(dal
+        .conversations()
+        .find_by_id(in_project.id)
+        .await?
+        .is_some());
+
+    dal.users().delete(user.id).await?;
+    Ok(())
+}
@@ -0,0 +1,302 @@
+use anyhow::Result;
+use uuid::Uuid;
+
+use crate::{
+    config::{AppConfig, EmbeddingConfig},
+    embeddings::local::{LocalEmbeddingProvider, HASHED_NGRAMS_MODEL},
+    error::AppError,
+    llm::{ChatMessage, ChatResponse},
+    models::{
+        Conversation, ConversationRole, CreateConversationRequest, CreateMessageRequest,
+        MessageRole, RetrievalSettings,
+    },
+    repositories::{embedding::ChunkEmbedding, Repository},
+    services::{
+        chat::ChatService,
+        conversation::ConversationService,
+        embedding::EmbeddingService,
+        retrieval::{Citation, RetrievalService},
+        DataAccessLayer,
+    },
+    tests::{create_user, test_dal, test_database},
+};
+
+#[tokio::test]
+#[ignore = "needs DATABASE_URL"]
+async fn test_retrieval_settings_and_citations() -> Result<()> {
+    let dal = test_dal().await?;
+    let owner = create_user(&dal, "rt-owner").await?;
+    let stranger = create_user(&dal, "rt-stranger").await?;
+
+    let conversation = dal
+        .conversations()
+        .create_from_request(
+            owner.id,
+            CreateConversationRequest {
+                title: Some("Grounded answers".to_string()),
+                model: "gpt-4".to_string(),
+                provider: None,
+                // Other metadata survives settings updates
+                metadata: Some(serde_json::json!({ "pinned": true })),
+                project_id: None,
+            },
+        )
+        .await?;
+
+    let conversations = ConversationService::new(dal.clone());
+    assert_eq!(
+        conversations
+            .get_retrieval_settings(conversation.id, owner.id)
+            .await?,
+        RetrievalSettings::default()
+    );
+
+    // Retrieval is off by default, so the prompt is left alone
+    let retrieval =
+        RetrievalService::new(EmbeddingService::new(AppConfig::default(), dal.clone())?);
+    let mut messages = vec![ChatMessage {
+        role: "user".to_string(),
+        content: "What did we decide about retries?".to_string(),
+    }];
+    let citations = retrieval.augment(&conversation, &mut messages, None).await;
+    assert!(citations.is_empty());
+    assert_eq!(messages.len(), 1);
+
+    let settings = RetrievalSettings {
+        enabled: true,
+        top_k: 3,
+        min_similarity: 0.6,
+    };
+    assert_eq!(
+        conversations
+            .update_retrieval_settings(conversation.id, owner.id, settings.clone())
+            .await?,
+        settings
+    );
+    assert_eq!(
+        conversations
+            .get_retrieval_settings(conversation.id, owner.id)
+            .await?,
+        settings
+    );
+    let stored = dal
+        .conversations()
+        .find_by_id(conversation.id)
+        .await?
+        .unwrap();
+    assert_eq!(stored.metadata["pinned"], serde_json::json!(true));
+
+    let invalid = conversations
+        .update_retrieval_settings(
+            conversation.id,
+            owner.id,
+            RetrievalSettings {
+                top_k: 0,
+                ..settings.clone()
+            },
+        )
+        .await;
+    assert!(matches!(
+        invalid.unwrap_err().downcast_ref::<AppError>(),
+        Some(AppError::BadRequest(_))
+    ));
+    let forbidden = conversations
+        .update_retrieval_settings(conversation.id, stranger.id, settings.clone())
+        .await;
+    assert!(matches!(
+        forbidden.unwrap_err().downcast_ref::<AppError>(),
+        Some(AppError::Forbidden(_))
+    ));
+
+    // The passages an answer was given are kept in its metadata
+    let chat = ChatService::new(dal.clone());
+    chat.send_message(owner.id, conversation.id, "What about retries?".to_string())
+        .await?;
+    let citation = Citation {
+        marker: 1,
+        message_id: Uuid::new_v4(),
+        conversation_id: Uuid::new_v4(),
+        conversation_title: Some("Queue design".to_string()),
+        start_offset: Some(0),
+        end_offset: Some(120),
+        similarity: Some(0.81),
+    };
+    let response = ChatResponse {
+        message: ChatMessage {
+            role: "assistant".to_string(),
+            content: "Back off exponentially, as decided earlier [1].".to_string(),
+        },
+        reasoning: None,
+        usage: None,
+        model: "gpt-4".to_string(),
+        provider: "openai".to_string(),
+        latency_ms: None,
+    };
+    let answer = chat
+        .save_llm_response(
+            owner.id,
+            conversation.id,
+            &response,
+            None,
+            std::slice::from_ref(&citation),
+        )
+        .await?;
+    let answer = dal.messages().find_by_id(answer.id).await?.unwrap();
+    let saved: Vec<Citation> = serde_json::from_value(answer.metadata["citations"].clone())?;
+    assert_eq!(saved, vec![citation]);
+
+    // Answers without retrieved passages carry no citations
+    let plain = chat
+        .save_llm_response(owner.id, conversation.id, &response, None, &[])
+        .await?;
+    let plain = dal.messages().find_by_id(plain.id).await?.unwrap();
+    assert!(plain.metadata.get("citations").is_none());
+
+    for user in [&owner, &stranger] {
+        dal.users().delete(user.id).await?;
+    }
+    Ok(())
+}
+
+/// A conversation of `user_id` holding one embedded assistant message
+async fn embedded_conversation(
+    dal: &DataAccessLayer,
+    user_id: Uuid,
+    title: &str,
+    content: &str,
+) -> Result<Conversation> {
+    let conversation = dal
+        .conversations()
+        .create_from_request(
+            user_id,
+            CreateConversationRequest {
+                title: Some(title.to_string()),
+                model: "gpt-4".to_string(),
+                provider: None,
+                metadata: None,
+                project_id: None,
+            },
+        )
+        .await?;
+    let message = dal
+        .messages()
+        .create_from_request(CreateMessageRequest {
+            conversation_id: conversation.id,
+            parent_id: None,
+            role: MessageRole::Assistant,
+            content: content.to_string(),
+            metadata: None,
+            author_id: None,
+        })
+        .await?;
+    dal.embeddings()
+        .replace_chunks(
+            message.id,
+            HASHED_NGRAMS_MODEL,
+            &[ChunkEmbedding {
+                chunk_index: 0,
+                start_offset: 0,
+                end_offset: content.chars().count() as i32,
+                content: content.to_string(),
+                embedding: LocalEmbeddingProvider::new(384).embed_text(content),
+            }],
+        )
+        .await?;
+    Ok(conversation)
+}
+
+#[tokio::test]
+#[ignore = "needs DATABASE_URL"]
+async fn test_retrieval_in_shared_conversation_searches_owner_history() -> Result<()> {
+    let database = test_database().await?;
+    let dal = DataAccessLayer::new(database.clone());
+    let owner = create_user(&dal, "rs-owner").await?;
+    let editor = create_user(&dal, "rs-editor").await?;
+
+    let passage = "Retry failed webhook deliveries with exponential backoff";
+    let owner_history = embedded_conversation(&dal, owner.id, "Webhooks", passage).await?;
+    let private = embedded_conversation(&dal, editor.id, "Private notes", passage).await?;
+
+    let shared = dal
+        .conversations()
+        .create_from_request(
+            owner.id,
+            CreateConversationRequest {
+                title: Some("Shared planning".to_string()),
+                model: "gpt-4".to_string(),
+                provider: None,
+                metadata: None,
+                project_id: None,
+            },
+        )
+        .await?;
+    dal.conversation_members()
+        .upsert(shared.id, editor.id, ConversationRole::Editor, owner.id)
+        .await?;
+    ConversationService::new(dal.clone())
+        .update_retrieval_settings(
+            shared.id,
+            owner.id,
+            RetrievalSettings {
+                enabled: true,
+                top_k: 5,
+                min_similarity: 0.3,
+            },
+        )
+        .await?;
+    let shared = dal.conversations().find_by_id(shared.id).await?.unwrap();
+
+    // The editor asks in the shared conversation; their private notes match the
+    // question as well as the owner's history does, but must not be cited
+    let retrieval = RetrievalService::new(EmbeddingService::new(
+        AppConfig {
+            embedding: EmbeddingConfig {
+                provider: "local".to_string(),
+                model_path: None,
+                model: HASHED_NGRAMS_MODEL.to_string(),
+                dimensions: 384,
+                api_base: None,
+                api_key: String::new(),
+            },
+            ..AppConfig::default()
+        },
+        dal.clone(),
+    )?);
+    let mut messages = vec![ChatMessage {
+        role: "user".to_string(),
+        content: "How should we retry failed webhook deliveries?".to_string(),
+    }];
+    let citations = retrieval.augment(&shared, &mut messages, None).await;
+
+    assert!(citations
+        .iter()
+        .all(|citation| citation.conversation_id == owner_history.id));
+    assert!(!citations
+        .iter()
+        .any(|citation| citation.conversation_id == private.id));
+
+    // Similarity search needs pgvector; without it retrieval finds nothing
+    let has_pgvector: bool =
+        sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM pg_extension WHERE extname = 'vector')")
+            .fetch_one(&database.pool)
+            .await?;
+    if has_pgvector {
+        assert!(!citations.is_empty());
+        assert_eq!(messages.len(), 2);
+    }
+
+    // A prompt the caller embedded already finds the same passages
+    let mut reused = vec![messages.last().unwrap().clone()];
+    let embedding = LocalEmbeddingProvider::new(384).embed_text(&reused[0].content);
+    assert_eq!(
+        retrieval
+            .augment(&shared, &mut reused, Some(embedding))
+            .await,
+        citations
+    );
+
+    for user in [&owner, &editor] {
+        dal.users().delete(user.id).await?;
+    }
+    Ok(())
+}
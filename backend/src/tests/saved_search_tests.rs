@@ -0,0 +1,211 @@
+use anyhow::Result;
+use uuid::Uuid;
+
+use crate::{
+    config::AppConfig,
+    error::AppError,
+    models::{
+        Conversation, CreateConversationRequest, CreateMessageRequest, MessageRole,
+        SaveSearchRequest, SearchFilters, SearchMode,
+    },
+    repositories::Repository,
+    services::{saved_search::SavedSearchService, DataAccessLayer},
+    tests::{create_user, test_dal, test_database},
+};
+
+async fn add_messages(
+    dal: &DataAccessLayer,
+    conversation: &Conversation,
+    contents: &[String],
+) -> Result<()> {
+    let mut parent_id = dal.messages().find_active_leaf_id(conversation.id).await?;
+    for content in contents {
+        let message = dal
+            .messages()
+            .create_from_request(CreateMessageRequest {
+                conversation_id: conversation.id,
+                parent_id,
+                role: MessageRole::User,
+                content: content.clone(),
+                metadata: None,
+                author_id: None,
+            })
+            .await?;
+        parent_id = Some(message.id);
+    }
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "needs DATABASE_URL"]
+async fn test_saved_search_counts_new_matches_until_run() -> Result<()> {
+    let database = test_database().await?;
+    let dal = DataAccessLayer::new(database.clone());
+    let suffix = Uuid::new_v4().simple().to_string();
+    let user = create_user(&dal, "saved").await?;
+    let stranger = create_user(&dal, "saved-stranger").await?;
+    let service = SavedSearchService::new(AppConfig::default(), dal.clone())?;
+
+    let conversation = dal
+        .conversations()
+        .create_from_request(
+            user.id,
+            CreateConversationRequest {
+                title: Some("Deployments".to_string()),
+                model: "gpt-4".to_string(),
+                provider: None,
+                metadata: None,
+                project_id: None,
+            },
+        )
+        .await?;
+
+    // A unique marker keeps other test data out of the results
+    let marker = format!("x{}", &suffix[..10]);
+    add_messages(
+        &dal,
+        &conversation,
+        &[format!("The {} rollout stalled in staging", marker)],
+    )
+    .await?;
+
+    let saved = service
+        .create(
+            user.id,
+            SaveSearchRequest {
+                name: "Rollouts".to_string(),
+                query: marker.clone(),
+                mode: SearchMode::Lexical,
+                filters: SearchFilters::default(),
+                similarity_threshold: None,
+            },
+        )
+        .await?;
+    assert_eq!(saved.new_match_count, 0);
+
+    // Matches from before the search was saved are not new
+    assert_eq!(service.check(&saved).await?, 0);
+
+    add_messages(
+        &dal,
+        &conversation,
+        &[
+            format!("Rolling back the {} deployment", marker),
+            "An unrelated question about lunch".to_string(),
+            format!("Is the {} canary healthy now?", marker),
+        ],
+    )
+    .await?;
+
+    // The background pass picks the search up and stores the count
+    assert!(service.check_due().await? >= 1);
+    let saved = service.get(saved.id, user.id).await?;
+    assert_eq!(saved.new_match_count, 2);
+    let counts = dal.saved_searches().find_match_counts(user.id).await?;
+    assert_eq!(counts.len(), 1);
+    assert_eq!(counts[0].new_match_count, 2);
+
+    // Running the search returns every match and marks the new ones seen
+    let run = service.run(saved.id, user.id, None).await?;
+    assert_eq!(run.search.results.len(), 3);
+    assert_eq!(run.new_since, saved.last_viewed_at);
+    assert_eq!(run.new_message_ids.len(), 2);
+    assert!(run.search.results.iter().all(|r| {
+        run.new_message_ids.contains(&r.message_id) == (r.created_at > run.new_since)
+    }));
+    assert_eq!(run.saved_search.new_match_count, 0);
+    assert_eq!(service.check(&run.saved_search).await?, 0);
+
+    // A check that started before the search was viewed does not overwrite it
+    assert!(
+        !dal.saved_searches()
+            .record_check(saved.id, saved.last_viewed_at, 5, chrono::Utc::now())
+            .await?
+    );
+    assert_eq!(service.get(saved.id, user.id).await?.new_match_count, 0);
+
+    // An imported message dated before the last view is new once it is embedded
+    let imported = dal
+        .messages()
+        .create_from_request(CreateMessageRequest {
+            conversation_id: conversation.id,
+            parent_id: dal.messages().find_active_leaf_id(conversation.id).await?,
+            role: MessageRole::User,
+            content: format!("Last year's {} rollout checklist", marker),
+            metadata: None,
+            author_id: None,
+        })
+        .await?;
+    sqlx::query("UPDATE messages SET created_at = NOW() - INTERVAL '1 year' WHERE id = $1")
+        .bind(imported.id)
+        .execute(&database.pool)
+        .await?;
+    assert_eq!(service.check(&run.saved_search).await?, 0);
+    dal.embeddings()
+        .upsert_embedding(imported.id, vec![0.01; 1536], "text-embedding-3-small")
+        .await?;
+    assert_eq!(service.check(&run.saved_search).await?, 1);
+    let rerun = service.run(saved.id, user.id, None).await?;
+    assert_eq!(rerun.new_message_ids, vec![imported.id]);
+
+    // Other users cannot see, run or delete the search
+    for result in [
+        service.get(saved.id, stranger.id).await.map(|_| ()),
+        service.run(saved.id, stranger.id, None).await.map(|_| ()),
+        service.delete(saved.id, stranger.id).await,
+    ] {
+        let error = AppError::from(result.unwrap_err());
+        assert!(matches!(error, AppError::NotFound(_)));
+    }
+    assert!(service.list(stranger.id).await?.is_empty());
+
+    service.delete(saved.id, user.id).await?;
+    assert!(service.list(user.id).await?.is_empty());
+
+    dal.users().delete(user.id).await?;
+    dal.users().delete(stranger.id).await?;
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "needs DATABASE_URL"]
+async fn test_saved_search_rejects_invalid_requests() -> Result<()> {
+    let dal = test_dal().await?;
+    let user = create_user(&dal, "saved").await?;
+    let service = SavedSearchService::new(AppConfig::default(), dal.clone())?;
+
+    let now = chrono::Utc::now();
+    for request in [
+        SaveSearchRequest {
+            name: String::new(),
+            query: "deploy".to_string(),
+            mode: SearchMode::Hybrid,
+            filters: SearchFilters::default(),
+            similarity_threshold: None,
+        },
+        SaveSearchRequest {
+            name: "Deploys".to_string(),
+            query: "deploy".to_string(),
+            mode: SearchMode::Semantic,
+            filters: SearchFilters::default(),
+            similarity_threshold: Some(1.5),
+        },
+        SaveSearchRequest {
+            name: "Deploys".to_string(),
+            query: "deploy".to_string(),
+            mode: SearchMode::Hybrid,
+            filters: SearchFilters {
+                created_after: Some(now),
+                created_before: Some(now - chrono::Duration::days(1)),
+                ..SearchFilters::default()
+            },
+            similarity_threshold: None,
+        },
+    ] {
+        let error = AppError::from(service.create(user.id, request).await.unwrap_err());
+        assert!(matches!(error, AppError::BadRequest(_)));
+    }
+
+    dal.users().delete(user.id).await?;
+    Ok(())
+}
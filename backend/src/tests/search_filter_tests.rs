@@ -0,0 +1,229 @@
+use anyhow::Result;
+use uuid::Uuid;
+
+use crate::{
+    config::AppConfig,
+    error::AppError,
+    models::{
+        CreateConversationRequest, CreateMessageRequest, CreateMessageResponse, MessageRole,
+        SearchFilters, SearchMode, SearchResultResponse,
+    },
+    repositories::Repository,
+    services::{
+        embedding::{EmbeddingService, MessageSearch},
+        DataAccessLayer,
+    },
+    tests::{create_user, test_dal},
+};
+
+async fn add_message(
+    dal: &DataAccessLayer,
+    conversation_id: Uuid,
+    parent_id: Option<Uuid>,
+    role: MessageRole,
+    content: String,
+    metadata: Option<serde_json::Value>,
+) -> Result<CreateMessageResponse> {
+    dal.messages()
+        .create_from_request(CreateMessageRequest {
+            conversation_id,
+            parent_id,
+            role,
+            content,
+            metadata,
+            author_id: None,
+        })
+        .await
+}
+
+fn ids(search: &MessageSearch) -> Vec<Uuid> {
+    let mut ids: Vec<Uuid> = search.results.iter().map(|r| r.message_id).collect();
+    ids.sort();
+    ids
+}
+
+fn sorted(mut ids: Vec<Uuid>) -> Vec<Uuid> {
+    ids.sort();
+    ids
+}
+
+#[tokio::test]
+#[ignore = "needs DATABASE_URL"]
+async fn test_search_filters_facets_and_snippets() -> Result<()> {
+    let dal = test_dal().await?;
+    let suffix = Uuid::new_v4().simple().to_string();
+    let user = create_user(&dal, "filters").await?;
+
+    let mut conversations = Vec::new();
+    for (title, model, provider) in [
+        ("Cluster", "gpt-4", "openai"),
+        ("Staging", "claude-3-opus", "anthropic"),
+    ] {
+        let conversation = dal
+            .conversations()
+            .create_from_request(
+                user.id,
+                CreateConversationRequest {
+                    title: Some(title.to_string()),
+                    model: model.to_string(),
+                    provider: Some(provider.to_string()),
+                    metadata: None,
+                    project_id: None,
+                },
+            )
+            .await?;
+        conversations.push(conversation);
+    }
+
+    // A unique marker keeps other test data out of the results
+    let marker = format!("x{}", &suffix[..10]);
+    let question = add_message(
+        &dal,
+        conversations[0].id,
+        None,
+        MessageRole::User,
+        format!("How do I expose {} services through an ingress?", marker),
+        None,
+    )
+    .await?;
+    let replaced = add_message(
+        &dal,
+        conversations[0].id,
+        Some(question.id),
+        MessageRole::Assistant,
+        format!("Install an ingress controller for {} first.", marker),
+        Some(serde_json::json!({ "model": "gpt-4o", "provider": "openai" })),
+    )
+    .await?;
+    // A regenerated answer moves the selected branch away from the first one
+    let answer = add_message(
+        &dal,
+        conversations[0].id,
+        Some(question.id),
+        MessageRole::Assistant,
+        format!("Annotate the {} ingress with the rewrite target.", marker),
+        None,
+    )
+    .await?;
+    let staging = add_message(
+        &dal,
+        conversations[1].id,
+        None,
+        MessageRole::User,
+        format!("The {} ingress returns 404 in staging", marker),
+        None,
+    )
+    .await?;
+
+    let service = EmbeddingService::new(AppConfig::default(), dal.clone())?;
+    let query = format!("{} ingress", marker);
+    let search = |filters: SearchFilters| {
+        let service = service.clone();
+        let query = query.clone();
+        async move {
+            service
+                .search_messages(
+                    &query,
+                    Some(user.id),
+                    SearchMode::Lexical,
+                    &filters,
+                    None,
+                    None,
+                )
+                .await
+        }
+    };
+
+    // By default only the selected branch is searched
+    let results = search(SearchFilters::default()).await?;
+    assert_eq!(
+        ids(&results),
+        sorted(vec![question.id, answer.id, staging.id])
+    );
+    let conversation_counts: Vec<(Uuid, usize)> = results
+        .facets
+        .conversations
+        .iter()
+        .map(|facet| (facet.conversation_id, facet.count))
+        .collect();
+    assert_eq!(
+        conversation_counts,
+        vec![(conversations[0].id, 2), (conversations[1].id, 1)]
+    );
+    assert_eq!(
+        results.facets.conversations[1].title.as_deref(),
+        Some("Staging")
+    );
+    assert_eq!(results.facets.roles[0].value, "user");
+    assert_eq!(results.facets.roles[0].count, 2);
+    assert_eq!(results.facets.providers[0].value, "openai");
+    assert_eq!(results.facets.providers[0].count, 2);
+
+    // Matched terms are highlighted in the snippet
+    for result in results.results.clone() {
+        let response = SearchResultResponse::from_search_result(result);
+        assert!(response
+            .snippet
+            .iter()
+            .any(|part| part.highlighted && part.text.eq_ignore_ascii_case("ingress")));
+        assert!(response.snippet.iter().any(|part| !part.highlighted));
+    }
+
+    let everything = SearchFilters {
+        active_branch_only: false,
+        ..SearchFilters::default()
+    };
+    assert_eq!(ids(&search(everything.clone()).await?).len(), 4);
+
+    let results = search(SearchFilters {
+        conversation_ids: Some(vec![conversations[1].id]),
+        ..SearchFilters::default()
+    })
+    .await?;
+    assert_eq!(ids(&results), vec![staging.id]);
+
+    let results = search(SearchFilters {
+        role: Some(MessageRole::Assistant),
+        ..everything.clone()
+    })
+    .await?;
+    assert_eq!(ids(&results), sorted(vec![replaced.id, answer.id]));
+
+    // Messages carry the model that wrote them; others fall back to the conversation's
+    let results = search(SearchFilters {
+        model: Some("gpt-4o".to_string()),
+        ..everything.clone()
+    })
+    .await?;
+    assert_eq!(ids(&results), vec![replaced.id]);
+    let results = search(SearchFilters {
+        provider: Some("anthropic".to_string()),
+        ..SearchFilters::default()
+    })
+    .await?;
+    assert_eq!(ids(&results), vec![staging.id]);
+
+    let results = search(SearchFilters {
+        created_after: Some(staging.created_at),
+        ..SearchFilters::default()
+    })
+    .await?;
+    assert_eq!(ids(&results), vec![staging.id]);
+    let results = search(SearchFilters {
+        created_before: Some(staging.created_at),
+        ..SearchFilters::default()
+    })
+    .await?;
+    assert_eq!(ids(&results), sorted(vec![question.id, answer.id]));
+
+    let inverted = search(SearchFilters {
+        created_after: Some(staging.created_at),
+        created_before: Some(question.created_at),
+        ..SearchFilters::default()
+    })
+    .await;
+    assert!(matches!(inverted, Err(AppError::BadRequest(_))));
+
+    dal.users().delete(user.id).await?;
+    Ok(())
+}
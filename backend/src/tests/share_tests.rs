@@ -0,0 +1,146 @@
+use anyhow::Result;
+
+use crate::{
+    models::{
+        CreateConversationRequest, CreateMessageRequest, CreateShareLinkRequest, MessageRole,
+    },
+    repositories::Repository,
+    services::{conversation::ConversationService, share::ShareService, DataAccessLayer},
+    tests::{create_user, test_database},
+};
+
+#[tokio::test]
+#[ignore = "needs DATABASE_URL"]
+async fn test_share_link_snapshot_expiry_and_revoke() -> Result<()> {
+    let database = test_database().await?;
+    let dal = DataAccessLayer::new(database.clone());
+    let conversation_service = ConversationService::new(dal.clone());
+    let share_service = ShareService::new(dal.clone());
+
+    let user = create_user(&dal, "share").await?;
+    let conversation = conversation_service
+        .create_conversation(
+            user.id,
+            CreateConversationRequest {
+                title: Some("Findings".to_string()),
+                model: "gpt-4".to_string(),
+                provider: None,
+                metadata: None,
+                project_id: None,
+            },
+        )
+        .await?;
+
+    let question = dal
+        .messages()
+        .create_from_request(CreateMessageRequest {
+            conversation_id: conversation.id,
+            parent_id: None,
+            role: MessageRole::User,
+            content: "What did we find?".to_string(),
+            metadata: None,
+            author_id: None,
+        })
+        .await?;
+    let answer = dal
+        .messages()
+        .create_from_request(CreateMessageRequest {
+            conversation_id: conversation.id,
+            parent_id: Some(question.id),
+            role: MessageRole::Assistant,
+            content: "A memory leak.".to_string(),
+            metadata: Some(serde_json::json!({ "reasoning": "private" })),
+            author_id: None,
+        })
+        .await?;
+    // An alternative answer that is not on the active thread
+    dal.messages()
+        .create_branch(
+            question.id,
+            "A race condition.".to_string(),
+            MessageRole::Assistant,
+            None,
+        )
+        .await?;
+    dal.messages().switch_to_branch(answer.id).await?;
+
+    let link = share_service
+        .create_share_link(&conversation, &CreateShareLinkRequest::default())
+        .await?;
+    assert_eq!(link.token.len(), 64);
+    assert!(link.expires_at.is_none());
+
+    let with_branches = share_service
+        .create_share_link(
+            &conversation,
+            &CreateShareLinkRequest {
+                expires_in_hours: Some(24),
+                include_branches: true,
+            },
+        )
+        .await?;
+    assert!(with_branches.expires_at.is_some());
+
+    // Later messages don't show up in the frozen snapshot
+    dal.messages()
+        .create_from_request(CreateMessageRequest {
+            conversation_id: conversation.id,
+            parent_id: Some(answer.id),
+            role: MessageRole::User,
+            content: "Written after sharing".to_string(),
+            metadata: None,
+            author_id: None,
+        })
+        .await?;
+
+    let share_links = dal.share_links();
+    let snapshot = share_links
+        .view_snapshot(&link.token)
+        .await?
+        .expect("active link");
+    assert_eq!(snapshot.title.as_deref(), Some("Findings"));
+    assert_eq!(
+        snapshot.messages.iter().map(|m| m.id).collect::<Vec<_>>(),
+        vec![question.id, answer.id]
+    );
+    assert!(snapshot.branches.is_none());
+    let json = serde_json::to_value(&snapshot)?;
+    assert!(!json.to_string().contains("private"));
+
+    let snapshot = share_links
+        .view_snapshot(&with_branches.token)
+        .await?
+        .expect("active link");
+    let branches = snapshot.branches.expect("branches included");
+    assert_eq!(branches.len(), 1);
+    assert_eq!(branches[0].content, "A race condition.");
+
+    let listed = share_links.find_by_conversation_id(conversation.id).await?;
+    assert_eq!(listed.len(), 2);
+    assert_eq!(
+        share_links
+            .find_by_id(link.id)
+            .await?
+            .expect("link exists")
+            .view_count,
+        1
+    );
+
+    // Revoked, unknown and expired tokens resolve to nothing
+    assert!(share_links.revoke(link.id).await?);
+    assert!(!share_links.revoke(link.id).await?);
+    assert!(share_links.view_snapshot(&link.token).await?.is_none());
+    assert!(share_links.view_snapshot("unknown").await?.is_none());
+
+    sqlx::query("UPDATE share_links SET expires_at = NOW() - INTERVAL '1 minute' WHERE id = $1")
+        .bind(with_branches.id)
+        .execute(&database.pool)
+        .await?;
+    assert!(share_links
+        .view_snapshot(&with_branches.token)
+        .await?
+        .is_none());
+
+    dal.users().delete(user.id).await?;
+    Ok(())
+}
@@ -0,0 +1,173 @@
+use anyhow::Result;
+use chrono::Utc;
+use uuid::Uuid;
+
+use crate::{
+    error::AppError,
+    models::{
+        ApiUsage, CreateConversationRequest, CreateMessageRequest, CreateMessageResponse,
+        MessageRole,
+    },
+    repositories::{attachment::CreateAttachment, Repository},
+    services::{conversation::ConversationService, DataAccessLayer},
+    tests::{create_user, test_dal},
+};
+
+async fn create_message(
+    dal: &DataAccessLayer,
+    conversation_id: Uuid,
+    parent_id: Option<Uuid>,
+    role: MessageRole,
+    metadata: Option<serde_json::Value>,
+) -> Result<CreateMessageResponse> {
+    dal.messages()
+        .create_from_request(CreateMessageRequest {
+            conversation_id,
+            parent_id,
+            role,
+            content: "Message".to_string(),
+            metadata,
+            author_id: None,
+        })
+        .await
+}
+
+#[tokio::test]
+#[ignore = "needs DATABASE_URL"]
+async fn test_conversation_stats_aggregate_usage_branches_and_files() -> Result<()> {
+    let dal = test_dal().await?;
+    let conversation_service = ConversationService::new(dal.clone());
+
+    let suffix = Uuid::new_v4().simple().to_string();
+    let user = create_user(&dal, "stats").await?;
+    let stranger = create_user(&dal, "stats-stranger").await?;
+
+    let conversation = conversation_service
+        .create_conversation(
+            user.id,
+            CreateConversationRequest {
+                title: Some("Stats".to_string()),
+                model: "gpt-4".to_string(),
+                provider: None,
+                metadata: None,
+                project_id: None,
+            },
+        )
+        .await?;
+
+    let question = create_message(&dal, conversation.id, None, MessageRole::User, None).await?;
+    // Tokens recorded on the usage record win over the ones in the metadata
+    let first_answer = create_message(
+        &dal,
+        conversation.id,
+        Some(question.id),
+        MessageRole::Assistant,
+        Some(serde_json::json!({
+            "model": "gpt-4",
+            "latency_ms": 100,
+            "usage": {"prompt_tokens": 10, "completion_tokens": 20, "total_tokens": 30}
+        })),
+    )
+    .await?;
+    dal.api_usage()
+        .create(&ApiUsage {
+            id: Uuid::new_v4(),
+            user_id: user.id,
+            model: "gpt-4".to_string(),
+            provider: "openai".to_string(),
+            tokens_prompt: Some(10),
+            tokens_completion: Some(25),
+            cost_cents: Some(3),
+            message_id: Some(first_answer.id),
+            created_at: Utc::now(),
+        })
+        .await?;
+    // Without a usage record the metadata is used, counting reasoning as completion
+    let second_answer = create_message(
+        &dal,
+        conversation.id,
+        Some(question.id),
+        MessageRole::Assistant,
+        Some(serde_json::json!({
+            "model": "claude-3-opus",
+            "latency_ms": 300,
+            "usage": {
+                "prompt_tokens": 5,
+                "completion_tokens": 7,
+                "total_tokens": 12,
+                "reasoning_tokens": 3
+            }
+        })),
+    )
+    .await?;
+    create_message(
+        &dal,
+        conversation.id,
+        Some(second_answer.id),
+        MessageRole::User,
+        None,
+    )
+    .await?;
+
+    for (message_id, size_bytes) in [(question.id, 42), (second_answer.id, 8)] {
+        dal.attachments()
+            .create(CreateAttachment {
+                message_id,
+                filename: "notes.txt".to_string(),
+                content_type: Some("text/plain".to_string()),
+                size_bytes: Some(size_bytes),
+                storage_path: format!("uploads/{}/notes.txt", suffix),
+            })
+            .await?;
+    }
+    dal.embeddings()
+        .upsert_embedding(question.id, vec![0.01; 1536], "text-embedding-3-small")
+        .await?;
+
+    let stats = conversation_service
+        .get_conversation_stats(conversation.id, user.id)
+        .await?;
+    assert_eq!(stats.message_count, 3);
+    assert_eq!(stats.total_messages, 4);
+    assert_eq!(stats.branch_count, 2);
+    assert_eq!(stats.max_depth, 3);
+    assert_eq!(stats.prompt_tokens, 15);
+    assert_eq!(stats.completion_tokens, 35);
+    assert_eq!(stats.total_tokens, 50);
+    assert_eq!(stats.cost_cents, 3);
+    assert_eq!(
+        stats
+            .models
+            .iter()
+            .map(|m| (m.model.as_str(), m.message_count))
+            .collect::<Vec<_>>(),
+        vec![("claude-3-opus", 1), ("gpt-4", 1)]
+    );
+    assert_eq!(stats.average_latency_ms, Some(200.0));
+    assert_eq!(stats.attachment_count, 2);
+    assert_eq!(stats.attachment_bytes, 50);
+    assert_eq!(stats.embedded_messages, 1);
+    assert_eq!(stats.embedding_coverage, 0.25);
+
+    // Deleted answers leave the counts but were still paid for
+    dal.messages().delete(first_answer.id).await?;
+    let stats = conversation_service
+        .get_conversation_stats(conversation.id, user.id)
+        .await?;
+    assert_eq!(stats.total_messages, 3);
+    assert_eq!(stats.branch_count, 1);
+    assert_eq!(stats.prompt_tokens, 15);
+    assert_eq!(stats.cost_cents, 3);
+    assert_eq!(stats.models.len(), 1);
+    assert_eq!(stats.average_latency_ms, Some(300.0));
+
+    let denied = conversation_service
+        .get_conversation_stats(conversation.id, stranger.id)
+        .await
+        .unwrap_err();
+    assert!(matches!(AppError::from(denied), AppError::Forbidden(_)));
+
+    dal.users().delete(user.id).await?;
+    dal.users().delete(stranger.id).await?;
+    Ok(())
+}
@@ -0,0 +1,146 @@
+use anyhow::Result;
+use std::collections::HashSet;
+
+use crate::{
+    config::{AppConfig, EmbeddingConfig},
+    models::{CreateConversationRequest, CreateMessageRequest, MessageRole},
+    repositories::Repository,
+    services::{embedding::EmbeddingService, topic::TopicService},
+    tests::{create_user, embed_message, test_dal},
+};
+
+#[tokio::test]
+#[ignore = "needs DATABASE_URL"]
+async fn test_topics_cluster_and_map_messages() -> Result<()> {
+    let dal = test_dal().await?;
+    let user = create_user(&dal, "topics").await?;
+
+    let config = AppConfig {
+        embedding: EmbeddingConfig {
+            provider: "local".to_string(),
+            model_path: None,
+            model: "hashed-ngrams-v1".to_string(),
+            dimensions: 384,
+            api_base: None,
+            api_key: String::new(),
+        },
+        ..AppConfig::default()
+    };
+    let embedding_service = EmbeddingService::new(config.clone(), dal.clone())?;
+    let topic_service = TopicService::new(config, dal.clone())?;
+
+    let themes = [
+        (
+            "Cluster networking",
+            [
+                "Kubernetes ingress controller returns 404 for every service",
+                "Kubernetes ingress annotations for path rewrites",
+                "Kubernetes service selector does not match the deployment pods",
+                "Kubernetes pods cannot resolve the service through cluster DNS",
+                "Kubernetes ingress TLS secret for the staging cluster",
+                "Kubernetes network policy blocks traffic between pods",
+            ],
+        ),
+        (
+            "Baking",
+            [
+                "Sourdough starter doubles overnight but the bread stays flat",
+                "Sourdough bread hydration with rye flour and spelt flour",
+                "Sourdough crumb is dense after a cold overnight proof",
+                "Sourdough starter feeding ratio of flour and water",
+                "Sourdough bread crust stays pale in a dutch oven",
+                "Sourdough loaf spreads when the flour is too weak",
+            ],
+        ),
+    ];
+
+    let mut by_conversation = Vec::new();
+    for (title, prompts) in themes {
+        let conversation = dal
+            .conversations()
+            .create_from_request(
+                user.id,
+                CreateConversationRequest {
+                    title: Some(title.to_string()),
+                    model: "gpt-4".to_string(),
+                    provider: None,
+                    metadata: None,
+                    project_id: None,
+                },
+            )
+            .await?;
+
+        let mut message_ids = HashSet::new();
+        let mut parent_id = None;
+        for prompt in prompts {
+            let message = dal
+                .messages()
+                .create_from_request(CreateMessageRequest {
+                    conversation_id: conversation.id,
+                    parent_id,
+                    role: MessageRole::User,
+                    content: prompt.to_string(),
+                    metadata: None,
+                    author_id: None,
+                })
+                .await?;
+            embed_message(&embedding_service, &dal, message.id, prompt).await?;
+            parent_id = Some(message.id);
+            message_ids.insert(message.id);
+        }
+        by_conversation.push((conversation.id, message_ids));
+    }
+
+    let clusters = topic_service.refresh(user.id).await?;
+    assert_eq!(clusters.len(), 2);
+    assert_eq!(clusters.iter().map(|c| c.size).sum::<i32>(), 12);
+    assert!(clusters.iter().all(|c| c.model == "hashed-ngrams-v1"));
+
+    // The two themes land in separate topics, named by their keywords without an LLM
+    let topics = dal.topics().find_members(user.id).await?;
+    assert_eq!(topics.len(), 2);
+    for topic in &topics {
+        let (conversation_id, message_ids) = by_conversation
+            .iter()
+            .find(|(id, _)| topic.conversation_ids == vec![*id])
+            .expect("each topic comes from one conversation");
+        assert_eq!(
+            topic.message_ids.iter().copied().collect::<HashSet<_>>(),
+            *message_ids
+        );
+        assert!(topic.conversation_ids.contains(conversation_id));
+        assert!(!topic.cluster.keywords.is_empty());
+        assert_eq!(topic.cluster.label, topic.cluster.keywords.join(", "));
+    }
+    let keywords: Vec<&String> = topics
+        .iter()
+        .flat_map(|topic| &topic.cluster.keywords)
+        .collect();
+    assert!(keywords.iter().any(|term| *term == "kubernetes"));
+    assert!(keywords.iter().any(|term| *term == "sourdough"));
+
+    let points = dal.topics().find_points(user.id).await?;
+    assert_eq!(points.len(), 12);
+    assert!(points
+        .iter()
+        .all(|p| (-1.0..=1.0).contains(&p.x) && (-1.0..=1.0).contains(&p.y)));
+
+    // Running again replaces the previous topics, also when runs overlap
+    let rerun = topic_service.refresh(user.id).await?;
+    assert_eq!(rerun.len(), 2);
+    let (first, second) = tokio::join!(
+        topic_service.refresh_users(Some(user.id)),
+        topic_service.refresh_users(Some(user.id))
+    );
+    assert_eq!((first?, second?), (1, 1));
+    assert_eq!(dal.topics().find_clusters(user.id).await?.len(), 2);
+    assert_eq!(dal.topics().find_points(user.id).await?.len(), 12);
+
+    // Too few messages leave no topics behind
+    dal.conversations().delete(by_conversation[0].0).await?;
+    assert!(topic_service.refresh(user.id).await?.is_empty());
+    assert!(dal.topics().find_clusters(user.id).await?.is_empty());
+
+    dal.users().delete(user.id).await?;
+    Ok(())
+}
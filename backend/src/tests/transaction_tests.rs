@@ -0,0 +1,171 @@
+use anyhow::Result;
+use uuid::Uuid;
+
+use crate::{
+    models::{CreateConversationRequest, CreateMessageRequest, Message, MessageRole},
+    repositories::Repository,
+    tests::{create_user, test_dal},
+};
+
+fn conversation_request() -> CreateConversationRequest {
+    CreateConversationRequest {
+        title: Some("Unit of work".to_string()),
+        model: "gpt-4".to_string(),
+        provider: None,
+        metadata: None,
+        project_id: None,
+    }
+}
+
+fn message(conversation_id: Uuid, parent_id: Option<Uuid>, content: &str) -> CreateMessageRequest {
+    CreateMessageRequest {
+        conversation_id,
+        parent_id,
+        role: MessageRole::User,
+        content: content.to_string(),
+        metadata: None,
+        author_id: None,
+    }
+}
+
+#[tokio::test]
+#[ignore = "needs DATABASE_URL"]
+async fn test_transaction_commits_or_rolls_back_every_step() -> Result<()> {
+    let dal = test_dal().await?;
+    let user = create_user(&dal, "uow").await?;
+
+    // A failure after several writes, including a nested branch operation, undoes all of them
+    let result: Result<()> = dal
+        .transaction(|dal| async move {
+            let conversation = dal
+                .conversations()
+                .create_from_request(user.id, conversation_request())
+                .await?;
+            assert_eq!(conversation.user_id, user.id);
+            let question = dal
+                .messages()
+                .create_from_request(message(conversation.id, None, "Question"))
+                .await?;
+            dal.messages()
+                .create_branch(
+                    question.id,
+                    "Answer".to_string(),
+                    MessageRole::Assistant,
+                    None,
+                )
+                .await?;
+            dal.messages().switch_to_branch(question.id).await?;
+
+            // Uncommitted writes are visible inside the transaction
+            assert_eq!(
+                dal.messages()
+                    .find_message_branches(question.id)
+                    .await?
+                    .len(),
+                1
+            );
+
+            Err(anyhow::anyhow!("injected failure"))
+        })
+        .await;
+    assert_eq!(result.unwrap_err().to_string(), "injected failure");
+    assert_eq!(dal.conversations().count_by_user(user.id).await?, 0);
+
+    // A failing step inside a repository operation rolls back the earlier steps of the caller
+    let result = dal
+        .transaction(|dal| async move {
+            let conversation = dal
+                .conversations()
+                .create_from_request(user.id, conversation_request())
+                .await?;
+            dal.messages()
+                .create_from_request(message(conversation.id, None, "Kept?"))
+                .await?;
+            dal.messages()
+                .create_branch(
+                    Uuid::new_v4(),
+                    "Orphan".to_string(),
+                    MessageRole::User,
+                    None,
+                )
+                .await
+        })
+        .await;
+    assert!(result.is_err());
+    assert_eq!(dal.conversations().count_by_user(user.id).await?, 0);
+
+    // A batch that fails halfway, with a reply written before its parent, leaves no conversation
+    let result = dal
+        .transaction(|dal| async move {
+            let conversation = dal
+                .conversations()
+                .create_from_request(user.id, conversation_request())
+                .await?;
+            let parent_id = Uuid::new_v4();
+            let batch: Vec<Message> = [(Uuid::new_v4(), Some(parent_id)), (parent_id, None)]
+                .into_iter()
+                .map(|(id, parent_id)| Message {
+                    id,
+                    conversation_id: conversation.id,
+                    parent_id,
+                    role: MessageRole::User,
+                    content: "Out of order".to_string(),
+                    tokens_used: None,
+                    created_at: chrono::Utc::now(),
+                    is_active: true,
+                    metadata: serde_json::json!({}),
+                    author_id: None,
+                    edited_from: None,
+                    deleted_at: None,
+                })
+                .collect();
+            dal.messages().create_many(&batch).await
+        })
+        .await;
+    assert!(result.is_err());
+    assert_eq!(dal.conversations().count_by_user(user.id).await?, 0);
+
+    // On success everything is committed together
+    let (conversation_id, answer_id) = dal
+        .transaction(|dal| async move {
+            let conversation = dal
+                .conversations()
+                .create_from_request(user.id, conversation_request())
+                .await?;
+            let question = dal
+                .messages()
+                .create_from_request(message(conversation.id, None, "Question"))
+                .await?;
+            let answer = dal
+                .messages()
+                .create_branch(
+                    question.id,
+                    "Answer".to_string(),
+                    MessageRole::Assistant,
+                    None,
+                )
+                .await?;
+            Ok((conversation.id, answer.id))
+        })
+        .await?;
+    let conversation = dal
+        .conversations()
+        .find_by_id(conversation_id)
+        .await?
+        .unwrap();
+    assert_eq!(conversation.active_leaf_id, Some(answer_id));
+    assert_eq!(
+        dal.messages()
+            .find_active_conversation_thread(conversation_id)
+            .await?
+            .len(),
+        2
+    );
+
+    // Repositories that escape the closure cannot use the finished transaction
+    let escaped = dal.transaction(|dal| async move { Ok(dal) }).await?;
+    assert!(escaped.messages().find_by_id(answer_id).await.is_err());
+
+    dal.users().delete(user.id).await?;
+    Ok(())
+}
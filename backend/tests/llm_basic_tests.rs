@@ -1,4 +1,7 @@
-use workbench_server::llm::{ChatMessage, ChatRequest, LLMServiceFactory, Provider};
+use workbench_server::llm::{
+    claude_code, openai, ChatMessage, ChatRequest, LLMServiceFactory, Provider, StreamEventType,
+    Usage,
+};
 
 #[test]
 fn test_available_models() {
@@ -65,3 +68,130 @@ fn test_chat_request_serialization() {
     assert_eq!(deserialized.messages.len(), 1);
     assert_eq!(deserialized.messages[0].content, "Hello, world!");
 }
+
+#[test]
+fn test_cost_counts_reasoning_as_output() {
+    let without_reasoning = Usage {
+        prompt_tokens: 1000,
+        completion_tokens: 1000,
+        total_tokens: 2000,
+        reasoning_tokens: 0,
+        cached_tokens: 0,
+    };
+    let with_reasoning = Usage {
+        reasoning_tokens: 1000,
+        total_tokens: 3000,
+        ..without_reasoning.clone()
+    };
+
+    assert_eq!(with_reasoning.output_tokens(), 2000);
+
+    let base = LLMServiceFactory::calculate_cost_cents("gpt-4", &without_reasoning)
+        .expect("gpt-4 has a cost");
+    let reasoning = LLMServiceFactory::calculate_cost_cents("gpt-4", &with_reasoning)
+        .expect("gpt-4 has a cost");
+    assert!(reasoning > base, "Reasoning tokens should be billed");
+
+    // Subscription models carry no direct cost
+    assert!(
+        LLMServiceFactory::calculate_cost_cents("claude-code-sonnet", &with_reasoning).is_none()
+    );
+}
+
+#[test]
+fn test_usage_deserializes_without_reasoning_fields() {
+    let usage: Usage = serde_json::from_str(
+        r#"{"prompt_tokens": 10, "completion_tokens": 20, "total_tokens": 30}"#,
+    )
+    .expect("Should deserialize");
+    assert_eq!(usage.reasoning_tokens, 0);
+    assert_eq!(usage.cached_tokens, 0);
+}
+
+#[test]
+fn test_openai_usage_excludes_reasoning_from_completion() {
+    let body = serde_json::json!({
+        "choices": [{
+            "index": 0,
+            "message": {"role": "assistant", "content": "42", "reasoning_content": "Six times seven."},
+            "finish_reason": "stop"
+        }],
+        "usage": {
+            "prompt_tokens": 50,
+            "completion_tokens": 1000,
+            "total_tokens": 1050,
+            "prompt_tokens_details": {"cached_tokens": 20},
+            "completion_tokens_details": {"reasoning_tokens": 600}
+        }
+    });
+
+    let usage = openai::usage_from_json(&body["usage"]).expect("Should have usage");
+    assert_eq!(usage.completion_tokens, 400);
+    assert_eq!(usage.reasoning_tokens, 600);
+    assert_eq!(usage.cached_tokens, 20);
+    // Reasoning is billed once, not on top of a completion figure that already has it
+    assert_eq!(usage.output_tokens(), 1000);
+    assert_eq!(
+        openai::reasoning_from_json(&body).as_deref(),
+        Some("Six times seven.")
+    );
+
+    let plain = openai::usage_from_json(&serde_json::json!({
+        "prompt_tokens": 5, "completion_tokens": 7, "total_tokens": 12
+    }))
+    .expect("Should have usage");
+    assert_eq!(plain.completion_tokens, 7);
+    assert_eq!(plain.reasoning_tokens, 0);
+}
+
+const CLAUDE_CODE_OUTPUT: &str = r#"{"type":"system","subtype":"init","session_id":"abc","model":"claude-sonnet-4"}
+{"type":"assistant","message":{"model":"claude-sonnet-4","content":[{"type":"thinking","thinking":"The user wants a greeting."}]}}
+{"type":"assistant","message":{"model":"claude-sonnet-4","content":[{"type":"text","text":"Hello!"}]}}
+{"type":"result","subtype":"success","is_error":false,"result":"Hello!","usage":{"input_tokens":12,"output_tokens":30}}"#;
+
+#[test]
+fn test_claude_code_stream_events_carry_thinking() {
+    let events: Vec<_> = CLAUDE_CODE_OUTPUT
+        .lines()
+        .flat_map(|line| claude_code::stream_events_from_line(line).expect("Should parse"))
+        .collect();
+
+    let kinds: Vec<_> = events.iter().map(|event| &event.event_type).collect();
+    assert!(matches!(
+        kinds.as_slice(),
+        [
+            StreamEventType::Reasoning,
+            StreamEventType::Token,
+            StreamEventType::Done
+        ]
+    ));
+    assert_eq!(
+        events[0].data.as_deref(),
+        Some("The user wants a greeting.")
+    );
+    assert_eq!(events[1].data.as_deref(), Some("Hello!"));
+    let usage = events[2].usage.as_ref().expect("Done should carry usage");
+    assert_eq!(usage.completion_tokens, 30);
+}
+
+#[test]
+fn test_claude_code_response_collects_reasoning() {
+    let response =
+        claude_code::response_from_stream_output(CLAUDE_CODE_OUTPUT, "claude-code-sonnet")
+            .expect("Should parse");
+    assert_eq!(response.message.content, "Hello!");
+    assert_eq!(
+        response.reasoning.as_deref(),
+        Some("The user wants a greeting.")
+    );
+
+    let error = r#"{"type":"result","subtype":"error","is_error":true,"result":"Invalid API key"}"#;
+    assert!(matches!(
+        claude_code::response_from_stream_output(error, "claude-code-sonnet"),
+        Err(claude_code::ClaudeCodeOutputError::Api(message)) if message == "Invalid API key"
+    ));
+    assert!(matches!(
+        claude_code::response_from_stream_output("not json", "claude-code-sonnet"),
+        Err(claude_code::ClaudeCodeOutputError::Invalid(_))
+    ));
+}
@@ -90,22 +90,42 @@ export const SearchResults: React.FC<SearchResultsProps> = ({
               </div>
             </div>
 
-            {/* Similarity Score */}
-            <div className="flex items-center gap-1 text-xs flex-shrink-0">
-              <Zap className={`h-3 w-3 ${getSimilarityColor(result.similarity)}`} />
-              <span className={getSimilarityColor(result.similarity)}>
-                {getSimilarityLabel(result.similarity)}
-              </span>
-              <span className="text-gray-400 dark:text-gray-500">
-                ({Math.round(result.similarity * 100)}%)
-              </span>
-            </div>
+            {/* Similarity Score, or an exact term match when only the text index matched */}
+            {result.similarity != null ? (
+              <div className="flex items-center gap-1 text-xs flex-shrink-0">
+                <Zap className={`h-3 w-3 ${getSimilarityColor(result.similarity)}`} />
+                <span className={getSimilarityColor(result.similarity)}>
+                  {getSimilarityLabel(result.similarity)}
+                </span>
+                <span className="text-gray-400 dark:text-gray-500">
+                  ({Math.round(result.similarity * 100)}%)
+                </span>
+              </div>
+            ) : (
+              <div className="flex items-center gap-1 text-xs flex-shrink-0 text-gray-600 dark:text-gray-400">
+                <Zap className="h-3 w-3" />
+                <span>Term match</span>
+              </div>
+            )}
           </div>
 
-          {/* Message Preview */}
+          {/* Snippet with the matched terms highlighted, or the preview */}
           <div className="mb-2">
             <p className="text-sm text-gray-800 dark:text-gray-200 line-clamp-3">
-              {result.preview}
+              {result.snippet?.length
+                ? result.snippet.map((part, index) =>
+                    part.highlighted ? (
+                      <mark
+                        key={index}
+                        className="bg-yellow-200 dark:bg-yellow-700 text-inherit rounded-sm"
+                      >
+                        {part.text}
+                      </mark>
+                    ) : (
+                      <React.Fragment key={index}>{part.text}</React.Fragment>
+                    )
+                  )
+                : result.preview}
             </p>
           </div>
 
@@ -74,7 +74,7 @@ export const useConversationStore = create<ConversationState>()(
           }
 
           set({
-            conversations: response.data || [],
+            conversations: response.data?.conversations || [],
             isLoading: false,
             error: null
           });
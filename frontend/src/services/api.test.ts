@@ -202,7 +202,7 @@ describe('ApiClient', () => {
         const mockResponse = {
           ok: true,
           status: 200,
-          json: vi.fn().mockResolvedValue([mockConversation]),
+          json: vi.fn().mockResolvedValue({ conversations: [mockConversation], next_cursor: null, total: 1 }),
         };
         mockFetch.mockResolvedValue(mockResponse);
 
@@ -212,25 +212,25 @@ describe('ApiClient', () => {
           '/api/v1/conversations?',
           expect.any(Object)
         );
-        expect(result.data).toEqual([mockConversation]);
+        expect(result.data?.conversations).toEqual([mockConversation]);
       });
 
       it('should fetch conversations with pagination', async () => {
         const mockResponse = {
           ok: true,
           status: 200,
-          json: vi.fn().mockResolvedValue([mockConversation]),
+          json: vi.fn().mockResolvedValue({ conversations: [mockConversation], next_cursor: null, total: 1 }),
         };
         mockFetch.mockResolvedValue(mockResponse);
 
-        const pagination: PaginationParams = { page: 2, limit: 10 };
+        const pagination: PaginationParams = { cursor: 'u.1700000000000000.abc', limit: 10 };
         const result = await apiClient.getConversations(pagination);
 
         expect(mockFetch).toHaveBeenCalledWith(
-          '/api/v1/conversations?page=2&limit=10',
+          '/api/v1/conversations?cursor=u.1700000000000000.abc&limit=10',
           expect.any(Object)
         );
-        expect(result.data).toEqual([mockConversation]);
+        expect(result.data?.conversations).toEqual([mockConversation]);
       });
     });
 
@@ -348,7 +348,8 @@ describe('ApiClient', () => {
         const mockMessagesResponse = {
           messages: [mockMessage],
           conversation_id: 'conv-123',
-          total_count: 1,
+          next_cursor: null,
+          total: 1,
         };
         const mockResponse = {
           ok: true,
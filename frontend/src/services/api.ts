@@ -1,12 +1,27 @@
 // API service for communicating with the workbench backend
 
 import {
+  Attachment,
+  Bookmark,
+  BranchComparison,
   Conversation,
+  ConversationPage,
+  ConversationStats,
   ConversationWithMessages,
+  CreateAnnotationRequest,
   CreateConversationRequest,
   CreateMessageRequest,
+  FileUploadResponse,
+  ForkConversationRequest,
   Message,
+  MessageAnnotation,
   PaginationParams,
+  RelatedConversation,
+  RetrievalSettings,
+  SimilarQuestion,
+  TopicCluster,
+  TopicMembers,
+  TopicPoint,
   ApiResponse
 } from '../types';
 import { authService } from './auth';
@@ -129,6 +144,11 @@ class ApiClient {
         };
       }
 
+      // No Content responses (e.g. deletes) carry no body to parse
+      if (response.status === 204) {
+        return { status: response.status };
+      }
+
       const data = await response.json();
       return {
         data,
@@ -143,12 +163,12 @@ class ApiClient {
   }
 
   // Conversation endpoints
-  async getConversations(pagination?: PaginationParams): Promise<ApiResponse<Conversation[]>> {
+  async getConversations(pagination?: PaginationParams): Promise<ApiResponse<ConversationPage>> {
     const params = new URLSearchParams();
-    if (pagination?.page) params.append('page', pagination.page.toString());
+    if (pagination?.cursor) params.append('cursor', pagination.cursor);
     if (pagination?.limit) params.append('limit', pagination.limit.toString());
 
-    return this.request<Conversation[]>(
+    return this.request<ConversationPage>(
       `/api/v1/conversations?${params.toString()}`
     );
   }
@@ -167,6 +187,10 @@ class ApiClient {
     return this.request<ConversationWithMessages>(`/api/v1/conversations/${id}`);
   }
 
+  async getConversationStats(id: string): Promise<ApiResponse<ConversationStats>> {
+    return this.request<ConversationStats>(`/api/v1/conversations/${id}/stats`);
+  }
+
   async updateConversationTitle(id: string, title: string): Promise<ApiResponse<void>> {
     return this.request<void>(`/api/v1/conversations/${id}/title`, {
       method: 'PATCH',
@@ -174,6 +198,27 @@ class ApiClient {
     });
   }
 
+  async getRetrievalSettings(id: string): Promise<ApiResponse<RetrievalSettings>> {
+    return this.request<RetrievalSettings>(`/api/v1/conversations/${id}/retrieval`);
+  }
+
+  async updateRetrievalSettings(id: string, settings: RetrievalSettings): Promise<ApiResponse<RetrievalSettings>> {
+    return this.request<RetrievalSettings>(`/api/v1/conversations/${id}/retrieval`, {
+      method: 'PUT',
+      body: JSON.stringify(settings),
+    });
+  }
+
+  async getRelatedConversations(
+    id: string,
+    limit?: number
+  ): Promise<ApiResponse<{ conversation_id: string; related: RelatedConversation[] }>> {
+    const query = limit ? `?limit=${limit}` : '';
+    return this.request<{ conversation_id: string; related: RelatedConversation[] }>(
+      `/api/v1/conversations/${id}/related${query}`
+    );
+  }
+
   async deleteConversation(id: string): Promise<ApiResponse<void>> {
     return this.request<void>(`/api/v1/conversations/${id}`, {
       method: 'DELETE',
@@ -181,8 +226,8 @@ class ApiClient {
   }
 
   // Message endpoints
-  async getMessages(conversationId: string): Promise<ApiResponse<{ messages: Message[]; conversation_id: string; total_count: number }>> {
-    return this.request<{ messages: Message[]; conversation_id: string; total_count: number }>(
+  async getMessages(conversationId: string): Promise<ApiResponse<{ messages: Message[]; conversation_id: string; next_cursor: string | null; total: number }>> {
+    return this.request<{ messages: Message[]; conversation_id: string; next_cursor: string | null; total: number }>(
       `/api/v1/conversations/${conversationId}/messages`
     );
   }
@@ -201,7 +246,8 @@ class ApiClient {
     onToken: (token: string) => void,
     onError: (error: string) => void,
     onComplete: (messageId?: string) => void,
-    abortSignal?: AbortSignal
+    abortSignal?: AbortSignal,
+    onSuggestion?: (suggestions: SimilarQuestion[]) => void
   ): Promise<void> {
     try {
       const baseHeaders = this.getBaseHeaders();
@@ -238,7 +284,7 @@ class ApiClient {
           try {
             await this.refreshSessionAndRetry();
             // Retry the streaming request with refreshed session
-            return this.streamMessage(conversationId, content, onToken, onError, onComplete, abortSignal);
+            return this.streamMessage(conversationId, content, onToken, onError, onComplete, abortSignal, onSuggestion);
           } catch (refreshError) {
             onError('Authentication failed. Please log in again.');
             return;
@@ -309,6 +355,8 @@ class ApiClient {
                 if (data.type === 'token' && data.data?.content) {
                   console.log('[ApiClient] Calling onToken with:', data.data.content);
                   onToken(data.data.content);
+                } else if (data.type === 'suggestion') {
+                  onSuggestion?.(data.data?.suggestions ?? []);
                 } else if (data.type === 'done') {
                   console.log('[ApiClient] Stream complete, messageId:', data.data?.messageId);
                   onComplete(data.data?.messageId);
@@ -354,6 +402,98 @@ class ApiClient {
     );
   }
 
+  async getMessageHistory(messageId: string): Promise<ApiResponse<Message[]>> {
+    return this.request<Message[]>(`/api/v1/messages/${messageId}/history`);
+  }
+
+  async forkMessage(messageId: string, request: ForkConversationRequest = {}): Promise<ApiResponse<Conversation>> {
+    return this.request<Conversation>(`/api/v1/messages/${messageId}/fork`, {
+      method: 'POST',
+      body: JSON.stringify(request),
+    });
+  }
+
+  async compareBranches(conversationId: string, left: string, right: string): Promise<ApiResponse<BranchComparison>> {
+    const params = new URLSearchParams({ left, right });
+    return this.request<BranchComparison>(`/api/v1/conversations/${conversationId}/compare?${params}`);
+  }
+
+  async createAnnotation(messageId: string, request: CreateAnnotationRequest): Promise<ApiResponse<MessageAnnotation>> {
+    return this.request<MessageAnnotation>(`/api/v1/messages/${messageId}/annotations`, {
+      method: 'POST',
+      body: JSON.stringify(request),
+    });
+  }
+
+  async getConversationAnnotations(conversationId: string): Promise<ApiResponse<MessageAnnotation[]>> {
+    return this.request<MessageAnnotation[]>(`/api/v1/conversations/${conversationId}/annotations`);
+  }
+
+  async updateAnnotation(id: string, changes: { note?: string; label?: string }): Promise<ApiResponse<MessageAnnotation>> {
+    return this.request<MessageAnnotation>(`/api/v1/annotations/${id}`, {
+      method: 'PATCH',
+      body: JSON.stringify(changes),
+    });
+  }
+
+  async deleteAnnotation(id: string): Promise<ApiResponse<void>> {
+    return this.request<void>(`/api/v1/annotations/${id}`, {
+      method: 'DELETE',
+    });
+  }
+
+  async getBookmarks(filters: { label?: string; q?: string } = {}): Promise<ApiResponse<Bookmark[]>> {
+    const params = new URLSearchParams();
+    if (filters.label) params.set('label', filters.label);
+    if (filters.q) params.set('q', filters.q);
+    const query = params.toString();
+    return this.request<Bookmark[]>(`/api/v1/bookmarks${query ? `?${query}` : ''}`);
+  }
+
+  // File attachment endpoints
+  async uploadAttachment(messageId: string, file: File): Promise<ApiResponse<FileUploadResponse>> {
+    const form = new FormData();
+    form.append('message_id', messageId);
+    form.append('file', file);
+
+    // The browser sets the multipart Content-Type with its boundary
+    return this.request<FileUploadResponse>('/api/v1/files', {
+      method: 'POST',
+      body: form,
+      headers: {},
+    });
+  }
+
+  async getMessageAttachments(messageId: string): Promise<ApiResponse<Attachment[]>> {
+    return this.request<Attachment[]>(`/api/v1/messages/${messageId}/attachments`);
+  }
+
+  getAttachmentDownloadUrl(attachment: Attachment): string {
+    return `${this.baseUrl}${attachment.download_url}`;
+  }
+
+  async deleteAttachment(id: string): Promise<ApiResponse<void>> {
+    return this.request<void>(`/api/v1/files/${id}`, {
+      method: 'DELETE',
+    });
+  }
+
+  // Topic endpoints
+  async getTopics(): Promise<ApiResponse<{ topics: TopicMembers[] }>> {
+    return this.request<{ topics: TopicMembers[] }>('/api/v1/topics');
+  }
+
+  // Clustering runs in the background; fetch the topics again once it is done
+  async refreshTopics(): Promise<ApiResponse<{ status: string }>> {
+    return this.request<{ status: string }>('/api/v1/topics', {
+      method: 'POST',
+    });
+  }
+
+  async getTopicMap(): Promise<ApiResponse<{ topics: TopicCluster[]; points: TopicPoint[] }>> {
+    return this.request<{ topics: TopicCluster[]; points: TopicPoint[] }>('/api/v1/topics/map');
+  }
+
   // Health check
   async healthCheck(): Promise<ApiResponse<{ status: string }>> {
     return this.request<{ status: string }>('/api/v1/health');
@@ -1,6 +1,14 @@
 // Search API service for semantic search functionality
 
 import {
+  EmbeddingJobResponse,
+  EmbeddingQueueStatus,
+  SavedSearch,
+  SavedSearchMatches,
+  SavedSearchRunResponse,
+  SaveSearchRequest,
+  SearchFilters,
+  SearchMode,
   SearchRequest,
   SearchResponse,
   ApiResponse
@@ -54,12 +62,26 @@ class SearchApiClient {
   async searchMessages(
     query: string,
     limit?: number,
-    similarityThreshold?: number
+    similarityThreshold?: number,
+    mode?: SearchMode,
+    filters?: SearchFilters
   ): Promise<ApiResponse<SearchResponse>> {
     const params = new URLSearchParams();
     params.append('q', query);
     if (limit) params.append('limit', limit.toString());
     if (similarityThreshold) params.append('similarity_threshold', similarityThreshold.toString());
+    if (mode) params.append('mode', mode);
+    if (filters?.conversation_ids?.length) {
+      params.append('conversation_ids', filters.conversation_ids.join(','));
+    }
+    if (filters?.role) params.append('role', filters.role);
+    if (filters?.model) params.append('model', filters.model);
+    if (filters?.provider) params.append('provider', filters.provider);
+    if (filters?.created_after) params.append('created_after', filters.created_after);
+    if (filters?.created_before) params.append('created_before', filters.created_before);
+    if (filters?.active_branch_only !== undefined) {
+      params.append('active_branch_only', String(filters.active_branch_only));
+    }
 
     return this.request<SearchResponse>(
       `/api/v1/search?${params.toString()}`
@@ -74,13 +96,71 @@ class SearchApiClient {
     });
   }
 
+  // Saved searches, those with new matches first
+  async getSavedSearches(): Promise<ApiResponse<SavedSearch[]>> {
+    return this.request<SavedSearch[]>('/api/v1/saved-searches');
+  }
+
+  async createSavedSearch(request: SaveSearchRequest): Promise<ApiResponse<SavedSearch>> {
+    return this.request<SavedSearch>('/api/v1/saved-searches', {
+      method: 'POST',
+      body: JSON.stringify(request),
+    });
+  }
+
+  // Replacing a saved search starts counting its new matches afresh
+  async updateSavedSearch(id: string, request: SaveSearchRequest): Promise<ApiResponse<SavedSearch>> {
+    return this.request<SavedSearch>(`/api/v1/saved-searches/${id}`, {
+      method: 'PUT',
+      body: JSON.stringify(request),
+    });
+  }
+
+  async deleteSavedSearch(id: string): Promise<ApiResponse<void>> {
+    return this.request<void>(`/api/v1/saved-searches/${id}`, {
+      method: 'DELETE',
+    });
+  }
+
+  // Run a saved search and mark its new matches seen
+  async runSavedSearch(id: string, limit?: number): Promise<ApiResponse<SavedSearchRunResponse>> {
+    const query = limit ? `?limit=${limit}` : '';
+    return this.request<SavedSearchRunResponse>(`/api/v1/saved-searches/${id}/run${query}`, {
+      method: 'POST',
+    });
+  }
+
+  // Listen for new-match counts; returns a function that closes the stream
+  subscribeToSavedSearchMatches(onMatches: (matches: SavedSearchMatches[]) => void): () => void {
+    const source = new EventSource(`${this.baseUrl}/api/v1/saved-searches/events`, {
+      withCredentials: true,
+    });
+    source.onmessage = (event) => {
+      try {
+        const message = JSON.parse(event.data);
+        if (message.type === 'matches') {
+          onMatches(message.data.saved_searches);
+        }
+      } catch {
+        // Ignore keep-alive messages
+      }
+    };
+    return () => source.close();
+  }
+
   // Trigger background embedding generation job (admin only)
-  async triggerEmbeddingJob(): Promise<ApiResponse<{ processed_count: number; success: boolean }>> {
-    return this.request<{ processed_count: number; success: boolean }>('/api/v1/search/embedding-job', {
+  async triggerEmbeddingJob(retryFailed = false): Promise<ApiResponse<EmbeddingJobResponse>> {
+    const query = retryFailed ? '?retry_failed=true' : '';
+    return this.request<EmbeddingJobResponse>(`/api/v1/search/embedding-job${query}`, {
       method: 'POST',
     });
   }
 
+  // Get embedding queue progress and recent failures
+  async getEmbeddingJobStatus(): Promise<ApiResponse<EmbeddingQueueStatus>> {
+    return this.request<EmbeddingQueueStatus>('/api/v1/search/embedding-job');
+  }
+
   // Get search service health
   async getSearchHealth(): Promise<ApiResponse<{ status: string; service: string; timestamp: string }>> {
     return this.request<{ status: string; service: string; timestamp: string }>('/api/v1/search/health');
@@ -17,6 +17,8 @@ export interface Conversation {
   created_at: string;
   updated_at: string;
   metadata: Record<string, any>;
+  author_id?: string;
+  active_leaf_id?: string;
 }
 
 export type MessageRole = 'user' | 'assistant' | 'system';
@@ -31,6 +33,9 @@ export interface Message {
   created_at: string;
   is_active: boolean;
   metadata: Record<string, any>;
+  author_id?: string;
+  edited_from?: string;
+  deleted_at?: string;
 }
 
 export interface ConversationWithMessages {
@@ -38,6 +43,26 @@ export interface ConversationWithMessages {
   messages: Message[];
 }
 
+export interface ConversationStats {
+  conversation_id: string;
+  message_count: number;
+  total_messages: number;
+  branch_count: number;
+  max_depth: number;
+  prompt_tokens: number;
+  completion_tokens: number;
+  total_tokens: number;
+  cost_cents: number;
+  models: { model: string; message_count: number }[];
+  average_latency_ms: number | null;
+  attachment_count: number;
+  attachment_bytes: number;
+  embedded_messages: number;
+  embedding_coverage: number;
+  created_at: string;
+  updated_at: string;
+}
+
 export interface CreateConversationRequest {
   title?: string;
   model: string;
@@ -45,6 +70,86 @@ export interface CreateConversationRequest {
   metadata?: Record<string, any>;
 }
 
+export interface ForkConversationRequest {
+  title?: string;
+  model?: string;
+  provider?: string;
+}
+
+export interface DiffSegment {
+  op: 'equal' | 'insert' | 'delete';
+  text: string;
+}
+
+export interface AnswerStats {
+  model: string | null;
+  prompt_tokens: number | null;
+  completion_tokens: number | null;
+  total_tokens: number | null;
+  cost_cents: number | null;
+  latency_ms: number | null;
+  word_count: number;
+}
+
+export interface BranchComparison {
+  conversation_id: string;
+  common_ancestor_id: string | null;
+  left: Message[];
+  right: Message[];
+  answers?: {
+    diff: DiffSegment[];
+    left: AnswerStats;
+    right: AnswerStats;
+  };
+}
+
+export interface Attachment {
+  id: string;
+  message_id: string;
+  filename: string;
+  content_type?: string | null;
+  size_bytes?: number | null;
+  download_url: string;
+  created_at: string;
+}
+
+export interface FileUploadResponse {
+  id: string;
+  message_id: string;
+  filename: string;
+  content_type?: string | null;
+  size_bytes?: number | null;
+  upload_url: string;
+  created_at: string;
+}
+
+export interface MessageAnnotation {
+  id: string;
+  message_id: string;
+  conversation_id: string;
+  user_id: string;
+  start_offset: number | null;
+  end_offset: number | null;
+  quote: string | null;
+  note: string | null;
+  label: string | null;
+  created_at: string;
+  updated_at: string;
+}
+
+export interface Bookmark extends MessageAnnotation {
+  conversation_title: string | null;
+  message_role: 'user' | 'assistant' | 'system';
+  message_preview: string;
+}
+
+export interface CreateAnnotationRequest {
+  start_offset?: number;
+  end_offset?: number;
+  note?: string;
+  label?: string;
+}
+
 export interface CreateMessageRequest {
   conversation_id: string;
   parent_id?: string;
@@ -54,10 +159,16 @@ export interface CreateMessageRequest {
 }
 
 export interface PaginationParams {
-  page?: number;
+  cursor?: string;
   limit?: number;
 }
 
+export interface ConversationPage {
+  conversations: Conversation[];
+  next_cursor: string | null;
+  total: number;
+}
+
 export interface ApiResponse<T> {
   data?: T;
   error?: string;
@@ -112,11 +223,122 @@ export interface ConversationState {
   clearError: () => void;
 }
 
+// Retrieval types
+/** Stored under `metadata.retrieval` on the conversation */
+export interface RetrievalSettings {
+  enabled: boolean;
+  top_k: number;
+  min_similarity: number;
+}
+
+/** A passage the answer was given, saved as `metadata.citations` on assistant messages */
+export interface Citation {
+  marker: number;
+  message_id: string;
+  conversation_id: string;
+  conversation_title?: string | null;
+  start_offset?: number | null;
+  end_offset?: number | null;
+  similarity?: number | null;
+}
+
+// Suggestion types
+/** An earlier question close to a new prompt, sent as a `suggestion` stream event */
+export interface SimilarQuestion {
+  question_id: string;
+  question: string;
+  asked_at: string;
+  conversation_id: string;
+  conversation_title?: string | null;
+  similarity: number;
+  answer_id?: string | null;
+  /** Preview of the answer; missing if the question was never answered */
+  answer?: string | null;
+}
+
+export interface RelatedConversation {
+  conversation_id: string;
+  title?: string | null;
+  model: string;
+  updated_at: string;
+  embedded_messages: number;
+  similarity: number;
+}
+
+// Topic types
+/** A cluster of the user's messages; the label can be offered as a tag */
+export interface TopicCluster {
+  id: string;
+  user_id: string;
+  cluster_index: number;
+  label: string;
+  keywords: string[];
+  size: number;
+  model: string;
+  created_at: string;
+}
+
+export interface TopicMembers extends TopicCluster {
+  message_ids: string[];
+  conversation_ids: string[];
+}
+
+/** Coordinates are scaled into -1..1 */
+export interface TopicPoint {
+  message_id: string;
+  conversation_id: string;
+  cluster_id: string;
+  role: MessageRole;
+  x: number;
+  y: number;
+  preview: string;
+}
+
 // Search types
+export type SearchMode = 'semantic' | 'lexical' | 'hybrid';
+
+/** Unset filters match everything; only the selected branch is searched by default */
+export interface SearchFilters {
+  conversation_ids?: string[];
+  role?: MessageRole;
+  model?: string;
+  provider?: string;
+  created_after?: string;
+  created_before?: string;
+  active_branch_only?: boolean;
+}
+
 export interface SearchRequest {
   query: string;
   limit?: number;
   similarity_threshold?: number;
+  mode?: SearchMode;
+  filters?: SearchFilters;
+}
+
+export interface SnippetPart {
+  text: string;
+  highlighted: boolean;
+}
+
+export interface FacetCount {
+  value: string;
+  count: number;
+}
+
+export interface SearchFacets {
+  conversations: { conversation_id: string; title?: string | null; count: number }[];
+  roles: FacetCount[];
+  models: FacetCount[];
+  providers: FacetCount[];
+}
+
+/** Character offsets into the message content, end exclusive */
+export interface MatchedChunk {
+  chunk_index: number;
+  start_offset: number;
+  end_offset: number;
+  content: string;
 }
 
 export interface SearchResult {
@@ -126,14 +348,82 @@ export interface SearchResult {
   created_at: string;
   conversation_id: string;
   conversation_title?: string;
-  similarity: number;
+  similarity: number | null;
+  text_rank?: number | null;
+  score?: number;
+  matched_chunk?: MatchedChunk | null;
   preview: string;
+  snippet?: SnippetPart[];
 }
 
 export interface SearchResponse {
   query: string;
+  mode?: SearchMode;
   results: SearchResult[];
   total_found: number;
+  facets?: SearchFacets;
+}
+
+/** A search kept to run again, watched for matches newer than last_viewed_at */
+export interface SavedSearch {
+  id: string;
+  user_id: string;
+  name: string;
+  query: string;
+  mode: SearchMode;
+  filters: SearchFilters;
+  similarity_threshold?: number | null;
+  new_match_count: number;
+  last_viewed_at: string;
+  last_checked_at: string;
+  created_at: string;
+  updated_at: string;
+}
+
+export interface SaveSearchRequest {
+  name: string;
+  query: string;
+  mode?: SearchMode;
+  filters?: SearchFilters;
+  similarity_threshold?: number;
+}
+
+/** new_message_ids are the matches created or embedded since new_since, the ones counted as new; running the search marks them seen */
+export interface SavedSearchRunResponse extends SearchResponse {
+  saved_search: SavedSearch;
+  new_since: string;
+  new_message_ids: string[];
+}
+
+export interface SavedSearchMatches {
+  id: string;
+  name: string;
+  new_match_count: number;
+}
+
+export interface FailedEmbeddingJob {
+  message_id: string;
+  status: 'pending' | 'failed';
+  attempts: number;
+  last_error?: string;
+  run_after: string;
+  updated_at: string;
+}
+
+export interface EmbeddingQueueStatus {
+  pending: number;
+  retrying: number;
+  processing: number;
+  completed: number;
+  failed: number;
+  recent_failures: FailedEmbeddingJob[];
+}
+
+export interface EmbeddingJobResponse {
+  processed_count: number;
+  success: boolean;
+  error?: string;
+  status: EmbeddingQueueStatus;
 }
 
 export interface SearchState {